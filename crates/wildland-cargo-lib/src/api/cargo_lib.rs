@@ -25,15 +25,22 @@ use thiserror::Error;
 use wildland_catlib::CatLib;
 use wildland_corex::catlib_service::CatLibService;
 use wildland_corex::container_manager::ContainerManager;
-use wildland_corex::dfs::interface::DfsFrontend;
+use wildland_corex::dfs::interface::{DfsEventReceiver, DfsFrontend};
 use wildland_corex::{LocalSecureStorage, LssService};
 use wildland_dfs::encrypted::EncryptedDfs as Dfs;
+use wildland_dfs::in_memory::{InMemoryBackendFactory, IN_MEMORY_BACKEND_TYPE};
 use wildland_dfs::unencrypted::StorageBackendFactory;
 #[cfg(feature = "lfs")]
 use wildland_lfs::LfsBackendFactory;
+use wildland_s3::{S3BackendFactory, S3_BACKEND_TYPE};
 
-use crate::api::config::{CargoConfig, FoundationStorageApiConfig};
+use crate::api::config::{CargoConfig, CatLibConfig, FoundationStorageApiConfig};
 use crate::api::user::UserApi;
+use crate::dfs_keys::ContainerKeyProvider;
+use crate::foundation_storage_backend::{
+    FoundationStorageBackendFactory,
+    FOUNDATION_STORAGE_BACKEND_TYPE,
+};
 use crate::logging;
 use crate::user::UserService;
 
@@ -73,27 +80,61 @@ impl CargoLib {
     pub fn new(
         lss: &'static dyn LocalSecureStorage,
         fsa_config: FoundationStorageApiConfig,
+        catlib_config: CatLibConfig,
+    ) -> Result<Self, CargoLibCreationError> {
+        let mut catlib = CatLib::with_default_location(catlib_config.catlib_backend)
+            .map_err(|e| CargoLibCreationError::Error(e.to_string()))?;
+        if let Some(sync_dir) = catlib_config.catlib_sync_dir {
+            catlib = catlib.with_sync_dir(sync_dir);
+        }
+        Ok(Self::with_catlib_service(
+            lss,
+            fsa_config,
+            CatLibService::new(Rc::new(catlib)),
+        ))
+    }
+
+    pub(crate) fn with_catlib_service(
+        lss: &'static dyn LocalSecureStorage,
+        fsa_config: FoundationStorageApiConfig,
+        catlib_service: CatLibService,
     ) -> Self {
         let lss_service = LssService::new(lss);
-        let container_manager = Rc::new(ContainerManager {});
+        let container_manager = Rc::new(ContainerManager::default());
 
         let mut dfs_storage_factories: HashMap<String, Box<dyn StorageBackendFactory>> =
             HashMap::new();
+        dfs_storage_factories.insert(
+            IN_MEMORY_BACKEND_TYPE.to_string(),
+            Box::<InMemoryBackendFactory>::default(),
+        );
+        dfs_storage_factories.insert(
+            FOUNDATION_STORAGE_BACKEND_TYPE.to_string(),
+            Box::new(FoundationStorageBackendFactory {}),
+        );
         #[cfg(feature = "lfs")]
         dfs_storage_factories.insert(
             "LocalFilesystem".to_string(),
             Box::new(LfsBackendFactory {}),
         );
+        dfs_storage_factories.insert(S3_BACKEND_TYPE.to_string(), Box::new(S3BackendFactory {}));
+
+        let key_provider = Rc::new(ContainerKeyProvider::new(
+            lss_service.clone(),
+            container_manager.clone(),
+        ));
 
         Self {
             user_api: UserApi::new(UserService::new(
                 lss_service,
-                CatLibService::new(Rc::new(CatLib::default())),
+                catlib_service,
                 fsa_config,
+                container_manager.clone(),
             )),
             dfs_api: Arc::new(Mutex::new(Dfs::new(
                 container_manager,
                 dfs_storage_factories,
+                key_provider,
             ))),
         }
     }
@@ -108,6 +149,17 @@ impl CargoLib {
     pub fn dfs_api(&self) -> Arc<Mutex<dyn DfsFrontend>> {
         self.dfs_api.clone()
     }
+
+    /// Registers a receiver of DFS events, like failures of storages, which are not reported by
+    /// results of DFS API methods. It is assumed that the receiver is valid for a whole program
+    /// execution (static lifetime), the same as lss.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn subscribe_dfs_events(&self, receiver: &'static dyn DfsEventReceiver) {
+        self.dfs_api
+            .lock()
+            .expect("Poisoned Mutex")
+            .subscribe_events(Box::new(receiver))
+    }
 }
 
 /// [`CargoLib`] initializer which is the main part of Cargo public API.
@@ -159,6 +211,10 @@ impl CargoLib {
 ///         evs_url: "some_url".to_owned(),
 ///         sc_url: "some_url".to_owned(),
 ///     },
+///     catlib_config: CatLibConfig {
+///         catlib_backend: CatLibBackend::Rustbreak,
+///         catlib_sync_dir: None,
+///     },
 /// };
 ///
 /// let lss: &'static TestLss = unsafe { std::mem::transmute(&lss) };
@@ -174,7 +230,11 @@ pub fn create_cargo_lib(
         logging::init_subscriber(cfg.logger_config)
             .map_err(|e| CargoLibCreationError::Error(e.to_string()))?;
 
-        let cargo_lib = Arc::new(Mutex::new(CargoLib::new(lss, cfg.fsa_config)));
+        let cargo_lib = Arc::new(Mutex::new(CargoLib::new(
+            lss,
+            cfg.fsa_config,
+            cfg.catlib_config,
+        )?));
 
         unsafe {
             CARGO_LIB.write(cargo_lib);
@@ -182,3 +242,132 @@ pub fn create_cargo_lib(
     }
     unsafe { Ok(CARGO_LIB.assume_init_ref().clone()) }
 }
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "lfs")]
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    #[cfg(feature = "lfs")]
+    use serde_json::json;
+    use wildland_corex::catlib_service::CatLibService;
+    use wildland_corex::dfs::interface::NodeType;
+    use wildland_corex::{LocalSecureStorage, StorageTemplate};
+    use wildland_dfs::in_memory::InMemoryStorageTemplate;
+
+    use super::CargoLib;
+    use crate::api::config::FoundationStorageApiConfig;
+    use crate::api::utils::test::{catlib_service, lss_stub};
+
+    #[rstest]
+    fn test_using_scratch_container_kept_in_memory(
+        catlib_service: CatLibService,
+        lss_stub: &'static dyn LocalSecureStorage,
+    ) {
+        let cargo_lib = CargoLib::with_catlib_service(
+            lss_stub,
+            FoundationStorageApiConfig::default(),
+            catlib_service,
+        );
+        let user_api = cargo_lib.user_api();
+        let mnemonic = user_api.generate_mnemonic().unwrap();
+        let user = user_api
+            .create_user_from_mnemonic(&mnemonic, "device".to_string())
+            .unwrap();
+        let template: StorageTemplate = InMemoryStorageTemplate::new().try_into().unwrap();
+        let container = user
+            .create_container("scratch".to_string(), &template, "/scratch".to_string())
+            .unwrap();
+        user.mount(&container).unwrap();
+
+        let dfs = cargo_lib.dfs_api();
+        let mut dfs = dfs.lock().unwrap();
+        dfs.mkdir("/scratch/dir".to_string()).unwrap();
+        let file = dfs.create("/scratch/dir/file".to_string()).unwrap();
+        dfs.write(&file, 0, b"content".to_vec()).unwrap();
+        dfs.close(&file).unwrap();
+
+        let nodes = dfs.readdir("/scratch/dir".to_string());
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].absolute_path.to_str(), Some("/scratch/dir/file"));
+        let file = dfs.open("/scratch/dir/file".to_string()).unwrap();
+        assert_eq!(dfs.read(&file, 0, 100).unwrap(), b"content".to_vec());
+        assert_eq!(
+            dfs.getattr("/scratch/dir".to_string()).unwrap().node_type,
+            NodeType::Dir
+        );
+    }
+
+    #[cfg(feature = "lfs")]
+    #[rstest]
+    fn test_accessing_mounted_container_through_dfs_api(
+        catlib_service: CatLibService,
+        lss_stub: &'static dyn LocalSecureStorage,
+    ) {
+        // given a user with a container backed by a local filesystem storage
+        let cargo_lib = CargoLib::with_catlib_service(
+            lss_stub,
+            FoundationStorageApiConfig::default(),
+            catlib_service,
+        );
+        let user_api = cargo_lib.user_api();
+        let mnemonic = user_api.generate_mnemonic().unwrap();
+        let user = user_api
+            .create_user_from_mnemonic(&mnemonic, "device".to_string())
+            .unwrap();
+
+        let storage_dir = tempfile::tempdir().unwrap();
+        let template = StorageTemplate::try_new(
+            "LocalFilesystem",
+            json!({
+                "local_dir": storage_dir.path(),
+                "container_prefix": "{{ CONTAINER_NAME }}"
+            }),
+        )
+        .unwrap();
+        let container = user
+            .create_container("books".to_string(), &template, "/books".to_string())
+            .unwrap();
+        fs::create_dir(storage_dir.path().join("books")).unwrap();
+
+        // when the container is mounted
+        user.mount(&container).unwrap();
+
+        // then it is available through DFS API
+        let dfs = cargo_lib.dfs_api();
+        let mut dfs = dfs.lock().unwrap();
+        let root_nodes = dfs.readdir("/".to_string());
+        assert_eq!(root_nodes.len(), 1);
+        assert_eq!(root_nodes[0].absolute_path.to_str(), Some("/books"));
+
+        // and files created through DFS can be read back
+        let file = dfs.create("/books/file".to_string()).unwrap();
+        dfs.write(&file, 0, b"content".to_vec()).unwrap();
+        dfs.close(&file).unwrap();
+        assert_eq!(
+            dfs.getattr("/books/file".to_string()).unwrap().node_type,
+            NodeType::File
+        );
+        let file = dfs.open("/books/file".to_string()).unwrap();
+        assert_eq!(dfs.read(&file, 0, 100).unwrap(), b"content".to_vec());
+        dfs.close(&file).unwrap();
+
+        // and neither the file name nor its content is stored in plain text
+        let stored_files: Vec<_> = fs::read_dir(storage_dir.path().join("books"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(stored_files.len(), 1);
+        assert_ne!(stored_files[0].file_name().unwrap(), "file");
+        assert!(!fs::read(&stored_files[0])
+            .unwrap()
+            .windows(b"content".len())
+            .any(|window| window == b"content"));
+
+        // and the content is no longer available after unmounting
+        user.unmount(&container).unwrap();
+        assert!(dfs.readdir("/".to_string()).is_empty());
+    }
+}
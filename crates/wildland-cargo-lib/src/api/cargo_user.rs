@@ -15,22 +15,36 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
 use derivative::Derivative;
 use wildland_corex::catlib_service::entities::ForestManifest;
 use wildland_corex::catlib_service::error::CatlibError;
 use wildland_corex::catlib_service::CatLibService;
-use wildland_corex::{ContainerManifest, ContainerPath, StorageTemplate};
+use wildland_corex::container_manager::ContainerManager;
+use wildland_corex::{
+    ContainerManifest,
+    ContainerPath,
+    LssService,
+    MasterIdentity,
+    StorageTemplate,
+};
 
 use super::config::FoundationStorageApiConfig;
+use super::device::{DeviceEnrollmentRequest, DeviceEnrollmentResponse, EnrolledForestKeys};
 use super::foundation_storage::{FoundationStorageApi, FreeTierProcessHandle, FsaError};
+use super::user::MnemonicPayload;
+use crate::errors::container::{ContainerMountError, ContainerUnmountError};
+use crate::errors::device::DeviceEnrollmentError;
 use crate::errors::storage::GetStorageTemplateError;
+use crate::errors::UserRetrievalError;
 
 /// Structure representing a User.
 ///
 /// It gives access to:
 /// - user's forest and containers,
+/// - mounting containers, so they are accessible through DFS API,
 /// - Foundation Storage API which includes the following methods:
 ///     - [`Self::request_free_tier_storage()`]
 ///     - [`Self::verify_email()`]
@@ -45,7 +59,11 @@ pub struct CargoUser {
     #[derivative(Debug = "ignore")]
     catlib_service: CatLibService,
     #[derivative(Debug = "ignore")]
+    lss_service: LssService,
+    #[derivative(Debug = "ignore")]
     fsa_api: FoundationStorageApi,
+    #[derivative(Debug = "ignore")]
+    container_manager: Rc<ContainerManager>,
 }
 
 impl CargoUser {
@@ -54,14 +72,18 @@ impl CargoUser {
         all_devices: Vec<String>,
         forest: Arc<Mutex<dyn ForestManifest>>,
         catlib_service: CatLibService,
+        lss_service: LssService,
         fsa_config: &FoundationStorageApiConfig,
+        container_manager: Rc<ContainerManager>,
     ) -> Self {
         Self {
             this_device,
             all_devices,
             forest,
             catlib_service,
+            lss_service,
             fsa_api: FoundationStorageApi::new(fsa_config),
+            container_manager,
         }
     }
 
@@ -104,6 +126,34 @@ All devices:
             .create_container(name, &self.forest, template, path)
     }
 
+    /// Mounts the container, so its content becomes accessible through DFS API under the paths
+    /// claimed by the container.
+    ///
+    /// Paths and storages of the container are read while mounting, so the container has to be
+    /// remounted in order to apply changes made to it afterwards.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn mount(
+        &self,
+        container: &Arc<Mutex<dyn ContainerManifest>>,
+    ) -> Result<(), ContainerMountError> {
+        self.container_manager.mount(container)
+    }
+
+    /// Makes the container inaccessible through DFS API
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn unmount(
+        &self,
+        container: &Arc<Mutex<dyn ContainerManifest>>,
+    ) -> Result<(), ContainerUnmountError> {
+        self.container_manager.unmount(container)
+    }
+
+    /// Returns vector of handles to the mounted containers.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn get_mounted_containers(&self) -> Vec<Arc<Mutex<dyn ContainerManifest>>> {
+        self.container_manager.mounted_containers()
+    }
+
     pub fn this_device(&self) -> &str {
         &self.this_device
     }
@@ -112,6 +162,103 @@ All devices:
         self.all_devices.as_slice()
     }
 
+    /// Exchanges changes of the user's forest with other devices of the user through the
+    /// directory set in [`super::config::CatLibConfig::catlib_sync_dir`]. Containers mounted
+    /// before have to be remounted to apply the received changes.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn sync_forest(&mut self) -> Result<(), CatlibError> {
+        let forest_uuid = self.forest.lock().expect("Poisoned Mutex").uuid();
+        self.catlib_service.sync_forest(&forest_uuid)?;
+        self.all_devices = self
+            .catlib_service
+            .get_parsed_forest_metadata(&self.forest)?
+            .devices()
+            .map(|device| device.name.clone())
+            .collect();
+        Ok(())
+    }
+
+    /// Accepts enrollment request of a new device, authorizing it to sign manifests of the
+    /// user's forest. The returned response carries the forest keys sealed for the new device,
+    /// which receives the forest once it is synchronized with [`CargoUser::sync_forest`].
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn enroll_device(
+        &mut self,
+        request: &DeviceEnrollmentRequest,
+    ) -> Result<DeviceEnrollmentResponse, DeviceEnrollmentError> {
+        let forest_owner = self.forest.lock().expect("Poisoned Mutex").owner();
+        let forest_index = self
+            .lss_service
+            .find_forest_index(&forest_owner)
+            .map_err(UserRetrievalError::from)?
+            .ok_or_else(|| UserRetrievalError::ForestNotFound(forest_owner.encode()))?;
+        let forest_identity = self
+            .lss_service
+            .get_forest_identity(forest_index)
+            .map_err(UserRetrievalError::from)?
+            .ok_or_else(|| UserRetrievalError::ForestNotFound(forest_owner.encode()))?;
+        let keys = EnrolledForestKeys {
+            forest_index,
+            forest_name: self
+                .lss_service
+                .get_forest_registry()?
+                .name_of(forest_index)
+                .unwrap_or_default()
+                .to_owned(),
+            forest_keypair: forest_identity.get_keypair(),
+            data_key: self
+                .lss_service
+                .get_forest_data_key(forest_index)?
+                .ok_or(UserRetrievalError::ForestDataKeyNotFound)?,
+        };
+        let response = DeviceEnrollmentResponse::seal(&keys, request)?;
+
+        self.catlib_service
+            .add_device(&self.forest, request.device_metadata())?;
+        self.all_devices.push(request.device_name());
+        Ok(response)
+    }
+
+    /// Revokes the device, e.g. a lost or stolen one. The device is no longer authorized to sign
+    /// manifests of the user's forest, so changes it makes afterwards are rejected by other
+    /// devices, and the forest encryption keypair, derived from the mnemonic, is rotated.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn revoke_device(
+        &mut self,
+        device_name: String,
+        mnemonic: &MnemonicPayload,
+    ) -> Result<(), DeviceEnrollmentError> {
+        if device_name == self.this_device {
+            return Err(DeviceEnrollmentError::CannotRevokeThisDevice);
+        }
+        let forest_metadata = self
+            .catlib_service
+            .get_parsed_forest_metadata(&self.forest)?;
+        let device_pubkey = forest_metadata
+            .devices()
+            .find(|device| device.name == device_name)
+            .map(|device| device.pubkey)
+            .ok_or(DeviceEnrollmentError::DeviceNotFound)?;
+
+        let master_identity = MasterIdentity::new(Some(mnemonic.identity()?));
+        let forest_owner = self.forest.lock().expect("Poisoned Mutex").owner();
+        if forest_owner.0 != master_identity.create_forest_identity(0)?.get_public_key() {
+            return Err(DeviceEnrollmentError::ForestOwnerMismatch);
+        }
+        let encryption_key_index = forest_metadata.encryption_key_index() + 1;
+        let encryption_keypair =
+            master_identity.create_forest_encryption_keypair(0, encryption_key_index)?;
+
+        self.catlib_service.revoke_device(
+            &self.forest,
+            device_pubkey,
+            encryption_key_index,
+            encryption_keypair.encode_pub(),
+        )?;
+        self.all_devices.retain(|name| *name != device_name);
+        Ok(())
+    }
+
     /// Returns vector of user's storage templates
     ///
     #[tracing::instrument(level = "debug", skip_all)]
@@ -168,6 +315,7 @@ All devices:
 
 #[cfg(test)]
 mod tests {
+    use std::rc::Rc;
     use std::str::FromStr;
     use std::sync::{Arc, Mutex};
 
@@ -179,16 +327,33 @@ mod tests {
     use wildland_corex::catlib_service::entities::ForestManifest;
     use wildland_corex::catlib_service::error::CatlibError;
     use wildland_corex::catlib_service::{CatLibService, DeviceMetadata, ForestMetaData};
-    use wildland_corex::{SigningKeypair, WildlandIdentity};
+    use wildland_corex::container_manager::ContainerManager;
+    use wildland_corex::{
+        EncryptingKeypair,
+        LocalSecureStorage,
+        LssService,
+        SigningKeypair,
+        SymmetricKey,
+        WildlandIdentity,
+    };
 
     use super::CargoUser;
     use crate::api::config::FoundationStorageApiConfig;
-    use crate::api::utils::test::catlib_service;
+    use crate::api::device::{
+        parse_device_enrollment_request,
+        parse_device_enrollment_response,
+        DeviceEnrollmentRequest,
+    };
+    use crate::api::user::MnemonicPayload;
+    use crate::api::utils::test::{catlib_service, lss_stub};
+    use crate::errors::container::{ContainerMountError, ContainerUnmountError};
+    use crate::errors::device::DeviceEnrollmentError;
     use crate::templates::foundation_storage::FoundationStorageTemplate;
 
     #[fixture]
     fn setup(
         catlib_service: CatLibService,
+        lss_stub: &'static dyn LocalSecureStorage,
     ) -> (CargoUser, CatLibService, Arc<Mutex<dyn ForestManifest>>) {
         let this_dev_name = "My device".to_string();
 
@@ -207,16 +372,26 @@ mod tests {
                 }]),
             )
             .unwrap();
+        let lss_service = LssService::new(lss_stub);
+        let mut registry = lss_service.get_forest_registry().unwrap();
+        registry.add(5, "work".to_owned());
+        lss_service.save_forest_registry(&registry).unwrap();
+        lss_service.save_identity(&forest_identity).unwrap();
+        lss_service
+            .save_forest_data_key(5, &SymmetricKey::from_bytes([6; 32]))
+            .unwrap();
 
         let cargo_user = CargoUser::new(
             this_dev_name.clone(),
             vec![this_dev_name],
             forest.clone(),
             catlib_service.clone(),
+            lss_service,
             &FoundationStorageApiConfig {
                 evs_url: mockito::server_url(),
                 sc_url: "".to_string(),
             },
+            Rc::new(ContainerManager::default()),
         );
 
         (cargo_user, catlib_service, forest)
@@ -412,4 +587,149 @@ mod tests {
             Err(CatlibError::NoRecordsFound)
         ));
     }
+
+    #[rstest]
+    fn test_mounting_created_container(
+        setup: (CargoUser, CatLibService, Arc<Mutex<dyn ForestManifest>>),
+    ) {
+        // given setup
+        let (cargo_user, _catlib_service, _forest) = setup;
+
+        // when a container is created
+        let storage_template = FoundationStorageTemplate::new(
+            Uuid::new_v4(),
+            "cred_id".to_owned(),
+            "cred_secret".to_owned(),
+            "some url".to_owned(),
+        )
+        .try_into()
+        .unwrap();
+        let container = cargo_user
+            .create_container(
+                "new container".to_string(),
+                &storage_template,
+                "/some/path".to_owned(),
+            )
+            .unwrap();
+
+        // and mounted
+        cargo_user.mount(&container).unwrap();
+
+        // then it is listed as mounted
+        let container_uuid = container.lock().unwrap().uuid();
+        let mounted_containers = cargo_user.get_mounted_containers();
+        assert_eq!(mounted_containers.len(), 1);
+        assert_eq!(mounted_containers[0].lock().unwrap().uuid(), container_uuid);
+
+        // and it cannot be mounted twice
+        assert_eq!(
+            cargo_user.mount(&container).unwrap_err(),
+            ContainerMountError::ContainerAlreadyMounted
+        );
+
+        // and after unmounting it is not listed anymore
+        cargo_user.unmount(&container).unwrap();
+        assert!(cargo_user.get_mounted_containers().is_empty());
+        assert_eq!(
+            cargo_user.unmount(&container).unwrap_err(),
+            ContainerUnmountError::ContainerNotMounted
+        );
+    }
+
+    #[rstest]
+    fn test_enrolling_device(setup: (CargoUser, CatLibService, Arc<Mutex<dyn ForestManifest>>)) {
+        // given setup
+        let (mut cargo_user, catlib_service, forest) = setup;
+
+        // when a new device is enrolled
+        let device_keypair = EncryptingKeypair::new();
+        let request = DeviceEnrollmentRequest::new(
+            "New device".to_owned(),
+            [5; 32],
+            device_keypair.encode_pub(),
+        );
+        let response = cargo_user.enroll_device(&request).unwrap();
+
+        // then it is listed among user's devices
+        assert_eq!(
+            cargo_user.all_devices(),
+            ["My device".to_owned(), "New device".to_owned()]
+        );
+        let forest_metadata = catlib_service.get_parsed_forest_metadata(&forest).unwrap();
+        assert_eq!(
+            forest_metadata.get_device_metadata([5; 32]).unwrap().name,
+            "New device"
+        );
+
+        // and it is one of the forest signers
+        assert!(forest
+            .lock()
+            .unwrap()
+            .signers()
+            .unwrap()
+            .contains(&[5; 32].into()));
+
+        // and the request and response can be passed as strings
+        assert_eq!(
+            parse_device_enrollment_request(request.stringify()).unwrap(),
+            request
+        );
+        let response = parse_device_enrollment_response(response.stringify()).unwrap();
+
+        // and only the new device can open the forest keys sealed in the response
+        let keys = response.open(&device_keypair).unwrap();
+        assert_eq!(keys.forest_index, 5);
+        assert_eq!(keys.forest_name, "work");
+        assert_eq!(
+            keys.forest_keypair,
+            SigningKeypair::try_from_bytes_slices([1; 32], [2; 32]).unwrap()
+        );
+        assert_eq!(keys.data_key, SymmetricKey::from_bytes([6; 32]));
+        assert!(response.open(&EncryptingKeypair::new()).is_err());
+
+        // and it can not be enrolled twice
+        assert_eq!(
+            cargo_user.enroll_device(&request).unwrap_err(),
+            DeviceEnrollmentError::DeviceAlreadyEnrolled
+        );
+    }
+
+    #[rstest]
+    fn test_revoking_device_requires_forest_owner_mnemonic(
+        setup: (CargoUser, CatLibService, Arc<Mutex<dyn ForestManifest>>),
+    ) {
+        // given setup with enrolled device
+        let (mut cargo_user, _catlib_service, _forest) = setup;
+        let request = DeviceEnrollmentRequest::new(
+            "New device".to_owned(),
+            [5; 32],
+            EncryptingKeypair::new().encode_pub(),
+        );
+        cargo_user.enroll_device(&request).unwrap();
+        let mnemonic = MnemonicPayload::from(wildland_corex::generate_random_mnemonic().unwrap());
+
+        // then this device can not be revoked
+        assert_eq!(
+            cargo_user
+                .revoke_device("My device".to_owned(), &mnemonic)
+                .unwrap_err(),
+            DeviceEnrollmentError::CannotRevokeThisDevice
+        );
+
+        // and unknown device can not be revoked
+        assert_eq!(
+            cargo_user
+                .revoke_device("Unknown device".to_owned(), &mnemonic)
+                .unwrap_err(),
+            DeviceEnrollmentError::DeviceNotFound
+        );
+
+        // and the device can not be revoked with mnemonic of another user
+        assert_eq!(
+            cargo_user
+                .revoke_device("New device".to_owned(), &mnemonic)
+                .unwrap_err(),
+            DeviceEnrollmentError::ForestOwnerMismatch
+        );
+    }
 }
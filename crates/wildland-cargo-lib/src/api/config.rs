@@ -28,7 +28,9 @@
 //!     "log_file_path": "cargo_lib_log",
 //!     "log_file_rotate_directory": ".",
 //!     "evs_url": "some_url",
-//!     "sc_url": "some_url"
+//!     "sc_url": "some_url",
+//!     "catlib_backend": "sqlite",
+//!     "catlib_sync_dir": "/mnt/wildland"
 //! }"#;
 //!
 //! let _  = parse_config(config_json.as_bytes().to_vec()).unwrap();
@@ -44,6 +46,7 @@ use serde::de::{Error, Unexpected};
 use serde::{Deserialize, Deserializer};
 use thiserror::Error;
 use tracing::Level;
+pub use wildland_catlib::CatLibBackend;
 
 const DEV_DEFAULT_EVS_URL: &str = "https://evs.cargo.wildland.dev/";
 const DEV_DEFAULT_SC_URL: &str = "https://storage-controller.cargo.wildland.dev/";
@@ -90,6 +93,9 @@ pub trait CargoCfgProvider {
     fn get_oslog_subsystem(&self) -> Option<String>;
 
     fn get_foundation_cloud_env_mode(&self) -> FoundationCloudMode;
+    fn get_catlib_backend(&self) -> CatLibBackend;
+    /// Directory shared by devices of the user, see [`CatLibConfig::sync_dir`]
+    fn get_catlib_sync_dir(&self) -> Option<String>;
 }
 
 #[derive(PartialEq, Eq, Error, Debug, Clone)]
@@ -122,6 +128,19 @@ fn default_sc_url() -> String {
     DEV_DEFAULT_SC_URL.to_owned()
 }
 
+/// Configuration of the catalog ([`wildland_catlib::CatLib`]) database.
+#[derive(Debug, Default, Deserialize, Clone, PartialEq, Eq)]
+pub struct CatLibConfig {
+    /// Database backend storing the catalog, `rustbreak` (default) or `sqlite`.
+    #[serde(default)]
+    pub catlib_backend: CatLibBackend,
+    /// Directory shared by devices of the user (e.g. synchronized by an external tool or mounted
+    /// from a network drive), through which they exchange changes of their forests. Forests are
+    /// not synchronized without it.
+    #[serde(default)]
+    pub catlib_sync_dir: Option<PathBuf>,
+}
+
 fn bool_default_as_true() -> bool {
     true
 }
@@ -288,6 +307,8 @@ pub struct CargoConfig {
     pub fsa_config: FoundationStorageApiConfig,
     #[serde(flatten)]
     pub logger_config: LoggerConfig,
+    #[serde(flatten)]
+    pub catlib_config: CatLibConfig,
 }
 
 impl CargoConfig {
@@ -333,6 +354,10 @@ pub fn collect_config(
             oslog_subsystem: config_provider.get_oslog_subsystem(),
         },
         fsa_config: config_provider.get_foundation_cloud_env_mode().into(),
+        catlib_config: CatLibConfig {
+            catlib_backend: config_provider.get_catlib_backend(),
+            catlib_sync_dir: config_provider.get_catlib_sync_dir().map(PathBuf::from),
+        },
     })
 }
 
@@ -354,7 +379,13 @@ mod tests {
 
     use tracing::Level;
 
-    use super::{CargoConfig, FoundationStorageApiConfig, LoggerConfig};
+    use super::{
+        CargoConfig,
+        CatLibBackend,
+        CatLibConfig,
+        FoundationStorageApiConfig,
+        LoggerConfig,
+    };
 
     #[test]
     fn test_parsing_debug_config() {
@@ -365,7 +396,9 @@ mod tests {
             "log_file_path": "cargo_lib_log",
             "log_file_rotate_directory": ".",
             "evs_url": "some_url",
-            "sc_url": "some_url"
+            "sc_url": "some_url",
+            "catlib_backend": "sqlite",
+            "catlib_sync_dir": "/mnt/wildland"
         }"#;
 
         let config: CargoConfig = serde_json::from_str(config_str).unwrap();
@@ -384,7 +417,11 @@ mod tests {
                     log_file_path: PathBuf::from("cargo_lib_log"),
                     log_file_rotate_directory: PathBuf::from("."),
                     log_file_enabled: true,
-                }
+                },
+                catlib_config: CatLibConfig {
+                    catlib_backend: CatLibBackend::Sqlite,
+                    catlib_sync_dir: Some(PathBuf::from("/mnt/wildland")),
+                },
             }
         )
     }
@@ -413,7 +450,11 @@ mod tests {
                     log_file_path: LoggerConfig::default().log_file_path,
                     log_file_rotate_directory: LoggerConfig::default().log_file_rotate_directory,
                     log_file_enabled: false,
-                }
+                },
+                catlib_config: CatLibConfig {
+                    catlib_backend: CatLibBackend::Rustbreak,
+                    catlib_sync_dir: None,
+                },
             }
         )
     }
@@ -0,0 +1,133 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+use wildland_corex::catlib_service::entities::PubKey;
+use wildland_corex::catlib_service::DeviceMetadata;
+use wildland_corex::{EncryptingKeypair, SigningKeypair, SymmetricKey};
+use zeroize::Zeroizing;
+
+use crate::errors::device::DeviceEnrollmentError;
+
+/// Request of a new device to join the user's forest.
+///
+/// It is created on the new device with [`super::UserApi::request_device_enrollment`] and passed
+/// (e.g. as a QR code) to one of the already enrolled devices, which accepts it with
+/// [`super::cargo_user::CargoUser::enroll_device`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceEnrollmentRequest {
+    device_name: String,
+    pubkey: PubKey,
+    /// Public key the forest keys are sealed with for the new device
+    encryption_pubkey: String,
+}
+
+impl DeviceEnrollmentRequest {
+    pub(crate) fn new(device_name: String, pubkey: PubKey, encryption_pubkey: String) -> Self {
+        Self {
+            device_name,
+            pubkey,
+            encryption_pubkey,
+        }
+    }
+
+    /// Returns string representation of the request, to be passed to an enrolled device.
+    pub fn stringify(&self) -> String {
+        serde_json::to_string(self).expect("Serialization of enrollment request can't fail")
+    }
+
+    pub fn device_name(&self) -> String {
+        self.device_name.clone()
+    }
+
+    pub(crate) fn device_metadata(&self) -> DeviceMetadata {
+        DeviceMetadata {
+            name: self.device_name.clone(),
+            pubkey: self.pubkey,
+        }
+    }
+
+    pub(crate) fn encryption_pubkey(&self) -> &str {
+        &self.encryption_pubkey
+    }
+}
+
+/// Parses request serialized with [`DeviceEnrollmentRequest::stringify`].
+pub fn parse_device_enrollment_request(
+    request: String,
+) -> Result<DeviceEnrollmentRequest, DeviceEnrollmentError> {
+    serde_json::from_str(&request)
+        .map_err(|e| DeviceEnrollmentError::MalformedRequest(e.to_string()))
+}
+
+/// Keys of the forest the device is enrolled in, sent by the enrolling device.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct EnrolledForestKeys {
+    pub(crate) forest_index: u64,
+    pub(crate) forest_name: String,
+    pub(crate) forest_keypair: SigningKeypair,
+    pub(crate) data_key: SymmetricKey,
+}
+
+/// Response of an enrolled device to [`DeviceEnrollmentRequest`].
+///
+/// It is created with [`super::cargo_user::CargoUser::enroll_device`] and carries the forest keys
+/// sealed with the encryption key of the new device, so only the new device can use them in
+/// [`super::UserApi::complete_device_enrollment`]. The mnemonic is not needed on the new device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceEnrollmentResponse {
+    sealed_keys: Vec<u8>,
+}
+
+impl DeviceEnrollmentResponse {
+    pub(crate) fn seal(
+        keys: &EnrolledForestKeys,
+        request: &DeviceEnrollmentRequest,
+    ) -> Result<Self, DeviceEnrollmentError> {
+        let recipient = EncryptingKeypair::decode_pub(request.encryption_pubkey())?;
+        let keys = Zeroizing::new(
+            serde_json::to_vec(keys).expect("Serialization of forest keys can't fail"),
+        );
+        Ok(Self {
+            sealed_keys: EncryptingKeypair::seal(&recipient, &keys),
+        })
+    }
+
+    pub(crate) fn open(
+        &self,
+        keypair: &EncryptingKeypair,
+    ) -> Result<EnrolledForestKeys, DeviceEnrollmentError> {
+        let keys = Zeroizing::new(keypair.open(&self.sealed_keys)?);
+        serde_json::from_slice(&keys)
+            .map_err(|e| DeviceEnrollmentError::MalformedResponse(e.to_string()))
+    }
+
+    /// Returns string representation of the response, to be passed to the new device.
+    pub fn stringify(&self) -> String {
+        hex::encode(&self.sealed_keys)
+    }
+}
+
+/// Parses response serialized with [`DeviceEnrollmentResponse::stringify`].
+pub fn parse_device_enrollment_response(
+    response: String,
+) -> Result<DeviceEnrollmentResponse, DeviceEnrollmentError> {
+    Ok(DeviceEnrollmentResponse {
+        sealed_keys: hex::decode(response)
+            .map_err(|e| DeviceEnrollmentError::MalformedResponse(e.to_string()))?,
+    })
+}
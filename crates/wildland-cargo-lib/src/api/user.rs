@@ -15,14 +15,23 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use wildland_corex::{utils, MnemonicPhrase};
+use wildland_corex::{utils, CryptoError, Identity, MnemonicPhrase};
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 use super::cargo_user::CargoUser;
+use super::device::{DeviceEnrollmentRequest, DeviceEnrollmentResponse};
+use crate::errors::device::DeviceEnrollmentError;
+use crate::errors::forest::ForestManagementError;
+use crate::errors::profile::ProfileBundleError;
 use crate::errors::{CreateMnemonicError, UserCreationError, UserRetrievalError};
 use crate::user::{generate_random_mnemonic, CreateUserInput, UserService};
 
-#[derive(Clone)]
-pub struct MnemonicPayload(MnemonicPhrase);
+/// Mnemonic along with optional BIP39 passphrase ("25th word") protecting it.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct MnemonicPayload {
+    words: MnemonicPhrase,
+    passphrase: Option<String>,
+}
 
 /// Wrapper to check the mnemonic.
 /// Accepts string. Returns Ok if the mnemonic is valid or Err otherwise
@@ -35,18 +44,34 @@ pub fn check_phrase_mnemonic(phrase: &str) -> Result<(), CreateMnemonicError> {
 }
 
 impl MnemonicPayload {
+    /// Returns the same mnemonic protected with the passphrase. Forgetting the passphrase means
+    /// losing the identity, as any passphrase derives some valid identity.
+    pub fn with_passphrase(&self, passphrase: String) -> MnemonicPayload {
+        Self {
+            words: self.words.clone(),
+            passphrase: Some(passphrase),
+        }
+    }
+
+    pub(crate) fn identity(&self) -> Result<Identity, CryptoError> {
+        Identity::from_mnemonic_with_passphrase(&self.words, self.passphrase.as_deref())
+    }
+
     pub fn stringify(&self) -> String {
-        self.0.join(" ")
+        self.words.join(" ")
     }
 
     pub fn get_vec(&self) -> Vec<String> {
-        self.0.clone().into()
+        self.words.clone().into()
     }
 }
 
 impl From<MnemonicPhrase> for MnemonicPayload {
-    fn from(mnemonic: MnemonicPhrase) -> Self {
-        Self(mnemonic)
+    fn from(words: MnemonicPhrase) -> Self {
+        Self {
+            words,
+            passphrase: None,
+        }
     }
 }
 
@@ -64,6 +89,19 @@ impl From<MnemonicPhrase> for MnemonicPayload {
 /// - saving forest uuid (CatLib key) in LSS
 /// - saving forest and device identities (keypairs) in LSS
 ///
+/// The user can be moved to another machine with a bundle created by [`UserApi::export_profile`]
+/// and restored there with [`UserApi::import_profile`].
+///
+/// Another device joins existing user's forest in the following steps:
+/// - the new device creates its identity with [`UserApi::request_device_enrollment`],
+/// - one of the enrolled devices accepts the request with [`CargoUser::enroll_device`], passes
+///   the response with the forest keys sealed for the new device back to it and sends the forest
+///   to other devices with [`CargoUser::sync_forest`],
+/// - the new device receives the forest and retrieves the user with
+///   [`UserApi::complete_device_enrollment`], without knowing the mnemonic.
+///
+/// Devices keep exchanging changes of forests with [`UserApi::sync_forests`].
+///
 #[derive(Clone)]
 pub struct UserApi {
     user_service: UserService,
@@ -93,7 +131,7 @@ impl UserApi {
     ) -> Result<MnemonicPayload, CreateMnemonicError> {
         tracing::trace!("creating mnemonic from vec");
         check_phrase_mnemonic(words.join(" ").as_str())?;
-        Ok(MnemonicPayload(
+        Ok(MnemonicPayload::from(
             MnemonicPhrase::try_from(words)
                 .map_err(|_| CreateMnemonicError::InvalidMnemonicWords)?,
         ))
@@ -110,7 +148,7 @@ impl UserApi {
     ) -> Result<MnemonicPayload, CreateMnemonicError> {
         tracing::trace!("creating mnemonic from vec");
         check_phrase_mnemonic(words.as_str())?;
-        Ok(MnemonicPayload(
+        Ok(MnemonicPayload::from(
             MnemonicPhrase::try_from(words.split(' ').map(|w| w.to_owned()).collect::<Vec<_>>())
                 .map_err(|_| CreateMnemonicError::InvalidMnemonicWords)?,
         ))
@@ -130,6 +168,8 @@ impl UserApi {
             .create_user(CreateUserInput::Entropy(entropy), device_name)
     }
 
+    /// Creates user from mnemonic. Identity of the user is derived with the passphrase of the
+    /// mnemonic, if any (see [`MnemonicPayload::with_passphrase`]).
     #[tracing::instrument(level = "debug", skip_all)]
     pub fn create_user_from_mnemonic(
         &self,
@@ -138,7 +178,7 @@ impl UserApi {
     ) -> Result<CargoUser, UserCreationError> {
         tracing::debug!("creating new user");
         self.user_service.create_user(
-            CreateUserInput::Mnemonic(Box::new(mnemonic.0.clone())),
+            CreateUserInput::Mnemonic(Box::new(mnemonic.clone())),
             device_name,
         )
     }
@@ -153,20 +193,164 @@ impl UserApi {
             None => Err(UserRetrievalError::UserNotFound),
         }
     }
+
+    /// Creates identity of this device and returns a request to be accepted by one of the devices
+    /// already enrolled in the user's forest.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn request_device_enrollment(
+        &self,
+        device_name: String,
+    ) -> Result<DeviceEnrollmentRequest, DeviceEnrollmentError> {
+        self.user_service.request_device_enrollment(device_name)
+    }
+
+    /// Finishes enrollment of this device with the forest keys sealed for it in the response of
+    /// the enrolling device. The forest is received from other devices, unless it is already
+    /// available in CatLib of this device, and it has to list this device among the enrolled ones.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn complete_device_enrollment(
+        &self,
+        response: &DeviceEnrollmentResponse,
+    ) -> Result<CargoUser, DeviceEnrollmentError> {
+        self.user_service.complete_device_enrollment(response)
+    }
+
+    /// Exchanges changes of all forests of the user with other devices through the directory set
+    /// in [`crate::api::config::CatLibConfig::catlib_sync_dir`].
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn sync_forests(&self) -> Result<(), ForestManagementError> {
+        self.user_service.sync_forests()
+    }
+
+    /// Exports keys and manifests of the user into a bundle encrypted with a key derived from the
+    /// user's mnemonic.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn export_profile(
+        &self,
+        mnemonic: &MnemonicPayload,
+    ) -> Result<Vec<u8>, ProfileBundleError> {
+        self.user_service.export_profile(mnemonic)
+    }
+
+    /// Restores the user from a bundle created with [`UserApi::export_profile`].
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn import_profile(
+        &self,
+        mnemonic: &MnemonicPayload,
+        bundle: Vec<u8>,
+    ) -> Result<CargoUser, ProfileBundleError> {
+        self.user_service.import_profile(mnemonic, &bundle)
+    }
+
+    /// Creates an additional forest (e.g. "work") with its own containers and devices. The
+    /// mnemonic is needed to derive the forest keys.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn create_forest(
+        &self,
+        mnemonic: &MnemonicPayload,
+        name: String,
+    ) -> Result<(), ForestManagementError> {
+        self.user_service.create_forest(mnemonic, name)
+    }
+
+    /// Returns names of all forests of the user, starting with the default one.
+    pub fn list_forests(&self) -> Result<Vec<String>, ForestManagementError> {
+        self.user_service.list_forests()
+    }
+
+    pub fn active_forest(&self) -> Result<String, ForestManagementError> {
+        self.user_service.active_forest()
+    }
+
+    /// Makes the named forest active and returns the user operating on it. The choice is kept
+    /// in LSS, so [`UserApi::get_user`] returns the same forest after restart.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn switch_forest(&self, name: String) -> Result<CargoUser, ForestManagementError> {
+        self.user_service.switch_forest(&name)
+    }
+
+    /// Deletes the named forest along with its containers. Neither the default nor the active
+    /// forest can be deleted.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn delete_forest(&self, name: String) -> Result<(), ForestManagementError> {
+        self.user_service.delete_forest(&name)
+    }
+
+    /// Derives data keys of the user's forests which are missing in LSS, e.g. for users created
+    /// before the data of containers was encrypted. [`UserApi::get_user`] fails with
+    /// [`UserRetrievalError::ForestDataKeyNotFound`] until the keys are restored.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn restore_forest_data_keys(
+        &self,
+        mnemonic: &MnemonicPayload,
+    ) -> Result<(), ForestManagementError> {
+        self.user_service.restore_forest_data_keys(mnemonic)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::rc::Rc;
+
     use rstest::rstest;
-    use wildland_corex::catlib_service::CatLibService;
-    use wildland_corex::{LocalSecureStorage, LssService};
+    use wildland_catlib::{CatLib, CatLibBackend};
+    use wildland_corex::catlib_service::entities::Identity as CatlibIdentity;
+    use wildland_corex::catlib_service::error::CatlibError;
+    use wildland_corex::catlib_service::interface::CatLib as ICatLib;
+    use wildland_corex::catlib_service::{CatLibService, ForestMetaData};
+    use wildland_corex::container_manager::ContainerManager;
+    use wildland_corex::{
+        CryptoError,
+        LocalSecureStorage,
+        LssService,
+        MasterIdentity,
+        StorageTemplate,
+    };
 
     use super::UserApi;
     use crate::api::config::FoundationStorageApiConfig;
     use crate::api::utils::test::{catlib_service, lss_stub};
+    use crate::errors::device::DeviceEnrollmentError;
+    use crate::errors::forest::ForestManagementError;
+    use crate::errors::profile::ProfileBundleError;
     use crate::errors::UserRetrievalError;
+    use crate::profile_bundle::ProfileBundle;
     use crate::user::UserService;
 
+    /// Creates API of a device sharing CatLib database with other devices.
+    fn device_user_api(lss: &'static dyn LocalSecureStorage, catlib_path: &Path) -> UserApi {
+        let catlib = CatLib::with_backend(CatLibBackend::Sqlite, catlib_path.to_owned()).unwrap();
+        UserApi::new(UserService::new(
+            LssService::new(lss),
+            CatLibService::new(Rc::new(catlib)),
+            FoundationStorageApiConfig::default(),
+            Rc::new(ContainerManager::default()),
+        ))
+    }
+
+    /// Creates API of a device with its own CatLib database, synchronized with other devices
+    /// through a directory in `dir`.
+    fn synced_device_user_api(
+        lss: &'static dyn LocalSecureStorage,
+        dir: &Path,
+        device_name: &str,
+    ) -> UserApi {
+        let catlib = CatLib::with_backend(
+            CatLibBackend::Sqlite,
+            dir.join(format!("{device_name}.sqlite")),
+        )
+        .unwrap()
+        .with_sync_dir(dir.join("sync"));
+        UserApi::new(UserService::new(
+            LssService::new(lss),
+            CatLibService::new(Rc::new(catlib)),
+            FoundationStorageApiConfig::default(),
+            Rc::new(ContainerManager::default()),
+        ))
+    }
+
     #[rstest]
     fn create_mnemonic_from_string_with_valid_words_should_succeed(
         catlib_service: CatLibService,
@@ -176,6 +360,7 @@ mod tests {
             LssService::new(lss_stub),
             catlib_service,
             FoundationStorageApiConfig::default(),
+            Rc::new(ContainerManager::default()),
         ));
         let words = "wise exile kingdom cabbage improve also ridge fortune when joke market argue";
 
@@ -191,6 +376,7 @@ mod tests {
             LssService::new(lss_stub),
             catlib_service,
             FoundationStorageApiConfig::default(),
+            Rc::new(ContainerManager::default()),
         ));
         let words =
             "wise exile kingdom cabbage improve also ridge fortune when joke market invalid_word";
@@ -207,6 +393,7 @@ mod tests {
             LssService::new(lss_stub),
             catlib_service,
             FoundationStorageApiConfig::default(),
+            Rc::new(ContainerManager::default()),
         ));
         let words = "wise exile kingdom cabbage improve also ridge fortune when joke market argue";
 
@@ -224,6 +411,7 @@ mod tests {
             LssService::new(lss_stub),
             catlib_service,
             FoundationStorageApiConfig::default(),
+            Rc::new(ContainerManager::default()),
         ));
         let words =
             "wise exile kingdom cabbage improve also ridge fortune when joke market invalid_word";
@@ -243,6 +431,7 @@ mod tests {
             lss_service,
             catlib_service,
             FoundationStorageApiConfig::default(),
+            Rc::new(ContainerManager::default()),
         );
         let user_api = UserApi::new(user_service);
 
@@ -263,6 +452,7 @@ mod tests {
             lss_service,
             catlib_service,
             FoundationStorageApiConfig::default(),
+            Rc::new(ContainerManager::default()),
         );
         let user_api = UserApi::new(user_service);
 
@@ -286,6 +476,7 @@ mod tests {
             lss_service,
             catlib_service,
             FoundationStorageApiConfig::default(),
+            Rc::new(ContainerManager::default()),
         );
         let user_api = UserApi::new(user_service);
 
@@ -299,4 +490,410 @@ mod tests {
         assert_eq!(user.this_device(), device_name);
         assert_eq!(user.all_devices(), [device_name]);
     }
+
+    #[rstest]
+    fn device_should_be_enrolled_and_revoked(
+        lss_stub: &'static dyn LocalSecureStorage,
+        #[from(lss_stub)] new_device_lss: &'static dyn LocalSecureStorage,
+        #[from(lss_stub)] other_device_lss: &'static dyn LocalSecureStorage,
+    ) {
+        let catlib_path = tempfile::tempdir()
+            .unwrap()
+            .into_path()
+            .join("catlib.sqlite");
+        let user_api = device_user_api(lss_stub, &catlib_path);
+        let new_device_api = device_user_api(new_device_lss, &catlib_path);
+        let mnemonic = user_api.generate_mnemonic().unwrap();
+        let mut user = user_api
+            .create_user_from_mnemonic(&mnemonic, "laptop".to_owned())
+            .unwrap();
+
+        let request = new_device_api
+            .request_device_enrollment("phone".to_owned())
+            .unwrap();
+        let response = user.enroll_device(&request).unwrap();
+        // keys are sealed for the requesting device only
+        let other_device_api = device_user_api(other_device_lss, &catlib_path);
+        other_device_api
+            .request_device_enrollment("tablet".to_owned())
+            .unwrap();
+        assert_eq!(
+            other_device_api
+                .complete_device_enrollment(&response)
+                .unwrap_err(),
+            DeviceEnrollmentError::CryptoError(CryptoError::DecryptionError)
+        );
+        let new_device_user = new_device_api
+            .complete_device_enrollment(&response)
+            .unwrap();
+
+        assert_eq!(new_device_user.this_device(), "phone");
+        assert_eq!(new_device_user.all_devices(), ["laptop", "phone"]);
+        assert_eq!(new_device_api.get_user().unwrap().this_device(), "phone");
+
+        user.revoke_device("phone".to_owned(), &mnemonic).unwrap();
+
+        assert_eq!(user.all_devices(), ["laptop"]);
+        assert_eq!(
+            new_device_api.get_user().unwrap_err(),
+            UserRetrievalError::DeviceMetadataNotFound
+        );
+
+        // the revoked device is not a signer of the forest anymore
+        let master_identity = MasterIdentity::new(Some(mnemonic.identity().unwrap()));
+        let forest_owner = master_identity
+            .create_forest_identity(0)
+            .unwrap()
+            .get_public_key();
+        let forest = CatLib::with_backend(CatLibBackend::Sqlite, catlib_path)
+            .unwrap()
+            .find_forest(&CatlibIdentity(forest_owner))
+            .unwrap();
+        let mut forest = forest.lock().unwrap();
+        assert!(!forest
+            .signers()
+            .unwrap()
+            .contains(&CatlibIdentity(request.device_metadata().pubkey)));
+
+        // and the forest encryption keypair is rotated
+        let forest_metadata: ForestMetaData =
+            serde_json::from_slice(&forest.data().unwrap()).unwrap();
+        assert_eq!(forest_metadata.encryption_key_index(), 1);
+        assert_eq!(
+            forest_metadata.encryption_pubkey().unwrap(),
+            master_identity
+                .create_forest_encryption_keypair(0, 1)
+                .unwrap()
+                .encode_pub()
+        );
+        drop(forest);
+
+        // so manifests it keeps creating are rejected
+        let template = StorageTemplate::try_new(
+            "FoundationStorage",
+            HashMap::from([("name", "{{ CONTAINER_NAME }}")]),
+        )
+        .unwrap();
+        assert_eq!(
+            new_device_user
+                .create_container("stolen".to_owned(), &template, "/stolen".to_owned())
+                .unwrap_err(),
+            CatlibError::InvalidRecordSignature
+        );
+        assert_eq!(
+            user.get_containers().unwrap_err(),
+            CatlibError::NoRecordsFound
+        );
+    }
+
+    #[rstest]
+    fn device_should_be_enrolled_through_synchronization(
+        lss_stub: &'static dyn LocalSecureStorage,
+        #[from(lss_stub)] new_device_lss: &'static dyn LocalSecureStorage,
+    ) {
+        let dir = tempfile::tempdir().unwrap().into_path();
+        let user_api = synced_device_user_api(lss_stub, &dir, "laptop");
+        let new_device_api = synced_device_user_api(new_device_lss, &dir, "phone");
+        let mnemonic = user_api.generate_mnemonic().unwrap();
+        let mut user = user_api
+            .create_user_from_mnemonic(&mnemonic, "laptop".to_owned())
+            .unwrap();
+        user.sync_forest().unwrap();
+
+        let request = new_device_api
+            .request_device_enrollment("phone".to_owned())
+            .unwrap();
+        let response = user.enroll_device(&request).unwrap();
+        // the forest received so far does not list the new device
+        assert!(matches!(
+            new_device_api
+                .complete_device_enrollment(&response)
+                .unwrap_err(),
+            DeviceEnrollmentError::UserRetrievalError(UserRetrievalError::ForestNotFound(_))
+        ));
+        user.sync_forest().unwrap();
+        let mut new_device_user = new_device_api
+            .complete_device_enrollment(&response)
+            .unwrap();
+        assert_eq!(new_device_user.all_devices(), ["laptop", "phone"]);
+
+        // containers created afterwards are synchronized as well
+        let template = StorageTemplate::try_new(
+            "FoundationStorage",
+            HashMap::from([("name", "{{ CONTAINER_NAME }}")]),
+        )
+        .unwrap();
+        user.create_container("photos".to_owned(), &template, "/photos".to_owned())
+            .unwrap();
+        user.sync_forest().unwrap();
+        new_device_user.sync_forest().unwrap();
+        let containers = new_device_user.get_containers().unwrap();
+        assert_eq!(containers.len(), 1);
+        assert_eq!(
+            containers[0].lock().unwrap().name().unwrap(),
+            "photos".to_owned()
+        );
+
+        // and revocation reaches the revoked device
+        user.revoke_device("phone".to_owned(), &mnemonic).unwrap();
+        user.sync_forest().unwrap();
+        new_device_api.sync_forests().unwrap();
+        assert_eq!(
+            new_device_api.get_user().unwrap_err(),
+            UserRetrievalError::DeviceMetadataNotFound
+        );
+    }
+
+    #[rstest]
+    fn forests_created_on_different_devices_should_not_share_index(
+        lss_stub: &'static dyn LocalSecureStorage,
+        #[from(lss_stub)] new_device_lss: &'static dyn LocalSecureStorage,
+    ) {
+        let catlib_path = tempfile::tempdir()
+            .unwrap()
+            .into_path()
+            .join("catlib.sqlite");
+        let user_api = device_user_api(lss_stub, &catlib_path);
+        let new_device_api = device_user_api(new_device_lss, &catlib_path);
+        let mnemonic = user_api.generate_mnemonic().unwrap();
+        let mut user = user_api
+            .create_user_from_mnemonic(&mnemonic, "laptop".to_owned())
+            .unwrap();
+        let request = new_device_api
+            .request_device_enrollment("phone".to_owned())
+            .unwrap();
+        let response = user.enroll_device(&request).unwrap();
+        new_device_api
+            .complete_device_enrollment(&response)
+            .unwrap();
+
+        user_api
+            .create_forest(&mnemonic, "work".to_owned())
+            .unwrap();
+        new_device_api
+            .create_forest(&mnemonic, "personal".to_owned())
+            .unwrap();
+        user_api
+            .create_forest(&mnemonic, "private".to_owned())
+            .unwrap();
+
+        assert_eq!(
+            user_api
+                .switch_forest("work".to_owned())
+                .unwrap()
+                .all_devices(),
+            ["laptop"]
+        );
+        assert_eq!(
+            new_device_api
+                .switch_forest("personal".to_owned())
+                .unwrap()
+                .all_devices(),
+            ["phone"]
+        );
+        assert_eq!(
+            user_api
+                .switch_forest("private".to_owned())
+                .unwrap()
+                .all_devices(),
+            ["laptop"]
+        );
+    }
+
+    #[rstest]
+    fn profile_should_be_restored_from_exported_bundle(
+        lss_stub: &'static dyn LocalSecureStorage,
+        #[from(lss_stub)] new_machine_lss: &'static dyn LocalSecureStorage,
+    ) {
+        let dir = tempfile::tempdir().unwrap().into_path();
+        let user_api = device_user_api(lss_stub, &dir.join("catlib.sqlite"));
+        let mnemonic = user_api.generate_mnemonic().unwrap();
+        user_api
+            .create_user_from_mnemonic(&mnemonic, "laptop".to_owned())
+            .unwrap();
+        user_api
+            .create_forest(&mnemonic, "work".to_owned())
+            .unwrap();
+        let other_mnemonic = user_api.generate_mnemonic().unwrap();
+        assert_eq!(
+            user_api.export_profile(&other_mnemonic).unwrap_err(),
+            ProfileBundleError::ForestOwnerMismatch
+        );
+        let bundle = user_api.export_profile(&mnemonic).unwrap();
+
+        let new_machine_api = device_user_api(new_machine_lss, &dir.join("other.sqlite"));
+        assert_eq!(
+            new_machine_api
+                .import_profile(&other_mnemonic, bundle.clone())
+                .unwrap_err(),
+            ProfileBundleError::DecryptionError
+        );
+
+        // keys are not left in LSS if manifests of the bundle can not be imported
+        let backup_key = MasterIdentity::new(Some(mnemonic.identity().unwrap()))
+            .create_backup_key()
+            .unwrap();
+        let mut malformed = ProfileBundle::open(&bundle, &backup_key).unwrap();
+        malformed.forest = b"malformed".to_vec();
+        assert!(matches!(
+            new_machine_api
+                .import_profile(&mnemonic, malformed.seal(&backup_key))
+                .unwrap_err(),
+            ProfileBundleError::CatlibError(_)
+        ));
+        assert!(new_machine_lss.is_empty().unwrap());
+
+        let user = new_machine_api.import_profile(&mnemonic, bundle).unwrap();
+
+        assert_eq!(user.this_device(), "laptop");
+        assert_eq!(user.all_devices(), ["laptop"]);
+        assert_eq!(new_machine_api.get_user().unwrap().this_device(), "laptop");
+        assert_eq!(
+            new_machine_api
+                .switch_forest("work".to_owned())
+                .unwrap()
+                .all_devices(),
+            ["laptop"]
+        );
+    }
+
+    #[rstest]
+    fn forest_data_key_of_legacy_user_should_be_restored(
+        lss_stub: &'static dyn LocalSecureStorage,
+    ) {
+        let dir = tempfile::tempdir().unwrap().into_path();
+        let user_api = device_user_api(lss_stub, &dir.join("catlib.sqlite"));
+        let mnemonic = user_api.generate_mnemonic().unwrap();
+        user_api
+            .create_user_from_mnemonic(&mnemonic, "laptop".to_owned())
+            .unwrap();
+        let data_key = LssService::new(lss_stub)
+            .get_default_forest_data_key()
+            .unwrap();
+        // users created before the data of containers was encrypted have no data keys
+        lss_stub
+            .remove("wildland.forest.0.data_key".to_owned())
+            .unwrap();
+
+        assert_eq!(
+            user_api.get_user().unwrap_err(),
+            UserRetrievalError::ForestDataKeyNotFound
+        );
+        assert_eq!(
+            user_api
+                .restore_forest_data_keys(&user_api.generate_mnemonic().unwrap())
+                .unwrap_err(),
+            ForestManagementError::ForestOwnerMismatch
+        );
+        user_api.restore_forest_data_keys(&mnemonic).unwrap();
+
+        assert_eq!(user_api.get_user().unwrap().this_device(), "laptop");
+        assert_eq!(
+            LssService::new(lss_stub)
+                .get_default_forest_data_key()
+                .unwrap(),
+            data_key
+        );
+    }
+
+    #[rstest]
+    fn user_should_be_bound_to_mnemonic_passphrase(lss_stub: &'static dyn LocalSecureStorage) {
+        let dir = tempfile::tempdir().unwrap().into_path();
+        let user_api = device_user_api(lss_stub, &dir.join("catlib.sqlite"));
+        let mnemonic = user_api.generate_mnemonic().unwrap();
+        let protected_mnemonic = mnemonic.with_passphrase("25th word".to_owned());
+        user_api
+            .create_user_from_mnemonic(&protected_mnemonic, "laptop".to_owned())
+            .unwrap();
+
+        assert_eq!(protected_mnemonic.stringify(), mnemonic.stringify());
+        assert_eq!(
+            user_api.export_profile(&mnemonic).unwrap_err(),
+            ProfileBundleError::ForestOwnerMismatch
+        );
+        assert_eq!(
+            user_api
+                .export_profile(&mnemonic.with_passphrase("other".to_owned()))
+                .unwrap_err(),
+            ProfileBundleError::ForestOwnerMismatch
+        );
+        assert!(user_api.export_profile(&protected_mnemonic).is_ok());
+    }
+
+    #[rstest]
+    fn forests_should_be_created_switched_and_deleted(lss_stub: &'static dyn LocalSecureStorage) {
+        let dir = tempfile::tempdir().unwrap().into_path();
+        let user_api = device_user_api(lss_stub, &dir.join("catlib.sqlite"));
+        let mnemonic = user_api.generate_mnemonic().unwrap();
+        user_api
+            .create_user_from_mnemonic(&mnemonic, "laptop".to_owned())
+            .unwrap();
+
+        assert_eq!(
+            user_api
+                .create_forest(&user_api.generate_mnemonic().unwrap(), "work".to_owned())
+                .unwrap_err(),
+            ForestManagementError::ForestOwnerMismatch
+        );
+        user_api
+            .create_forest(&mnemonic, "work".to_owned())
+            .unwrap();
+        user_api
+            .create_forest(&mnemonic, "personal".to_owned())
+            .unwrap();
+        assert_eq!(
+            user_api
+                .create_forest(&mnemonic, "work".to_owned())
+                .unwrap_err(),
+            ForestManagementError::ForestAlreadyExists("work".to_owned())
+        );
+        assert_eq!(
+            user_api.list_forests().unwrap(),
+            ["default", "work", "personal"]
+        );
+
+        let default_user = user_api.get_user().unwrap();
+        let template = StorageTemplate::try_new(
+            "FoundationStorage",
+            HashMap::from([("name", "{{ CONTAINER_NAME }}")]),
+        )
+        .unwrap();
+        let container = default_user
+            .create_container("photos".to_owned(), &template, "/photos".to_owned())
+            .unwrap();
+        default_user.mount(&container).unwrap();
+
+        let user = user_api.switch_forest("work".to_owned()).unwrap();
+        assert_eq!(user.all_devices(), ["laptop"]);
+        assert_eq!(user_api.active_forest().unwrap(), "work");
+        // containers of the previously active forest are unmounted
+        assert!(user.get_mounted_containers().is_empty());
+        assert_eq!(
+            user_api.delete_forest("work".to_owned()).unwrap_err(),
+            ForestManagementError::CannotDeleteActiveForest
+        );
+        assert_eq!(
+            user_api.delete_forest("default".to_owned()).unwrap_err(),
+            ForestManagementError::CannotDeleteDefaultForest
+        );
+
+        user_api.delete_forest("personal".to_owned()).unwrap();
+        assert_eq!(user_api.list_forests().unwrap(), ["default", "work"]);
+        assert_eq!(
+            user_api.switch_forest("personal".to_owned()).unwrap_err(),
+            ForestManagementError::ForestNotFound("personal".to_owned())
+        );
+        user_api.switch_forest("default".to_owned()).unwrap();
+        assert_eq!(user_api.active_forest().unwrap(), "default");
+
+        // the active forest is kept if the user can not be read from the other one
+        lss_stub
+            .remove("wildland.forest.1.data_key".to_owned())
+            .unwrap();
+        assert_eq!(
+            user_api.switch_forest("work".to_owned()).unwrap_err(),
+            ForestManagementError::UserRetrievalError(UserRetrievalError::ForestDataKeyNotFound)
+        );
+        assert_eq!(user_api.active_forest().unwrap(), "default");
+    }
 }
@@ -0,0 +1,81 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::rc::Rc;
+
+use anyhow::anyhow;
+use wildland_corex::container_manager::ContainerManager;
+use wildland_corex::{LssService, PathResolver, Storage, SymmetricKey};
+use wildland_dfs::encrypted::EncryptionKeyProvider;
+
+/// Provides DFS with per-container keys derived from the data key of the container's forest kept
+/// in LSS.
+pub(crate) struct ContainerKeyProvider {
+    lss_service: LssService,
+    container_manager: Rc<ContainerManager>,
+}
+
+impl ContainerKeyProvider {
+    pub(crate) fn new(lss_service: LssService, container_manager: Rc<ContainerManager>) -> Self {
+        Self {
+            lss_service,
+            container_manager,
+        }
+    }
+}
+
+impl EncryptionKeyProvider for ContainerKeyProvider {
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn container_key(&self, storage: &Storage) -> Result<SymmetricKey, anyhow::Error> {
+        let container_uuid = self
+            .container_manager
+            .container_uuid_by_storage(&storage.uuid())
+            .ok_or_else(|| {
+                anyhow!(
+                    "Storage {} does not belong to any mounted container",
+                    storage.uuid()
+                )
+            })?;
+        let container = self
+            .container_manager
+            .mounted_containers()
+            .into_iter()
+            .find(|container| container.lock().expect("Poisoned Mutex").uuid() == container_uuid)
+            .ok_or_else(|| anyhow!("Container {container_uuid} is not mounted"))?;
+        let forest_owner = container
+            .lock()
+            .expect("Poisoned Mutex")
+            .forest()?
+            .lock()
+            .expect("Poisoned Mutex")
+            .owner();
+        let forest_index = self
+            .lss_service
+            .find_forest_index(&forest_owner)?
+            .ok_or_else(|| anyhow!("Forest of container {container_uuid} not found in LSS"))?;
+        let forest_data_key = self
+            .lss_service
+            .get_forest_data_key(forest_index)?
+            .ok_or_else(|| {
+                anyhow!(
+                    "Data key of forest {forest_index} not found in LSS, restore it with \
+                     UserApi::restore_forest_data_keys"
+                )
+            })?;
+        Ok(forest_data_key.derive_subkey(container_uuid.as_bytes()))
+    }
+}
@@ -15,18 +15,4 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use thiserror::Error;
-
-#[derive(Error, Debug, Clone, PartialEq, Eq)]
-#[repr(C)]
-pub enum ContainerMountError {
-    #[error("Container mount error")]
-    Error,
-}
-
-#[derive(Error, Debug, Clone, PartialEq, Eq)]
-#[repr(C)]
-pub enum ContainerUnmountError {
-    #[error("Container unmount error")]
-    Error,
-}
+pub use wildland_corex::container_manager::{ContainerMountError, ContainerUnmountError};
@@ -0,0 +1,61 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use thiserror::Error;
+use wildland_corex::catlib_service::error::CatlibError;
+use wildland_corex::{CryptoError, ForestIdentityCreationError, LssError};
+
+use super::UserRetrievalError;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub enum DeviceEnrollmentError {
+    #[error("Device is already enrolled")]
+    DeviceAlreadyEnrolled,
+    #[error("Device has not been found among the enrolled devices")]
+    DeviceNotFound,
+    #[error("This device can not revoke itself")]
+    CannotRevokeThisDevice,
+    #[error("User already exists on this device")]
+    UserAlreadyExists,
+    #[error("Mnemonic does not belong to the owner of the forest")]
+    ForestOwnerMismatch,
+    #[error("Malformed device enrollment request: {0}")]
+    MalformedRequest(String),
+    #[error("Malformed device enrollment response: {0}")]
+    MalformedResponse(String),
+    #[error(transparent)]
+    CryptoError(#[from] CryptoError),
+    #[error("Could not derive forest keys: {0}")]
+    ForestIdentityCreationError(#[from] ForestIdentityCreationError),
+    #[error(transparent)]
+    LssError(#[from] LssError),
+    #[error(transparent)]
+    UserRetrievalError(#[from] UserRetrievalError),
+    #[error(transparent)]
+    CatlibError(CatlibError),
+}
+
+impl From<CatlibError> for DeviceEnrollmentError {
+    fn from(catlib_err: CatlibError) -> Self {
+        match catlib_err {
+            CatlibError::RecordAlreadyExists => DeviceEnrollmentError::DeviceAlreadyEnrolled,
+            CatlibError::NoRecordsFound => DeviceEnrollmentError::DeviceNotFound,
+            _ => DeviceEnrollmentError::CatlibError(catlib_err),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use thiserror::Error;
+use wildland_corex::catlib_service::error::CatlibError;
+use wildland_corex::{CryptoError, ForestIdentityCreationError, ForestRetrievalError, LssError};
+
+use super::UserRetrievalError;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub enum ForestManagementError {
+    #[error("Forest named {0} already exists")]
+    ForestAlreadyExists(String),
+    #[error("Forest named {0} not found")]
+    ForestNotFound(String),
+    #[error("Default forest cannot be deleted")]
+    CannotDeleteDefaultForest,
+    #[error("Active forest cannot be deleted")]
+    CannotDeleteActiveForest,
+    #[error("Mnemonic does not belong to the user")]
+    ForestOwnerMismatch,
+    #[error("Device identity not found")]
+    DeviceNotFound,
+    #[error(transparent)]
+    CryptoError(#[from] CryptoError),
+    #[error("Could not create forest identity: {0}")]
+    ForestIdentityCreationError(#[from] ForestIdentityCreationError),
+    #[error(transparent)]
+    LssError(#[from] LssError),
+    #[error(transparent)]
+    ForestRetrievalError(#[from] ForestRetrievalError),
+    #[error(transparent)]
+    UserRetrievalError(#[from] UserRetrievalError),
+    #[error(transparent)]
+    CatlibError(#[from] CatlibError),
+}
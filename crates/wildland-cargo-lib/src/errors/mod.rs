@@ -16,6 +16,9 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 pub mod container;
+pub mod device;
+pub mod forest;
+pub mod profile;
 pub mod storage;
 pub mod user;
 use std::fmt::Display;
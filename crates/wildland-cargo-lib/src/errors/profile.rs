@@ -0,0 +1,47 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use thiserror::Error;
+use wildland_corex::catlib_service::error::CatlibError;
+use wildland_corex::{CryptoError, ForestIdentityCreationError, LssError};
+
+use super::UserRetrievalError;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub enum ProfileBundleError {
+    #[error("User already exists on this device")]
+    UserAlreadyExists,
+    #[error("Mnemonic does not belong to the user")]
+    ForestOwnerMismatch,
+    #[error("Malformed profile bundle")]
+    MalformedBundle,
+    #[error("Unsupported profile bundle version {0}")]
+    UnsupportedVersion(u32),
+    #[error("Profile bundle could not be decrypted with the mnemonic")]
+    DecryptionError,
+    #[error(transparent)]
+    CryptoError(#[from] CryptoError),
+    #[error("Could not derive backup key: {0}")]
+    ForestIdentityCreationError(#[from] ForestIdentityCreationError),
+    #[error(transparent)]
+    LssError(#[from] LssError),
+    #[error(transparent)]
+    UserRetrievalError(#[from] UserRetrievalError),
+    #[error(transparent)]
+    CatlibError(#[from] CatlibError),
+}
@@ -71,6 +71,8 @@ impl From<CatlibError> for UserCreationError {
         match catlib_err {
             CatlibError::NoRecordsFound
             | CatlibError::MalformedDatabaseRecord
+            | CatlibError::UnsupportedSchemaVersion(_)
+            | CatlibError::InvalidRecordSignature
             | CatlibError::Generic(_) => UserCreationError::CatlibError(catlib_err.to_string()),
             CatlibError::RecordAlreadyExists => UserCreationError::UserAlreadyExists,
         }
@@ -82,7 +84,7 @@ impl From<CatlibError> for UserCreationError {
 pub enum UserRetrievalError {
     #[error(transparent)]
     ForestRetrievalError(#[from] ForestRetrievalError),
-    #[error("Default forest not found in LSS: {0}")]
+    #[error("Forest not found in LSS: {0}")]
     ForestNotFound(String),
     #[error(transparent)]
     LssError(#[from] LssError),
@@ -90,6 +92,8 @@ pub enum UserRetrievalError {
     CatlibError(#[from] CatlibError),
     #[error("Metadata of this device has not been found in Forest")]
     DeviceMetadataNotFound,
+    #[error("Forest data key not found in LSS, restore it with the user's mnemonic")]
+    ForestDataKeyNotFound,
     #[error("User not found")]
     UserNotFound,
     #[error("Generic: {0}")]
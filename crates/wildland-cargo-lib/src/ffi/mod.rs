@@ -34,8 +34,13 @@ pub use wildland_corex::{
 use crate::api::cargo_lib::*;
 use crate::api::cargo_user::*;
 use crate::api::config::*;
+use crate::api::device::*;
 use crate::api::foundation_storage::*;
 use crate::api::user::*;
+use crate::errors::container::*;
+use crate::errors::device::*;
+use crate::errors::forest::*;
+use crate::errors::profile::*;
 use crate::errors::storage::*;
 use crate::errors::user::*;
 use crate::errors::ExceptionTrait;
@@ -66,8 +71,49 @@ mod ffi_binding {
         LssError(_),
         CatlibError(_),
         DeviceMetadataNotFound,
+        ForestDataKeyNotFound,
         UserNotFound,
     }
+    enum DeviceEnrollmentError {
+        DeviceAlreadyEnrolled,
+        DeviceNotFound,
+        CannotRevokeThisDevice,
+        UserAlreadyExists,
+        ForestOwnerMismatch,
+        MalformedRequest(_),
+        MalformedResponse(_),
+        CryptoError(_),
+        ForestIdentityCreationError(_),
+        LssError(_),
+        UserRetrievalError(_),
+        CatlibError(_),
+    }
+    enum ProfileBundleError {
+        UserAlreadyExists,
+        ForestOwnerMismatch,
+        MalformedBundle,
+        UnsupportedVersion(_),
+        DecryptionError,
+        CryptoError(_),
+        ForestIdentityCreationError(_),
+        LssError(_),
+        UserRetrievalError(_),
+        CatlibError(_),
+    }
+    enum ForestManagementError {
+        ForestAlreadyExists(_),
+        ForestNotFound(_),
+        CannotDeleteDefaultForest,
+        CannotDeleteActiveForest,
+        ForestOwnerMismatch,
+        DeviceNotFound,
+        CryptoError(_),
+        ForestIdentityCreationError(_),
+        LssError(_),
+        ForestRetrievalError(_),
+        UserRetrievalError(_),
+        CatlibError(_),
+    }
     enum FsaError {
         StorageAlreadyExists,
         EvsError(_),
@@ -97,21 +143,59 @@ mod ffi_binding {
         NoRecordsFound(_),
         MalformedDatabaseEntry(_),
         RecordAlreadyExists(_),
+        UnsupportedSchemaVersion(_),
+        InvalidRecordSignature(_),
         Generic(_),
     }
     enum ContainerMountError {
-        Error,
+        ContainerAlreadyMounted,
+        CatlibError(_),
+        InvalidStorage(_),
     }
     enum ContainerUnmountError {
-        Error,
+        ContainerNotMounted,
     }
     enum FoundationCloudMode {
         Dev,
     }
+    enum CatLibBackend {
+        Rustbreak,
+        Sqlite,
+    }
     enum GetStorageTemplateError {
         LssError(_),
         DeserializationError(_),
     }
+    enum DfsFrontendError {
+        NoSuchPath,
+        PathAlreadyExists,
+        NotADirectory,
+        NotAFile,
+        DirNotEmpty,
+        NameTooLong,
+        ReadOnlyPath,
+        MoveBetweenContainers,
+        InvalidFileHandle,
+        StorageNotResponsive,
+        Generic(_),
+    }
+    enum NodeType {
+        File,
+        Dir,
+        Symlink,
+        Other,
+    }
+    enum ConflictStatus {
+        NoConflict,
+        Unresolved,
+        Renamed,
+    }
+    enum DfsEventType {
+        BackendUnsupported,
+        ReplicaFailed,
+        AllReplicasFailed,
+        StorageRecovered,
+    }
 
     extern "Traits" {
 
@@ -130,6 +214,8 @@ mod ffi_binding {
         fn get_oslog_subsystem(self: &dyn CargoCfgProvider) -> Option<String>;
 
         fn get_foundation_cloud_env_mode(self: &dyn CargoCfgProvider) -> FoundationCloudMode;
+        fn get_catlib_backend(self: &dyn CargoCfgProvider) -> CatLibBackend;
+        fn get_catlib_sync_dir(self: &dyn CargoCfgProvider) -> Option<String>;
 
         // # traits required for lss:
         //
@@ -148,6 +234,10 @@ mod ffi_binding {
         fn remove(self: &dyn LocalSecureStorage, key: String) -> Result<Option<String>, LssError>;
         fn len(self: &dyn LocalSecureStorage) -> Result<usize, LssError>;
         fn is_empty(self: &dyn LocalSecureStorage) -> Result<bool, LssError>;
+
+        // # traits required for receiving DFS events:
+        //
+        fn receive_event(self: &dyn DfsEventReceiver, event: DfsEvent);
     }
 
     extern "Rust" {
@@ -169,6 +259,10 @@ mod ffi_binding {
         ) -> Result<Arc<Mutex<CargoLib>>, CargoLibCreationError>;
         fn user_api(self: &Arc<Mutex<CargoLib>>) -> UserApi;
         fn dfs_api(self: &Arc<Mutex<CargoLib>>) -> Arc<Mutex<dyn DfsFrontend>>;
+        fn subscribe_dfs_events(
+            self: &Arc<Mutex<CargoLib>>,
+            receiver: &'static dyn DfsEventReceiver,
+        );
 
         //
         // UserApi
@@ -197,12 +291,63 @@ mod ffi_binding {
             device_name: String,
         ) -> Result<CargoUser, UserCreationError>;
         fn get_user(self: &UserApi) -> Result<CargoUser, UserRetrievalError>;
+        fn request_device_enrollment(
+            self: &UserApi,
+            device_name: String,
+        ) -> Result<DeviceEnrollmentRequest, DeviceEnrollmentError>;
+        fn complete_device_enrollment(
+            self: &UserApi,
+            response: &DeviceEnrollmentResponse,
+        ) -> Result<CargoUser, DeviceEnrollmentError>;
+        fn sync_forests(self: &UserApi) -> Result<VoidType, ForestManagementError>;
+        fn export_profile(
+            self: &UserApi,
+            mnemonic: &MnemonicPayload,
+        ) -> Result<Vec<u8>, ProfileBundleError>;
+        fn import_profile(
+            self: &UserApi,
+            mnemonic: &MnemonicPayload,
+            bundle: Vec<u8>,
+        ) -> Result<CargoUser, ProfileBundleError>;
+
+        // Forests
+        fn create_forest(
+            self: &UserApi,
+            mnemonic: &MnemonicPayload,
+            name: String,
+        ) -> Result<VoidType, ForestManagementError>;
+        fn list_forests(self: &UserApi) -> Result<Vec<String>, ForestManagementError>;
+        fn active_forest(self: &UserApi) -> Result<String, ForestManagementError>;
+        fn switch_forest(self: &UserApi, name: String) -> Result<CargoUser, ForestManagementError>;
+        fn delete_forest(self: &UserApi, name: String) -> Result<VoidType, ForestManagementError>;
+        fn restore_forest_data_keys(
+            self: &UserApi,
+            mnemonic: &MnemonicPayload,
+        ) -> Result<VoidType, ForestManagementError>;
+
+        //
+        // DeviceEnrollmentRequest
+        //
+        fn parse_device_enrollment_request(
+            request: String,
+        ) -> Result<DeviceEnrollmentRequest, DeviceEnrollmentError>;
+        fn stringify(self: &DeviceEnrollmentRequest) -> String;
+        fn device_name(self: &DeviceEnrollmentRequest) -> String;
+
+        //
+        // DeviceEnrollmentResponse
+        //
+        fn parse_device_enrollment_response(
+            response: String,
+        ) -> Result<DeviceEnrollmentResponse, DeviceEnrollmentError>;
+        fn stringify(self: &DeviceEnrollmentResponse) -> String;
 
         //
         // MnemonicPayload
         //
         fn stringify(self: &MnemonicPayload) -> String;
         fn get_vec(self: &MnemonicPayload) -> Vec<String>;
+        fn with_passphrase(self: &MnemonicPayload, passphrase: String) -> MnemonicPayload;
 
         type Identity;
         type Signers;
@@ -237,6 +382,29 @@ mod ffi_binding {
         ) -> Result<StorageTemplate, FsaError>;
         fn is_free_storage_granted(self: &CargoUser) -> Result<bool, CatlibError>;
 
+        // Mounting
+        fn mount(
+            self: &CargoUser,
+            container: &Arc<Mutex<dyn ContainerManifest>>,
+        ) -> Result<VoidType, ContainerMountError>;
+        fn unmount(
+            self: &CargoUser,
+            container: &Arc<Mutex<dyn ContainerManifest>>,
+        ) -> Result<VoidType, ContainerUnmountError>;
+        fn get_mounted_containers(self: &CargoUser) -> Vec<Arc<Mutex<dyn ContainerManifest>>>;
+
+        // Devices
+        fn sync_forest(self: &CargoUser) -> Result<VoidType, CatlibError>;
+        fn enroll_device(
+            self: &CargoUser,
+            request: &DeviceEnrollmentRequest,
+        ) -> Result<DeviceEnrollmentResponse, DeviceEnrollmentError>;
+        fn revoke_device(
+            self: &CargoUser,
+            device_name: String,
+            mnemonic: &MnemonicPayload,
+        ) -> Result<VoidType, DeviceEnrollmentError>;
+
         //
         // ForestManifest
         //
@@ -342,7 +510,65 @@ mod ffi_binding {
 
         // DFS Frontend
         fn readdir(self: &Arc<Mutex<dyn DfsFrontend>>, path: String) -> Vec<NodeDescriptor>;
+        fn getattr(
+            self: &Arc<Mutex<dyn DfsFrontend>>,
+            path: String,
+        ) -> Result<Stat, DfsFrontendError>;
+        fn open(
+            self: &Arc<Mutex<dyn DfsFrontend>>,
+            path: String,
+        ) -> Result<FileHandle, DfsFrontendError>;
+        fn close(
+            self: &Arc<Mutex<dyn DfsFrontend>>,
+            file: &FileHandle,
+        ) -> Result<VoidType, DfsFrontendError>;
+        fn read(
+            self: &Arc<Mutex<dyn DfsFrontend>>,
+            file: &FileHandle,
+            offset: u64,
+            count: usize,
+        ) -> Result<Vec<u8>, DfsFrontendError>;
+        fn write(
+            self: &Arc<Mutex<dyn DfsFrontend>>,
+            file: &FileHandle,
+            offset: u64,
+            buf: Vec<u8>,
+        ) -> Result<usize, DfsFrontendError>;
+        fn create(
+            self: &Arc<Mutex<dyn DfsFrontend>>,
+            path: String,
+        ) -> Result<FileHandle, DfsFrontendError>;
+        fn unlink(
+            self: &Arc<Mutex<dyn DfsFrontend>>,
+            path: String,
+        ) -> Result<VoidType, DfsFrontendError>;
+        fn mkdir(
+            self: &Arc<Mutex<dyn DfsFrontend>>,
+            path: String,
+        ) -> Result<VoidType, DfsFrontendError>;
+        fn rmdir(
+            self: &Arc<Mutex<dyn DfsFrontend>>,
+            path: String,
+        ) -> Result<VoidType, DfsFrontendError>;
+        fn rename(
+            self: &Arc<Mutex<dyn DfsFrontend>>,
+            old_path: String,
+            new_path: String,
+        ) -> Result<VoidType, DfsFrontendError>;
+        fn truncate(
+            self: &Arc<Mutex<dyn DfsFrontend>>,
+            path: String,
+            size: u64,
+        ) -> Result<VoidType, DfsFrontendError>;
 
         type NodeDescriptor;
+        type Stat;
+        type FileHandle;
+
+        // DFS Events
+        fn event_type(self: &DfsEvent) -> DfsEventType;
+        fn storage_uuids(self: &DfsEvent) -> Vec<String>;
+        fn description(self: &DfsEvent) -> String;
+        type DfsEvent;
     }
 }
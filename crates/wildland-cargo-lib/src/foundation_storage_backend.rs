@@ -0,0 +1,273 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::anyhow;
+use wildland_corex::Storage;
+use wildland_dfs::storage_backend::StorageBackend;
+use wildland_dfs::unencrypted::StorageBackendFactory;
+use wildland_http_client::sc::client::StorageControllerClient;
+use wildland_http_client::sc::models::SignatureRequestReq;
+use wildland_s3::{
+    CanonicalRequest,
+    RequestSigner,
+    S3Client,
+    S3Storage,
+    DEFAULT_MULTIPART_PART_SIZE,
+};
+
+use crate::templates::foundation_storage::FoundationStorageTemplate;
+
+pub(crate) const FOUNDATION_STORAGE_BACKEND_TYPE: &str = "FoundationStorage";
+
+type SignatureResult = Result<String, anyhow::Error>;
+type SignatureRequest = (SignatureRequestReq, Sender<SignatureResult>);
+
+/// Requests sent to Foundation Storage are signed by the storage controller which keeps the
+/// credentials of the underlying storage. The client authenticates with the credentials granted
+/// together with the storage.
+///
+/// The storage controller signs a request given its method, path with query and timestamp, so
+/// the signature does not depend on anything else and may be reused by identical requests sent
+/// within the same second (`x-amz-date` header has second precision).
+struct StorageControllerSigner {
+    /// [`StorageControllerClient`] is not thread-safe, while requests may be signed by multiple
+    /// threads, so a single client is kept by a dedicated thread.
+    requests: Mutex<Sender<SignatureRequest>>,
+    credential_id: String,
+    /// Signatures of requests sent in the second of the given Unix time, by method and path.
+    cache: Mutex<(u64, HashMap<(String, String), String>)>,
+}
+
+impl StorageControllerSigner {
+    fn new(sc_url: String, credential_id: String, credential_secret: String) -> Self {
+        let (requests, requests_rx) = mpsc::channel();
+        let thread_credential_id = credential_id.clone();
+        // the thread exits when the signer, the only sender of requests, is dropped
+        thread::spawn(move || {
+            let mut sc_client = StorageControllerClient::new(&sc_url);
+            sc_client.credential_id = thread_credential_id;
+            sc_client.credential_secret = credential_secret;
+            Self::serve(sc_client, requests_rx)
+        });
+        Self {
+            requests: Mutex::new(requests),
+            credential_id,
+            cache: Mutex::new((0, HashMap::new())),
+        }
+    }
+
+    fn serve(sc_client: StorageControllerClient, requests: Receiver<SignatureRequest>) {
+        for (request, response_tx) in requests {
+            let response = sc_client
+                .request_signature(request)
+                .map(|response| response.message)
+                .map_err(|e| anyhow!("Storage controller did not sign the request: {e}"));
+            // the requesting thread might have given up already
+            let _ = response_tx.send(response);
+        }
+    }
+
+    fn cached_signature(&self, second: u64, key: &(String, String)) -> Option<String> {
+        let (cached_second, signatures) = &*self.cache.lock().expect("Poisoned Mutex");
+        if *cached_second == second {
+            signatures.get(key).cloned()
+        } else {
+            None
+        }
+    }
+
+    fn cache_signature(&self, second: u64, key: (String, String), signature: String) {
+        let (cached_second, signatures) = &mut *self.cache.lock().expect("Poisoned Mutex");
+        if second > *cached_second {
+            *cached_second = second;
+            signatures.clear();
+        }
+        if second == *cached_second {
+            signatures.insert(key, signature);
+        }
+    }
+}
+
+impl RequestSigner for StorageControllerSigner {
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn authorization(&self, request: &CanonicalRequest, time: SystemTime) -> SignatureResult {
+        let query = request.canonical_query();
+        let storage_path = if query.is_empty() {
+            request.path.to_owned()
+        } else {
+            format!("{}?{query}", request.path)
+        };
+        let since_epoch = time.duration_since(UNIX_EPOCH)?;
+        let key = (request.method.to_owned(), storage_path);
+        if let Some(signature) = self.cached_signature(since_epoch.as_secs(), &key) {
+            return Ok(signature);
+        }
+
+        let (response_tx, response_rx) = mpsc::channel();
+        self.requests
+            .lock()
+            .expect("Poisoned Mutex")
+            .send((
+                SignatureRequestReq {
+                    credential_id: self.credential_id.clone(),
+                    timestamp: since_epoch.as_millis().to_string(),
+                    storage_method: key.0.clone(),
+                    storage_path: key.1.clone(),
+                },
+                response_tx,
+            ))
+            .map_err(|_| anyhow!("Storage controller client is gone"))?;
+        let signature = response_rx
+            .recv()
+            .map_err(|_| anyhow!("Storage controller client is gone"))??;
+        self.cache_signature(since_epoch.as_secs(), key, signature.clone());
+        Ok(signature)
+    }
+}
+
+/// Creates backends of Foundation Storage granted with the free tier.
+///
+/// The storage controller exposes S3 API of the bucket assigned to the user at
+/// `{sc_url}/{bucket_uuid}` (path-style addressing), and signs requests to it with its
+/// `/signature/request` endpoint, which returns the value of the `Authorization` header.
+pub(crate) struct FoundationStorageBackendFactory {}
+
+impl StorageBackendFactory for FoundationStorageBackendFactory {
+    fn init_backend(&self, storage: Storage) -> Result<Arc<dyn StorageBackend>, anyhow::Error> {
+        let template: FoundationStorageTemplate = serde_json::from_value(storage.data().clone())?;
+        let signer = StorageControllerSigner::new(
+            template.sc_url.clone(),
+            template.credential_id,
+            template.credential_secret,
+        );
+        let client = S3Client::new(
+            &template.sc_url,
+            template.bucket_uuid.to_string(),
+            Arc::new(signer),
+            false,
+        )?;
+        Ok(Arc::new(S3Storage::new(
+            client,
+            &template.container_prefix,
+            DEFAULT_MULTIPART_PART_SIZE,
+        )?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use mockito::Matcher;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use wildland_corex::SigningKeypair;
+
+    use super::*;
+
+    #[test]
+    fn test_listing_foundation_storage_with_requests_signed_by_storage_controller() {
+        let keypair = SigningKeypair::try_from_secret_bytes(&[7; 32]).unwrap();
+        let credential_id = hex::encode(keypair.public());
+        let bucket_uuid = "00000000-0000-0000-0000-00000000f5f5";
+        let storage = Storage::new(
+            None,
+            FOUNDATION_STORAGE_BACKEND_TYPE.to_owned(),
+            json!({
+                "bucket_uuid": bucket_uuid,
+                "credential_id": credential_id,
+                "credential_secret": hex::encode(*keypair.secret()),
+                "sc_url": mockito::server_url(),
+                "container_prefix": "owner/Movies"
+            }),
+        );
+
+        let signature_mock = mockito::mock("POST", "/signature/request")
+            .match_header("X-Wildland-Signature", Matcher::Any)
+            .match_body(Matcher::PartialJson(json!({
+                "credentialID": credential_id,
+                "storageMethod": "GET",
+                "storagePath": format!(
+                    "/{bucket_uuid}/?delimiter=%2F&list-type=2&prefix=owner%2FMovies%2F"
+                )
+            })))
+            .with_body(r#"{"message": "signed by sc"}"#)
+            .create();
+        let list_mock = mockito::mock("GET", Matcher::Regex(format!("^/{bucket_uuid}/\\?")))
+            .match_header("authorization", "signed by sc")
+            .with_body(
+                "<ListBucketResult><IsTruncated>false</IsTruncated><Contents>\
+                 <Key>owner/Movies/movie.mp4</Key><Size>100</Size></Contents></ListBucketResult>",
+            )
+            .create();
+
+        let backend = FoundationStorageBackendFactory {}
+            .init_backend(storage)
+            .unwrap();
+        let files = backend.readdir(Path::new("/")).unwrap();
+
+        assert_eq!(files, vec![PathBuf::from("/movie.mp4")]);
+        signature_mock.assert();
+        list_mock.assert();
+    }
+
+    #[test]
+    fn test_signatures_are_reused_by_identical_requests_within_a_second() {
+        let keypair = SigningKeypair::try_from_secret_bytes(&[7; 32]).unwrap();
+        let signer = StorageControllerSigner::new(
+            mockito::server_url(),
+            hex::encode(keypair.public()),
+            hex::encode(*keypair.secret()),
+        );
+        let signature_mock = mockito::mock("POST", "/signature/request")
+            .match_body(Matcher::PartialJson(json!({
+                "storageMethod": "HEAD",
+                "storagePath": "/bucket/reused"
+            })))
+            .with_body(r#"{"message": "signed by sc"}"#)
+            .expect(2)
+            .create();
+        let headers = Default::default();
+        let request = CanonicalRequest {
+            method: "HEAD",
+            path: "/bucket/reused",
+            query: &[],
+            headers: &headers,
+            payload_hash: "",
+        };
+        let time = UNIX_EPOCH + std::time::Duration::from_millis(1_000_100);
+
+        for time in [
+            time,
+            time + std::time::Duration::from_millis(800),
+            time + std::time::Duration::from_millis(900),
+        ] {
+            assert_eq!(
+                signer.authorization(&request, time).unwrap(),
+                "signed by sc"
+            );
+        }
+
+        signature_mock.assert();
+    }
+}
@@ -29,9 +29,12 @@
 //! [`api::CargoLib`] must be initialized with some set of parameters (see [`api::config`]).
 
 pub mod api;
+mod dfs_keys;
 pub mod errors;
 #[cfg(feature = "bindings")]
 pub mod ffi;
+mod foundation_storage_backend;
 mod logging;
+mod profile_bundle;
 mod templates;
 mod user;
@@ -0,0 +1,159 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Encrypted archive with everything needed to restore a user on another machine.
+//!
+//! The bundle starts with [`BUNDLE_MAGIC`] and a big-endian format version, followed by the JSON
+//! serialized [`ProfileBundle`] encrypted with the backup key derived from the user's mnemonic.
+//! Since version 2 the magic and version are authenticated as associated data of the encryption.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use wildland_corex::SymmetricKey;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::errors::profile::ProfileBundleError;
+
+const BUNDLE_MAGIC: &[u8] = b"WLPROFILE";
+const BUNDLE_VERSION: u32 = 2;
+/// Last version encrypted without associated data
+const UNAUTHENTICATED_HEADER_VERSION: u32 = 1;
+const HEADER_LEN: usize = BUNDLE_MAGIC.len() + 4;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub(crate) struct ProfileBundle {
+    /// All LSS entries, including device and forest keypairs
+    pub(crate) lss_entries: BTreeMap<String, String>,
+    /// User's default forest exported from CatLib
+    pub(crate) forest: Vec<u8>,
+    /// Additional forests of the user, absent in bundles created before they were introduced
+    #[serde(default)]
+    pub(crate) other_forests: Vec<Vec<u8>>,
+    pub(crate) storage_templates: Vec<String>,
+}
+
+impl ProfileBundle {
+    pub(crate) fn seal(&self, key: &SymmetricKey) -> Vec<u8> {
+        let content =
+            Zeroizing::new(serde_json::to_vec(self).expect("Serialization of bundle can't fail"));
+        let header = [BUNDLE_MAGIC, &BUNDLE_VERSION.to_be_bytes()].concat();
+        let ciphertext = key.encrypt_with_associated_data(&content, &header);
+        [header, ciphertext].concat()
+    }
+
+    pub(crate) fn open(bundle: &[u8], key: &SymmetricKey) -> Result<Self, ProfileBundleError> {
+        if !bundle.starts_with(BUNDLE_MAGIC) || bundle.len() < HEADER_LEN {
+            return Err(ProfileBundleError::MalformedBundle);
+        }
+        let (header, ciphertext) = bundle.split_at(HEADER_LEN);
+        let version = u32::from_be_bytes(
+            header[BUNDLE_MAGIC.len()..]
+                .try_into()
+                .expect("Length checked above"),
+        );
+        if version > BUNDLE_VERSION {
+            return Err(ProfileBundleError::UnsupportedVersion(version));
+        }
+        let associated_data = if version > UNAUTHENTICATED_HEADER_VERSION {
+            header
+        } else {
+            &[]
+        };
+        let content = Zeroizing::new(
+            key.decrypt_with_associated_data(ciphertext, associated_data)
+                .map_err(|_| ProfileBundleError::DecryptionError)?,
+        );
+        serde_json::from_slice(&content).map_err(|_| ProfileBundleError::MalformedBundle)
+    }
+}
+
+impl Drop for ProfileBundle {
+    fn drop(&mut self) {
+        self.lss_entries.values_mut().for_each(Zeroize::zeroize);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn bundle() -> ProfileBundle {
+        ProfileBundle {
+            lss_entries: BTreeMap::from([("key".to_owned(), "value".to_owned())]),
+            forest: b"forest".to_vec(),
+            other_forests: vec![b"work forest".to_vec()],
+            storage_templates: vec!["template".to_owned()],
+        }
+    }
+
+    #[test]
+    fn sealed_bundle_is_opened_with_the_same_key_only() {
+        let key = SymmetricKey::from_bytes([1; 32]);
+        let sealed = bundle().seal(&key);
+
+        assert_eq!(ProfileBundle::open(&sealed, &key).unwrap(), bundle());
+        assert_eq!(
+            ProfileBundle::open(&sealed, &SymmetricKey::from_bytes([2; 32])).unwrap_err(),
+            ProfileBundleError::DecryptionError
+        );
+    }
+
+    #[test]
+    fn bundle_with_modified_header_is_rejected() {
+        let key = SymmetricKey::from_bytes([1; 32]);
+        let mut sealed = bundle().seal(&key);
+        sealed[BUNDLE_MAGIC.len() + 3] -= 1;
+
+        assert_eq!(
+            ProfileBundle::open(&sealed, &key).unwrap_err(),
+            ProfileBundleError::DecryptionError
+        );
+    }
+
+    #[test]
+    fn bundle_of_first_version_is_opened() {
+        let key = SymmetricKey::from_bytes([1; 32]);
+        let content = serde_json::to_vec(&bundle()).unwrap();
+        let sealed = [
+            BUNDLE_MAGIC,
+            &UNAUTHENTICATED_HEADER_VERSION.to_be_bytes(),
+            &key.encrypt(&content),
+        ]
+        .concat();
+
+        assert_eq!(ProfileBundle::open(&sealed, &key).unwrap(), bundle());
+    }
+
+    #[test]
+    fn bundle_of_newer_version_is_rejected() {
+        let key = SymmetricKey::from_bytes([1; 32]);
+        let mut sealed = bundle().seal(&key);
+        sealed[BUNDLE_MAGIC.len() + 3] += 1;
+
+        assert_eq!(
+            ProfileBundle::open(&sealed, &key).unwrap_err(),
+            ProfileBundleError::UnsupportedVersion(BUNDLE_VERSION + 1)
+        );
+        assert_eq!(
+            ProfileBundle::open(b"not a bundle", &key).unwrap_err(),
+            ProfileBundleError::MalformedBundle
+        );
+    }
+}
@@ -20,14 +20,15 @@ use uuid::Uuid;
 use wildland_corex::{StorageTemplate, StorageTemplateError, CONTAINER_NAME_PARAM, OWNER_PARAM};
 
 use crate::api::foundation_storage::StorageCredentials;
+use crate::foundation_storage_backend::FOUNDATION_STORAGE_BACKEND_TYPE;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FoundationStorageTemplate {
-    bucket_uuid: Uuid,
-    credential_id: String,
-    credential_secret: String,
-    sc_url: String,
-    container_prefix: String,
+    pub(crate) bucket_uuid: Uuid,
+    pub(crate) credential_id: String,
+    pub(crate) credential_secret: String,
+    pub(crate) sc_url: String,
+    pub(crate) container_prefix: String,
 }
 
 impl FoundationStorageTemplate {
@@ -72,7 +73,7 @@ impl FoundationStorageTemplate {
 impl TryFrom<FoundationStorageTemplate> for StorageTemplate {
     type Error = StorageTemplateError;
     fn try_from(fst: FoundationStorageTemplate) -> Result<Self, Self::Error> {
-        StorageTemplate::try_new("FoundationStorage", fst)
+        StorageTemplate::try_new(FOUNDATION_STORAGE_BACKEND_TYPE, fst)
     }
 }
 
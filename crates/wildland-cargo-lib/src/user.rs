@@ -15,21 +15,38 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::rc::Rc;
+
 use uuid::Uuid;
 use wildland_corex::catlib_service::error::CatlibError;
 use wildland_corex::catlib_service::{CatLibService, DeviceMetadata, ForestMetaData};
-use wildland_corex::{CryptoError, Identity, LssService, MasterIdentity, MnemonicPhrase};
+use wildland_corex::container_manager::ContainerManager;
+use wildland_corex::{
+    CryptoError,
+    EncryptingKeypair,
+    Identity,
+    LssService,
+    MasterIdentity,
+    MnemonicPhrase,
+    WildlandIdentity,
+};
 
 use crate::api::cargo_user::CargoUser;
 use crate::api::config::FoundationStorageApiConfig;
+use crate::api::device::{DeviceEnrollmentRequest, DeviceEnrollmentResponse};
+use crate::api::user::MnemonicPayload;
+use crate::errors::device::DeviceEnrollmentError;
+use crate::errors::forest::ForestManagementError;
+use crate::errors::profile::ProfileBundleError;
 use crate::errors::{UserCreationError, UserRetrievalError};
+use crate::profile_bundle::ProfileBundle;
 
 pub fn generate_random_mnemonic() -> Result<MnemonicPhrase, CryptoError> {
     wildland_corex::generate_random_mnemonic()
 }
 
 pub enum CreateUserInput {
-    Mnemonic(Box<MnemonicPhrase>),
+    Mnemonic(Box<MnemonicPayload>),
     Entropy(Vec<u8>),
 }
 
@@ -40,6 +57,7 @@ pub(crate) struct UserService {
     lss_service: LssService,
     catlib_service: CatLibService,
     fsa_config: FoundationStorageApiConfig,
+    container_manager: Rc<ContainerManager>,
 }
 
 impl UserService {
@@ -47,11 +65,13 @@ impl UserService {
         lss_service: LssService,
         catlib_service: CatLibService,
         fsa_config: FoundationStorageApiConfig,
+        container_manager: Rc<ContainerManager>,
     ) -> Self {
         Self {
             lss_service,
             catlib_service,
             fsa_config,
+            container_manager,
         }
     }
 
@@ -69,14 +89,21 @@ impl UserService {
         }?;
         tracing::trace!("User does not exist yet, creating new one");
         let crypto_identity = match input {
-            CreateUserInput::Mnemonic(mnemonic) => Identity::try_from(mnemonic.as_ref())?,
+            CreateUserInput::Mnemonic(mnemonic) => mnemonic.identity()?,
             CreateUserInput::Entropy(entropy) => Identity::try_from(entropy.as_slice())?,
         };
         let master_identity = MasterIdentity::new(Some(crypto_identity));
         let default_forest_identity = master_identity
             .create_forest_identity(0)
             .map_err(UserCreationError::ForestIdentityCreationError)?;
+        let default_forest_data_key = master_identity
+            .create_forest_data_key(0)
+            .map_err(UserCreationError::ForestIdentityCreationError)?;
         let device_identity = master_identity.create_device_identity(device_name.clone());
+        self.catlib_service
+            .set_signing_keypair(device_identity.get_keypair());
+        self.catlib_service
+            .set_forest_keypair(default_forest_identity.get_keypair());
 
         let forest = self.catlib_service.add_forest(
             &default_forest_identity,
@@ -93,24 +120,44 @@ impl UserService {
 
         self.lss_service.save_identity(&default_forest_identity)?;
         self.lss_service.save_identity(&device_identity)?;
+        self.lss_service
+            .save_forest_data_key(0, &default_forest_data_key)?;
+        self.lss_service.mark_forest_signed(0)?;
 
         Ok(CargoUser::new(
             device_name.clone(),
             vec![device_name],
             forest.clone(),
             self.catlib_service.clone(),
+            self.lss_service.clone(),
             &self.fsa_config,
+            self.container_manager.clone(),
         ))
     }
 
-    /// Retrieves default forest keypair from LSS and then basing on that reads User metadata from CatLib.
+    /// Retrieves active forest keypair from LSS and then basing on that reads User metadata from CatLib.
     /// Result is presented in from of [`crate::api::user::CargoUser`].
     ///
     #[tracing::instrument(level = "debug", skip_all)]
     pub(crate) fn get_user(&self) -> Result<Option<CargoUser>, UserRetrievalError> {
-        let default_forest_uuid = self.get_default_forest_uuid()?;
+        let active_forest_index = self.lss_service.get_forest_registry()?.active_index();
+        self.get_user_of_forest(active_forest_index)
+    }
 
-        match self.catlib_service.get_forest(&default_forest_uuid) {
+    /// Reads User metadata from the forest of the given index, see [`Self::get_user`].
+    ///
+    fn get_user_of_forest(
+        &self,
+        forest_index: u64,
+    ) -> Result<Option<CargoUser>, UserRetrievalError> {
+        let forest_uuid = self.get_forest_uuid(forest_index)?;
+        if let Some(device_identity) = self.lss_service.get_this_device_identity()? {
+            self.catlib_service
+                .set_signing_keypair(device_identity.get_keypair());
+        }
+        self.trust_forests()?;
+
+        match self.catlib_service.get_forest(&forest_uuid) {
             Ok(forest) => {
                 let user_metadata: ForestMetaData = serde_json::from_slice(
                     &forest
@@ -125,6 +172,15 @@ impl UserService {
                     ))
                 })?;
 
+                // users created before the data of containers was encrypted have no data keys
+                if self
+                    .lss_service
+                    .get_forest_data_key(forest_index)?
+                    .is_none()
+                {
+                    return Err(UserRetrievalError::ForestDataKeyNotFound);
+                }
+
                 let device_identity = self
                     .lss_service
                     .get_this_device_identity()?
@@ -136,7 +192,9 @@ impl UserService {
                         user_metadata.devices().map(|dm| dm.name.clone()).collect(),
                         forest,
                         self.catlib_service.clone(),
+                        self.lss_service.clone(),
                         &self.fsa_config,
+                        self.container_manager.clone(),
                     ))),
                     None => Err(UserRetrievalError::DeviceMetadataNotFound),
                 }
@@ -146,14 +204,448 @@ impl UserService {
         }
     }
 
-    /// Retrieves default forest uuid from LSS
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub(crate) fn request_device_enrollment(
+        &self,
+        device_name: String,
+    ) -> Result<DeviceEnrollmentRequest, DeviceEnrollmentError> {
+        match self.get_user() {
+            Ok(_) => return Err(DeviceEnrollmentError::UserAlreadyExists),
+            Err(UserRetrievalError::ForestNotFound(_)) => Ok(()),
+            Err(e) => Err(e),
+        }?;
+        let device_identity = MasterIdentity::new(None).create_device_identity(device_name.clone());
+        let encryption_keypair = EncryptingKeypair::new();
+        self.lss_service.save_identity(&device_identity)?;
+        self.lss_service
+            .save_this_device_encryption_keypair(&encryption_keypair)?;
+        self.catlib_service
+            .set_signing_keypair(device_identity.get_keypair());
+
+        Ok(DeviceEnrollmentRequest::new(
+            device_name,
+            device_identity.get_public_key(),
+            encryption_keypair.encode_pub(),
+        ))
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub(crate) fn complete_device_enrollment(
+        &self,
+        response: &DeviceEnrollmentResponse,
+    ) -> Result<CargoUser, DeviceEnrollmentError> {
+        let device_identity = self
+            .lss_service
+            .get_this_device_identity()?
+            .ok_or(DeviceEnrollmentError::DeviceNotFound)?;
+        self.catlib_service
+            .set_signing_keypair(device_identity.get_keypair());
+        let encryption_keypair = self
+            .lss_service
+            .get_this_device_encryption_keypair()?
+            .ok_or(DeviceEnrollmentError::DeviceNotFound)?;
+        let keys = response.open(&encryption_keypair)?;
+        let forest_identity = WildlandIdentity::Forest(keys.forest_index, keys.forest_keypair);
+        self.catlib_service
+            .set_forest_keypair(forest_identity.get_keypair());
+
+        let forest = match self.catlib_service.find_forest(&forest_identity) {
+            Err(CatlibError::NoRecordsFound) => self
+                .catlib_service
+                .join_forest(&forest_identity)
+                .and_then(|forest_uuid| self.catlib_service.get_forest(&forest_uuid)),
+            forest => forest,
+        }
+        .map_err(|e| match e {
+            CatlibError::NoRecordsFound => UserRetrievalError::ForestNotFound(
+                "Forest has not been synchronized to this device yet".to_owned(),
+            )
+            .into(),
+            e => DeviceEnrollmentError::from(e),
+        })?;
+        self.catlib_service
+            .get_parsed_forest_metadata(&forest)?
+            .get_device_metadata(device_identity.get_public_key())
+            .ok_or(DeviceEnrollmentError::DeviceNotFound)?;
+
+        tracing::trace!("saving identities of enrolled device to lss");
+        self.lss_service
+            .save_forest_uuid(&*forest.lock().expect("Poisoned Mutex"))?;
+        self.lss_service.save_identity(&forest_identity)?;
+        self.lss_service
+            .save_forest_data_key(keys.forest_index, &keys.data_key)?;
+        self.lss_service.mark_forest_signed(keys.forest_index)?;
+        let mut registry = self.lss_service.get_forest_registry()?;
+        // the default forest is registered implicitly, its keys are not on this device though
+        if keys.forest_index != 0
+            && self
+                .lss_service
+                .get_default_forest_identity()
+                .map_err(UserRetrievalError::from)?
+                .is_none()
+        {
+            registry.remove(0);
+        }
+        registry.add(keys.forest_index, keys.forest_name);
+        registry.activate(keys.forest_index);
+        self.lss_service.save_forest_registry(&registry)?;
+
+        Ok(self.get_user()?.ok_or(UserRetrievalError::UserNotFound)?)
+    }
+
+    /// Exchanges changes of the forests registered on this device with other devices.
     ///
     #[tracing::instrument(level = "debug", skip_all)]
-    fn get_default_forest_uuid(&self) -> Result<Uuid, UserRetrievalError> {
-        tracing::debug!("searching for user");
+    pub(crate) fn sync_forests(&self) -> Result<(), ForestManagementError> {
+        let device_identity = self
+            .lss_service
+            .get_this_device_identity()?
+            .ok_or(ForestManagementError::DeviceNotFound)?;
+        self.catlib_service
+            .set_signing_keypair(device_identity.get_keypair());
+        self.trust_forests()?;
+        for forest_index in self.lss_service.get_forest_registry()?.indices() {
+            let forest_uuid = self.get_forest_uuid(forest_index)?;
+            self.catlib_service.sync_forest(&forest_uuid)?;
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub(crate) fn export_profile(
+        &self,
+        mnemonic: &MnemonicPayload,
+    ) -> Result<Vec<u8>, ProfileBundleError> {
+        let forest_uuid = self.get_forest_uuid(0)?;
+        let master_identity = MasterIdentity::new(Some(mnemonic.identity()?));
         let forest_identity = self
+            .lss_service
+            .get_default_forest_identity()
+            .map_err(UserRetrievalError::from)?;
+        if forest_identity.map(|identity| identity.get_public_key())
+            != Some(master_identity.create_forest_identity(0)?.get_public_key())
+        {
+            return Err(ProfileBundleError::ForestOwnerMismatch);
+        }
+
+        let other_forests = self
+            .lss_service
+            .get_forest_registry()?
+            .indices()
+            .filter(|&forest_index| forest_index != 0)
+            .map(|forest_index| {
+                let forest_uuid = self.get_forest_uuid(forest_index)?;
+                Ok(self.catlib_service.export_forest(&forest_uuid)?)
+            })
+            .collect::<Result<_, ProfileBundleError>>()?;
+
+        let bundle = ProfileBundle {
+            lss_entries: self.lss_service.export_entries()?,
+            forest: self.catlib_service.export_forest(&forest_uuid)?,
+            other_forests,
+            storage_templates: self.catlib_service.get_storage_templates_data()?,
+        };
+        Ok(bundle.seal(&master_identity.create_backup_key()?))
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub(crate) fn import_profile(
+        &self,
+        mnemonic: &MnemonicPayload,
+        bundle: &[u8],
+    ) -> Result<CargoUser, ProfileBundleError> {
+        match self.get_user() {
+            Ok(_) => return Err(ProfileBundleError::UserAlreadyExists),
+            Err(UserRetrievalError::ForestNotFound(_)) => Ok(()),
+            Err(e) => Err(e),
+        }?;
+        let master_identity = MasterIdentity::new(Some(mnemonic.identity()?));
+        let bundle = ProfileBundle::open(bundle, &master_identity.create_backup_key()?)?;
+
+        // keys imported to LSS are needed to verify the imported manifests, so they are removed
+        // again if the manifests can not be imported
+        tracing::trace!("restoring lss entries");
+        let overwritten = self.lss_service.import_entries(&bundle.lss_entries)?;
+        if let Err(e) = self.import_catlib_records(&bundle) {
+            tracing::warn!("Reverting import of the profile: {e}");
+            self.lss_service.revert_import(overwritten)?;
+            return Err(e);
+        }
+
+        Ok(self.get_user()?.ok_or(UserRetrievalError::UserNotFound)?)
+    }
+
+    /// Imports manifests and storage templates of the bundle, verified with the keys from LSS.
+    fn import_catlib_records(&self, bundle: &ProfileBundle) -> Result<(), ProfileBundleError> {
+        if let Some(device_identity) = self.lss_service.get_this_device_identity()? {
+            self.catlib_service
+                .set_signing_keypair(device_identity.get_keypair());
+        }
+        for forest_index in self.lss_service.get_forest_registry()?.indices() {
+            if let Some(forest_identity) = self
+                .lss_service
+                .get_forest_identity(forest_index)
+                .map_err(UserRetrievalError::from)?
+            {
+                self.catlib_service
+                    .set_forest_keypair(forest_identity.get_keypair());
+            }
+        }
+        tracing::trace!("restoring catlib manifests");
+        self.catlib_service.import_forest(&bundle.forest)?;
+        for forest in &bundle.other_forests {
+            self.catlib_service.import_forest(forest)?;
+        }
+        for template in &bundle.storage_templates {
+            let template =
+                serde_json::from_str(template).map_err(|_| ProfileBundleError::MalformedBundle)?;
+            self.catlib_service.save_storage_template(&template)?;
+        }
+        Ok(())
+    }
+
+    /// Creates an additional forest of the user with this device as its only member.
+    ///
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub(crate) fn create_forest(
+        &self,
+        mnemonic: &MnemonicPayload,
+        name: String,
+    ) -> Result<(), ForestManagementError> {
+        let master_identity = MasterIdentity::new(Some(mnemonic.identity()?));
+        let default_forest_identity = master_identity.create_forest_identity(0)?;
+        if self
             .lss_service
             .get_default_forest_identity()?
+            .map(|identity| identity.get_public_key())
+            != Some(default_forest_identity.get_public_key())
+        {
+            return Err(ForestManagementError::ForestOwnerMismatch);
+        }
+        let mut registry = self.lss_service.get_forest_registry()?;
+        if registry.index_of(&name).is_some() {
+            return Err(ForestManagementError::ForestAlreadyExists(name));
+        }
+        let device_identity = self
+            .lss_service
+            .get_this_device_identity()?
+            .ok_or(ForestManagementError::DeviceNotFound)?;
+        self.catlib_service
+            .set_signing_keypair(device_identity.get_keypair());
+
+        // indices are shared by all devices of the user, so the forest index is taken from the
+        // default forest, skipping the ones of forests not accounted for there yet
+        self.catlib_service
+            .set_forest_keypair(default_forest_identity.get_keypair());
+        let default_forest = self.catlib_service.find_forest(&default_forest_identity)?;
+        let mut forest_index = registry.next_index().max(
+            self.catlib_service
+                .get_parsed_forest_metadata(&default_forest)?
+                .next_forest_index(),
+        );
+        let forest_identity = loop {
+            let forest_identity = master_identity.create_forest_identity(forest_index)?;
+            match self.catlib_service.find_forest(&forest_identity) {
+                Ok(_) => forest_index += 1,
+                Err(CatlibError::NoRecordsFound) => break forest_identity,
+                Err(e) => return Err(e.into()),
+            }
+        };
+        self.catlib_service
+            .reserve_forest_index(&default_forest, forest_index)?;
+        registry.add(forest_index, name);
+        let forest_data_key = master_identity.create_forest_data_key(forest_index)?;
+        self.catlib_service
+            .set_forest_keypair(forest_identity.get_keypair());
+        let forest = self.catlib_service.add_forest(
+            &forest_identity,
+            &device_identity,
+            ForestMetaData::new(vec![DeviceMetadata {
+                name: device_identity.get_identifier(),
+                pubkey: device_identity.get_public_key(),
+            }]),
+        )?;
+
+        tracing::trace!("saving forest {forest_index} to lss");
+        self.lss_service
+            .save_forest_uuid(&*forest.lock().expect("Poisoned Mutex"))?;
+        self.lss_service.save_identity(&forest_identity)?;
+        self.lss_service
+            .save_forest_data_key(forest_index, &forest_data_key)?;
+        self.lss_service.mark_forest_signed(forest_index)?;
+        self.lss_service.save_forest_registry(&registry)?;
+        Ok(())
+    }
+
+    /// Derives and saves data keys of the forests registered on this device which are missing in
+    /// LSS.
+    ///
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub(crate) fn restore_forest_data_keys(
+        &self,
+        mnemonic: &MnemonicPayload,
+    ) -> Result<(), ForestManagementError> {
+        let master_identity = MasterIdentity::new(Some(mnemonic.identity()?));
+        if self
+            .lss_service
+            .get_default_forest_identity()?
+            .map(|identity| identity.get_public_key())
+            != Some(master_identity.create_forest_identity(0)?.get_public_key())
+        {
+            return Err(ForestManagementError::ForestOwnerMismatch);
+        }
+        for forest_index in self.lss_service.get_forest_registry()?.indices() {
+            if self
+                .lss_service
+                .get_forest_data_key(forest_index)?
+                .is_none()
+            {
+                tracing::debug!("restoring data key of forest {forest_index}");
+                self.lss_service.save_forest_data_key(
+                    forest_index,
+                    &master_identity.create_forest_data_key(forest_index)?,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn list_forests(&self) -> Result<Vec<String>, ForestManagementError> {
+        Ok(self
+            .lss_service
+            .get_forest_registry()?
+            .names()
+            .map(str::to_owned)
+            .collect())
+    }
+
+    pub(crate) fn active_forest(&self) -> Result<String, ForestManagementError> {
+        Ok(self
+            .lss_service
+            .get_forest_registry()?
+            .active_name()
+            .to_owned())
+    }
+
+    /// Makes the named forest the one [`Self::get_user`] works on and unmounts containers of the
+    /// previously active forest. The active forest is left unchanged if the user can not be read
+    /// from the named one.
+    ///
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub(crate) fn switch_forest(&self, name: &str) -> Result<CargoUser, ForestManagementError> {
+        let mut registry = self.lss_service.get_forest_registry()?;
+        let forest_index = registry
+            .index_of(name)
+            .ok_or_else(|| ForestManagementError::ForestNotFound(name.to_owned()))?;
+        let user = self
+            .get_user_of_forest(forest_index)?
+            .ok_or(UserRetrievalError::UserNotFound)?;
+
+        let previous_forest_index = registry.active_index();
+        if previous_forest_index != forest_index {
+            self.unmount_forest_containers(&self.get_forest_uuid(previous_forest_index)?)?;
+        }
+        registry.activate(forest_index);
+        self.lss_service.save_forest_registry(&registry)?;
+        Ok(user)
+    }
+
+    /// Removes the named forest with all its containers from CatLib and its keys from LSS.
+    ///
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub(crate) fn delete_forest(&self, name: &str) -> Result<(), ForestManagementError> {
+        let mut registry = self.lss_service.get_forest_registry()?;
+        let forest_index = registry
+            .index_of(name)
+            .ok_or_else(|| ForestManagementError::ForestNotFound(name.to_owned()))?;
+        if forest_index == 0 {
+            return Err(ForestManagementError::CannotDeleteDefaultForest);
+        }
+        if forest_index == registry.active_index() {
+            return Err(ForestManagementError::CannotDeleteActiveForest);
+        }
+
+        if let Some(forest_identity) = self.lss_service.get_forest_identity(forest_index)? {
+            if let Some(device_identity) = self.lss_service.get_this_device_identity()? {
+                self.catlib_service
+                    .set_signing_keypair(device_identity.get_keypair());
+            }
+            if let Some(forest_uuid) = self
+                .lss_service
+                .get_forest_uuid_by_identity(&forest_identity)?
+            {
+                self.unmount_forest_containers(&forest_uuid)?;
+                match self.catlib_service.get_forest(&forest_uuid) {
+                    Ok(forest) => self.catlib_service.delete_forest(&forest)?,
+                    Err(CatlibError::NoRecordsFound) => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            self.lss_service.remove_forest(&forest_identity)?;
+        }
+
+        registry.remove(forest_index);
+        self.lss_service.save_forest_registry(&registry)?;
+        Ok(())
+    }
+
+    /// Unmounts containers of the forest, so that its paths are no longer resolved to storages.
+    ///
+    fn unmount_forest_containers(&self, forest_uuid: &Uuid) -> Result<(), CatlibError> {
+        for container in self.container_manager.mounted_containers() {
+            let container_forest = container.lock().expect("Poisoned Mutex").forest()?;
+            if container_forest.lock().expect("Poisoned Mutex").uuid() == *forest_uuid {
+                if let Err(e) = self.container_manager.unmount(&container) {
+                    tracing::debug!("container of the forest not unmounted: {e}");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Lets CatLib sign manifests of the user's forests with keypairs of their owners and reject
+    /// unsigned ones. Forests created before they were signed by their owners are signed once,
+    /// which is recorded in LSS, out of reach of whoever can modify CatLib database.
+    ///
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn trust_forests(&self) -> Result<(), UserRetrievalError> {
+        for forest_index in self.lss_service.get_forest_registry()?.indices() {
+            let forest_identity = match self.lss_service.get_forest_identity(forest_index)? {
+                Some(forest_identity) => forest_identity,
+                None => continue,
+            };
+            self.catlib_service
+                .set_forest_keypair(forest_identity.get_keypair());
+            if self.lss_service.is_forest_signed(forest_index)? {
+                continue;
+            }
+            let forest_uuid = match self
+                .lss_service
+                .get_forest_uuid_by_identity(&forest_identity)?
+            {
+                Some(forest_uuid) => forest_uuid,
+                None => continue,
+            };
+            match self.catlib_service.sign_legacy_forest(&forest_uuid) {
+                Ok(()) => {
+                    self.lss_service.mark_forest_signed(forest_index)?;
+                }
+                // the forest has not been synchronized to this device yet
+                Err(CatlibError::NoRecordsFound) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Retrieves uuid of the forest with the given index from LSS
+    ///
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn get_forest_uuid(&self, forest_index: u64) -> Result<Uuid, UserRetrievalError> {
+        tracing::debug!("searching for user");
+        let forest_identity = self
+            .lss_service
+            .get_forest_identity(forest_index)?
             .ok_or_else(|| {
                 UserRetrievalError::ForestNotFound("Forest identity keypair not found".to_owned())
             })?;
@@ -30,11 +30,15 @@ pub struct BridgeData {
     pub forest_uuid: Uuid,
     pub path: ContainerPath,
     pub link: Vec<u8>,
+    #[serde(default)]
+    pub signature: Option<RecordSignature>,
 }
 
-impl From<&str> for BridgeData {
-    fn from(data_str: &str) -> Self {
-        ron::from_str(data_str).unwrap()
+impl TryFrom<&str> for BridgeData {
+    type Error = CatlibError;
+
+    fn try_from(data_str: &str) -> CatlibResult<Self> {
+        record::deserialize(data_str)
     }
 }
 
@@ -44,17 +48,23 @@ pub(crate) struct Bridge {
     pub(crate) data: BridgeData,
 
     #[derivative(Debug = "ignore")]
-    pub(crate) db: Rc<StoreDb>,
+    pub(crate) db: Rc<dyn CatLibStore>,
 }
 
 impl Bridge {
-    pub fn new(forest_uuid: Uuid, path: ContainerPath, link: Vec<u8>, db: Rc<StoreDb>) -> Self {
+    pub fn new(
+        forest_uuid: Uuid,
+        path: ContainerPath,
+        link: Vec<u8>,
+        db: Rc<dyn CatLibStore>,
+    ) -> Self {
         Self {
             data: BridgeData {
                 uuid: Uuid::new_v4(),
                 forest_uuid,
                 path,
                 link,
+                signature: None,
             },
             db,
         }
@@ -96,19 +106,15 @@ impl BridgeManifest for Bridge {
 
 impl Model for Bridge {
     fn save(&self) -> CatlibResult<()> {
-        save_model(
-            self.db.clone(),
-            format!("bridge-{}", self.data.uuid),
-            ron::to_string(&self.data).unwrap(),
-        )
+        self.db.save_bridge(&self.data)
     }
 
     fn delete(&mut self) -> CatlibResult<()> {
-        delete_model(self.db.clone(), format!("bridge-{}", self.data.uuid))
+        self.db.delete_bridge(&self.data.uuid)
     }
 
     fn sync(&mut self) -> CatlibResult<()> {
-        let data = fetch_bridge_data_by_uuid(self.db.clone(), &self.data.uuid)?;
+        let data = self.db.bridge(&self.data.uuid)?;
         self.data = data;
         Ok(())
     }
@@ -117,6 +123,7 @@ impl Model for Bridge {
 #[cfg(test)]
 mod tests {
     use rstest::*;
+    use wildland_corex::catlib_service::CatLibService;
 
     use super::db::test::catlib;
     use crate::*;
@@ -148,4 +155,32 @@ mod tests {
 
         assert_eq!(bridge.err(), Some(CatlibError::NoRecordsFound));
     }
+
+    #[rstest]
+    fn bridges_are_deleted_with_forest(catlib: CatLib) {
+        let forest = catlib
+            .create_forest(Identity([1; 32]), Signers::new(), vec![])
+            .unwrap();
+        let other_forest = catlib
+            .create_forest(Identity([2; 32]), Signers::new(), vec![])
+            .unwrap();
+        for forest in [&forest, &other_forest] {
+            forest
+                .lock()
+                .unwrap()
+                .create_bridge("/other/forest".to_string(), vec![])
+                .unwrap();
+        }
+        assert_eq!(forest.lock().unwrap().bridges().unwrap().len(), 1);
+
+        CatLibService::new(Rc::new(catlib.clone()))
+            .delete_forest(&forest)
+            .unwrap();
+
+        assert_eq!(
+            forest.lock().unwrap().bridges().err(),
+            Some(CatlibError::NoRecordsFound)
+        );
+        assert_eq!(other_forest.lock().unwrap().bridges().unwrap().len(), 1);
+    }
 }
@@ -37,11 +37,15 @@ pub struct ContainerData {
     pub forest_uuid: Uuid,
     pub name: String,
     pub paths: ContainerPaths,
+    #[serde(default)]
+    pub signature: Option<RecordSignature>,
 }
 
-impl From<&str> for ContainerData {
-    fn from(str_data: &str) -> Self {
-        ron::from_str(str_data).unwrap()
+impl TryFrom<&str> for ContainerData {
+    type Error = CatlibError;
+
+    fn try_from(str_data: &str) -> CatlibResult<Self> {
+        record::deserialize(str_data)
     }
 }
 
@@ -52,7 +56,7 @@ pub(crate) struct Container {
     pub(crate) forest_owner: Arc<Mutex<dyn ForestManifest>>,
     pub(crate) storages: Vec<Arc<Mutex<dyn StorageManifest>>>,
     #[derivative(Debug = "ignore")]
-    pub(crate) db: Rc<StoreDb>,
+    pub(crate) db: Rc<dyn CatLibStore>,
 }
 
 impl Container {
@@ -61,7 +65,7 @@ impl Container {
         storage_template: &StorageTemplate,
         name: String,
         path: ContainerPath,
-        db: Rc<StoreDb>,
+        db: Rc<dyn CatLibStore>,
     ) -> Result<Self, CatlibError> {
         let container_uuid = Uuid::new_v4();
         let forest_uuid = forest_owner.lock().expect("Poisoned Mutex").uuid();
@@ -70,6 +74,7 @@ impl Container {
             forest_uuid,
             name,
             paths: ContainerPaths::from([path]),
+            signature: None,
         };
         let mut container = Self {
             container_data,
@@ -89,7 +94,7 @@ impl Container {
 
     pub fn from_container_data(
         container_data: ContainerData,
-        db: Rc<StoreDb>,
+        db: Rc<dyn CatLibStore>,
     ) -> Result<Self, CatlibError> {
         let container_uuid = container_data.uuid;
         let forest_uuid = container_data.forest_uuid;
@@ -290,23 +295,15 @@ impl ContainerManifest for Container {
 
 impl Model for Container {
     fn save(&self) -> CatlibResult<()> {
-        save_model(
-            self.db.clone(),
-            format!("container-{}", self.container_data.uuid),
-            ron::to_string(&self.container_data).unwrap(),
-        )
+        self.db.save_container(&self.container_data)
     }
 
     fn delete(&mut self) -> CatlibResult<()> {
-        delete_model(
-            self.db.clone(),
-            format!("container-{}", self.container_data.uuid),
-        )
+        self.db.delete_container(&self.container_data.uuid)
     }
 
     fn sync(&mut self) -> CatlibResult<()> {
-        self.container_data =
-            fetch_container_data_by_uuid(self.db.clone(), &self.container_data.uuid)?;
+        self.container_data = self.db.container(&self.container_data.uuid)?;
         self.storages =
             fetch_storages_by_container_uuid(self.db.clone(), &self.container_data.uuid)?;
         Ok(())
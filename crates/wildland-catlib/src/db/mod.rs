@@ -0,0 +1,178 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Persistence of CatLib records. Entities operate on records through [`CatLibStore`], so they do
+//! not depend on the database backend.
+
+pub(crate) mod record;
+mod rustbreak;
+mod signed;
+mod sqlite;
+
+use serde::{Deserialize, Serialize};
+use wildland_corex::catlib_service::entities::{
+    ContainerManifest,
+    ContainerPath,
+    ForestManifest,
+    StorageManifest,
+};
+
+pub(crate) use self::rustbreak::{CatLibData, RustbreakStore};
+pub use self::signed::RecordSignature;
+pub(crate) use self::signed::{SignedRecord, SignedStore};
+pub(crate) use self::sqlite::SqliteStore;
+use super::*;
+use crate::bridge::BridgeData;
+use crate::container::ContainerData;
+use crate::forest::ForestData;
+use crate::storage::StorageData;
+
+/// Database backend keeping CatLib records.
+///
+/// Methods fetching a single record return [`CatlibError::NoRecordsFound`] if there is no such
+/// record, while methods looking for many records return an empty vector.
+pub(crate) trait CatLibStore {
+    fn save_forest(&self, forest: &ForestData) -> CatlibResult<()>;
+    fn delete_forest(&self, uuid: &Uuid) -> CatlibResult<()>;
+    fn forest(&self, uuid: &Uuid) -> CatlibResult<ForestData>;
+    fn forests_of_owner(&self, owner: &Identity) -> CatlibResult<Vec<ForestData>>;
+
+    fn save_container(&self, container: &ContainerData) -> CatlibResult<()>;
+    fn delete_container(&self, uuid: &Uuid) -> CatlibResult<()>;
+    fn container(&self, uuid: &Uuid) -> CatlibResult<ContainerData>;
+    fn containers_of_forest(&self, forest_uuid: &Uuid) -> CatlibResult<Vec<ContainerData>>;
+    /// Returns containers of the forest claiming any of the `paths` or, if `include_subdirs` is
+    /// set, any path starting with one of them.
+    fn containers_with_paths(
+        &self,
+        forest_uuid: &Uuid,
+        paths: &[ContainerPath],
+        include_subdirs: bool,
+    ) -> CatlibResult<Vec<ContainerData>>;
+
+    fn save_storage(&self, storage: &StorageData) -> CatlibResult<()>;
+    fn delete_storage(&self, uuid: &Uuid) -> CatlibResult<()>;
+    fn storage(&self, uuid: &Uuid) -> CatlibResult<StorageData>;
+    fn storages_of_container(&self, container_uuid: &Uuid) -> CatlibResult<Vec<StorageData>>;
+    fn storages_with_template(&self, template_uuid: &Uuid) -> CatlibResult<Vec<StorageData>>;
+
+    fn save_bridge(&self, bridge: &BridgeData) -> CatlibResult<()>;
+    fn delete_bridge(&self, uuid: &Uuid) -> CatlibResult<()>;
+    fn bridge(&self, uuid: &Uuid) -> CatlibResult<BridgeData>;
+    fn bridges_of_forest(&self, forest_uuid: &Uuid) -> CatlibResult<Vec<BridgeData>>;
+    fn bridges_with_path(
+        &self,
+        forest_uuid: &Uuid,
+        path: &ContainerPath,
+    ) -> CatlibResult<Vec<BridgeData>>;
+
+    fn save_storage_template(&self, uuid: &Uuid, value: String) -> CatlibResult<()>;
+    fn storage_templates(&self) -> CatlibResult<Vec<String>>;
+}
+
+/// All records of a forest, as exported by [`crate::CatLib`] and exchanged between devices.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ForestRecords {
+    pub(crate) forest: ForestData,
+    pub(crate) containers: Vec<ContainerData>,
+    pub(crate) storages: Vec<StorageData>,
+    pub(crate) bridges: Vec<BridgeData>,
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub(crate) fn fetch_forest_records(
+    db: &dyn CatLibStore,
+    forest_uuid: &Uuid,
+) -> CatlibResult<ForestRecords> {
+    let forest = db.forest(forest_uuid)?;
+    let containers = db.containers_of_forest(forest_uuid)?;
+    let mut storages = Vec::new();
+    for container in &containers {
+        storages.extend(db.storages_of_container(&container.uuid)?);
+    }
+    let bridges = db.bridges_of_forest(forest_uuid)?;
+    Ok(ForestRecords {
+        forest,
+        containers,
+        storages,
+        bridges,
+    })
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub(crate) fn fetch_forest_by_uuid(
+    db: Rc<dyn CatLibStore>,
+    uuid: &Uuid,
+) -> CatlibResult<Arc<Mutex<dyn ForestManifest>>> {
+    let data = db.forest(uuid)?;
+    let forest = Forest { data, db };
+    Ok(Arc::new(Mutex::new(forest)))
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub(crate) fn fetch_container_by_uuid(
+    db: Rc<dyn CatLibStore>,
+    uuid: &Uuid,
+) -> CatlibResult<Arc<Mutex<dyn ContainerManifest>>> {
+    let container_data = db.container(uuid)?;
+    Container::from_container_data(container_data, db)
+        .map(|c| Arc::new(Mutex::new(c)) as Arc<Mutex<dyn ContainerManifest>>)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub(crate) fn fetch_storages_by_container_uuid(
+    db: Rc<dyn CatLibStore>,
+    uuid: &Uuid,
+) -> CatlibResult<Vec<Arc<Mutex<dyn StorageManifest>>>> {
+    let storages: Vec<_> = db
+        .storages_of_container(uuid)?
+        .into_iter()
+        .map(|storage_data| {
+            Arc::new(Mutex::new(StorageEntity {
+                data: storage_data,
+                db: db.clone(),
+            })) as Arc<Mutex<dyn StorageManifest>>
+        })
+        .collect();
+
+    match storages.len() {
+        0 => Err(CatlibError::NoRecordsFound),
+        _ => Ok(storages),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+
+    use rstest::fixture;
+
+    #[fixture]
+    pub fn catlib() -> crate::CatLib {
+        let random = rand::random::<uuid::Bytes>();
+        let uuid = uuid::Builder::from_random_bytes(random).into_uuid();
+        let dir = tempfile::tempdir().unwrap().into_path();
+        let path = dir.join(format!("{uuid}-db.ron"));
+        crate::CatLib::new(path)
+    }
+
+    #[fixture]
+    pub fn sqlite_catlib() -> crate::CatLib {
+        let dir = tempfile::tempdir().unwrap().into_path();
+        crate::CatLib::with_backend(crate::CatLibBackend::Sqlite, dir.join("catlib.sqlite"))
+            .unwrap()
+    }
+}
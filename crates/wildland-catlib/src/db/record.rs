@@ -0,0 +1,117 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Envelope of records serialized by CatLib. Every record is stored along with the version of its
+//! layout, so records written by older versions of CatLib can be recognized and migrated.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{CatlibError, CatlibResult};
+
+/// Version of records layout written by this version of CatLib.
+pub(crate) const RECORD_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct VersionedRecord<'a, T> {
+    version: u32,
+    record: &'a T,
+}
+
+#[derive(Deserialize)]
+struct RecordContent<T> {
+    record: T,
+}
+
+#[derive(Deserialize)]
+struct RecordVersion {
+    version: u32,
+}
+
+/// Serializes the record in the current version of the envelope.
+pub(crate) fn serialize<T: Serialize>(record: &T) -> CatlibResult<String> {
+    ron::to_string(&VersionedRecord {
+        version: RECORD_VERSION,
+        record,
+    })
+    .map_err(|e| CatlibError::Generic(format!("Could not serialize record: {e}")))
+}
+
+/// Deserializes a record in the current version of the envelope.
+///
+/// ## Errors
+///
+/// - [`CatlibError::UnsupportedSchemaVersion`] if the record was written by a newer CatLib.
+/// - [`CatlibError::MalformedDatabaseRecord`] if the record could not be parsed.
+pub(crate) fn deserialize<T: DeserializeOwned>(serialized: &str) -> CatlibResult<T> {
+    let version = ron::from_str::<RecordVersion>(serialized)
+        .map_err(|_| CatlibError::MalformedDatabaseRecord)?
+        .version;
+    if version > RECORD_VERSION {
+        return Err(CatlibError::UnsupportedSchemaVersion(version));
+    }
+    ron::from_str::<RecordContent<T>>(serialized)
+        .map(|content| content.record)
+        .map_err(|_| CatlibError::MalformedDatabaseRecord)
+}
+
+/// Deserializes a record written before records were versioned, i.e. as a bare RON structure.
+pub(crate) fn deserialize_unversioned<T: DeserializeOwned>(serialized: &str) -> CatlibResult<T> {
+    ron::from_str(serialized).map_err(|_| CatlibError::MalformedDatabaseRecord)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Data {
+        field: String,
+    }
+
+    #[test]
+    fn record_is_deserialized_from_envelope() {
+        let data = Data {
+            field: "value".to_owned(),
+        };
+        let serialized = serialize(&data).unwrap();
+        assert_eq!(deserialize::<Data>(&serialized).unwrap(), data);
+    }
+
+    #[test]
+    fn record_of_newer_version_is_rejected() {
+        let serialized = r#"(version: 2, record: (field: "value", new_field: 1))"#;
+        assert_eq!(
+            deserialize::<Data>(serialized),
+            Err(CatlibError::UnsupportedSchemaVersion(2))
+        );
+    }
+
+    #[test]
+    fn malformed_record_is_reported() {
+        assert_eq!(
+            deserialize::<Data>("(version: 1, record: (other: 1))"),
+            Err(CatlibError::MalformedDatabaseRecord)
+        );
+        assert_eq!(
+            deserialize::<Data>(r#"(field: "value")"#),
+            Err(CatlibError::MalformedDatabaseRecord)
+        );
+    }
+}
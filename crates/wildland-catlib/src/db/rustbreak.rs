@@ -0,0 +1,374 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+
+use ::rustbreak::deser::Ron;
+use ::rustbreak::PathDatabase;
+use wildland_corex::catlib_service::entities::ContainerPath;
+
+use super::*;
+use crate::error::to_catlib_error;
+
+pub(crate) type CatLibData = HashMap<String, String>;
+type StoreDb = PathDatabase<CatLibData, Ron>;
+
+/// Key of the record keeping version of the database, i.e. number of migrations applied to it.
+/// Databases without it were written before migrations were introduced and have version 0.
+const SCHEMA_VERSION_KEY: &str = "schema-version";
+
+/// Migrations of the database. The one at index `n` upgrades the database from version `n` to
+/// `n + 1`, so the length of the list is the current version.
+const MIGRATIONS: &[fn(&mut CatLibData) -> CatlibResult<()>] = &[wrap_records_in_envelope];
+
+/// Wraps records, which used to be stored as bare RON structures, in [`record`] envelope.
+fn wrap_records_in_envelope(data: &mut CatLibData) -> CatlibResult<()> {
+    for (key, value) in data.iter_mut() {
+        let versioned = if key.starts_with("forest-") {
+            record::serialize(&record::deserialize_unversioned::<ForestData>(value)?)
+        } else if key.starts_with("container-") {
+            record::serialize(&record::deserialize_unversioned::<ContainerData>(value)?)
+        } else if key.starts_with("storage-") {
+            record::serialize(&record::deserialize_unversioned::<StorageData>(value)?)
+        } else if key.starts_with("bridge-") {
+            record::serialize(&record::deserialize_unversioned::<BridgeData>(value)?)
+        } else {
+            continue;
+        };
+        *value = versioned?;
+    }
+    Ok(())
+}
+
+fn schema_version(data: &CatLibData) -> CatlibResult<usize> {
+    data.get(SCHEMA_VERSION_KEY)
+        .map(|version| {
+            version
+                .parse()
+                .map_err(|_| CatlibError::MalformedDatabaseRecord)
+        })
+        .unwrap_or(Ok(0))
+}
+
+/// Applies migrations missing in the database.
+///
+/// ## Errors
+///
+/// Returns [`CatlibError::UnsupportedSchemaVersion`] if the database was written by a newer
+/// version of CatLib.
+fn migrate(data: &mut CatLibData) -> CatlibResult<()> {
+    let version = schema_version(data)?;
+    if version > MIGRATIONS.len() {
+        return Err(CatlibError::UnsupportedSchemaVersion(version as u32));
+    }
+    for (from_version, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        tracing::info!("Migrating CatLib database from version {from_version}");
+        migration(data)?;
+    }
+    data.insert(SCHEMA_VERSION_KEY.to_owned(), MIGRATIONS.len().to_string());
+    Ok(())
+}
+
+/// Keeps all records in a single RON file, as serialized structures under keys prefixed with the
+/// record type, e.g. `container-<uuid>`. Every operation reloads the whole file.
+pub(crate) struct RustbreakStore {
+    db: StoreDb,
+}
+
+impl RustbreakStore {
+    /// Wraps the database, migrating it to the current schema version if needed.
+    pub(crate) fn new(db: StoreDb) -> CatlibResult<Self> {
+        db.load().map_err(to_catlib_error)?;
+        db.write(migrate).map_err(to_catlib_error)??;
+        db.save().map_err(to_catlib_error)?;
+        Ok(Self { db })
+    }
+
+    fn records_with_prefix<T>(
+        &self,
+        prefix: &str,
+        parse: impl Fn(&str) -> CatlibResult<T>,
+    ) -> CatlibResult<Vec<T>> {
+        self.db.load().map_err(to_catlib_error)?;
+        self.db
+            .read(|db| {
+                db.iter()
+                    .filter(|(id, _)| id.starts_with(prefix))
+                    .map(|(_, record)| parse(record.as_str()))
+                    .collect()
+            })
+            .map_err(to_catlib_error)?
+    }
+
+    fn record<T>(&self, key: &str, parse: impl Fn(&str) -> CatlibResult<T>) -> CatlibResult<T> {
+        let mut records = self.records_with_prefix(key, parse)?;
+        match records.len() {
+            0 => Err(CatlibError::NoRecordsFound),
+            1 => Ok(records.remove(0)),
+            _ => Err(CatlibError::MalformedDatabaseRecord),
+        }
+    }
+
+    fn save(&self, key: String, record: String) -> CatlibResult<()> {
+        self.db.load().map_err(to_catlib_error)?;
+
+        self.db
+            .write(|db| db.insert(key, record))
+            .map_err(to_catlib_error)?;
+
+        self.db.save().map_err(to_catlib_error)
+    }
+
+    fn delete(&self, key: String) -> CatlibResult<()> {
+        self.db.load().map_err(to_catlib_error)?;
+
+        self.db
+            .write(|db| db.remove_entry(&key))
+            .map_err(to_catlib_error)?;
+
+        self.db.save().map_err(to_catlib_error)
+    }
+}
+
+impl CatLibStore for RustbreakStore {
+    fn save_forest(&self, forest: &ForestData) -> CatlibResult<()> {
+        self.save(
+            format!("forest-{}", forest.uuid),
+            record::serialize(forest)?,
+        )
+    }
+
+    fn delete_forest(&self, uuid: &Uuid) -> CatlibResult<()> {
+        self.delete(format!("forest-{uuid}"))
+    }
+
+    fn forest(&self, uuid: &Uuid) -> CatlibResult<ForestData> {
+        self.record(&format!("forest-{uuid}"), |record| {
+            ForestData::try_from(record)
+        })
+    }
+
+    fn forests_of_owner(&self, owner: &Identity) -> CatlibResult<Vec<ForestData>> {
+        Ok(self
+            .records_with_prefix("forest-", |record| ForestData::try_from(record))?
+            .into_iter()
+            .filter(|forest| &forest.owner == owner)
+            .collect())
+    }
+
+    fn save_container(&self, container: &ContainerData) -> CatlibResult<()> {
+        self.save(
+            format!("container-{}", container.uuid),
+            record::serialize(container)?,
+        )
+    }
+
+    fn delete_container(&self, uuid: &Uuid) -> CatlibResult<()> {
+        self.delete(format!("container-{uuid}"))
+    }
+
+    fn container(&self, uuid: &Uuid) -> CatlibResult<ContainerData> {
+        self.record(&format!("container-{uuid}"), |record| {
+            ContainerData::try_from(record)
+        })
+    }
+
+    fn containers_of_forest(&self, forest_uuid: &Uuid) -> CatlibResult<Vec<ContainerData>> {
+        Ok(self
+            .records_with_prefix("container-", |record| ContainerData::try_from(record))?
+            .into_iter()
+            .filter(|container| &container.forest_uuid == forest_uuid)
+            .collect())
+    }
+
+    fn containers_with_paths(
+        &self,
+        forest_uuid: &Uuid,
+        paths: &[ContainerPath],
+        include_subdirs: bool,
+    ) -> CatlibResult<Vec<ContainerData>> {
+        Ok(self
+            .containers_of_forest(forest_uuid)?
+            .into_iter()
+            .filter(|container| {
+                container.paths.iter().any(|container_path| {
+                    paths.iter().any(|path| {
+                        (include_subdirs && container_path.starts_with(path))
+                            || container_path.eq(path)
+                    })
+                })
+            })
+            .collect())
+    }
+
+    fn save_storage(&self, storage: &StorageData) -> CatlibResult<()> {
+        self.save(
+            format!("storage-{}", storage.uuid),
+            record::serialize(storage)?,
+        )
+    }
+
+    fn delete_storage(&self, uuid: &Uuid) -> CatlibResult<()> {
+        self.delete(format!("storage-{uuid}"))
+    }
+
+    fn storage(&self, uuid: &Uuid) -> CatlibResult<StorageData> {
+        self.record(&format!("storage-{uuid}"), |record| {
+            StorageData::try_from(record)
+        })
+    }
+
+    fn storages_of_container(&self, container_uuid: &Uuid) -> CatlibResult<Vec<StorageData>> {
+        Ok(self
+            .records_with_prefix("storage-", |record| StorageData::try_from(record))?
+            .into_iter()
+            .filter(|storage| &storage.container_uuid == container_uuid)
+            .collect())
+    }
+
+    fn storages_with_template(&self, template_uuid: &Uuid) -> CatlibResult<Vec<StorageData>> {
+        Ok(self
+            .records_with_prefix("storage-", |record| StorageData::try_from(record))?
+            .into_iter()
+            .filter(|storage| storage.template_uuid.as_ref() == Some(template_uuid))
+            .collect())
+    }
+
+    fn save_bridge(&self, bridge: &BridgeData) -> CatlibResult<()> {
+        self.save(
+            format!("bridge-{}", bridge.uuid),
+            record::serialize(bridge)?,
+        )
+    }
+
+    fn delete_bridge(&self, uuid: &Uuid) -> CatlibResult<()> {
+        self.delete(format!("bridge-{uuid}"))
+    }
+
+    fn bridge(&self, uuid: &Uuid) -> CatlibResult<BridgeData> {
+        self.record(&format!("bridge-{uuid}"), |record| {
+            BridgeData::try_from(record)
+        })
+    }
+
+    fn bridges_of_forest(&self, forest_uuid: &Uuid) -> CatlibResult<Vec<BridgeData>> {
+        Ok(self
+            .records_with_prefix("bridge-", |record| BridgeData::try_from(record))?
+            .into_iter()
+            .filter(|bridge| &bridge.forest_uuid == forest_uuid)
+            .collect())
+    }
+
+    fn bridges_with_path(
+        &self,
+        forest_uuid: &Uuid,
+        path: &ContainerPath,
+    ) -> CatlibResult<Vec<BridgeData>> {
+        Ok(self
+            .records_with_prefix("bridge-", |record| BridgeData::try_from(record))?
+            .into_iter()
+            .filter(|bridge| &bridge.forest_uuid == forest_uuid && &bridge.path == path)
+            .collect())
+    }
+
+    fn save_storage_template(&self, uuid: &Uuid, value: String) -> CatlibResult<()> {
+        self.save(format!("template-storage-{uuid}"), value)
+    }
+
+    fn storage_templates(&self) -> CatlibResult<Vec<String>> {
+        self.records_with_prefix("template-storage-", |template| Ok(template.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn database_with(records: CatLibData) -> PathBuf {
+        let path = tempfile::tempdir().unwrap().into_path().join("db.ron");
+        StoreDb::create_at_path(path.clone(), records)
+            .unwrap()
+            .save()
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn unversioned_records_are_migrated() {
+        let forest = ForestData {
+            uuid: Uuid::new_v4(),
+            signers: Signers::new(),
+            owner: Identity([1; 32]),
+            data: b"data".to_vec(),
+            signature: None,
+        };
+        let path = database_with(CatLibData::from([
+            (
+                format!("forest-{}", forest.uuid),
+                ron::to_string(&forest).unwrap(),
+            ),
+            ("template-storage-1".to_owned(), "template".to_owned()),
+        ]));
+
+        let catlib = CatLib::with_backend(CatLibBackend::Rustbreak, path.clone()).unwrap();
+        let found = catlib.get_forest(&forest.uuid).unwrap();
+        assert_eq!(found.lock().unwrap().data().unwrap(), b"data".to_vec());
+        assert_eq!(
+            catlib.get_storage_templates_data().unwrap(),
+            vec!["template".to_owned()]
+        );
+
+        // migrated database is opened again without any changes
+        let catlib = CatLib::with_backend(CatLibBackend::Rustbreak, path).unwrap();
+        assert!(catlib.get_forest(&forest.uuid).is_ok());
+    }
+
+    #[test]
+    fn database_of_newer_version_is_rejected() {
+        let path = database_with(CatLibData::from([(
+            SCHEMA_VERSION_KEY.to_owned(),
+            (MIGRATIONS.len() + 1).to_string(),
+        )]));
+
+        assert_eq!(
+            CatLib::with_backend(CatLibBackend::Rustbreak, path).err(),
+            Some(CatlibError::UnsupportedSchemaVersion(
+                MIGRATIONS.len() as u32 + 1
+            ))
+        );
+    }
+
+    #[test]
+    fn malformed_record_is_reported() {
+        let uuid = Uuid::new_v4();
+        let path = database_with(CatLibData::from([
+            (SCHEMA_VERSION_KEY.to_owned(), MIGRATIONS.len().to_string()),
+            (
+                format!("forest-{uuid}"),
+                "(version: 1, record: ())".to_owned(),
+            ),
+        ]));
+
+        let catlib = CatLib::with_backend(CatLibBackend::Rustbreak, path).unwrap();
+        assert_eq!(
+            catlib.get_forest(&uuid).err(),
+            Some(CatlibError::MalformedDatabaseRecord)
+        );
+    }
+}
@@ -0,0 +1,731 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Signatures of CatLib records.
+//!
+//! Records are signed with the keypair of this device when they are saved and verified against
+//! the signers of the forest they belong to when they are loaded, so records modified outside of
+//! Wildland are rejected with [`CatlibError::InvalidRecordSignature`].
+//!
+//! Signers of a forest are trusted only if the forest record itself is signed by the forest owner,
+//! or by a signer already trusted on this device, so they can not be extended by whoever is able
+//! to write the database. Forest records are signed with the owner keypair if it was set with
+//! [`SignedStore::set_forest_keypair`].
+//!
+//! Records written before signing was introduced, or by CatLib without a signing keypair, are
+//! unsigned. They are accepted with a warning unless the forest they belong to is signed, or is
+//! known to be signed on this device.
+// TODO WILX-373 reject unsigned records once databases written without signatures are migrated
+
+use std::cell::RefCell;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use wildland_corex::catlib_service::entities::{ContainerPath, Signers};
+use wildland_corex::SigningKeypair;
+use wildland_crypto::signature::Signature;
+
+use super::*;
+
+/// Signature of a record made by one of the signers of the forest the record belongs to.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct RecordSignature {
+    pub signer: Identity,
+    pub signature: Vec<u8>,
+}
+
+/// Record covered by a signature. The signed content includes all fields of the record but the
+/// signature itself.
+pub(crate) trait SignedRecord: Clone {
+    fn signed_content(&self) -> Vec<u8>;
+    fn signature(&self) -> Option<&RecordSignature>;
+    fn set_signature(&mut self, signature: Option<RecordSignature>);
+}
+
+/// Unambiguous encoding of record fields, each field is prefixed with its length.
+struct SignedContent(Vec<u8>);
+
+impl SignedContent {
+    fn new(record_type: &str) -> Self {
+        Self(Vec::new()).field(record_type)
+    }
+
+    fn field(mut self, bytes: impl AsRef<[u8]>) -> Self {
+        let bytes = bytes.as_ref();
+        self.0.extend((bytes.len() as u64).to_le_bytes());
+        self.0.extend(bytes);
+        self
+    }
+
+    /// Encodes fields of an unordered collection, sorted to make the content deterministic.
+    fn sorted_fields<T: AsRef<[u8]>>(self, fields: impl IntoIterator<Item = T>) -> Self {
+        let mut fields: Vec<Vec<u8>> = fields
+            .into_iter()
+            .map(|field| field.as_ref().to_vec())
+            .collect();
+        fields.sort();
+        fields.iter().fold(
+            self.field((fields.len() as u64).to_le_bytes()),
+            |content, field| content.field(field),
+        )
+    }
+}
+
+impl SignedRecord for ForestData {
+    fn signed_content(&self) -> Vec<u8> {
+        SignedContent::new("forest")
+            .field(self.uuid)
+            .field(self.owner.0)
+            .sorted_fields(self.signers.iter().map(|signer| signer.0))
+            .field(&self.data)
+            .0
+    }
+
+    fn signature(&self) -> Option<&RecordSignature> {
+        self.signature.as_ref()
+    }
+
+    fn set_signature(&mut self, signature: Option<RecordSignature>) {
+        self.signature = signature;
+    }
+}
+
+impl SignedRecord for ContainerData {
+    fn signed_content(&self) -> Vec<u8> {
+        SignedContent::new("container")
+            .field(self.uuid)
+            .field(self.forest_uuid)
+            .field(&self.name)
+            .sorted_fields(&self.paths)
+            .0
+    }
+
+    fn signature(&self) -> Option<&RecordSignature> {
+        self.signature.as_ref()
+    }
+
+    fn set_signature(&mut self, signature: Option<RecordSignature>) {
+        self.signature = signature;
+    }
+}
+
+impl SignedRecord for StorageData {
+    fn signed_content(&self) -> Vec<u8> {
+        SignedContent::new("storage")
+            .field(self.uuid)
+            .field(self.container_uuid)
+            .field(
+                self.template_uuid
+                    .map(|uuid| uuid.into_bytes())
+                    .unwrap_or_default(),
+            )
+            .field(&self.data)
+            .0
+    }
+
+    fn signature(&self) -> Option<&RecordSignature> {
+        self.signature.as_ref()
+    }
+
+    fn set_signature(&mut self, signature: Option<RecordSignature>) {
+        self.signature = signature;
+    }
+}
+
+impl SignedRecord for BridgeData {
+    fn signed_content(&self) -> Vec<u8> {
+        SignedContent::new("bridge")
+            .field(self.uuid)
+            .field(self.forest_uuid)
+            .field(&self.path)
+            .field(&self.link)
+            .0
+    }
+
+    fn signature(&self) -> Option<&RecordSignature> {
+        self.signature.as_ref()
+    }
+
+    fn set_signature(&mut self, signature: Option<RecordSignature>) {
+        self.signature = signature;
+    }
+}
+
+fn verify_signature<T: SignedRecord>(record: &T, signature: &RecordSignature) -> CatlibResult<()> {
+    Signature::try_from(signature.signature.as_slice())
+        .and_then(|sig| sig.verify(&record.signed_content(), &signature.signer.0))
+        .map_err(|_| CatlibError::InvalidRecordSignature)
+}
+
+/// Verifies signature of the record against signers of the forest it belongs to.
+fn verify<T: SignedRecord>(record: &T, forest: &ForestData) -> CatlibResult<()> {
+    match record.signature() {
+        Some(signature) => {
+            if !forest.signers.contains(&signature.signer) {
+                return Err(CatlibError::InvalidRecordSignature);
+            }
+            verify_signature(record, signature)
+        }
+        None if forest.signature.is_some() => Err(CatlibError::InvalidRecordSignature),
+        None => {
+            tracing::warn!("Unsigned record found in forest {}", forest.uuid);
+            Ok(())
+        }
+    }
+}
+
+/// Store signing records saved to and verifying records loaded from the inner store.
+pub(crate) struct SignedStore {
+    inner: Box<dyn CatLibStore>,
+    keypair: RefCell<Option<SigningKeypair>>,
+    /// Keypairs of forest owners, signing forest records
+    forest_keypairs: RefCell<HashMap<Identity, SigningKeypair>>,
+    /// Signers of forests whose records were signed by a trusted party, by forest UUID
+    trusted_signers: RefCell<HashMap<Uuid, Signers>>,
+}
+
+impl SignedStore {
+    pub(crate) fn new(inner: Box<dyn CatLibStore>) -> Self {
+        Self {
+            inner,
+            keypair: RefCell::new(None),
+            forest_keypairs: RefCell::new(HashMap::new()),
+            trusted_signers: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Sets keypair used to sign records saved from now on. Without a keypair records are saved
+    /// unsigned.
+    pub(crate) fn set_signing_keypair(&self, keypair: SigningKeypair) {
+        self.keypair.replace(Some(keypair));
+    }
+
+    /// Returns keypair set with [`SignedStore::set_signing_keypair`].
+    pub(crate) fn signing_keypair(&self) -> Option<SigningKeypair> {
+        self.keypair.borrow().as_ref().map(SigningKeypair::from)
+    }
+
+    /// Sets keypair of a forest owner. Records of forests of the owner are signed with it from now
+    /// on, and they are rejected if unsigned.
+    pub(crate) fn set_forest_keypair(&self, keypair: SigningKeypair) {
+        self.forest_keypairs
+            .borrow_mut()
+            .insert(Identity(keypair.public()), keypair);
+    }
+
+    /// Signs the forest written before forests were signed by their owners with the owner
+    /// keypair, which must be set with [`SignedStore::set_forest_keypair`]. The forest is accepted
+    /// if it is unsigned or signed by one of its signers. Forests signed by the owner are only
+    /// verified.
+    pub(crate) fn sign_legacy_forest(&self, uuid: &Uuid) -> CatlibResult<()> {
+        let forest = self.inner.forest(uuid)?;
+        if forest.signature.as_ref().map(|signature| &signature.signer) == Some(&forest.owner) {
+            return self.verify_forest(&forest);
+        }
+        if !self.forest_keypairs.borrow().contains_key(&forest.owner) {
+            return Err(CatlibError::Generic(
+                "Keypair of the forest owner not set".to_owned(),
+            ));
+        }
+        verify(&forest, &forest)?;
+        tracing::info!("Signing forest {uuid} with the owner keypair");
+        self.save_forest(&forest)
+    }
+
+    /// Returns the store saving records as they are, for records already signed elsewhere
+    pub(crate) fn inner(&self) -> &dyn CatLibStore {
+        self.inner.as_ref()
+    }
+
+    /// Verifies the record received from another device against the forest it belongs to.
+    pub(crate) fn verify_received<T: SignedRecord>(
+        &self,
+        record: &T,
+        forest: &ForestData,
+    ) -> CatlibResult<()> {
+        verify(record, forest)
+    }
+
+    /// Verifies that the forest received from another device belongs to and is signed by the
+    /// `owner`, known to this device.
+    pub(crate) fn verify_received_forest(
+        &self,
+        forest: &ForestData,
+        owner: &Identity,
+    ) -> CatlibResult<()> {
+        match &forest.signature {
+            Some(signature) if &signature.signer == owner && &forest.owner == owner => {
+                verify_signature(forest, signature)
+            }
+            _ => Err(CatlibError::InvalidRecordSignature),
+        }
+    }
+
+    fn sign<T: SignedRecord>(&self, record: &T) -> T {
+        Self::signed_with(record, self.keypair.borrow().as_ref())
+    }
+
+    fn signed_with<T: SignedRecord>(record: &T, keypair: Option<&SigningKeypair>) -> T {
+        let mut signed = record.clone();
+        signed.set_signature(keypair.map(|keypair| RecordSignature {
+            signer: Identity(keypair.public()),
+            signature: keypair.sign(&record.signed_content()).to_bytes(),
+        }));
+        signed
+    }
+
+    /// Verifies that the forest record is signed by its owner or by a signer trusted on this
+    /// device, and trusts its signers afterwards.
+    fn verify_forest(&self, forest: &ForestData) -> CatlibResult<()> {
+        let mut trusted_signers = self.trusted_signers.borrow_mut();
+        match &forest.signature {
+            Some(signature) => {
+                let trusted = signature.signer == forest.owner
+                    || trusted_signers
+                        .get(&forest.uuid)
+                        .is_some_and(|signers| signers.contains(&signature.signer));
+                if !trusted {
+                    return Err(CatlibError::InvalidRecordSignature);
+                }
+                verify_signature(forest, signature)?;
+                trusted_signers.insert(forest.uuid, forest.signers.clone());
+                Ok(())
+            }
+            None if trusted_signers.contains_key(&forest.uuid)
+                || self.forest_keypairs.borrow().contains_key(&forest.owner) =>
+            {
+                Err(CatlibError::InvalidRecordSignature)
+            }
+            None => {
+                tracing::warn!("Unsigned forest {} found", forest.uuid);
+                Ok(())
+            }
+        }
+    }
+
+    fn verified<T: SignedRecord>(records: Vec<T>, forest: &ForestData) -> CatlibResult<Vec<T>> {
+        records
+            .into_iter()
+            .map(|record| verify(&record, forest).map(|_| record))
+            .collect()
+    }
+
+    fn container_with_forest(&self, uuid: &Uuid) -> CatlibResult<(ContainerData, ForestData)> {
+        let container = self.inner.container(uuid)?;
+        let forest = self.forest(&container.forest_uuid)?;
+        verify(&container, &forest)?;
+        Ok((container, forest))
+    }
+}
+
+impl CatLibStore for SignedStore {
+    fn save_forest(&self, forest: &ForestData) -> CatlibResult<()> {
+        let signed = match self.forest_keypairs.borrow().get(&forest.owner) {
+            Some(owner_keypair) => Self::signed_with(forest, Some(owner_keypair)),
+            None => self.sign(forest),
+        };
+        self.inner.save_forest(&signed)?;
+        if signed.signature.is_some() {
+            // the forest was written by this device, so its signers are trusted
+            self.trusted_signers
+                .borrow_mut()
+                .insert(forest.uuid, forest.signers.clone());
+        }
+        Ok(())
+    }
+
+    fn delete_forest(&self, uuid: &Uuid) -> CatlibResult<()> {
+        self.inner.delete_forest(uuid)
+    }
+
+    fn forest(&self, uuid: &Uuid) -> CatlibResult<ForestData> {
+        let forest = self.inner.forest(uuid)?;
+        self.verify_forest(&forest)?;
+        Ok(forest)
+    }
+
+    fn forests_of_owner(&self, owner: &Identity) -> CatlibResult<Vec<ForestData>> {
+        self.inner
+            .forests_of_owner(owner)?
+            .into_iter()
+            .map(|forest| self.verify_forest(&forest).map(|_| forest))
+            .collect()
+    }
+
+    fn save_container(&self, container: &ContainerData) -> CatlibResult<()> {
+        self.inner.save_container(&self.sign(container))
+    }
+
+    fn delete_container(&self, uuid: &Uuid) -> CatlibResult<()> {
+        self.inner.delete_container(uuid)
+    }
+
+    fn container(&self, uuid: &Uuid) -> CatlibResult<ContainerData> {
+        self.container_with_forest(uuid)
+            .map(|(container, _)| container)
+    }
+
+    fn containers_of_forest(&self, forest_uuid: &Uuid) -> CatlibResult<Vec<ContainerData>> {
+        let forest = self.forest(forest_uuid)?;
+        Self::verified(self.inner.containers_of_forest(forest_uuid)?, &forest)
+    }
+
+    fn containers_with_paths(
+        &self,
+        forest_uuid: &Uuid,
+        paths: &[ContainerPath],
+        include_subdirs: bool,
+    ) -> CatlibResult<Vec<ContainerData>> {
+        let forest = self.forest(forest_uuid)?;
+        Self::verified(
+            self.inner
+                .containers_with_paths(forest_uuid, paths, include_subdirs)?,
+            &forest,
+        )
+    }
+
+    fn save_storage(&self, storage: &StorageData) -> CatlibResult<()> {
+        self.inner.save_storage(&self.sign(storage))
+    }
+
+    fn delete_storage(&self, uuid: &Uuid) -> CatlibResult<()> {
+        self.inner.delete_storage(uuid)
+    }
+
+    fn storage(&self, uuid: &Uuid) -> CatlibResult<StorageData> {
+        let storage = self.inner.storage(uuid)?;
+        let (_, forest) = self.container_with_forest(&storage.container_uuid)?;
+        verify(&storage, &forest)?;
+        Ok(storage)
+    }
+
+    fn storages_of_container(&self, container_uuid: &Uuid) -> CatlibResult<Vec<StorageData>> {
+        let storages = self.inner.storages_of_container(container_uuid)?;
+        if storages.is_empty() {
+            return Ok(storages);
+        }
+        let (_, forest) = self.container_with_forest(container_uuid)?;
+        Self::verified(storages, &forest)
+    }
+
+    fn storages_with_template(&self, template_uuid: &Uuid) -> CatlibResult<Vec<StorageData>> {
+        let mut forests = HashMap::new();
+        self.inner
+            .storages_with_template(template_uuid)?
+            .into_iter()
+            .map(|storage| {
+                let forest = match forests.entry(storage.container_uuid) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        entry.insert(self.container_with_forest(&storage.container_uuid)?.1)
+                    }
+                };
+                verify(&storage, forest).map(|_| storage)
+            })
+            .collect()
+    }
+
+    fn save_bridge(&self, bridge: &BridgeData) -> CatlibResult<()> {
+        self.inner.save_bridge(&self.sign(bridge))
+    }
+
+    fn delete_bridge(&self, uuid: &Uuid) -> CatlibResult<()> {
+        self.inner.delete_bridge(uuid)
+    }
+
+    fn bridge(&self, uuid: &Uuid) -> CatlibResult<BridgeData> {
+        let bridge = self.inner.bridge(uuid)?;
+        verify(&bridge, &self.forest(&bridge.forest_uuid)?)?;
+        Ok(bridge)
+    }
+
+    fn bridges_of_forest(&self, forest_uuid: &Uuid) -> CatlibResult<Vec<BridgeData>> {
+        let forest = self.forest(forest_uuid)?;
+        Self::verified(self.inner.bridges_of_forest(forest_uuid)?, &forest)
+    }
+
+    fn bridges_with_path(
+        &self,
+        forest_uuid: &Uuid,
+        path: &ContainerPath,
+    ) -> CatlibResult<Vec<BridgeData>> {
+        let forest = self.forest(forest_uuid)?;
+        Self::verified(self.inner.bridges_with_path(forest_uuid, path)?, &forest)
+    }
+
+    fn save_storage_template(&self, uuid: &Uuid, value: String) -> CatlibResult<()> {
+        self.inner.save_storage_template(uuid, value)
+    }
+
+    fn storage_templates(&self) -> CatlibResult<Vec<String>> {
+        self.inner.storage_templates()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+    use wildland_corex::catlib_service::entities::ContainerPaths;
+
+    use super::*;
+
+    struct Stores {
+        signed: SignedStore,
+        // modifies records bypassing signatures
+        raw: SqliteStore,
+        keypair: SigningKeypair,
+        path: std::path::PathBuf,
+    }
+
+    impl Stores {
+        /// Opens the database as another device, which does not trust any signers yet.
+        fn other_device(&self) -> SignedStore {
+            SignedStore::new(Box::new(SqliteStore::open(&self.path).unwrap()))
+        }
+    }
+
+    #[fixture]
+    fn stores() -> Stores {
+        let path = tempfile::tempdir()
+            .unwrap()
+            .into_path()
+            .join("catlib.sqlite");
+        let signed = SignedStore::new(Box::new(SqliteStore::open(&path).unwrap()));
+        let keypair = SigningKeypair::try_from_secret_bytes(&[7; 32]).unwrap();
+        signed.set_signing_keypair(SigningKeypair::from(&keypair));
+        Stores {
+            signed,
+            raw: SqliteStore::open(&path).unwrap(),
+            keypair,
+            path,
+        }
+    }
+
+    fn forest(signer: &SigningKeypair) -> ForestData {
+        ForestData {
+            uuid: Uuid::new_v4(),
+            signers: Signers::from([Identity(signer.public())]),
+            owner: Identity([1; 32]),
+            data: b"data".to_vec(),
+            signature: None,
+        }
+    }
+
+    fn container(forest: &ForestData) -> ContainerData {
+        ContainerData {
+            uuid: Uuid::new_v4(),
+            forest_uuid: forest.uuid,
+            name: "container".to_owned(),
+            paths: ContainerPaths::from(["/a".to_owned(), "/b".to_owned()]),
+            signature: None,
+        }
+    }
+
+    #[rstest]
+    fn signed_records_are_loaded(stores: Stores) {
+        let forest = forest(&stores.keypair);
+        let container = container(&forest);
+        let storage = StorageData {
+            uuid: Uuid::new_v4(),
+            container_uuid: container.uuid,
+            template_uuid: Some(Uuid::new_v4()),
+            data: b"storage".to_vec(),
+            signature: None,
+        };
+        let bridge = BridgeData {
+            uuid: Uuid::new_v4(),
+            forest_uuid: forest.uuid,
+            path: "/bridge".to_owned(),
+            link: b"link".to_vec(),
+            signature: None,
+        };
+        stores.signed.save_forest(&forest).unwrap();
+        stores.signed.save_container(&container).unwrap();
+        stores.signed.save_storage(&storage).unwrap();
+        stores.signed.save_bridge(&bridge).unwrap();
+
+        let loaded = stores.signed.forest(&forest.uuid).unwrap();
+        assert_eq!(
+            loaded.signature.unwrap().signer,
+            Identity(stores.keypair.public())
+        );
+        assert!(stores.signed.container(&container.uuid).is_ok());
+        assert_eq!(
+            stores
+                .signed
+                .storages_of_container(&container.uuid)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(stores.signed.storage(&storage.uuid).is_ok());
+        assert!(stores.signed.bridge(&bridge.uuid).is_ok());
+    }
+
+    #[rstest]
+    fn tampered_record_is_rejected(stores: Stores) {
+        let forest = forest(&stores.keypair);
+        stores.signed.save_forest(&forest).unwrap();
+        stores.signed.save_container(&container(&forest)).unwrap();
+
+        let mut tampered = stores
+            .raw
+            .containers_of_forest(&forest.uuid)
+            .unwrap()
+            .remove(0);
+        tampered.paths.insert("/c".to_owned());
+        stores.raw.save_container(&tampered).unwrap();
+
+        assert_eq!(
+            stores.signed.container(&tampered.uuid).err(),
+            Some(CatlibError::InvalidRecordSignature)
+        );
+        assert_eq!(
+            stores.signed.containers_of_forest(&forest.uuid).err(),
+            Some(CatlibError::InvalidRecordSignature)
+        );
+    }
+
+    #[rstest]
+    fn record_signed_by_other_key_is_rejected(stores: Stores) {
+        let other_keypair = SigningKeypair::try_from_secret_bytes(&[8; 32]).unwrap();
+        let forest = forest(&other_keypair);
+        stores.signed.save_forest(&forest).unwrap();
+
+        assert_eq!(
+            stores.signed.forest(&forest.uuid).err(),
+            Some(CatlibError::InvalidRecordSignature)
+        );
+    }
+
+    #[rstest]
+    fn unsigned_record_of_signed_forest_is_rejected(stores: Stores) {
+        let forest = forest(&stores.keypair);
+        let container = container(&forest);
+        stores.signed.save_forest(&forest).unwrap();
+        stores.raw.save_container(&container).unwrap();
+
+        assert_eq!(
+            stores.signed.container(&container.uuid).err(),
+            Some(CatlibError::InvalidRecordSignature)
+        );
+    }
+
+    #[rstest]
+    fn unsigned_records_are_accepted(stores: Stores) {
+        let forest = forest(&stores.keypair);
+        let container = container(&forest);
+        stores.raw.save_forest(&forest).unwrap();
+        stores.raw.save_container(&container).unwrap();
+
+        assert!(stores.signed.forest(&forest.uuid).is_ok());
+        assert!(stores.signed.container(&container.uuid).is_ok());
+    }
+
+    #[rstest]
+    fn forest_signed_by_owner_is_trusted_by_other_device(stores: Stores) {
+        let owner_keypair = SigningKeypair::try_from_secret_bytes(&[1; 32]).unwrap();
+        let forest = ForestData {
+            owner: Identity(owner_keypair.public()),
+            ..forest(&stores.keypair)
+        };
+        stores.signed.set_forest_keypair(owner_keypair);
+        stores.signed.save_forest(&forest).unwrap();
+        stores.signed.save_container(&container(&forest)).unwrap();
+
+        let other_device = stores.other_device();
+        assert_eq!(
+            other_device
+                .forest(&forest.uuid)
+                .unwrap()
+                .signature
+                .unwrap()
+                .signer,
+            forest.owner
+        );
+        assert_eq!(
+            other_device
+                .containers_of_forest(&forest.uuid)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[rstest]
+    fn forest_signed_by_its_own_new_signer_is_rejected(stores: Stores) {
+        let forest = forest(&stores.keypair);
+        stores.signed.save_forest(&forest).unwrap();
+
+        // whoever can write the database adds own key to signers and signs the forest with it
+        let intruder = SigningKeypair::try_from_secret_bytes(&[8; 32]).unwrap();
+        let mut tampered = forest.clone();
+        tampered.signers.insert(Identity(intruder.public()));
+        stores
+            .raw
+            .save_forest(&SignedStore::signed_with(&tampered, Some(&intruder)))
+            .unwrap();
+
+        assert_eq!(
+            stores.signed.forest(&forest.uuid).err(),
+            Some(CatlibError::InvalidRecordSignature)
+        );
+        assert_eq!(
+            stores.other_device().forest(&forest.uuid).err(),
+            Some(CatlibError::InvalidRecordSignature)
+        );
+    }
+
+    #[rstest]
+    fn forest_stripped_of_signature_is_rejected(stores: Stores) {
+        let owner_keypair = SigningKeypair::try_from_secret_bytes(&[1; 32]).unwrap();
+        let forest = ForestData {
+            owner: Identity(owner_keypair.public()),
+            ..forest(&stores.keypair)
+        };
+        stores.signed.save_forest(&forest).unwrap();
+        stores.raw.save_forest(&forest).unwrap();
+
+        assert_eq!(
+            stores.signed.forest(&forest.uuid).err(),
+            Some(CatlibError::InvalidRecordSignature)
+        );
+        let other_device = stores.other_device();
+        other_device.set_forest_keypair(owner_keypair);
+        assert_eq!(
+            other_device.forest(&forest.uuid).err(),
+            Some(CatlibError::InvalidRecordSignature)
+        );
+    }
+
+    #[rstest]
+    fn legacy_forest_is_signed_by_owner(stores: Stores) {
+        let owner_keypair = SigningKeypair::try_from_secret_bytes(&[1; 32]).unwrap();
+        let forest = ForestData {
+            owner: Identity(owner_keypair.public()),
+            ..forest(&stores.keypair)
+        };
+        stores.raw.save_forest(&forest).unwrap();
+        let other_device = stores.other_device();
+        other_device.set_forest_keypair(owner_keypair);
+
+        other_device.sign_legacy_forest(&forest.uuid).unwrap();
+
+        assert!(stores.other_device().forest(&forest.uuid).is_ok());
+    }
+}
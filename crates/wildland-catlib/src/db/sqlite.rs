@@ -0,0 +1,817 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use wildland_corex::catlib_service::entities::{ContainerPath, ContainerPaths};
+
+use super::*;
+
+/// Migrations of the database schema. The one at index `n` upgrades the schema from version `n` to
+/// `n + 1`, so the length of the list is the current version, kept in `PRAGMA user_version`.
+const MIGRATIONS: &[&str] = &[INITIAL_SCHEMA, RECORD_SIGNATURES];
+
+const INITIAL_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS forests (
+        uuid BLOB PRIMARY KEY NOT NULL,
+        owner BLOB NOT NULL,
+        data BLOB NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS forests_owner ON forests (owner);
+    CREATE TABLE IF NOT EXISTS forest_signers (
+        forest_uuid BLOB NOT NULL,
+        signer BLOB NOT NULL,
+        PRIMARY KEY (forest_uuid, signer)
+    );
+
+    CREATE TABLE IF NOT EXISTS containers (
+        uuid BLOB PRIMARY KEY NOT NULL,
+        forest_uuid BLOB NOT NULL,
+        name TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS containers_forest ON containers (forest_uuid);
+    CREATE TABLE IF NOT EXISTS container_paths (
+        container_uuid BLOB NOT NULL,
+        path TEXT NOT NULL,
+        PRIMARY KEY (container_uuid, path)
+    );
+    CREATE INDEX IF NOT EXISTS container_paths_path ON container_paths (path);
+
+    CREATE TABLE IF NOT EXISTS storages (
+        uuid BLOB PRIMARY KEY NOT NULL,
+        container_uuid BLOB NOT NULL,
+        template_uuid BLOB,
+        data BLOB NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS storages_container ON storages (container_uuid);
+    CREATE INDEX IF NOT EXISTS storages_template ON storages (template_uuid);
+
+    CREATE TABLE IF NOT EXISTS bridges (
+        uuid BLOB PRIMARY KEY NOT NULL,
+        forest_uuid BLOB NOT NULL,
+        path TEXT NOT NULL,
+        link BLOB NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS bridges_forest_path ON bridges (forest_uuid, path);
+
+    CREATE TABLE IF NOT EXISTS storage_templates (
+        uuid BLOB PRIMARY KEY NOT NULL,
+        value TEXT NOT NULL
+    );
+";
+
+const RECORD_SIGNATURES: &str = "
+    ALTER TABLE forests ADD COLUMN signer BLOB;
+    ALTER TABLE forests ADD COLUMN signature BLOB;
+    ALTER TABLE containers ADD COLUMN signer BLOB;
+    ALTER TABLE containers ADD COLUMN signature BLOB;
+    ALTER TABLE storages ADD COLUMN signer BLOB;
+    ALTER TABLE storages ADD COLUMN signature BLOB;
+    ALTER TABLE bridges ADD COLUMN signer BLOB;
+    ALTER TABLE bridges ADD COLUMN signature BLOB;
+";
+
+fn to_catlib_error(error: rusqlite::Error) -> CatlibError {
+    CatlibError::Generic(format!("SQLite error: {error}"))
+}
+
+/// Applies migrations missing in the database within a single transaction.
+///
+/// ## Errors
+///
+/// Returns [`CatlibError::UnsupportedSchemaVersion`] if the database was written by a newer
+/// version of CatLib.
+fn migrate(connection: &mut Connection) -> CatlibResult<()> {
+    let version: u32 = connection
+        .query_row("PRAGMA user_version", params![], |row| row.get(0))
+        .map_err(to_catlib_error)?;
+    if version as usize > MIGRATIONS.len() {
+        return Err(CatlibError::UnsupportedSchemaVersion(version));
+    }
+    let transaction = connection.transaction().map_err(to_catlib_error)?;
+    for (from_version, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        tracing::info!("Migrating CatLib database from version {from_version}");
+        transaction
+            .execute_batch(migration)
+            .map_err(to_catlib_error)?;
+    }
+    transaction
+        .execute_batch(&format!("PRAGMA user_version = {}", MIGRATIONS.len()))
+        .map_err(to_catlib_error)?;
+    transaction.commit().map_err(to_catlib_error)
+}
+
+fn identity(bytes: Vec<u8>) -> CatlibResult<Identity> {
+    bytes
+        .try_into()
+        .map(Identity)
+        .map_err(|_| CatlibError::MalformedDatabaseRecord)
+}
+
+type SignatureColumns = (Option<Vec<u8>>, Option<Vec<u8>>);
+
+fn signature_columns(signature: &Option<RecordSignature>) -> (Option<&[u8]>, Option<&[u8]>) {
+    match signature {
+        Some(signature) => (
+            Some(&signature.signer.0[..]),
+            Some(&signature.signature[..]),
+        ),
+        None => (None, None),
+    }
+}
+
+fn record_signature(columns: SignatureColumns) -> CatlibResult<Option<RecordSignature>> {
+    match columns {
+        (Some(signer), Some(signature)) => Ok(Some(RecordSignature {
+            signer: identity(signer)?,
+            signature,
+        })),
+        (None, None) => Ok(None),
+        _ => Err(CatlibError::MalformedDatabaseRecord),
+    }
+}
+
+fn load_forest(connection: &Connection, uuid: &Uuid) -> CatlibResult<ForestData> {
+    let (owner, data, signature): (Vec<u8>, Vec<u8>, SignatureColumns) = connection
+        .query_row(
+            "SELECT owner, data, signer, signature FROM forests WHERE uuid = ?1",
+            params![uuid],
+            |row| Ok((row.get(0)?, row.get(1)?, (row.get(2)?, row.get(3)?))),
+        )
+        .optional()
+        .map_err(to_catlib_error)?
+        .ok_or(CatlibError::NoRecordsFound)?;
+    let signers = connection
+        .prepare_cached("SELECT signer FROM forest_signers WHERE forest_uuid = ?1")
+        .map_err(to_catlib_error)?
+        .query_map(params![uuid], |row| row.get(0))
+        .map_err(to_catlib_error)?
+        .map(|signer| identity(signer.map_err(to_catlib_error)?))
+        .collect::<CatlibResult<Signers>>()?;
+    Ok(ForestData {
+        uuid: *uuid,
+        signers,
+        owner: identity(owner)?,
+        data,
+        signature: record_signature(signature)?,
+    })
+}
+
+fn load_container(
+    connection: &Connection,
+    uuid: Uuid,
+    forest_uuid: Uuid,
+    name: String,
+    signature: SignatureColumns,
+) -> CatlibResult<ContainerData> {
+    let paths = connection
+        .prepare_cached("SELECT path FROM container_paths WHERE container_uuid = ?1")
+        .map_err(to_catlib_error)?
+        .query_map(params![uuid], |row| row.get(0))
+        .map_err(to_catlib_error)?
+        .collect::<Result<ContainerPaths, _>>()
+        .map_err(to_catlib_error)?;
+    Ok(ContainerData {
+        uuid,
+        forest_uuid,
+        name,
+        paths,
+        signature: record_signature(signature)?,
+    })
+}
+
+fn load_containers(
+    connection: &Connection,
+    query: &str,
+    params: impl rusqlite::Params,
+) -> CatlibResult<Vec<ContainerData>> {
+    let containers = connection
+        .prepare_cached(query)
+        .map_err(to_catlib_error)?
+        .query_map(params, |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                (row.get(3)?, row.get(4)?),
+            ))
+        })
+        .map_err(to_catlib_error)?
+        .collect::<Result<Vec<(Uuid, Uuid, String, SignatureColumns)>, _>>()
+        .map_err(to_catlib_error)?;
+    containers
+        .into_iter()
+        .map(|(uuid, forest_uuid, name, signature)| {
+            load_container(connection, uuid, forest_uuid, name, signature)
+        })
+        .collect()
+}
+
+fn load_storages(
+    connection: &Connection,
+    query: &str,
+    params: impl rusqlite::Params,
+) -> CatlibResult<Vec<StorageData>> {
+    connection
+        .prepare_cached(query)
+        .map_err(to_catlib_error)?
+        .query_map(params, |row| {
+            Ok((
+                StorageData {
+                    uuid: row.get(0)?,
+                    container_uuid: row.get(1)?,
+                    template_uuid: row.get(2)?,
+                    data: row.get(3)?,
+                    signature: None,
+                },
+                (row.get(4)?, row.get(5)?),
+            ))
+        })
+        .map_err(to_catlib_error)?
+        .map(|row| {
+            let (storage, signature) = row.map_err(to_catlib_error)?;
+            Ok(StorageData {
+                signature: record_signature(signature)?,
+                ..storage
+            })
+        })
+        .collect()
+}
+
+fn load_bridges(
+    connection: &Connection,
+    query: &str,
+    params: impl rusqlite::Params,
+) -> CatlibResult<Vec<BridgeData>> {
+    connection
+        .prepare_cached(query)
+        .map_err(to_catlib_error)?
+        .query_map(params, |row| {
+            Ok((
+                BridgeData {
+                    uuid: row.get(0)?,
+                    forest_uuid: row.get(1)?,
+                    path: row.get(2)?,
+                    link: row.get(3)?,
+                    signature: None,
+                },
+                (row.get(4)?, row.get(5)?),
+            ))
+        })
+        .map_err(to_catlib_error)?
+        .map(|row| {
+            let (bridge, signature) = row.map_err(to_catlib_error)?;
+            Ok(BridgeData {
+                signature: record_signature(signature)?,
+                ..bridge
+            })
+        })
+        .collect()
+}
+
+/// Keeps records in an SQLite database, in tables indexed by the columns records are looked up
+/// by.
+pub(crate) struct SqliteStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Opens the database at the given path, creating it or migrating its schema if needed.
+    pub(crate) fn open(path: &Path) -> CatlibResult<Self> {
+        let mut connection = Connection::open(path).map_err(to_catlib_error)?;
+        migrate(&mut connection)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.connection.lock().expect("Poisoned Mutex")
+    }
+
+    /// Executes statements within a single transaction
+    fn transaction(
+        &self,
+        statements: impl FnOnce(&Connection) -> rusqlite::Result<()>,
+    ) -> CatlibResult<()> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(to_catlib_error)?;
+        statements(&transaction).map_err(to_catlib_error)?;
+        transaction.commit().map_err(to_catlib_error)
+    }
+}
+
+impl CatLibStore for SqliteStore {
+    fn save_forest(&self, forest: &ForestData) -> CatlibResult<()> {
+        let (signer, signature) = signature_columns(&forest.signature);
+        self.transaction(|connection| {
+            connection.execute(
+                "INSERT INTO forests (uuid, owner, data, signer, signature)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT (uuid) DO UPDATE SET owner = excluded.owner, data = excluded.data,
+                     signer = excluded.signer, signature = excluded.signature",
+                params![
+                    forest.uuid,
+                    &forest.owner.0[..],
+                    forest.data,
+                    signer,
+                    signature
+                ],
+            )?;
+            connection.execute(
+                "DELETE FROM forest_signers WHERE forest_uuid = ?1",
+                params![forest.uuid],
+            )?;
+            for signer in &forest.signers {
+                connection.execute(
+                    "INSERT INTO forest_signers (forest_uuid, signer) VALUES (?1, ?2)",
+                    params![forest.uuid, &signer.0[..]],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    fn delete_forest(&self, uuid: &Uuid) -> CatlibResult<()> {
+        self.transaction(|connection| {
+            connection.execute(
+                "DELETE FROM forest_signers WHERE forest_uuid = ?1",
+                params![uuid],
+            )?;
+            connection.execute("DELETE FROM forests WHERE uuid = ?1", params![uuid])?;
+            Ok(())
+        })
+    }
+
+    fn forest(&self, uuid: &Uuid) -> CatlibResult<ForestData> {
+        load_forest(&self.connection(), uuid)
+    }
+
+    fn forests_of_owner(&self, owner: &Identity) -> CatlibResult<Vec<ForestData>> {
+        let connection = self.connection();
+        let uuids = connection
+            .prepare_cached("SELECT uuid FROM forests WHERE owner = ?1")
+            .map_err(to_catlib_error)?
+            .query_map(params![&owner.0[..]], |row| row.get(0))
+            .map_err(to_catlib_error)?
+            .collect::<Result<Vec<Uuid>, _>>()
+            .map_err(to_catlib_error)?;
+        uuids
+            .iter()
+            .map(|uuid| load_forest(&connection, uuid))
+            .collect()
+    }
+
+    fn save_container(&self, container: &ContainerData) -> CatlibResult<()> {
+        let (signer, signature) = signature_columns(&container.signature);
+        self.transaction(|connection| {
+            connection.execute(
+                "INSERT INTO containers (uuid, forest_uuid, name, signer, signature)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT (uuid) DO UPDATE
+                 SET forest_uuid = excluded.forest_uuid, name = excluded.name,
+                     signer = excluded.signer, signature = excluded.signature",
+                params![
+                    container.uuid,
+                    container.forest_uuid,
+                    container.name,
+                    signer,
+                    signature
+                ],
+            )?;
+            connection.execute(
+                "DELETE FROM container_paths WHERE container_uuid = ?1",
+                params![container.uuid],
+            )?;
+            for path in &container.paths {
+                connection.execute(
+                    "INSERT INTO container_paths (container_uuid, path) VALUES (?1, ?2)",
+                    params![container.uuid, path],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    fn delete_container(&self, uuid: &Uuid) -> CatlibResult<()> {
+        self.transaction(|connection| {
+            connection.execute(
+                "DELETE FROM container_paths WHERE container_uuid = ?1",
+                params![uuid],
+            )?;
+            connection.execute("DELETE FROM containers WHERE uuid = ?1", params![uuid])?;
+            Ok(())
+        })
+    }
+
+    fn container(&self, uuid: &Uuid) -> CatlibResult<ContainerData> {
+        load_containers(
+            &self.connection(),
+            "SELECT uuid, forest_uuid, name, signer, signature FROM containers WHERE uuid = ?1",
+            params![uuid],
+        )?
+        .pop()
+        .ok_or(CatlibError::NoRecordsFound)
+    }
+
+    fn containers_of_forest(&self, forest_uuid: &Uuid) -> CatlibResult<Vec<ContainerData>> {
+        load_containers(
+            &self.connection(),
+            "SELECT uuid, forest_uuid, name, signer, signature FROM containers WHERE forest_uuid = ?1",
+            params![forest_uuid],
+        )
+    }
+
+    fn containers_with_paths(
+        &self,
+        forest_uuid: &Uuid,
+        paths: &[ContainerPath],
+        include_subdirs: bool,
+    ) -> CatlibResult<Vec<ContainerData>> {
+        let connection = self.connection();
+        let mut uuids = HashSet::new();
+        let mut containers = Vec::new();
+        for path in paths {
+            // `path >= ?2` lets the index narrow down the prefix search
+            let found = load_containers(
+                &connection,
+                "SELECT DISTINCT c.uuid, c.forest_uuid, c.name, c.signer, c.signature
+                 FROM containers c JOIN container_paths p ON p.container_uuid = c.uuid
+                 WHERE c.forest_uuid = ?1 AND (p.path = ?2 OR (?3 AND p.path >= ?2
+                     AND substr(p.path, 1, length(?2)) = ?2))",
+                params![forest_uuid, path, include_subdirs],
+            )?;
+            containers.extend(
+                found
+                    .into_iter()
+                    .filter(|container| uuids.insert(container.uuid)),
+            );
+        }
+        Ok(containers)
+    }
+
+    fn save_storage(&self, storage: &StorageData) -> CatlibResult<()> {
+        let (signer, signature) = signature_columns(&storage.signature);
+        self.connection()
+            .execute(
+                "INSERT INTO storages (uuid, container_uuid, template_uuid, data, signer, signature)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT (uuid) DO UPDATE SET container_uuid = excluded.container_uuid,
+                     template_uuid = excluded.template_uuid, data = excluded.data,
+                     signer = excluded.signer, signature = excluded.signature",
+                params![
+                    storage.uuid,
+                    storage.container_uuid,
+                    storage.template_uuid,
+                    storage.data,
+                    signer,
+                    signature
+                ],
+            )
+            .map(|_| ())
+            .map_err(to_catlib_error)
+    }
+
+    fn delete_storage(&self, uuid: &Uuid) -> CatlibResult<()> {
+        self.connection()
+            .execute("DELETE FROM storages WHERE uuid = ?1", params![uuid])
+            .map(|_| ())
+            .map_err(to_catlib_error)
+    }
+
+    fn storage(&self, uuid: &Uuid) -> CatlibResult<StorageData> {
+        load_storages(
+            &self.connection(),
+            "SELECT uuid, container_uuid, template_uuid, data, signer, signature FROM storages WHERE uuid = ?1",
+            params![uuid],
+        )?
+        .pop()
+        .ok_or(CatlibError::NoRecordsFound)
+    }
+
+    fn storages_of_container(&self, container_uuid: &Uuid) -> CatlibResult<Vec<StorageData>> {
+        load_storages(
+            &self.connection(),
+            "SELECT uuid, container_uuid, template_uuid, data, signer, signature FROM storages
+             WHERE container_uuid = ?1",
+            params![container_uuid],
+        )
+    }
+
+    fn storages_with_template(&self, template_uuid: &Uuid) -> CatlibResult<Vec<StorageData>> {
+        load_storages(
+            &self.connection(),
+            "SELECT uuid, container_uuid, template_uuid, data, signer, signature FROM storages
+             WHERE template_uuid = ?1",
+            params![template_uuid],
+        )
+    }
+
+    fn save_bridge(&self, bridge: &BridgeData) -> CatlibResult<()> {
+        let (signer, signature) = signature_columns(&bridge.signature);
+        self.connection()
+            .execute(
+                "INSERT INTO bridges (uuid, forest_uuid, path, link, signer, signature)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT (uuid) DO UPDATE SET forest_uuid = excluded.forest_uuid,
+                     path = excluded.path, link = excluded.link,
+                     signer = excluded.signer, signature = excluded.signature",
+                params![
+                    bridge.uuid,
+                    bridge.forest_uuid,
+                    bridge.path,
+                    bridge.link,
+                    signer,
+                    signature
+                ],
+            )
+            .map(|_| ())
+            .map_err(to_catlib_error)
+    }
+
+    fn delete_bridge(&self, uuid: &Uuid) -> CatlibResult<()> {
+        self.connection()
+            .execute("DELETE FROM bridges WHERE uuid = ?1", params![uuid])
+            .map(|_| ())
+            .map_err(to_catlib_error)
+    }
+
+    fn bridge(&self, uuid: &Uuid) -> CatlibResult<BridgeData> {
+        load_bridges(
+            &self.connection(),
+            "SELECT uuid, forest_uuid, path, link, signer, signature FROM bridges WHERE uuid = ?1",
+            params![uuid],
+        )?
+        .pop()
+        .ok_or(CatlibError::NoRecordsFound)
+    }
+
+    fn bridges_of_forest(&self, forest_uuid: &Uuid) -> CatlibResult<Vec<BridgeData>> {
+        load_bridges(
+            &self.connection(),
+            "SELECT uuid, forest_uuid, path, link, signer, signature FROM bridges
+             WHERE forest_uuid = ?1",
+            params![forest_uuid],
+        )
+    }
+
+    fn bridges_with_path(
+        &self,
+        forest_uuid: &Uuid,
+        path: &ContainerPath,
+    ) -> CatlibResult<Vec<BridgeData>> {
+        load_bridges(
+            &self.connection(),
+            "SELECT uuid, forest_uuid, path, link, signer, signature FROM bridges
+             WHERE forest_uuid = ?1 AND path = ?2",
+            params![forest_uuid, path],
+        )
+    }
+
+    fn save_storage_template(&self, uuid: &Uuid, value: String) -> CatlibResult<()> {
+        self.connection()
+            .execute(
+                "INSERT INTO storage_templates (uuid, value) VALUES (?1, ?2)
+                 ON CONFLICT (uuid) DO UPDATE SET value = excluded.value",
+                params![uuid, value],
+            )
+            .map(|_| ())
+            .map_err(to_catlib_error)
+    }
+
+    fn storage_templates(&self) -> CatlibResult<Vec<String>> {
+        self.connection()
+            .prepare_cached("SELECT value FROM storage_templates")
+            .map_err(to_catlib_error)?
+            .query_map([], |row| row.get(0))
+            .map_err(to_catlib_error)?
+            .collect::<Result<_, _>>()
+            .map_err(to_catlib_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use rstest::*;
+    use wildland_corex::catlib_service::interface::CatLib as ICatLib;
+    use wildland_corex::StorageTemplate;
+
+    use super::super::test::sqlite_catlib;
+    use super::{Connection, MIGRATIONS};
+    use crate::*;
+
+    fn storage_template() -> StorageTemplate {
+        StorageTemplate::try_new(
+            "FoundationStorage",
+            HashMap::from([("uuid".to_owned(), "{{ CONTAINER_UUID }}".to_owned())]),
+        )
+        .unwrap()
+    }
+
+    #[rstest(sqlite_catlib as catlib)]
+    fn forest_is_found_by_owner_and_updated(catlib: CatLib) {
+        let forest = catlib
+            .create_forest(
+                Identity([1; 32]),
+                HashSet::from([Identity([2; 32])]),
+                vec![],
+            )
+            .unwrap();
+        forest
+            .lock()
+            .unwrap()
+            .add_signer(Identity([3; 32]))
+            .unwrap();
+        forest.lock().unwrap().update(b"data".to_vec()).unwrap();
+
+        let found = catlib.find_forest(&Identity([1; 32])).unwrap();
+        let mut found = found.lock().unwrap();
+        assert_eq!(found.uuid(), forest.lock().unwrap().uuid());
+        assert_eq!(found.data().unwrap(), b"data".to_vec());
+        assert_eq!(
+            found.signers().unwrap(),
+            HashSet::from([Identity([2; 32]), Identity([3; 32])])
+        );
+
+        found.remove().unwrap();
+        assert_eq!(
+            catlib.find_forest(&Identity([1; 32])).err(),
+            Some(CatlibError::NoRecordsFound)
+        );
+    }
+
+    #[rstest(sqlite_catlib as catlib)]
+    fn containers_are_found_by_paths(catlib: CatLib) {
+        let forest = catlib
+            .create_forest(Identity([1; 32]), Signers::new(), vec![])
+            .unwrap();
+        let template = storage_template();
+        let books = forest
+            .lock()
+            .unwrap()
+            .create_container("books".to_owned(), &template, "/books".to_owned())
+            .unwrap();
+        books
+            .lock()
+            .unwrap()
+            .add_path("/shared/books".to_owned())
+            .unwrap();
+        forest
+            .lock()
+            .unwrap()
+            .create_container("films".to_owned(), &template, "/films".to_owned())
+            .unwrap();
+
+        let found = forest
+            .lock()
+            .unwrap()
+            .find_containers(vec!["/shared/books".to_owned()], false)
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].lock().unwrap().name().unwrap(), "books");
+
+        let found = forest
+            .lock()
+            .unwrap()
+            .find_containers(vec!["/".to_owned(), "/books".to_owned()], true)
+            .unwrap();
+        assert_eq!(found.len(), 2);
+
+        assert_eq!(
+            forest
+                .lock()
+                .unwrap()
+                .find_containers(vec!["/shared/b".to_owned()], false)
+                .err(),
+            Some(CatlibError::NoRecordsFound)
+        );
+        assert_eq!(forest.lock().unwrap().containers().unwrap().len(), 2);
+    }
+
+    #[rstest(sqlite_catlib as catlib)]
+    fn storages_are_found_by_template(catlib: CatLib) {
+        let forest = catlib
+            .create_forest(Identity([1; 32]), Signers::new(), vec![])
+            .unwrap();
+        let template = storage_template();
+        let container = forest
+            .lock()
+            .unwrap()
+            .create_container("books".to_owned(), &template, "/books".to_owned())
+            .unwrap();
+        let container_uuid = container.lock().unwrap().uuid();
+        container.lock().unwrap().add_storage(&template).unwrap();
+
+        assert_eq!(container.lock().unwrap().get_storages().unwrap().len(), 2);
+        let storages = catlib
+            .find_storages_with_template(&template.uuid())
+            .unwrap();
+        assert_eq!(storages.len(), 2);
+        let containers = catlib
+            .find_containers_with_template(&template.uuid())
+            .unwrap();
+        assert!(containers
+            .iter()
+            .all(|container| container.lock().unwrap().uuid() == container_uuid));
+
+        storages[0].lock().unwrap().remove().unwrap();
+        assert_eq!(container.lock().unwrap().get_storages().unwrap().len(), 1);
+    }
+
+    #[rstest(sqlite_catlib as catlib)]
+    fn bridges_are_found_by_path(catlib: CatLib) {
+        let forest = catlib
+            .create_forest(Identity([1; 32]), Signers::new(), vec![])
+            .unwrap();
+        let bridge = forest
+            .lock()
+            .unwrap()
+            .create_bridge("/other/forest".to_owned(), b"link".to_vec())
+            .unwrap();
+        bridge.lock().unwrap().update(b"new link".to_vec()).unwrap();
+
+        let found = forest
+            .lock()
+            .unwrap()
+            .find_bridge("/other/forest".to_owned())
+            .unwrap();
+        assert_eq!(
+            found.lock().unwrap().path().unwrap(),
+            "/other/forest".to_owned()
+        );
+
+        found.lock().unwrap().remove().unwrap();
+        assert_eq!(
+            forest
+                .lock()
+                .unwrap()
+                .find_bridge("/other/forest".to_owned())
+                .err(),
+            Some(CatlibError::NoRecordsFound)
+        );
+    }
+
+    #[test]
+    fn database_of_newer_version_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("catlib.sqlite");
+        let newer_version = MIGRATIONS.len() as u32 + 1;
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(&format!("PRAGMA user_version = {newer_version}"))
+            .unwrap();
+
+        assert_eq!(
+            CatLib::with_backend(CatLibBackend::Sqlite, path).err(),
+            Some(CatlibError::UnsupportedSchemaVersion(newer_version))
+        );
+    }
+
+    #[test]
+    fn records_are_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("catlib.sqlite");
+        let template_uuid = Uuid::new_v4();
+
+        let catlib = CatLib::with_backend(CatLibBackend::Sqlite, path.clone()).unwrap();
+        let forest_uuid = catlib
+            .create_forest(Identity([1; 32]), Signers::new(), b"data".to_vec())
+            .unwrap()
+            .lock()
+            .unwrap()
+            .uuid();
+        catlib
+            .save_storage_template(&template_uuid, "first".to_owned())
+            .unwrap();
+        catlib
+            .save_storage_template(&template_uuid, "second".to_owned())
+            .unwrap();
+
+        let catlib = CatLib::with_backend(CatLibBackend::Sqlite, path).unwrap();
+        let forest = catlib.get_forest(&forest_uuid).unwrap();
+        assert_eq!(forest.lock().unwrap().data().unwrap(), b"data".to_vec());
+        assert_eq!(
+            catlib.get_storage_templates_data().unwrap(),
+            vec!["second".to_owned()]
+        );
+    }
+}
@@ -30,9 +30,6 @@ use wildland_corex::catlib_service::entities::{
 use wildland_corex::StorageTemplate;
 
 use super::*;
-use crate::bridge::BridgeData;
-use crate::container::ContainerData;
-use crate::db::{delete_model, save_model};
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct ForestData {
@@ -40,11 +37,15 @@ pub struct ForestData {
     pub signers: Signers,
     pub owner: Identity,
     pub data: Vec<u8>,
+    #[serde(default)]
+    pub signature: Option<RecordSignature>,
 }
 
-impl From<&str> for ForestData {
-    fn from(str_data: &str) -> Self {
-        ron::from_str(str_data).unwrap()
+impl TryFrom<&str> for ForestData {
+    type Error = CatlibError;
+
+    fn try_from(str_data: &str) -> CatlibResult<Self> {
+        record::deserialize(str_data)
     }
 }
 
@@ -54,17 +55,18 @@ pub(crate) struct Forest {
     pub(crate) data: ForestData,
 
     #[derivative(Debug = "ignore")]
-    pub(crate) db: Rc<StoreDb>,
+    pub(crate) db: Rc<dyn CatLibStore>,
 }
 
 impl Forest {
-    pub fn new(owner: Identity, signers: Signers, data: Vec<u8>, db: Rc<StoreDb>) -> Self {
+    pub fn new(owner: Identity, signers: Signers, data: Vec<u8>, db: Rc<dyn CatLibStore>) -> Self {
         Self {
             data: ForestData {
                 uuid: Uuid::new_v4(),
                 signers,
                 owner,
                 data,
+                signature: None,
             },
             db,
         }
@@ -97,13 +99,10 @@ impl ForestManifest for Forest {
     /// - Returns `RustbreakError` cast on [`CatlibResult`] upon failure to save to the database.
     #[tracing::instrument(level = "debug", skip_all)]
     fn containers(&self) -> CatlibResult<Vec<Arc<Mutex<dyn ContainerManifest>>>> {
-        self.db.load().map_err(to_catlib_error)?;
-        let data = self.db.read(|db| db.clone()).map_err(to_catlib_error)?;
-        let containers: Result<Vec<Arc<Mutex<dyn ContainerManifest>>>, CatlibError> = data
-            .iter()
-            .filter(|(id, _)| id.starts_with("container-"))
-            .map(|(_, container_str)| ContainerData::from(container_str.as_str()))
-            .filter(|container_data| container_data.forest_uuid == self.data.uuid)
+        let containers: Result<Vec<Arc<Mutex<dyn ContainerManifest>>>, CatlibError> = self
+            .db
+            .containers_of_forest(&self.data.uuid)?
+            .into_iter()
             .map(|container_data| {
                 Container::from_container_data(container_data, self.db.clone()).map(|container| {
                     Arc::new(Mutex::new(container)) as Arc<Mutex<dyn ContainerManifest>>
@@ -118,6 +117,30 @@ impl ForestManifest for Forest {
         }
     }
 
+    /// ## Errors
+    ///
+    /// - Returns [`CatlibError::NoRecordsFound`] if Forest has no [`Bridge`].
+    /// - Returns `RustbreakError` cast on [`CatlibResult`] upon failure to save to the database.
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn bridges(&self) -> CatlibResult<Vec<Arc<Mutex<dyn BridgeManifest>>>> {
+        let bridges: Vec<Arc<Mutex<dyn BridgeManifest>>> = self
+            .db
+            .bridges_of_forest(&self.data.uuid)?
+            .into_iter()
+            .map(|data| {
+                Arc::new(Mutex::new(Bridge {
+                    data,
+                    db: self.db.clone(),
+                })) as Arc<Mutex<dyn BridgeManifest>>
+            })
+            .collect();
+
+        match bridges.len() {
+            0 => Err(CatlibError::NoRecordsFound),
+            _ => Ok(bridges),
+        }
+    }
+
     /// ## Errors
     ///
     /// Returns `RustbreakError` cast on [`CatlibResult`] upon failure to save to the database.
@@ -234,17 +257,14 @@ impl ForestManifest for Forest {
         &self,
         path: ContainerPath,
     ) -> Result<Arc<Mutex<dyn BridgeManifest>>, CatlibError> {
-        self.db.load().map_err(to_catlib_error)?;
-        let data = self.db.read(|db| db.clone()).map_err(to_catlib_error)?;
-
-        let bridges: Vec<_> = data
-            .iter()
-            .filter(|(id, _)| id.starts_with("bridge-"))
-            .map(|(_, bridge_str)| Bridge {
-                data: BridgeData::from(bridge_str.as_str()),
+        let bridges: Vec<_> = self
+            .db
+            .bridges_with_path(&self.data.uuid, &path)?
+            .into_iter()
+            .map(|data| Bridge {
+                data,
                 db: self.db.clone(),
             })
-            .filter(|bridge| bridge.data.forest_uuid == self.data.uuid && bridge.data.path == path)
             .map(|bridge| Arc::new(Mutex::new(bridge)))
             .collect();
 
@@ -281,22 +301,10 @@ impl ForestManifest for Forest {
         paths: Vec<String>,
         include_subdirs: bool,
     ) -> CatlibResult<Vec<Arc<Mutex<dyn ContainerManifest>>>> {
-        self.db.load().map_err(to_catlib_error)?;
-        let data = self.db.read(|db| db.clone()).map_err(to_catlib_error)?;
-
-        let containers: Result<Vec<Arc<Mutex<dyn ContainerManifest>>>, CatlibError> = data
-            .iter()
-            .filter(|(id, _)| id.starts_with("container-"))
-            .map(|(_, container_str)| ContainerData::from(container_str.as_str()))
-            .filter(|container_data| container_data.forest_uuid == self.data.uuid)
-            .filter(|container_data| {
-                container_data.paths.iter().any(|container_path| {
-                    paths.iter().any(|path| {
-                        (include_subdirs && container_path.starts_with(path))
-                            || container_path.eq(path)
-                    })
-                })
-            })
+        let containers: Result<Vec<Arc<Mutex<dyn ContainerManifest>>>, CatlibError> = self
+            .db
+            .containers_with_paths(&self.data.uuid, &paths, include_subdirs)?
+            .into_iter()
             .map(|container_data| {
                 Container::from_container_data(container_data, self.db.clone()).map(|container| {
                     Arc::new(Mutex::new(container)) as Arc<Mutex<dyn ContainerManifest>>
@@ -332,19 +340,15 @@ impl ForestManifest for Forest {
 
 impl Model for Forest {
     fn save(&self) -> CatlibResult<()> {
-        save_model(
-            self.db.clone(),
-            format!("forest-{}", self.data.uuid),
-            ron::to_string(&self.data).unwrap(),
-        )
+        self.db.save_forest(&self.data)
     }
 
     fn delete(&mut self) -> CatlibResult<()> {
-        delete_model(self.db.clone(), format!("forest-{}", self.data.uuid))
+        self.db.delete_forest(&self.data.uuid)
     }
 
     fn sync(&mut self) -> CatlibResult<()> {
-        let forest_data = fetch_forest_data_by_uuid(self.db.clone(), &self.data.uuid)?;
+        let forest_data = self.db.forest(&self.data.uuid)?;
         self.data = forest_data;
         Ok(())
     }
@@ -517,4 +521,49 @@ mod tests {
         let forest = catlib.get_forest(&forest.lock().unwrap().uuid()).unwrap();
         assert_eq!(forest.lock().unwrap().signers().unwrap().len(), 4);
     }
+
+    #[rstest]
+    fn exported_forest_is_imported_to_another_catlib(
+        catlib: CatLib,
+        #[from(catlib)] other_catlib: CatLib,
+    ) {
+        let forest = make_forest_with_signer(&catlib);
+        let template = wildland_corex::StorageTemplate::try_new(
+            "FoundationStorage",
+            std::collections::HashMap::from([("field", "{{ CONTAINER_NAME }}")]),
+        )
+        .unwrap();
+        forest
+            .lock()
+            .unwrap()
+            .create_container("container".to_owned(), &template, "/path".to_owned())
+            .unwrap();
+        forest
+            .lock()
+            .unwrap()
+            .create_bridge("/other/forest".to_owned(), vec![])
+            .unwrap();
+        let forest_uuid = forest.lock().unwrap().uuid();
+
+        let exported = catlib.export_forest(&forest_uuid).unwrap();
+        assert_eq!(other_catlib.import_forest(&exported).unwrap(), forest_uuid);
+
+        let imported = other_catlib.get_forest(&forest_uuid).unwrap();
+        let containers = imported.lock().unwrap().containers().unwrap();
+        assert_eq!(containers.len(), 1);
+        assert_eq!(containers[0].lock().unwrap().name().unwrap(), "container");
+        assert_eq!(
+            containers[0].lock().unwrap().get_storages().unwrap().len(),
+            1
+        );
+        assert!(imported
+            .lock()
+            .unwrap()
+            .find_bridge("/other/forest".to_owned())
+            .is_ok());
+        assert_eq!(
+            other_catlib.import_forest(b"garbage").unwrap_err(),
+            CatlibError::MalformedDatabaseRecord
+        );
+    }
 }
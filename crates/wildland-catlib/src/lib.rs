@@ -20,9 +20,11 @@
 //! This library is used by Wildland Core to allow persistent storage for Wildland manifests that
 //! describe Wildland entities such as Containers, Storages, Bridges etc.
 //!
-//! The library acts as a database client depending on the database backend used. The current
-//! version of CatLib stores manifests in a local single-file nosql, unstructured database.
-//! Location of the database file depends on the platform where the application runs, these are:
+//! The library acts as a database client depending on the database backend used (see
+//! [`CatLibBackend`]). By default CatLib stores manifests in a local single-file nosql,
+//! unstructured database. Alternatively an SQLite database, with records indexed by the fields
+//! they are looked up by, may be used for big forests. Location of the database file depends on
+//! the platform where the application runs, these are:
 //!
 //! - `Linux:   /home/alice/.config/catlib`
 //! - `Windows: C:\Users\Alice\AppData\Roaming\com.wildland.Cargo\catlib`
@@ -95,9 +97,18 @@ use container::Container;
 use db::*;
 use directories::ProjectDirs;
 use error::*;
-use forest::{Forest, ForestData};
+use forest::Forest;
 use rustbreak::PathDatabase;
-use storage::{StorageData, StorageEntity};
+use serde::Deserialize;
+use storage::StorageEntity;
+pub use sync::{
+    DirectoryTransport,
+    ForestSync,
+    InMemoryTransport,
+    SyncError,
+    SyncReport,
+    SyncTransport,
+};
 use uuid::Uuid;
 use wildland_corex::catlib_service::entities::{
     ContainerManifest,
@@ -107,6 +118,7 @@ use wildland_corex::catlib_service::entities::{
     StorageManifest,
 };
 use wildland_corex::catlib_service::interface::CatLib as ICatLib;
+use wildland_corex::SigningKeypair;
 
 mod bridge;
 mod common;
@@ -115,10 +127,36 @@ mod db;
 mod error;
 mod forest;
 mod storage;
+mod sync;
+
+/// Database backend used by [`CatLib`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(C)]
+pub enum CatLibBackend {
+    /// Single RON file loaded as a whole on every operation
+    #[default]
+    Rustbreak,
+    /// SQLite database with indexed tables, suitable for forests with many containers
+    Sqlite,
+}
+
+impl CatLibBackend {
+    fn default_file_name(&self) -> &'static str {
+        match self {
+            CatLibBackend::Rustbreak => "catlib.database",
+            CatLibBackend::Sqlite => "catlib.sqlite",
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct CatLib {
-    db: Rc<StoreDb>,
+    db: Rc<SignedStore>,
+    /// Directory shared with other devices of the user, see [`CatLib::with_sync_dir`]
+    sync_dir: Option<PathBuf>,
+    /// Directory keeping state of synchronization of forests, next to the database
+    sync_state_dir: PathBuf,
 }
 
 impl CatLib {
@@ -130,9 +168,78 @@ impl CatLib {
             panic!("Could not create CatLib database at {path_str}");
         }
 
+        let store = RustbreakStore::new(db.unwrap());
+
+        if let Err(e) = store {
+            let path_str = path.to_str().unwrap();
+            panic!("Could not open CatLib database at {path_str}: {e}");
+        }
+
         CatLib {
-            db: Rc::new(db.unwrap()),
+            db: Rc::new(SignedStore::new(Box::new(store.unwrap()))),
+            sync_dir: None,
+            sync_state_dir: path.with_extension("sync"),
+        }
+    }
+
+    /// Opens CatLib database of the given backend at `path`, creating it if it does not exist.
+    pub fn with_backend(backend: CatLibBackend, path: PathBuf) -> CatlibResult<Self> {
+        let sync_state_dir = path.with_extension("sync");
+        let store: Box<dyn CatLibStore> = match backend {
+            CatLibBackend::Rustbreak => Box::new(RustbreakStore::new(
+                PathDatabase::load_from_path_or_default(path).map_err(to_catlib_error)?,
+            )?),
+            CatLibBackend::Sqlite => Box::new(SqliteStore::open(&path)?),
+        };
+        Ok(CatLib {
+            db: Rc::new(SignedStore::new(store)),
+            sync_dir: None,
+            sync_state_dir,
+        })
+    }
+
+    /// Opens CatLib database of the given backend at the default, platform specific location.
+    pub fn with_default_location(backend: CatLibBackend) -> CatlibResult<Self> {
+        Self::with_backend(backend, default_db_dir().join(backend.default_file_name()))
+    }
+
+    /// Lets forests be synchronized with other devices of the user through batches of changes
+    /// kept in the directory, e.g. synchronized by an external tool or mounted from a network
+    /// drive (see [`DirectoryTransport`]).
+    pub fn with_sync_dir(mut self, sync_dir: PathBuf) -> Self {
+        self.sync_dir = Some(sync_dir);
+        self
+    }
+
+    /// Returns transport of the synchronization directory, along with the keypair of this device
+    /// signing the sent changes.
+    fn sync_transport(&self) -> CatlibResult<(Box<dyn SyncTransport>, SigningKeypair)> {
+        let sync_dir = self.sync_dir.clone().ok_or_else(|| {
+            CatlibError::Generic("Synchronization directory is not configured".to_owned())
+        })?;
+        let keypair = self
+            .db
+            .signing_keypair()
+            .ok_or_else(|| CatlibError::Generic("Signing keypair is not set".to_owned()))?;
+        let transport = DirectoryTransport::new(sync_dir, hex::encode(keypair.public()))?;
+        Ok((Box::new(transport), keypair))
+    }
+}
+
+fn default_db_dir() -> PathBuf {
+    let project_dirs = ProjectDirs::from("com", "wildland", "Cargo");
+
+    if let Some(project_dirs) = project_dirs {
+        let db_dir = project_dirs.data_local_dir().join("catlib");
+
+        if !db_dir.exists() {
+            std::fs::create_dir_all(&db_dir).unwrap();
         }
+
+        db_dir
+    } else {
+        tracing::info!("Could not create ProjectDirs. Using working directory.");
+        ".".into()
     }
 }
 
@@ -181,17 +288,14 @@ impl ICatLib for CatLib {
     /// - Returns `RustbreakError` cast on [`CatlibResult`] upon failure to save to the database.
     #[tracing::instrument(level = "debug", skip_all)]
     fn find_forest(&self, owner: &Identity) -> CatlibResult<Arc<Mutex<dyn ForestManifest>>> {
-        self.db.load().map_err(to_catlib_error)?;
-        let data = self.db.read(|db| db.clone()).map_err(to_catlib_error)?;
-
-        let forests: Vec<_> = data
-            .iter()
-            .filter(|(id, _)| id.starts_with("forest-"))
-            .map(|(_, forest_str)| Forest {
-                data: ForestData::from(forest_str.as_str()),
+        let forests: Vec<_> = self
+            .db
+            .forests_of_owner(owner)?
+            .into_iter()
+            .map(|data| Forest {
+                data,
                 db: self.db.clone(),
             })
-            .filter(|forest| &forest.owner() == owner)
             .map(|forest| Arc::new(Mutex::new(forest)))
             .collect();
 
@@ -215,19 +319,14 @@ impl ICatLib for CatLib {
         &self,
         template_id: &Uuid,
     ) -> CatlibResult<Vec<Arc<Mutex<dyn StorageManifest>>>> {
-        self.db.load().map_err(to_catlib_error)?;
-        let data = self.db.read(|db| db.clone()).map_err(to_catlib_error)?;
-
-        let storages: Vec<_> = data
-            .iter()
-            .filter(|(id, _)| id.starts_with("storage-"))
-            .map(|(_, storage_str)| StorageEntity {
-                data: StorageData::from(storage_str.as_str()),
+        let storages: Vec<_> = self
+            .db
+            .storages_with_template(template_id)?
+            .into_iter()
+            .map(|data| StorageEntity {
+                data,
                 db: self.db.clone(),
             })
-            .filter(
-                |storage| matches!(storage.as_ref().template_uuid, Some(val) if val == *template_id),
-            )
             .map(|storage| Arc::new(Mutex::new(storage)) as Arc<Mutex<dyn StorageManifest>>)
             .collect();
 
@@ -255,47 +354,93 @@ impl ICatLib for CatLib {
 
     #[tracing::instrument(level = "debug", skip_all)]
     fn save_storage_template(&self, template_id: &Uuid, value: String) -> CatlibResult<()> {
-        save_model(
-            self.db.clone(),
-            format!("template-storage-{template_id}"),
-            value,
-        )
+        self.db.save_storage_template(template_id, value)
     }
 
     #[tracing::instrument(level = "debug", skip_all)]
     fn get_storage_templates_data(&self) -> CatlibResult<Vec<String>> {
-        self.db.load().map_err(to_catlib_error)?;
-        let data = self.db.read(|db| db.clone()).map_err(to_catlib_error)?;
+        self.db.storage_templates()
+    }
 
-        let storages: Vec<_> = data
-            .iter()
-            .filter(|(id, _)| id.starts_with("template-storage-"))
-            .map(|(_, storage_str)| storage_str)
-            .cloned()
-            .collect();
-        Ok(storages)
+    fn set_signing_keypair(&self, keypair: SigningKeypair) {
+        self.db.set_signing_keypair(keypair)
     }
-}
 
-impl Default for CatLib {
-    fn default() -> Self {
-        let project_dirs = ProjectDirs::from("com", "wildland", "Cargo");
+    fn set_forest_keypair(&self, keypair: SigningKeypair) {
+        self.db.set_forest_keypair(keypair)
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn sign_legacy_forest(&self, forest_uuid: &Uuid) -> CatlibResult<()> {
+        self.db.sign_legacy_forest(forest_uuid)
+    }
 
-        let db_file = if let Some(project_dirs) = project_dirs {
-            let db_dir = project_dirs.data_local_dir().join("catlib");
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn export_forest(&self, forest_uuid: &Uuid) -> CatlibResult<Vec<u8>> {
+        let records = fetch_forest_records(self.db.as_ref(), forest_uuid)?;
+        record::serialize(&records).map(String::into_bytes)
+    }
 
-            if !db_dir.exists() {
-                std::fs::create_dir_all(&db_dir).unwrap();
-            }
+    /// ## Errors
+    ///
+    /// - [`CatlibError::Generic`] if the synchronization directory or signing keypair is not set.
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn sync_forest(&self, forest_uuid: &Uuid) -> CatlibResult<()> {
+        let (transport, keypair) = self.sync_transport()?;
+        let report = ForestSync::new(self, *forest_uuid, keypair, transport, &self.sync_state_dir)?
+            .sync()?;
+        tracing::debug!("Synchronized forest {forest_uuid}: {report:?}");
+        Ok(())
+    }
 
-            db_dir.join("catlib.database")
-        } else {
-            tracing::info!("Could not create ProjectDirs. Using working directory.");
-            "./catlib.database".into()
-        };
+    /// ## Errors
+    ///
+    /// - [`CatlibError::NoRecordsFound`] if no other device has sent the forest yet.
+    /// - [`CatlibError::Generic`] if the synchronization directory or signing keypair is not set.
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn join_forest(&self, owner: &Identity) -> CatlibResult<Uuid> {
+        let (transport, keypair) = self.sync_transport()?;
+        let mut sync = ForestSync::join(self, owner, keypair, transport, &self.sync_state_dir)?;
+        let report = sync.sync()?;
+        tracing::debug!("Joined forest {}: {report:?}", sync.forest_uuid());
+        Ok(sync.forest_uuid())
+    }
+
+    /// ## Errors
+    ///
+    /// - [`CatlibError::MalformedDatabaseRecord`] if the data is not an export of a forest.
+    /// - [`CatlibError::UnsupportedSchemaVersion`] if the forest was exported by a newer CatLib.
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn import_forest(&self, data: &[u8]) -> CatlibResult<Uuid> {
+        let records: ForestRecords = std::str::from_utf8(data)
+            .map_err(|_| CatlibError::MalformedDatabaseRecord)
+            .and_then(record::deserialize)?;
+        // the forest goes first, as other records are verified against its signers
+        self.db.save_forest(&records.forest)?;
+        for container in &records.containers {
+            self.db.save_container(container)?;
+        }
+        for storage in &records.storages {
+            self.db.save_storage(storage)?;
+        }
+        for bridge in &records.bridges {
+            self.db.save_bridge(bridge)?;
+        }
+        Ok(records.forest.uuid)
+    }
+}
+
+impl Default for CatLib {
+    fn default() -> Self {
+        let db_file = default_db_dir().join(CatLibBackend::Rustbreak.default_file_name());
 
         CatLib {
-            db: Rc::new(PathDatabase::load_from_path_or_default(db_file).unwrap()),
+            sync_dir: None,
+            sync_state_dir: db_file.with_extension("sync"),
+            db: Rc::new(SignedStore::new(Box::new(
+                RustbreakStore::new(PathDatabase::load_from_path_or_default(db_file).unwrap())
+                    .unwrap(),
+            ))),
         }
     }
 }
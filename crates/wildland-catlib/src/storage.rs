@@ -29,11 +29,15 @@ pub(crate) struct StorageData {
     pub container_uuid: Uuid,
     pub template_uuid: Option<Uuid>,
     pub data: Vec<u8>,
+    #[serde(default)]
+    pub signature: Option<RecordSignature>,
 }
 
-impl From<&str> for StorageData {
-    fn from(data_str: &str) -> Self {
-        ron::from_str(data_str).unwrap()
+impl TryFrom<&str> for StorageData {
+    type Error = CatlibError;
+
+    fn try_from(data_str: &str) -> CatlibResult<Self> {
+        record::deserialize(data_str)
     }
 }
 
@@ -43,7 +47,7 @@ pub(crate) struct StorageEntity {
     pub(crate) data: StorageData,
 
     #[derivative(Debug = "ignore")]
-    pub(crate) db: Rc<StoreDb>,
+    pub(crate) db: Rc<dyn CatLibStore>,
 }
 
 impl StorageEntity {
@@ -51,7 +55,7 @@ impl StorageEntity {
         container_uuid: Uuid,
         template_uuid: Option<Uuid>,
         data: Vec<u8>,
-        db: Rc<StoreDb>,
+        db: Rc<dyn CatLibStore>,
     ) -> Self {
         Self {
             data: StorageData {
@@ -59,6 +63,7 @@ impl StorageEntity {
                 container_uuid,
                 template_uuid,
                 data,
+                signature: None,
             },
             db,
         }
@@ -113,19 +118,15 @@ impl StorageManifest for StorageEntity {
 
 impl Model for StorageEntity {
     fn save(&self) -> CatlibResult<()> {
-        save_model(
-            self.db.clone(),
-            format!("storage-{}", self.data.uuid),
-            ron::to_string(&self.data).unwrap(),
-        )
+        self.db.save_storage(&self.data)
     }
 
     fn delete(&mut self) -> CatlibResult<()> {
-        delete_model(self.db.clone(), format!("storage-{}", self.data.uuid))
+        self.db.delete_storage(&self.data.uuid)
     }
 
     fn sync(&mut self) -> CatlibResult<()> {
-        let data = fetch_storage_data_by_uuid(self.db.clone(), &self.data.uuid)?;
+        let data = self.db.storage(&self.data.uuid)?;
         self.data = data;
         Ok(())
     }
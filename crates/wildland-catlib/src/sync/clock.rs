@@ -0,0 +1,106 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use wildland_corex::catlib_service::entities::PubKey;
+
+/// Vector clock tracking how many changes of a record were made by each device.
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub(crate) struct VectorClock(BTreeMap<PubKey, u64>);
+
+impl VectorClock {
+    /// Records a change made by the device.
+    pub(crate) fn increment(&mut self, device: PubKey) {
+        *self.0.entry(device).or_default() += 1;
+    }
+
+    /// Returns the clock covering changes known to both clocks.
+    pub(crate) fn merged(&self, other: &Self) -> Self {
+        let mut merged = self.clone();
+        for (device, count) in &other.0 {
+            let entry = merged.0.entry(*device).or_default();
+            *entry = (*entry).max(*count);
+        }
+        merged
+    }
+
+    /// Returns the causal order of the clocks, or `None` if they are concurrent.
+    pub(crate) fn compare(&self, other: &Self) -> Option<Ordering> {
+        let count = |clock: &Self, device| clock.0.get(device).copied().unwrap_or_default();
+        self.0
+            .keys()
+            .chain(other.0.keys())
+            .map(|device| count(self, device).cmp(&count(other, device)))
+            .try_fold(Ordering::Equal, |order, device_order| {
+                match (order, device_order) {
+                    (order, Ordering::Equal) => Some(order),
+                    (Ordering::Equal, device_order) => Some(device_order),
+                    (order, device_order) if order == device_order => Some(order),
+                    _ => None,
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use super::*;
+
+    fn clock(counts: &[(u8, u64)]) -> VectorClock {
+        VectorClock(
+            counts
+                .iter()
+                .map(|(device, count)| ([*device; 32], *count))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn clocks_are_ordered_causally() {
+        assert_eq!(
+            clock(&[(1, 1)]).compare(&clock(&[(1, 1)])),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(
+            clock(&[(1, 1)]).compare(&clock(&[(1, 2), (2, 1)])),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            clock(&[(1, 2), (2, 1)]).compare(&clock(&[(2, 1)])),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(clock(&[(1, 2)]).compare(&clock(&[(1, 1), (2, 1)])), None);
+    }
+
+    #[test]
+    fn merged_clock_follows_both_clocks() {
+        let mut local = clock(&[(1, 2)]);
+        local.increment([2; 32]);
+        let remote = clock(&[(1, 1), (2, 3)]);
+
+        let merged = local.merged(&remote);
+
+        assert_eq!(merged, clock(&[(1, 2), (2, 3)]));
+        assert_eq!(local.compare(&merged), Some(Ordering::Less));
+        assert_eq!(remote.compare(&merged), Some(Ordering::Less));
+    }
+}
@@ -0,0 +1,870 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Synchronization of forests between devices of a user.
+//!
+//! Every device keeps the last known version of each record of a forest, along with a
+//! [`VectorClock`] of changes and a Lamport stamp of the last change. On [`ForestSync::sync`] the
+//! device:
+//!
+//! 1. detects records created, modified or removed locally since the last synchronization,
+//! 2. sends them to other devices as a batch signed with its keypair,
+//! 3. applies batches received from other devices, provided they are signed by a signer of the
+//!    forest.
+//!
+//! Each received record has to be signed by a signer of the forest (the owner in case of the
+//! forest record itself) and it is saved along with that signature.
+//! A received version replaces the local one if it follows it causally. Concurrent versions are
+//! merged deterministically, so all devices end up with the same records regardless of the order
+//! in which they receive the changes:
+//!
+//! - paths of a container are merged as a set (additions win over concurrent removals),
+//! - signers of a forest are merged as a set where removals win, so a revoked device is not
+//!   restored by concurrent changes of the forest. Signers ever removed from the forest are
+//!   tracked along with its versions for that purpose,
+//! - other fields are taken from the version with the greater stamp,
+//! - removal of a record wins over its concurrent modification.
+//!
+//! Merged versions are new records, so they are signed by the device merging them.
+
+mod clock;
+mod transport;
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+use wildland_corex::catlib_service::entities::{Identity, PubKey, Signers};
+use wildland_corex::SigningKeypair;
+use wildland_crypto::signature::Signature;
+
+use self::clock::VectorClock;
+pub use self::transport::{DirectoryTransport, InMemoryTransport, SyncTransport};
+use crate::bridge::BridgeData;
+use crate::container::ContainerData;
+use crate::db::{fetch_forest_records, CatLibStore, SignedRecord, SignedStore};
+use crate::error::CatlibError;
+use crate::forest::ForestData;
+use crate::storage::StorageData;
+use crate::CatLib;
+
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum SyncError {
+    #[error(transparent)]
+    Catlib(#[from] CatlibError),
+    #[error("Sync transport error: {0}")]
+    Transport(String),
+    #[error("Could not persist sync state: {0}")]
+    State(String),
+}
+
+impl From<SyncError> for CatlibError {
+    fn from(error: SyncError) -> Self {
+        match error {
+            SyncError::Catlib(error) => error,
+            error => CatlibError::Generic(error.to_string()),
+        }
+    }
+}
+
+/// Record of a forest exchanged between devices.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+enum SyncRecord {
+    Forest(ForestData),
+    Container(ContainerData),
+    Storage(StorageData),
+    Bridge(BridgeData),
+}
+
+impl SyncRecord {
+    fn uuid(&self) -> Uuid {
+        match self {
+            SyncRecord::Forest(forest) => forest.uuid,
+            SyncRecord::Container(container) => container.uuid,
+            SyncRecord::Storage(storage) => storage.uuid,
+            SyncRecord::Bridge(bridge) => bridge.uuid,
+        }
+    }
+
+    fn signed_content(&self) -> Vec<u8> {
+        match self {
+            SyncRecord::Forest(forest) => forest.signed_content(),
+            SyncRecord::Container(container) => container.signed_content(),
+            SyncRecord::Storage(storage) => storage.signed_content(),
+            SyncRecord::Bridge(bridge) => bridge.signed_content(),
+        }
+    }
+
+    /// Verifies signature of the record received from another device against the local version of
+    /// the forest. Forests have to be signed by their owner.
+    fn verify(&self, db: &SignedStore, forest: &ForestData) -> Result<(), CatlibError> {
+        match self {
+            SyncRecord::Forest(received) => db.verify_received_forest(received, &forest.owner),
+            SyncRecord::Container(container) => db.verify_received(container, forest),
+            SyncRecord::Storage(storage) => db.verify_received(storage, forest),
+            SyncRecord::Bridge(bridge) => db.verify_received(bridge, forest),
+        }
+    }
+
+    /// Saves the record along with the signature of its author.
+    fn save_unchanged(&self, db: &SignedStore) -> Result<(), CatlibError> {
+        let db = db.inner();
+        match self {
+            SyncRecord::Forest(forest) => db.save_forest(forest),
+            SyncRecord::Container(container) => db.save_container(container),
+            SyncRecord::Storage(storage) => db.save_storage(storage),
+            SyncRecord::Bridge(bridge) => db.save_bridge(bridge),
+        }
+    }
+
+    /// Saves the record signed by this device.
+    fn save_signed(&self, db: &SignedStore) -> Result<(), CatlibError> {
+        match self {
+            SyncRecord::Forest(forest) => db.save_forest(forest),
+            SyncRecord::Container(container) => db.save_container(container),
+            SyncRecord::Storage(storage) => db.save_storage(storage),
+            SyncRecord::Bridge(bridge) => db.save_bridge(bridge),
+        }
+    }
+
+    fn delete(&self, db: &SignedStore) -> Result<(), CatlibError> {
+        match self {
+            SyncRecord::Forest(forest) => db.delete_forest(&forest.uuid),
+            SyncRecord::Container(container) => db.delete_container(&container.uuid),
+            SyncRecord::Storage(storage) => db.delete_storage(&storage.uuid),
+            SyncRecord::Bridge(bridge) => db.delete_bridge(&bridge.uuid),
+        }
+    }
+}
+
+/// Lamport timestamp of a change, ordering concurrent changes.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Stamp {
+    counter: u64,
+    device: PubKey,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct RecordVersion {
+    record: SyncRecord,
+    clock: VectorClock,
+    stamp: Stamp,
+    deleted: bool,
+    /// Signers removed from the forest in this or any preceding version, empty for other records
+    #[serde(default)]
+    removed_signers: Signers,
+}
+
+impl RecordVersion {
+    /// Merges concurrent versions of a record. The result does not depend on the order of
+    /// arguments.
+    fn merged(&self, other: &Self) -> Self {
+        let (winner, loser) = if self.stamp > other.stamp {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let record = match (&winner.record, &loser.record) {
+            (SyncRecord::Container(winner), SyncRecord::Container(loser)) => {
+                SyncRecord::Container(ContainerData {
+                    paths: winner.paths.union(&loser.paths).cloned().collect(),
+                    ..winner.clone()
+                })
+            }
+            (SyncRecord::Forest(winner), SyncRecord::Forest(loser)) => {
+                SyncRecord::Forest(ForestData {
+                    signers: winner.signers.union(&loser.signers).cloned().collect(),
+                    ..winner.clone()
+                })
+            }
+            (winner, _) => winner.clone(),
+        };
+        Self {
+            record,
+            clock: self.clock.merged(&other.clock),
+            stamp: winner.stamp,
+            deleted: self.deleted || other.deleted,
+            removed_signers: Signers::new(),
+        }
+        .with_removed_signers(self)
+        .with_removed_signers(other)
+    }
+
+    /// Removes signers removed in the given version from this version of the forest.
+    fn with_removed_signers(mut self, other: &Self) -> Self {
+        self.removed_signers
+            .extend(other.removed_signers.iter().cloned());
+        if let SyncRecord::Forest(forest) = &mut self.record {
+            forest
+                .signers
+                .retain(|signer| !self.removed_signers.contains(signer));
+        }
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct SyncState {
+    /// Lamport counter, greater than counters of all known stamps
+    counter: u64,
+    records: HashMap<Uuid, RecordVersion>,
+    /// Identifiers of batches received from the transport
+    #[serde(default)]
+    received: HashSet<String>,
+}
+
+/// Changes of a forest made by a device. `changes` are kept serialized, as they were signed.
+#[derive(Serialize, Deserialize)]
+struct SignedBatch {
+    forest_uuid: Uuid,
+    device: Identity,
+    changes: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+/// Summary of a single synchronization.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Number of local changes sent to other devices
+    pub sent: usize,
+    /// Number of received changes that replaced local versions of records
+    pub applied: usize,
+    /// Number of received changes merged with concurrent local changes
+    pub merged: usize,
+    /// Number of received batches and changes rejected because of invalid signatures
+    pub rejected: usize,
+}
+
+/// Synchronizes records of a forest with other devices of the user.
+pub struct ForestSync {
+    db: Rc<SignedStore>,
+    forest_uuid: Uuid,
+    keypair: SigningKeypair,
+    transport: Box<dyn SyncTransport>,
+    state_path: PathBuf,
+    state: SyncState,
+}
+
+impl ForestSync {
+    /// Creates synchronization of the forest, which must exist in `catlib`. The state of
+    /// synchronization (last known versions of records) is kept in a file in `state_dir`.
+    ///
+    /// Records saved in `catlib` are signed with `keypair` from now on.
+    pub fn new(
+        catlib: &CatLib,
+        forest_uuid: Uuid,
+        keypair: SigningKeypair,
+        transport: Box<dyn SyncTransport>,
+        state_dir: &Path,
+    ) -> Result<Self, SyncError> {
+        catlib
+            .db
+            .set_signing_keypair(SigningKeypair::from(&keypair));
+        catlib.db.forest(&forest_uuid)?;
+        let state_path = state_path(state_dir, &forest_uuid);
+        let state = match std::fs::read_to_string(&state_path) {
+            Ok(state) => ron::from_str(&state).map_err(|e| SyncError::State(e.to_string()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => SyncState::default(),
+            Err(e) => return Err(SyncError::State(e.to_string())),
+        };
+        Ok(Self {
+            db: catlib.db.clone(),
+            forest_uuid,
+            keypair,
+            transport,
+            state_path,
+            state,
+        })
+    }
+
+    /// Creates synchronization of the forest of `owner` which this device has been enrolled in,
+    /// but which does not exist in `catlib` yet. The forest is taken from changes received from
+    /// other devices: its latest version signed by `owner` which lists `keypair` among signers.
+    ///
+    /// Returns [`CatlibError::NoRecordsFound`] if no other device has sent such a forest.
+    pub fn join(
+        catlib: &CatLib,
+        owner: &Identity,
+        keypair: SigningKeypair,
+        transport: Box<dyn SyncTransport>,
+        state_dir: &Path,
+    ) -> Result<Self, SyncError> {
+        let device = Identity(keypair.public());
+        let mut latest: Option<RecordVersion> = None;
+        // batches are received again by the synchronization of the forest
+        for batch in transport.receive(&mut HashSet::new())? {
+            let (sender, _, changes) = match open_batch(&batch) {
+                Ok(batch) => batch,
+                Err(e) => {
+                    tracing::warn!("Ignored batch of changes: {e}");
+                    continue;
+                }
+            };
+            for change in changes {
+                let forest = match &change.record {
+                    SyncRecord::Forest(forest) if !change.deleted => forest,
+                    _ => continue,
+                };
+                let acceptable = forest.signers.contains(&device)
+                    && forest.signers.contains(&sender)
+                    && catlib.db.verify_received_forest(forest, owner).is_ok();
+                let newer = !latest
+                    .as_ref()
+                    .is_some_and(|latest| latest.stamp >= change.stamp);
+                if acceptable && newer {
+                    latest = Some(change);
+                }
+            }
+        }
+        let version = latest.ok_or(CatlibError::NoRecordsFound)?;
+        let forest_uuid = version.record.uuid();
+        version.record.save_unchanged(&catlib.db)?;
+
+        let mut sync = Self::new(catlib, forest_uuid, keypair, transport, state_dir)?;
+        // the forest is known, so it is not sent back as a change of this device
+        sync.state.counter = sync.state.counter.max(version.stamp.counter);
+        sync.state.records.insert(forest_uuid, version);
+        Ok(sync)
+    }
+
+    pub fn forest_uuid(&self) -> Uuid {
+        self.forest_uuid
+    }
+
+    /// Sends local changes of the forest to other devices and applies changes received from
+    /// them.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn sync(&mut self) -> Result<SyncReport, SyncError> {
+        let mut report = SyncReport::default();
+
+        let (changes, counter) = self.local_changes()?;
+        if !changes.is_empty() {
+            self.transport.send(self.sign_batch(&changes)?)?;
+            report.sent = changes.len();
+        }
+        self.state.counter = counter;
+        for change in changes {
+            self.state.records.insert(change.record.uuid(), change);
+        }
+
+        for batch in self.transport.receive(&mut self.state.received)? {
+            let changes = match self.verify_batch(&batch) {
+                Ok(Some(changes)) => changes,
+                Ok(None) => continue,
+                Err(e) => {
+                    tracing::warn!("Rejected batch of changes: {e}");
+                    report.rejected += 1;
+                    continue;
+                }
+            };
+            for change in changes {
+                self.apply(change, &mut report)?;
+            }
+        }
+
+        self.save_state()?;
+        Ok(report)
+    }
+
+    fn local_records(&self) -> Result<Vec<SyncRecord>, SyncError> {
+        let records = fetch_forest_records(self.db.as_ref(), &self.forest_uuid)?;
+        Ok(std::iter::once(SyncRecord::Forest(records.forest))
+            .chain(records.containers.into_iter().map(SyncRecord::Container))
+            .chain(records.storages.into_iter().map(SyncRecord::Storage))
+            .chain(records.bridges.into_iter().map(SyncRecord::Bridge))
+            .collect())
+    }
+
+    /// Returns versions of records changed locally since the last synchronization along with the
+    /// updated Lamport counter.
+    fn local_changes(&self) -> Result<(Vec<RecordVersion>, u64), SyncError> {
+        let device = self.keypair.public();
+        let mut counter = self.state.counter;
+        let mut next_stamp = || {
+            counter += 1;
+            Stamp { counter, device }
+        };
+
+        let mut changes = Vec::new();
+        let mut present = HashSet::new();
+        for record in self.local_records()? {
+            present.insert(record.uuid());
+            let known = self.state.records.get(&record.uuid());
+            let mut clock = match known {
+                Some(known) if known.deleted => continue,
+                Some(known) if known.record.signed_content() == record.signed_content() => continue,
+                Some(known) => known.clock.clone(),
+                None => VectorClock::default(),
+            };
+            clock.increment(device);
+            let removed_signers = match (known, &record) {
+                (Some(known), SyncRecord::Forest(forest)) => match &known.record {
+                    SyncRecord::Forest(known_forest) => known_forest
+                        .signers
+                        .difference(&forest.signers)
+                        .chain(&known.removed_signers)
+                        .cloned()
+                        .collect(),
+                    _ => Signers::new(),
+                },
+                _ => Signers::new(),
+            };
+            changes.push(RecordVersion {
+                record,
+                clock,
+                stamp: next_stamp(),
+                deleted: false,
+                removed_signers,
+            });
+        }
+        for (uuid, known) in &self.state.records {
+            if !known.deleted && !present.contains(uuid) {
+                let mut clock = known.clock.clone();
+                clock.increment(device);
+                changes.push(RecordVersion {
+                    record: known.record.clone(),
+                    clock,
+                    stamp: next_stamp(),
+                    deleted: true,
+                    removed_signers: known.removed_signers.clone(),
+                });
+            }
+        }
+        Ok((changes, counter))
+    }
+
+    fn sign_batch(&self, changes: &[RecordVersion]) -> Result<Vec<u8>, SyncError> {
+        let changes = ron::to_string(changes)
+            .map_err(|e| SyncError::State(e.to_string()))?
+            .into_bytes();
+        let batch = SignedBatch {
+            forest_uuid: self.forest_uuid,
+            device: Identity(self.keypair.public()),
+            signature: self.keypair.sign(&changes).to_bytes(),
+            changes,
+        };
+        ron::to_string(&batch)
+            .map(String::into_bytes)
+            .map_err(|e| SyncError::State(e.to_string()))
+    }
+
+    /// Returns changes of the batch if it concerns synchronized forest and is signed by one of its
+    /// signers.
+    fn verify_batch(&self, batch: &[u8]) -> Result<Option<Vec<RecordVersion>>, CatlibError> {
+        let (sender, forest_uuid, changes) = open_batch(batch)?;
+        if forest_uuid != self.forest_uuid || sender.0 == self.keypair.public() {
+            return Ok(None);
+        }
+        if !self.db.forest(&self.forest_uuid)?.signers.contains(&sender) {
+            return Err(CatlibError::InvalidRecordSignature);
+        }
+        Ok(Some(changes))
+    }
+
+    fn belongs_to_forest(&self, record: &SyncRecord) -> bool {
+        match record {
+            SyncRecord::Forest(forest) => forest.uuid == self.forest_uuid,
+            SyncRecord::Container(container) => container.forest_uuid == self.forest_uuid,
+            SyncRecord::Bridge(bridge) => bridge.forest_uuid == self.forest_uuid,
+            SyncRecord::Storage(storage) => matches!(
+                self.state.records.get(&storage.container_uuid),
+                Some(RecordVersion { record: SyncRecord::Container(container), .. })
+                    if container.forest_uuid == self.forest_uuid
+            ),
+        }
+    }
+
+    fn apply(&mut self, remote: RecordVersion, report: &mut SyncReport) -> Result<(), SyncError> {
+        if !self.belongs_to_forest(&remote.record) {
+            tracing::warn!("Ignored change of a record from outside of the synchronized forest");
+            return Ok(());
+        }
+        if !remote.deleted {
+            let forest = self.db.forest(&self.forest_uuid)?;
+            if let Err(e) = remote.record.verify(&self.db, &forest) {
+                tracing::warn!("Rejected change of record {}: {e}", remote.record.uuid());
+                report.rejected += 1;
+                return Ok(());
+            }
+        }
+        self.state.counter = self.state.counter.max(remote.stamp.counter);
+
+        let uuid = remote.record.uuid();
+        let received = remote.record.clone();
+        let version = match self.state.records.get(&uuid) {
+            None => {
+                report.applied += 1;
+                remote
+            }
+            Some(local) => match remote.clock.compare(&local.clock) {
+                // a removed signer stays removed, even if the remote device claims otherwise
+                Some(Ordering::Greater) => {
+                    report.applied += 1;
+                    remote.with_removed_signers(local)
+                }
+                Some(_) => return Ok(()),
+                None => {
+                    report.merged += 1;
+                    local.merged(&remote)
+                }
+            },
+        };
+
+        // versions merged with local changes are new records, signed by this device as its own
+        if version.deleted {
+            version.record.delete(&self.db)
+        } else if version.record.signed_content() == received.signed_content() {
+            received.save_unchanged(&self.db)
+        } else {
+            version.record.save_signed(&self.db)
+        }?;
+        self.state.records.insert(uuid, version);
+        Ok(())
+    }
+
+    fn save_state(&self) -> Result<(), SyncError> {
+        let state = ron::to_string(&self.state).map_err(|e| SyncError::State(e.to_string()))?;
+        if let Some(dir) = self.state_path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| SyncError::State(e.to_string()))?;
+        }
+        std::fs::write(&self.state_path, state).map_err(|e| SyncError::State(e.to_string()))
+    }
+}
+
+fn state_path(state_dir: &Path, forest_uuid: &Uuid) -> PathBuf {
+    state_dir.join(format!("{forest_uuid}.ron"))
+}
+
+/// Returns the sender, forest and changes of the batch, provided it is signed by the sender.
+fn open_batch(batch: &[u8]) -> Result<(Identity, Uuid, Vec<RecordVersion>), CatlibError> {
+    let batch: SignedBatch = std::str::from_utf8(batch)
+        .ok()
+        .and_then(|batch| ron::from_str(batch).ok())
+        .ok_or(CatlibError::MalformedDatabaseRecord)?;
+    Signature::try_from(batch.signature.as_slice())
+        .and_then(|signature| signature.verify(&batch.changes, &batch.device.0))
+        .map_err(|_| CatlibError::InvalidRecordSignature)?;
+    let changes = std::str::from_utf8(&batch.changes)
+        .ok()
+        .and_then(|changes| ron::from_str(changes).ok())
+        .ok_or(CatlibError::MalformedDatabaseRecord)?;
+    Ok((batch.device, batch.forest_uuid, changes))
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+    use wildland_corex::catlib_service::entities::{ContainerPaths, Signers};
+
+    use super::*;
+    use crate::db::RecordSignature;
+    use crate::error::CatlibResult;
+
+    struct Device {
+        catlib: CatLib,
+        sync: ForestSync,
+    }
+
+    impl Device {
+        fn container(&self, uuid: &Uuid) -> CatlibResult<ContainerData> {
+            self.catlib.db.container(uuid)
+        }
+    }
+
+    fn keypair(seed: u8) -> SigningKeypair {
+        SigningKeypair::try_from_secret_bytes(&[seed; 32]).unwrap()
+    }
+
+    fn owner_keypair() -> SigningKeypair {
+        keypair(9)
+    }
+
+    fn device(
+        forest: &ForestData,
+        keypair: SigningKeypair,
+        transport: Box<dyn SyncTransport>,
+    ) -> Device {
+        let dir = tempfile::tempdir().unwrap().into_path();
+        let catlib =
+            CatLib::with_backend(crate::CatLibBackend::Sqlite, dir.join("catlib.sqlite")).unwrap();
+        catlib
+            .db
+            .set_signing_keypair(SigningKeypair::from(&keypair));
+        catlib.db.set_forest_keypair(owner_keypair());
+        catlib.db.save_forest(forest).unwrap();
+        let sync =
+            ForestSync::new(&catlib, forest.uuid, keypair, transport, &dir.join("sync")).unwrap();
+        Device { catlib, sync }
+    }
+
+    #[fixture]
+    fn devices() -> (Device, Device) {
+        let forest = ForestData {
+            uuid: Uuid::new_v4(),
+            signers: Signers::from([Identity(keypair(1).public()), Identity(keypair(2).public())]),
+            owner: Identity(owner_keypair().public()),
+            data: vec![],
+            signature: None,
+        };
+        let transport = InMemoryTransport::default();
+        let second = device(&forest, keypair(2), Box::new(transport.connect()));
+        let first = device(&forest, keypair(1), Box::new(transport));
+        (first, second)
+    }
+
+    fn container(forest_uuid: Uuid) -> ContainerData {
+        ContainerData {
+            uuid: Uuid::new_v4(),
+            forest_uuid,
+            name: "container".to_owned(),
+            paths: ContainerPaths::from(["/a".to_owned()]),
+            signature: None,
+        }
+    }
+
+    #[rstest]
+    fn container_is_propagated_to_other_device(devices: (Device, Device)) {
+        let (mut first, mut second) = devices;
+        let container = container(first.sync.forest_uuid);
+        first.catlib.db.save_container(&container).unwrap();
+
+        first.sync.sync().unwrap();
+        let report = second.sync.sync().unwrap();
+
+        assert_eq!(report.applied, 1);
+        assert_eq!(
+            second.container(&container.uuid).unwrap().paths,
+            container.paths
+        );
+    }
+
+    #[rstest]
+    fn received_records_keep_signatures_of_their_authors(devices: (Device, Device)) {
+        let (mut first, mut second) = devices;
+        let container = container(first.sync.forest_uuid);
+        first.catlib.db.save_container(&container).unwrap();
+
+        first.sync.sync().unwrap();
+        second.sync.sync().unwrap();
+
+        let received = second.catlib.db.inner().container(&container.uuid).unwrap();
+        assert_eq!(
+            received.signature.unwrap().signer,
+            Identity(keypair(1).public())
+        );
+    }
+
+    #[rstest]
+    fn forest_not_signed_by_owner_is_rejected(devices: (Device, Device)) {
+        let (mut first, mut second) = devices;
+        // a signer extends the forest on its own, without the owner keypair
+        let mut forest = first.catlib.db.forest(&first.sync.forest_uuid).unwrap();
+        forest.signers.insert(Identity(keypair(3).public()));
+        forest.signature = Some(RecordSignature {
+            signer: Identity(keypair(1).public()),
+            signature: keypair(1).sign(&forest.signed_content()).to_bytes(),
+        });
+        first.catlib.db.inner().save_forest(&forest).unwrap();
+
+        first.sync.sync().unwrap();
+        let report = second.sync.sync().unwrap();
+
+        assert_eq!(report.rejected, 1);
+        assert!(!second
+            .catlib
+            .db
+            .forest(&forest.uuid)
+            .unwrap()
+            .signers
+            .contains(&Identity(keypair(3).public())));
+    }
+
+    #[rstest]
+    fn concurrent_edits_are_merged_identically(devices: (Device, Device)) {
+        let (mut first, mut second) = devices;
+        let container = container(first.sync.forest_uuid);
+        first.catlib.db.save_container(&container).unwrap();
+        first.sync.sync().unwrap();
+        second.sync.sync().unwrap();
+
+        let mut renamed = container.clone();
+        renamed.name = "renamed".to_owned();
+        first.catlib.db.save_container(&renamed).unwrap();
+        let mut extended = container.clone();
+        extended.paths.insert("/b".to_owned());
+        second.catlib.db.save_container(&extended).unwrap();
+
+        first.sync.sync().unwrap();
+        let report = second.sync.sync().unwrap();
+        first.sync.sync().unwrap();
+
+        assert_eq!(report.merged, 1);
+        let merged = first.container(&container.uuid).unwrap();
+        assert_eq!(
+            merged.paths,
+            ContainerPaths::from(["/a".to_owned(), "/b".to_owned()])
+        );
+        assert_eq!(
+            merged.signed_content(),
+            second.container(&container.uuid).unwrap().signed_content()
+        );
+    }
+
+    #[rstest]
+    fn deletion_is_propagated_to_other_device(devices: (Device, Device)) {
+        let (mut first, mut second) = devices;
+        let container = container(first.sync.forest_uuid);
+        first.catlib.db.save_container(&container).unwrap();
+        first.sync.sync().unwrap();
+        second.sync.sync().unwrap();
+
+        first.catlib.db.delete_container(&container.uuid).unwrap();
+        first.sync.sync().unwrap();
+        second.sync.sync().unwrap();
+
+        assert_eq!(
+            second.container(&container.uuid).err(),
+            Some(CatlibError::NoRecordsFound)
+        );
+    }
+
+    #[rstest]
+    fn batch_of_device_outside_of_signers_is_rejected(devices: (Device, Device)) {
+        let (first, mut second) = devices;
+        let transport = InMemoryTransport::default();
+        // the intruder considers itself a signer, other devices do not
+        let mut forest = first.catlib.db.forest(&first.sync.forest_uuid).unwrap();
+        forest.signers.insert(Identity(keypair(3).public()));
+        let mut intruder = device(&forest, keypair(3), Box::new(transport.connect()));
+        second.sync.transport = Box::new(transport);
+        let container = container(forest.uuid);
+        intruder.catlib.db.save_container(&container).unwrap();
+
+        intruder.sync.sync().unwrap();
+        let report = second.sync.sync().unwrap();
+
+        assert_eq!(report.rejected, 1);
+        assert!(second.container(&container.uuid).is_err());
+    }
+
+    #[test]
+    fn enrolled_device_joins_forest() {
+        let forest = ForestData {
+            uuid: Uuid::new_v4(),
+            signers: Signers::from([Identity(keypair(1).public())]),
+            owner: Identity(owner_keypair().public()),
+            data: vec![],
+            signature: None,
+        };
+        let transport = InMemoryTransport::default();
+        let new_device_transport = transport.connect();
+        let mut first = device(&forest, keypair(1), Box::new(transport));
+        let container = container(forest.uuid);
+        first.catlib.db.save_container(&container).unwrap();
+        first.sync.sync().unwrap();
+
+        let dir = tempfile::tempdir().unwrap().into_path();
+        let catlib =
+            CatLib::with_backend(crate::CatLibBackend::Sqlite, dir.join("catlib.sqlite")).unwrap();
+        let join = |transport: &InMemoryTransport| {
+            ForestSync::join(
+                &catlib,
+                &forest.owner,
+                keypair(2),
+                Box::new(transport.connect()),
+                &dir.join("sync"),
+            )
+        };
+        assert_eq!(
+            join(&new_device_transport).err(),
+            Some(SyncError::Catlib(CatlibError::NoRecordsFound))
+        );
+
+        let mut enrolled = forest.clone();
+        enrolled.signers.insert(Identity(keypair(2).public()));
+        first.catlib.db.save_forest(&enrolled).unwrap();
+        first.sync.sync().unwrap();
+        let mut sync = join(&new_device_transport).unwrap();
+        let report = sync.sync().unwrap();
+
+        assert_eq!(report.sent, 0);
+        assert_eq!(
+            catlib.db.forest(&forest.uuid).unwrap().signers,
+            enrolled.signers
+        );
+        assert_eq!(
+            catlib.db.container(&container.uuid).unwrap().paths,
+            container.paths
+        );
+    }
+
+    #[test]
+    fn revoked_signer_is_not_restored_by_concurrent_change() {
+        let forest = ForestData {
+            uuid: Uuid::new_v4(),
+            signers: Signers::from([1, 2, 3].map(|seed| Identity(keypair(seed).public()))),
+            owner: Identity(owner_keypair().public()),
+            data: vec![],
+            signature: None,
+        };
+        let transport = InMemoryTransport::default();
+        let mut second = device(&forest, keypair(2), Box::new(transport.connect()));
+        let mut revoked = device(&forest, keypair(3), Box::new(transport.connect()));
+        let mut first = device(&forest, keypair(1), Box::new(transport));
+        // devices saved the forest concurrently, so they agree on it after two rounds
+        for _ in 0..2 {
+            for device in [&mut first, &mut second, &mut revoked] {
+                device.sync.sync().unwrap();
+            }
+        }
+
+        // the first device revokes the third one, while the second one, not aware of it yet,
+        // enrolls another device
+        let mut revoking = forest.clone();
+        revoking.signers.remove(&Identity(keypair(3).public()));
+        first.catlib.db.save_forest(&revoking).unwrap();
+        let mut enrolling = forest.clone();
+        enrolling.signers.insert(Identity(keypair(4).public()));
+        second.catlib.db.save_forest(&enrolling).unwrap();
+
+        first.sync.sync().unwrap();
+        let report = second.sync.sync().unwrap();
+        first.sync.sync().unwrap();
+
+        assert_eq!(report.merged, 1);
+        let expected_signers =
+            Signers::from([1, 2, 4].map(|seed| Identity(keypair(seed).public())));
+        for device in [&first, &second] {
+            assert_eq!(
+                device.catlib.db.forest(&forest.uuid).unwrap().signers,
+                expected_signers
+            );
+        }
+
+        let container = container(forest.uuid);
+        revoked.catlib.db.save_container(&container).unwrap();
+        revoked.sync.sync().unwrap();
+        let report = second.sync.sync().unwrap();
+
+        assert_eq!(report.rejected, 1);
+        assert!(second.container(&container.uuid).is_err());
+    }
+}
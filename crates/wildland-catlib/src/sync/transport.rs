@@ -0,0 +1,232 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use uuid::Uuid;
+
+use super::SyncError;
+
+/// Channel exchanging batches of changes between devices of a user. Batches are opaque to the
+/// transport, they are signed by the devices and verified on receipt.
+pub trait SyncTransport {
+    /// Publishes batch of changes made by this device.
+    fn send(&self, batch: Vec<u8>) -> Result<(), SyncError>;
+
+    /// Returns batches published by other devices whose identifiers are not in `received`, and
+    /// adds their identifiers to it. The set is persisted by the caller, so batches are not
+    /// delivered again after restart.
+    fn receive(&self, received: &mut HashSet<String>) -> Result<Vec<Vec<u8>>, SyncError>;
+}
+
+/// Batches along with uuid of the transport which sent them.
+type SentBatches = Arc<Mutex<Vec<(Uuid, Vec<u8>)>>>;
+
+/// Transport keeping batches in memory, for devices running within a single process (tests).
+///
+/// Transports connected with [`InMemoryTransport::connect`] share the batches.
+pub struct InMemoryTransport {
+    id: Uuid,
+    batches: SentBatches,
+}
+
+impl Default for InMemoryTransport {
+    fn default() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            batches: Default::default(),
+        }
+    }
+}
+
+impl InMemoryTransport {
+    /// Returns transport of another device, exchanging batches with this one.
+    pub fn connect(&self) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            batches: self.batches.clone(),
+        }
+    }
+}
+
+impl SyncTransport for InMemoryTransport {
+    fn send(&self, batch: Vec<u8>) -> Result<(), SyncError> {
+        self.batches
+            .lock()
+            .expect("Poisoned Mutex")
+            .push((self.id, batch));
+        Ok(())
+    }
+
+    /// Batches are identified by their position among all sent batches.
+    fn receive(&self, received: &mut HashSet<String>) -> Result<Vec<Vec<u8>>, SyncError> {
+        Ok(self
+            .batches
+            .lock()
+            .expect("Poisoned Mutex")
+            .iter()
+            .enumerate()
+            .filter(|(_, (sender, _))| sender != &self.id)
+            .filter(|(position, _)| received.insert(position.to_string()))
+            .map(|(_, (_, batch))| batch.clone())
+            .collect())
+    }
+}
+
+/// Transport keeping batches as files in a directory shared by devices, e.g. synchronized by an
+/// external tool or mounted from a network drive.
+///
+/// Files are named `<device>-<sequence number>.batch`, where `device` is the hex encoded name of
+/// the sender, so names of devices can not be confused with each other.
+pub struct DirectoryTransport {
+    dir: PathBuf,
+    /// Prefix of names of batches sent by this device
+    prefix: String,
+}
+
+impl DirectoryTransport {
+    /// Creates transport of the `device` using the given directory. Device name must be unique
+    /// among devices sharing the directory and stable across restarts.
+    pub fn new(dir: PathBuf, device: String) -> Result<Self, SyncError> {
+        std::fs::create_dir_all(&dir).map_err(to_sync_error)?;
+        Ok(Self {
+            dir,
+            prefix: format!("{}-", hex::encode(device)),
+        })
+    }
+
+    /// Returns names of complete batch files, sorted so batches of a device are in order.
+    fn batch_names(&self) -> Result<Vec<String>, SyncError> {
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(&self.dir).map_err(to_sync_error)? {
+            if let Ok(name) = entry.map_err(to_sync_error)?.file_name().into_string() {
+                if name.ends_with(".batch") && !name.starts_with('.') {
+                    names.push(name);
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+}
+
+fn to_sync_error(error: std::io::Error) -> SyncError {
+    SyncError::Transport(error.to_string())
+}
+
+impl SyncTransport for DirectoryTransport {
+    fn send(&self, batch: Vec<u8>) -> Result<(), SyncError> {
+        let sequence = self
+            .batch_names()?
+            .iter()
+            .filter(|name| name.starts_with(&self.prefix))
+            .count();
+        let name = format!("{}{sequence:010}.batch", self.prefix);
+        // batch becomes visible to other devices only when it is complete
+        let temporary_path = self.dir.join(format!(".{name}"));
+        std::fs::write(&temporary_path, batch).map_err(to_sync_error)?;
+        std::fs::rename(temporary_path, self.dir.join(name)).map_err(to_sync_error)
+    }
+
+    /// Batches are identified by names of their files.
+    fn receive(&self, received: &mut HashSet<String>) -> Result<Vec<Vec<u8>>, SyncError> {
+        let names: Vec<_> = self
+            .batch_names()?
+            .into_iter()
+            .filter(|name| !name.starts_with(&self.prefix) && !received.contains(name))
+            .collect();
+        let batches = names
+            .iter()
+            .map(|name| std::fs::read(self.dir.join(name)).map_err(to_sync_error))
+            .collect::<Result<_, _>>()?;
+        received.extend(names);
+        Ok(batches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn in_memory_transport_delivers_batches_of_other_devices_once() {
+        let laptop = InMemoryTransport::default();
+        let phone = laptop.connect();
+        let (mut laptop_received, mut phone_received) = (HashSet::new(), HashSet::new());
+
+        laptop.send(b"first".to_vec()).unwrap();
+        phone.send(b"second".to_vec()).unwrap();
+
+        assert_eq!(
+            phone.receive(&mut phone_received).unwrap(),
+            vec![b"first".to_vec()]
+        );
+        assert_eq!(
+            laptop.receive(&mut laptop_received).unwrap(),
+            vec![b"second".to_vec()]
+        );
+        assert!(phone.receive(&mut phone_received).unwrap().is_empty());
+    }
+
+    #[test]
+    fn directory_transport_delivers_batches_of_other_devices_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let laptop = DirectoryTransport::new(dir.path().to_owned(), "laptop".to_owned()).unwrap();
+        let phone = DirectoryTransport::new(dir.path().to_owned(), "phone".to_owned()).unwrap();
+        let (mut laptop_received, mut phone_received) = (HashSet::new(), HashSet::new());
+
+        laptop.send(b"first".to_vec()).unwrap();
+        laptop.send(b"second".to_vec()).unwrap();
+        phone.send(b"third".to_vec()).unwrap();
+
+        assert_eq!(
+            phone.receive(&mut phone_received).unwrap(),
+            vec![b"first".to_vec(), b"second".to_vec()]
+        );
+        assert_eq!(
+            laptop.receive(&mut laptop_received).unwrap(),
+            vec![b"third".to_vec()]
+        );
+        assert!(phone.receive(&mut phone_received).unwrap().is_empty());
+
+        // e.g. after restart with the received batches restored
+        let phone = DirectoryTransport::new(dir.path().to_owned(), "phone".to_owned()).unwrap();
+        assert!(phone.receive(&mut phone_received).unwrap().is_empty());
+    }
+
+    #[test]
+    fn directory_transport_tells_devices_with_common_prefix_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = DirectoryTransport::new(dir.path().to_owned(), "a".to_owned()).unwrap();
+        let a_b = DirectoryTransport::new(dir.path().to_owned(), "a-b".to_owned()).unwrap();
+
+        a_b.send(b"first".to_vec()).unwrap();
+        a.send(b"second".to_vec()).unwrap();
+
+        assert_eq!(
+            a.receive(&mut HashSet::new()).unwrap(),
+            vec![b"first".to_vec()]
+        );
+        assert_eq!(
+            a_b.receive(&mut HashSet::new()).unwrap(),
+            vec![b"second".to_vec()]
+        );
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+}
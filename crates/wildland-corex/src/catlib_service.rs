@@ -30,7 +30,7 @@ use wildland_crypto::identity::signing_keypair::PubKey;
 use self::entities::{ContainerManifest, ForestManifest};
 use self::error::{CatlibError, CatlibResult};
 use self::interface::CatLib;
-use crate::{ContainerPath, StorageTemplate, WildlandIdentity};
+use crate::{ContainerPath, SigningKeypair, StorageTemplate, WildlandIdentity};
 
 #[derive(Serialize, Deserialize)]
 pub struct DeviceMetadata {
@@ -42,6 +42,15 @@ pub struct DeviceMetadata {
 pub struct ForestMetaData {
     devices: Vec<DeviceMetadata>,
     free_storage_granted: bool,
+    /// Index of the current encryption keypair of the forest, bumped whenever a device is revoked
+    #[serde(default)]
+    encryption_key_index: u64,
+    /// Public part of the current encryption keypair of the forest
+    #[serde(default)]
+    encryption_pubkey: Option<String>,
+    /// Lowest index not used by any forest of the user, kept in the metadata of the default forest
+    #[serde(default)]
+    next_forest_index: u64,
 }
 
 impl ForestMetaData {
@@ -49,6 +58,9 @@ impl ForestMetaData {
         Self {
             devices,
             free_storage_granted: false,
+            encryption_key_index: 0,
+            encryption_pubkey: None,
+            next_forest_index: 1,
         }
     }
 
@@ -59,6 +71,18 @@ impl ForestMetaData {
     pub fn devices(&self) -> impl Iterator<Item = &DeviceMetadata> {
         self.devices.iter()
     }
+
+    pub fn encryption_key_index(&self) -> u64 {
+        self.encryption_key_index
+    }
+
+    pub fn encryption_pubkey(&self) -> Option<&str> {
+        self.encryption_pubkey.as_deref()
+    }
+
+    pub fn next_forest_index(&self) -> u64 {
+        self.next_forest_index.max(1)
+    }
 }
 
 impl TryFrom<ForestMetaData> for Vec<u8> {
@@ -116,6 +140,78 @@ impl CatLibService {
         self.catlib.get_forest(forest_uuid)
     }
 
+    pub fn find_forest(
+        &self,
+        forest_identity: &WildlandIdentity,
+    ) -> CatlibResult<Arc<Mutex<dyn ForestManifest>>> {
+        self.catlib
+            .find_forest(&forest_identity.get_public_key().into())
+    }
+
+    /// Authorizes the device to sign manifests of the forest and records its metadata.
+    ///
+    /// Returns [`CatlibError::RecordAlreadyExists`] if the device is already enrolled.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn add_device(
+        &self,
+        forest: &Arc<Mutex<dyn ForestManifest>>,
+        device: DeviceMetadata,
+    ) -> CatlibResult<()> {
+        let mut forest_metadata = self.get_parsed_forest_metadata(forest)?;
+        if forest_metadata.get_device_metadata(device.pubkey).is_some() {
+            return Err(CatlibError::RecordAlreadyExists);
+        }
+        let mut forest = forest.lock().expect("Poisoned Mutex");
+        forest.add_signer(device.pubkey.into())?;
+        forest_metadata.devices.push(device);
+        forest.update(forest_metadata.try_into()?)
+    }
+
+    /// Removes the device from signers of the forest and replaces the encryption key of the
+    /// forest, so the device can not read secrets shared with the user afterwards.
+    ///
+    /// Returns [`CatlibError::NoRecordsFound`] if the device is not enrolled.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn revoke_device(
+        &self,
+        forest: &Arc<Mutex<dyn ForestManifest>>,
+        device_pubkey: PubKey,
+        encryption_key_index: u64,
+        encryption_pubkey: String,
+    ) -> CatlibResult<DeviceMetadata> {
+        let mut forest_metadata = self.get_parsed_forest_metadata(forest)?;
+        let position = forest_metadata
+            .devices
+            .iter()
+            .position(|d| d.pubkey == device_pubkey)
+            .ok_or(CatlibError::NoRecordsFound)?;
+        let device = forest_metadata.devices.remove(position);
+        forest_metadata.encryption_key_index = encryption_key_index;
+        forest_metadata.encryption_pubkey = Some(encryption_pubkey);
+
+        let mut forest = forest.lock().expect("Poisoned Mutex");
+        forest.del_signer(device_pubkey.into())?;
+        forest.update(forest_metadata.try_into()?)?;
+        Ok(device)
+    }
+
+    /// Records in the metadata of the default forest that the forest index is used, so other
+    /// devices of the user do not derive their forests with it.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn reserve_forest_index(
+        &self,
+        default_forest: &Arc<Mutex<dyn ForestManifest>>,
+        forest_index: u64,
+    ) -> CatlibResult<()> {
+        let mut forest_metadata = self.get_parsed_forest_metadata(default_forest)?;
+        forest_metadata.next_forest_index =
+            forest_metadata.next_forest_index().max(forest_index + 1);
+        default_forest
+            .lock()
+            .expect("Poisoned Mutex")
+            .update(forest_metadata.try_into()?)
+    }
+
     #[tracing::instrument(level = "debug", skip_all)]
     pub fn create_container(
         &self,
@@ -130,11 +226,40 @@ impl CatLibService {
             .create_container(name, storage_template, path)
     }
 
+    /// Removes the forest along with its containers, their storages and bridges of the forest.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn delete_forest(&self, forest: &Arc<Mutex<dyn ForestManifest>>) -> CatlibResult<()> {
+        let mut forest = forest.lock().expect("Poisoned Mutex");
+        let containers = match forest.containers() {
+            Err(CatlibError::NoRecordsFound) => Vec::new(),
+            containers => containers?,
+        };
+        for container in containers {
+            let mut container = container.lock().expect("Poisoned Mutex");
+            let storages = match container.get_storages() {
+                Err(CatlibError::NoRecordsFound) => Vec::new(),
+                storages => storages?,
+            };
+            for storage in storages {
+                storage.lock().expect("Poisoned Mutex").remove()?;
+            }
+            container.remove()?;
+        }
+        let bridges = match forest.bridges() {
+            Err(CatlibError::NoRecordsFound) => Vec::new(),
+            bridges => bridges?,
+        };
+        for bridge in bridges {
+            bridge.lock().expect("Poisoned Mutex").remove()?;
+        }
+        forest.remove().map(|_| ())
+    }
+
     pub fn delete_container(&self, container: &mut dyn ContainerManifest) -> CatlibResult<()> {
         container.remove().map(|_| ())
     }
 
-    fn get_parsed_forest_metadata(
+    pub fn get_parsed_forest_metadata(
         &self,
         forest: &Arc<Mutex<dyn ForestManifest>>,
     ) -> CatlibResult<ForestMetaData> {
@@ -142,6 +267,39 @@ impl CatLibService {
             .map_err(|e| CatlibError::Generic(format!("Could not deserialize forest metadata {e}")))
     }
 
+    /// Sets keypair signing manifests saved by this device, see [`CatLib::set_signing_keypair`].
+    pub fn set_signing_keypair(&self, keypair: SigningKeypair) {
+        self.catlib.set_signing_keypair(keypair)
+    }
+
+    /// Sets keypair of the owner of the user's forest, see [`CatLib::set_forest_keypair`].
+    pub fn set_forest_keypair(&self, keypair: SigningKeypair) {
+        self.catlib.set_forest_keypair(keypair)
+    }
+
+    pub fn sign_legacy_forest(&self, forest_uuid: &Uuid) -> CatlibResult<()> {
+        self.catlib.sign_legacy_forest(forest_uuid)
+    }
+
+    /// Exchanges changes of the forest with other devices, see [`CatLib::sync_forest`].
+    pub fn sync_forest(&self, forest_uuid: &Uuid) -> CatlibResult<()> {
+        self.catlib.sync_forest(forest_uuid)
+    }
+
+    /// Retrieves the forest of the owner from other devices, see [`CatLib::join_forest`].
+    pub fn join_forest(&self, forest_identity: &WildlandIdentity) -> CatlibResult<Uuid> {
+        self.catlib
+            .join_forest(&forest_identity.get_public_key().into())
+    }
+
+    pub fn export_forest(&self, forest_uuid: &Uuid) -> CatlibResult<Vec<u8>> {
+        self.catlib.export_forest(forest_uuid)
+    }
+
+    pub fn import_forest(&self, data: &[u8]) -> CatlibResult<Uuid> {
+        self.catlib.import_forest(data)
+    }
+
     pub fn get_storage_templates_data(&self) -> CatlibResult<Vec<String>> {
         self.catlib.get_storage_templates_data()
     }
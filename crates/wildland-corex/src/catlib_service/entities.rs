@@ -80,6 +80,10 @@ pub trait ForestManifest: std::fmt::Debug {
     ///
     fn containers(&self) -> Result<Vec<Arc<Mutex<dyn ContainerManifest>>>, CatlibError>;
 
+    /// Return list of Forest Bridges
+    ///
+    fn bridges(&self) -> Result<Vec<Arc<Mutex<dyn BridgeManifest>>>, CatlibError>;
+
     /// Set Forest arbitrary data
     ///
     fn update(&mut self, data: Vec<u8>) -> Result<(), CatlibError>;
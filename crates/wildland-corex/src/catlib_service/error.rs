@@ -10,6 +10,10 @@ pub enum CatlibError {
     MalformedDatabaseRecord,
     #[error("Record already exists")]
     RecordAlreadyExists,
+    #[error("Database schema version {0} is not supported, it was written by a newer version")]
+    UnsupportedSchemaVersion(u32),
+    #[error("Record signature is invalid or made by a key that is not a forest signer")]
+    InvalidRecordSignature,
     #[error("Catlib error: {0}")]
     Generic(String),
 }
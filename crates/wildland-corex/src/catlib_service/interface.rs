@@ -4,6 +4,7 @@ use uuid::Uuid;
 
 use super::entities::{ContainerManifest, ForestManifest, Identity, Signers, StorageManifest};
 use super::error::CatlibResult;
+use crate::SigningKeypair;
 
 #[cfg_attr(test, mockall::automock)]
 pub trait CatLib {
@@ -47,4 +48,34 @@ pub trait CatLib {
 
     /// Fetche every StorageTemplate data from CatLib.
     fn get_storage_templates_data(&self) -> CatlibResult<Vec<String>>;
+
+    /// Set keypair of this device, used to sign manifests saved from now on. Loaded manifests are
+    /// verified against signers of the forest they belong to.
+    fn set_signing_keypair(&self, keypair: SigningKeypair);
+
+    /// Set keypair of a forest owner. Manifests of its forests are signed with it, so other
+    /// devices trust their signers, and unsigned manifests of its forests are rejected.
+    fn set_forest_keypair(&self, keypair: SigningKeypair);
+
+    /// Sign the forest manifest written before forests were signed by their owners with the owner
+    /// keypair set with [`CatLib::set_forest_keypair`]. It is meant to be done once per forest, as
+    /// the manifest is accepted as long as it is signed by any of its own signers.
+    fn sign_legacy_forest(&self, forest_uuid: &Uuid) -> CatlibResult<()>;
+
+    /// Exchange changes of manifests of the forest with other devices of the user. Changes are
+    /// signed with the keypair set with [`CatLib::set_signing_keypair`] and changes of other
+    /// devices are accepted only if they are signers of the forest.
+    fn sync_forest(&self, forest_uuid: &Uuid) -> CatlibResult<()>;
+
+    /// Retrieve the forest of the `owner` from changes sent by other devices of the user and
+    /// synchronize it, see [`CatLib::sync_forest`]. The forest has to list this device among its
+    /// signers. Returns UUID of the forest.
+    fn join_forest(&self, owner: &Identity) -> CatlibResult<Uuid>;
+
+    /// Serialize all manifests of the forest, so it can be restored in another CatLib with
+    /// [`CatLib::import_forest`].
+    fn export_forest(&self, forest_uuid: &Uuid) -> CatlibResult<Vec<u8>>;
+
+    /// Save manifests exported with [`CatLib::export_forest`]. Returns UUID of the imported forest.
+    fn import_forest(&self, data: &[u8]) -> CatlibResult<Uuid>;
 }
@@ -15,16 +15,333 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod error;
 mod path_resolver;
 
-use std::path::Path;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
+pub use error::*;
 pub use path_resolver::*;
+use uuid::Uuid;
 
-pub struct ContainerManager;
+use crate::{ContainerManifest, Storage};
+
+struct MountedContainer {
+    uuid: Uuid,
+    name: String,
+    container: Arc<Mutex<dyn ContainerManifest>>,
+    /// paths claimed by the container at the moment of mounting
+    paths: Vec<PathBuf>,
+    storages: Vec<Storage>,
+}
+
+/// Keeps track of mounted containers and resolves paths of the user's forest to their storages.
+///
+/// Container's paths and storages are read from CatLib when the container is mounted, so a container
+/// has to be remounted in order to make changes of its manifest visible.
+#[derive(Default)]
+pub struct ContainerManager {
+    mounted_containers: Mutex<Vec<MountedContainer>>,
+}
+
+impl ContainerManager {
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn mount(
+        &self,
+        container: &Arc<Mutex<dyn ContainerManifest>>,
+    ) -> Result<(), ContainerMountError> {
+        let mut container_lock = container.lock().expect("Poisoned Mutex");
+        let uuid = container_lock.uuid();
+        let mut mounted_containers = self.mounted_containers.lock().expect("Poisoned Mutex");
+        if mounted_containers
+            .iter()
+            .any(|mounted| mounted.uuid == uuid)
+        {
+            return Err(ContainerMountError::ContainerAlreadyMounted);
+        }
+
+        let name = container_lock.name()?;
+        let paths = container_lock
+            .get_paths()?
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        let storages = container_lock
+            .get_storages()?
+            .into_iter()
+            .map(|storage_manifest| {
+                let storage_data = storage_manifest.lock().expect("Poisoned Mutex").data()?;
+                serde_json::from_slice(&storage_data)
+                    .map_err(|e| ContainerMountError::InvalidStorage(e.to_string()))
+            })
+            .collect::<Result<Vec<Storage>, _>>()?;
+
+        tracing::debug!("Mounting container {uuid}");
+        mounted_containers.push(MountedContainer {
+            uuid,
+            name,
+            container: container.clone(),
+            paths,
+            storages,
+        });
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn unmount(
+        &self,
+        container: &Arc<Mutex<dyn ContainerManifest>>,
+    ) -> Result<(), ContainerUnmountError> {
+        let uuid = container.lock().expect("Poisoned Mutex").uuid();
+        let mut mounted_containers = self.mounted_containers.lock().expect("Poisoned Mutex");
+        let position = mounted_containers
+            .iter()
+            .position(|mounted| mounted.uuid == uuid)
+            .ok_or(ContainerUnmountError::ContainerNotMounted)?;
+
+        tracing::debug!("Unmounting container {uuid}");
+        mounted_containers.remove(position);
+        Ok(())
+    }
+
+    pub fn is_mounted(&self, container_uuid: &Uuid) -> bool {
+        self.mounted_containers
+            .lock()
+            .expect("Poisoned Mutex")
+            .iter()
+            .any(|mounted| &mounted.uuid == container_uuid)
+    }
+
+    /// Returns handles of mounted containers in the order of mounting
+    pub fn mounted_containers(&self) -> Vec<Arc<Mutex<dyn ContainerManifest>>> {
+        self.mounted_containers
+            .lock()
+            .expect("Poisoned Mutex")
+            .iter()
+            .map(|mounted| mounted.container.clone())
+            .collect()
+    }
+}
 
 impl PathResolver for ContainerManager {
-    fn resolve(&self, _path: &Path) -> Vec<ResolvedPath> {
-        todo!() // TODO WILX-353 implement when ContainerManager is filled with information about mounted containers
+    fn resolve(&self, path: &Path) -> Vec<ResolvedPath> {
+        let mounted_containers = self.mounted_containers.lock().expect("Poisoned Mutex");
+        let mut resolved_paths = Vec::new();
+        // virtual nodes may be created by many containers, so they are deduplicated
+        let mut virtual_paths = BTreeSet::new();
+
+        for mounted in mounted_containers.iter() {
+            // if a container claims more than one path matching the given one, the most specific
+            // claimed path is used
+            let longest_claimed_prefix = mounted
+                .paths
+                .iter()
+                .filter(|claimed_path| path.starts_with(claimed_path))
+                .max_by_key(|claimed_path| claimed_path.components().count());
+            if let Some(claimed_path) = longest_claimed_prefix {
+                resolved_paths.push(ResolvedPath::PathWithStorages {
+                    path_within_storage: Path::new("/")
+                        .join(path.strip_prefix(claimed_path).unwrap()),
+                    storages: mounted.storages.clone(),
+                });
+            }
+
+            virtual_paths.extend(mounted.paths.iter().filter_map(|claimed_path| {
+                claimed_path
+                    .strip_prefix(path)
+                    .ok()
+                    .and_then(|remaining_path| remaining_path.components().next())
+                    .map(|next_component| path.join(next_component))
+            }));
+        }
+
+        resolved_paths.extend(virtual_paths.into_iter().map(ResolvedPath::VirtualPath));
+        resolved_paths
+    }
+
+    /// Only mounted containers are taken into account.
+    fn container_uuid_by_storage(&self, storage_uuid: &Uuid) -> Option<Uuid> {
+        self.mounted_containers
+            .lock()
+            .expect("Poisoned Mutex")
+            .iter()
+            .find(|mounted| {
+                mounted
+                    .storages
+                    .iter()
+                    .any(|storage| &storage.uuid() == storage_uuid)
+            })
+            .map(|mounted| mounted.uuid)
+    }
+
+    /// Only mounted containers are taken into account. Returns the name the container had at the
+    /// moment of mounting.
+    fn container_name_by_storage(&self, storage_uuid: &Uuid) -> Option<String> {
+        self.mounted_containers
+            .lock()
+            .expect("Poisoned Mutex")
+            .iter()
+            .find(|mounted| {
+                mounted
+                    .storages
+                    .iter()
+                    .any(|storage| &storage.uuid() == storage_uuid)
+            })
+            .map(|mounted| mounted.name.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{MockContainerManifest, MockStorageManifest, StorageManifest};
+
+    fn new_storage() -> Storage {
+        Storage::new(None, "Test".to_owned(), serde_json::Value::Null)
+    }
+
+    fn mock_container(paths: &[&str], storage: Storage) -> Arc<Mutex<dyn ContainerManifest>> {
+        let mut container = MockContainerManifest::new();
+        container.expect_uuid().return_const(Uuid::new_v4());
+        container
+            .expect_name()
+            .returning(|| Ok("Test container".to_owned()));
+        let paths: Vec<String> = paths.iter().map(|p| p.to_string()).collect();
+        container
+            .expect_get_paths()
+            .returning(move || Ok(paths.clone()));
+        container.expect_get_storages().returning(move || {
+            let storage_data = serde_json::to_vec(&storage).unwrap();
+            let mut storage_manifest = MockStorageManifest::new();
+            storage_manifest
+                .expect_data()
+                .returning(move || Ok(storage_data.clone()));
+            Ok(vec![
+                Arc::new(Mutex::new(storage_manifest)) as Arc<Mutex<dyn StorageManifest>>
+            ])
+        });
+        Arc::new(Mutex::new(container))
+    }
+
+    #[rstest]
+    fn test_resolving_path_claimed_by_nested_containers() {
+        let container_manager = ContainerManager::default();
+        let storage1 = new_storage();
+        let storage2 = new_storage();
+        container_manager
+            .mount(&mock_container(&["/a"], storage1.clone()))
+            .unwrap();
+        container_manager
+            .mount(&mock_container(&["/a/b/c"], storage2.clone()))
+            .unwrap();
+
+        assert_eq!(
+            container_manager.resolve(Path::new("/a/b")),
+            vec![
+                ResolvedPath::PathWithStorages {
+                    path_within_storage: "/b".into(),
+                    storages: vec![storage1.clone()],
+                },
+                ResolvedPath::VirtualPath("/a/b/c".into()),
+            ]
+        );
+        assert_eq!(
+            container_manager.resolve(Path::new("/a/b/c/d")),
+            vec![
+                ResolvedPath::PathWithStorages {
+                    path_within_storage: "/b/c/d".into(),
+                    storages: vec![storage1],
+                },
+                ResolvedPath::PathWithStorages {
+                    path_within_storage: "/d".into(),
+                    storages: vec![storage2],
+                },
+            ]
+        );
+        assert_eq!(
+            container_manager.resolve(Path::new("/")),
+            vec![ResolvedPath::VirtualPath("/a".into())]
+        );
+        assert_eq!(container_manager.resolve(Path::new("/x")), vec![]);
+    }
+
+    #[rstest]
+    fn test_resolving_uses_the_longest_path_claimed_by_container() {
+        let container_manager = ContainerManager::default();
+        let storage = new_storage();
+        container_manager
+            .mount(&mock_container(&["/a", "/a/b", "/x/y"], storage.clone()))
+            .unwrap();
+
+        assert_eq!(
+            container_manager.resolve(Path::new("/a/b/c")),
+            vec![ResolvedPath::PathWithStorages {
+                path_within_storage: "/c".into(),
+                storages: vec![storage],
+            }]
+        );
+        assert_eq!(
+            container_manager.resolve(Path::new("/x")),
+            vec![ResolvedPath::VirtualPath("/x/y".into())]
+        );
+    }
+
+    #[rstest]
+    fn test_finding_container_of_storage() {
+        let container_manager = ContainerManager::default();
+        let storage = new_storage();
+        let container = mock_container(&["/a"], storage.clone());
+        let container_uuid = container.lock().unwrap().uuid();
+
+        assert_eq!(
+            container_manager.container_uuid_by_storage(&storage.uuid()),
+            None
+        );
+        container_manager.mount(&container).unwrap();
+        assert_eq!(
+            container_manager.container_uuid_by_storage(&storage.uuid()),
+            Some(container_uuid)
+        );
+        assert_eq!(
+            container_manager.container_uuid_by_storage(&new_storage().uuid()),
+            None
+        );
+        assert_eq!(
+            container_manager.container_name_by_storage(&storage.uuid()),
+            Some("Test container".to_owned())
+        );
+    }
+
+    #[rstest]
+    fn test_mounting_and_unmounting_container() {
+        let container_manager = ContainerManager::default();
+        let container = mock_container(&["/a"], new_storage());
+        let container_uuid = container.lock().unwrap().uuid();
+
+        container_manager.mount(&container).unwrap();
+        assert!(container_manager.is_mounted(&container_uuid));
+        assert_eq!(
+            container_manager.mount(&container).unwrap_err(),
+            ContainerMountError::ContainerAlreadyMounted
+        );
+        assert_eq!(container_manager.mounted_containers().len(), 1);
+
+        container_manager.unmount(&container).unwrap();
+        assert!(!container_manager.is_mounted(&container_uuid));
+        assert!(container_manager.mounted_containers().is_empty());
+        assert!(container_manager.resolve(Path::new("/a")).is_empty());
+        assert_eq!(
+            container_manager.unmount(&container).unwrap_err(),
+            ContainerUnmountError::ContainerNotMounted
+        );
     }
 }
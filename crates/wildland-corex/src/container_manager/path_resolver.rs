@@ -17,11 +17,14 @@
 
 use std::path::{Path, PathBuf};
 
+use uuid::Uuid;
+
 use crate::Storage;
 
 /// Represents result of a possible path within a Storage. Storages field represents all alternative
 /// locations of the path.
 ///
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum ResolvedPath {
     PathWithStorages {
         /// path within storages
@@ -52,4 +55,10 @@ pub trait PathResolver {
     /// ]
     ///
     fn resolve(&self, path: &Path) -> Vec<ResolvedPath>;
+
+    /// Returns uuid of the container the storage belongs to.
+    fn container_uuid_by_storage(&self, storage_uuid: &Uuid) -> Option<Uuid>;
+
+    /// Returns name of the container the storage belongs to.
+    fn container_name_by_storage(&self, storage_uuid: &Uuid) -> Option<String>;
 }
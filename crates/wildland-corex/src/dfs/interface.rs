@@ -15,14 +15,32 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use std::path::PathBuf;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use thiserror::Error;
+use uuid::Uuid;
 
 use crate::Storage;
 
+/// Tells whether a node shares its full path with nodes of other containers
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[repr(C)]
+pub enum ConflictStatus {
+    #[default]
+    NoConflict,
+    /// Node keeps its path even though other nodes claim it as well
+    Unresolved,
+    /// Node got a suffix distinguishing it from other nodes claiming the same path
+    Renamed,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct NodeDescriptor {
     pub storage: Option<NodeStorage>, // nodes may not have storage - so called virtual nodes
     pub absolute_path: PathBuf,
+    pub conflict_status: ConflictStatus,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -38,9 +56,250 @@ impl NodeStorage {
             path_within_storage,
         }
     }
+
+    pub fn storage(&self) -> &Storage {
+        &self.storage
+    }
+
+    pub fn path_within_storage(&self) -> &Path {
+        &self.path_within_storage
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(C)]
+pub enum NodeType {
+    File,
+    Dir,
+    Symlink,
+    Other,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct UnixTimestamp {
+    pub sec: u64,
+    pub nano_sec: u32,
+}
+
+impl From<SystemTime> for UnixTimestamp {
+    fn from(time: SystemTime) -> Self {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        Self {
+            sec: since_epoch.as_secs(),
+            nano_sec: since_epoch.subsec_nanos(),
+        }
+    }
+}
+
+/// Node attributes, equivalent of POSIX `stat` structure limited to fields meaningful for Wildland
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Stat {
+    pub node_type: NodeType,
+    /// size in bytes, 0 for directories
+    pub size: u64,
+    pub access_time: Option<UnixTimestamp>,
+    pub modification_time: Option<UnixTimestamp>,
+    pub change_time: Option<UnixTimestamp>,
+}
+
+impl Stat {
+    /// Attributes of a node which is not backed by any storage (e.g. a directory created only by
+    /// a path claimed by some container)
+    pub fn virtual_dir() -> Self {
+        Self {
+            node_type: NodeType::Dir,
+            size: 0,
+            access_time: None,
+            modification_time: None,
+            change_time: None,
+        }
+    }
+}
+
+/// Opaque handle of a file opened within DFS
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct FileHandle {
+    descriptor: u64,
+}
+
+impl FileHandle {
+    pub fn new(descriptor: u64) -> Self {
+        Self { descriptor }
+    }
+
+    pub fn descriptor(&self) -> u64 {
+        self.descriptor
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+#[repr(C)]
+pub enum DfsFrontendError {
+    #[error("No such path")]
+    NoSuchPath,
+    #[error("Path already exists")]
+    PathAlreadyExists,
+    #[error("Not a directory")]
+    NotADirectory,
+    #[error("Not a file")]
+    NotAFile,
+    #[error("Directory is not empty")]
+    DirNotEmpty,
+    #[error("File name is too long")]
+    NameTooLong,
+    #[error("Path is not claimed by any container so it can not be modified")]
+    ReadOnlyPath,
+    #[error("Moving nodes between containers is not supported")]
+    MoveBetweenContainers,
+    #[error("Invalid file handle")]
+    InvalidFileHandle,
+    #[error("None of the storages is available")]
+    StorageNotResponsive,
+    #[error("DFS error: {0}")]
+    Generic(String),
+}
+
+/// Kind of a [`DfsEvent`], used by FFI consumers which cannot match on the event itself
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(C)]
+pub enum DfsEventType {
+    BackendUnsupported,
+    ReplicaFailed,
+    AllReplicasFailed,
+    StorageRecovered,
+}
+
+/// Event reported by DFS to the application bypassing results of [`DfsFrontend`] methods, e.g. to
+/// let a user know that some storage is degraded even though operations still succeed thanks to
+/// other replicas.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum DfsEvent {
+    /// Storage cannot be used because there is no backend supporting its type. Reported once per
+    /// storage.
+    BackendUnsupported {
+        storage_uuid: Uuid,
+        backend_type: String,
+    },
+    /// Storage which has been working so far failed to execute an operation. Subsequent failures
+    /// are not reported until the storage recovers.
+    ReplicaFailed {
+        storage_uuid: Uuid,
+        backend_type: String,
+        reason: String,
+    },
+    /// Operation could not be executed because none of the container's storages works. Reported for
+    /// every such operation.
+    AllReplicasFailed { storage_uuids: Vec<Uuid> },
+    /// Storage previously reported with [`DfsEvent::ReplicaFailed`] works again.
+    StorageRecovered { storage_uuid: Uuid },
+}
+
+impl DfsEvent {
+    pub fn event_type(&self) -> DfsEventType {
+        match self {
+            DfsEvent::BackendUnsupported { .. } => DfsEventType::BackendUnsupported,
+            DfsEvent::ReplicaFailed { .. } => DfsEventType::ReplicaFailed,
+            DfsEvent::AllReplicasFailed { .. } => DfsEventType::AllReplicasFailed,
+            DfsEvent::StorageRecovered { .. } => DfsEventType::StorageRecovered,
+        }
+    }
+
+    /// Returns uuids of all storages the event concerns
+    pub fn storage_uuids(&self) -> Vec<String> {
+        match self {
+            DfsEvent::BackendUnsupported { storage_uuid, .. }
+            | DfsEvent::ReplicaFailed { storage_uuid, .. }
+            | DfsEvent::StorageRecovered { storage_uuid } => vec![storage_uuid.to_string()],
+            DfsEvent::AllReplicasFailed { storage_uuids } => {
+                storage_uuids.iter().map(Uuid::to_string).collect()
+            }
+        }
+    }
+
+    /// Returns human readable description of the event
+    pub fn description(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Display for DfsEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DfsEvent::BackendUnsupported {
+                storage_uuid,
+                backend_type,
+            } => write!(
+                f,
+                "Storage {storage_uuid} of unsupported backend type {backend_type}"
+            ),
+            DfsEvent::ReplicaFailed {
+                storage_uuid,
+                backend_type,
+                reason,
+            } => write!(
+                f,
+                "Storage {storage_uuid} ({backend_type}) failed: {reason}"
+            ),
+            DfsEvent::AllReplicasFailed { storage_uuids } => write!(
+                f,
+                "None of the storages {} works",
+                storage_uuids
+                    .iter()
+                    .map(Uuid::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            DfsEvent::StorageRecovered { storage_uuid } => {
+                write!(f, "Storage {storage_uuid} works again")
+            }
+        }
+    }
+}
+
+/// Callback registered by the application in order to receive [`DfsEvent`]s.
+///
+/// Events may be delivered from threads other than the one calling [`DfsFrontend`] methods.
+pub trait DfsEventReceiver: Send + Sync {
+    fn receive_event(&self, event: DfsEvent);
+}
+
+impl<T: DfsEventReceiver + ?Sized> DfsEventReceiver for &T {
+    fn receive_event(&self, event: DfsEvent) {
+        (**self).receive_event(event)
+    }
 }
 
 /// Interface that DFS should expose towards frontends
+///
+/// Paths passed to the methods are absolute paths within the user's forest.
 pub trait DfsFrontend {
     fn readdir(&mut self, path: String) -> Vec<NodeDescriptor>;
+    fn getattr(&mut self, path: String) -> Result<Stat, DfsFrontendError>;
+    /// Opens an existing file. Returned handle must be released with [`DfsFrontend::close`].
+    fn open(&mut self, path: String) -> Result<FileHandle, DfsFrontendError>;
+    fn close(&mut self, file: &FileHandle) -> Result<(), DfsFrontendError>;
+    /// Reads up to `count` bytes starting at `offset`. Returns less bytes if the end of file has
+    /// been reached.
+    fn read(
+        &mut self,
+        file: &FileHandle,
+        offset: u64,
+        count: usize,
+    ) -> Result<Vec<u8>, DfsFrontendError>;
+    /// Writes `buf` at `offset` and returns number of bytes written.
+    fn write(
+        &mut self,
+        file: &FileHandle,
+        offset: u64,
+        buf: Vec<u8>,
+    ) -> Result<usize, DfsFrontendError>;
+    /// Creates a new empty file and opens it.
+    fn create(&mut self, path: String) -> Result<FileHandle, DfsFrontendError>;
+    fn unlink(&mut self, path: String) -> Result<(), DfsFrontendError>;
+    fn mkdir(&mut self, path: String) -> Result<(), DfsFrontendError>;
+    fn rmdir(&mut self, path: String) -> Result<(), DfsFrontendError>;
+    fn rename(&mut self, old_path: String, new_path: String) -> Result<(), DfsFrontendError>;
+    fn truncate(&mut self, path: String, size: u64) -> Result<(), DfsFrontendError>;
+    /// Registers a receiver of [`DfsEvent`]s. All registered receivers get every event.
+    fn subscribe_events(&mut self, receiver: Box<dyn DfsEventReceiver>);
 }
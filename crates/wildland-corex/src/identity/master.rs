@@ -17,7 +17,9 @@
 
 use thiserror::Error;
 use wildland_crypto::error::KeyDeriveError;
+use wildland_crypto::identity::encrypting_keypair::EncryptingKeypair;
 use wildland_crypto::identity::{new_device_identity, Identity as CryptoIdentity};
+use wildland_crypto::symmetric::SymmetricKey;
 
 use super::wildland::WildlandIdentity;
 
@@ -57,6 +59,49 @@ impl MasterIdentity {
         Ok(identity)
     }
 
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn create_forest_data_key(
+        &self,
+        index: u64,
+    ) -> Result<SymmetricKey, ForestIdentityCreationError> {
+        let data_key = self
+            .crypto_identity
+            .as_ref()
+            .map(|identity| identity.forest_data_key(index))
+            .ok_or(ForestIdentityCreationError::CryptoIdentityNotFound)??;
+
+        Ok(data_key)
+    }
+
+    /// Derives encryption keypair of the forest. The `index` is bumped whenever the keypair has to
+    /// be rotated, e.g. after revoking one of the user's devices.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn create_forest_encryption_keypair(
+        &self,
+        forest_index: u64,
+        index: u64,
+    ) -> Result<EncryptingKeypair, ForestIdentityCreationError> {
+        let keypair = self
+            .crypto_identity
+            .as_ref()
+            .map(|identity| identity.encryption_keypair(forest_index, index))
+            .ok_or(ForestIdentityCreationError::CryptoIdentityNotFound)??;
+
+        Ok(keypair)
+    }
+
+    /// Derives key encrypting backups of the user's profile.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn create_backup_key(&self) -> Result<SymmetricKey, ForestIdentityCreationError> {
+        let keypair = self
+            .crypto_identity
+            .as_ref()
+            .map(|identity| identity.backup_keypair())
+            .ok_or(ForestIdentityCreationError::CryptoIdentityNotFound)??;
+
+        Ok(SymmetricKey::from_bytes(keypair.secret.to_bytes()).derive_subkey(b"wildland.backup"))
+    }
+
     #[tracing::instrument(level = "debug", skip_all)]
     pub fn create_device_identity(&self, name: String) -> WildlandIdentity {
         let keypair = new_device_identity();
@@ -98,6 +143,58 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_create_forest_data_key_only_with_crypto_identity() {
+        let master_identity = MasterIdentity::new(Some(create_crypto_identity()));
+        assert!(master_identity.create_forest_data_key(0).is_ok());
+
+        let master_identity = MasterIdentity::new(None);
+        assert_eq!(
+            master_identity.create_forest_data_key(0).unwrap_err(),
+            ForestIdentityCreationError::CryptoIdentityNotFound
+        );
+    }
+
+    #[test]
+    fn should_rotate_forest_encryption_keypair() {
+        let master_identity = MasterIdentity::new(Some(create_crypto_identity()));
+        let current = master_identity
+            .create_forest_encryption_keypair(0, 0)
+            .unwrap();
+        let rotated = master_identity
+            .create_forest_encryption_keypair(0, 1)
+            .unwrap();
+
+        assert_ne!(current.encode_pub(), rotated.encode_pub());
+        assert_eq!(
+            MasterIdentity::new(None)
+                .create_forest_encryption_keypair(0, 1)
+                .unwrap_err(),
+            ForestIdentityCreationError::CryptoIdentityNotFound
+        );
+    }
+
+    #[test]
+    fn should_derive_the_same_backup_key_from_the_same_mnemonic() {
+        let mnemonic = generate_random_mnemonic().unwrap();
+        let backup_key = MasterIdentity::new(Some(Identity::try_from(&mnemonic).unwrap()))
+            .create_backup_key()
+            .unwrap();
+
+        assert_eq!(
+            MasterIdentity::new(Some(Identity::try_from(&mnemonic).unwrap()))
+                .create_backup_key()
+                .unwrap(),
+            backup_key
+        );
+        assert_ne!(
+            MasterIdentity::new(Some(create_crypto_identity()))
+                .create_backup_key()
+                .unwrap(),
+            backup_key
+        );
+    }
+
     #[test]
     fn should_create_device_identity_with_crypto_identity() {
         let crypto_identity = create_crypto_identity();
@@ -19,6 +19,7 @@ use std::fmt::{self, Display, Formatter};
 
 use wildland_crypto::identity::signing_keypair::{PubKey, SecKey};
 use wildland_crypto::identity::SigningKeypair;
+use zeroize::Zeroizing;
 
 use crate::WildlandIdentity::{Device, Forest};
 
@@ -52,13 +53,13 @@ impl WildlandIdentity {
         }
     }
 
-    pub fn get_private_key(&self) -> SecKey {
+    pub fn get_private_key(&self) -> Zeroizing<SecKey> {
         match self {
             Forest(_, keypair) | Device(_, keypair) => keypair.secret(),
         }
     }
 
-    pub fn get_keypair_bytes(&self) -> Vec<u8> {
+    pub fn get_keypair_bytes(&self) -> Zeroizing<Vec<u8>> {
         match self {
             Forest(_, keypair) | Device(_, keypair) => keypair.to_bytes(),
         }
@@ -78,6 +79,18 @@ mod tests {
     use crate::test_utilities::{SIGNING_PUBLIC_KEY, SIGNING_SECRET_KEY};
     use crate::WildlandIdentity;
 
+    #[test]
+    fn debug_output_does_not_reveal_private_key() {
+        let keypair = SigningKeypair::try_from_str(SIGNING_PUBLIC_KEY, SIGNING_SECRET_KEY).unwrap();
+        let wildland_identity = WildlandIdentity::Forest(0, keypair);
+
+        let debug = format!("{wildland_identity:?}");
+
+        assert!(debug.contains(SIGNING_PUBLIC_KEY));
+        assert!(!debug.contains(SIGNING_SECRET_KEY));
+        assert!(!debug.contains(&format!("{:?}", &wildland_identity.get_private_key()[..])));
+    }
+
     #[test]
     fn should_get_correct_fingerprint() {
         let keypair = SigningKeypair::try_from_str(SIGNING_PUBLIC_KEY, SIGNING_SECRET_KEY).unwrap();
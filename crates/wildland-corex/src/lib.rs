@@ -39,7 +39,8 @@ pub use wildland_crypto::identity::{
     MnemonicPhrase,
     SigningKeypair,
 };
-pub use wildland_crypto::utils;
+pub use wildland_crypto::symmetric::{SymmetricKey, ENCRYPTION_OVERHEAD};
+pub use wildland_crypto::{stream, utils};
 
 pub type CorexResult<T> = Result<T, CoreXError>;
 
@@ -0,0 +1,458 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use fs2::FileExt;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use wildland_crypto::symmetric::{PassphraseKdfParams, SymmetricKey};
+use zeroize::{Zeroize, Zeroizing};
+
+use super::api::LocalSecureStorage;
+use super::result::{LssError, LssResult};
+
+/// Version 2 stores parameters of the passphrase key derivation, files of version 1 were created
+/// with [`PassphraseKdfParams::legacy`].
+const FILE_FORMAT_VERSION: u32 = 2;
+const SALT_LEN: usize = 16;
+
+/// Secret protecting [`EncryptedFileLss`].
+pub enum LssSecret {
+    /// Passphrase provided by the user, the key is derived from it with Argon2id.
+    Passphrase(String),
+    /// File with a random key, created with [`EncryptedFileLss::create_key_file`].
+    KeyFile(PathBuf),
+}
+
+impl Drop for LssSecret {
+    fn drop(&mut self) {
+        if let LssSecret::Passphrase(passphrase) = self {
+            passphrase.zeroize();
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct LssFile {
+    version: u32,
+    /// Hex encoded salt of the passphrase, absent if the storage is protected with a key file
+    salt: Option<String>,
+    /// Parameters the key is derived from the passphrase with, absent if the storage is protected
+    /// with a key file or the file was created before they were stored
+    #[serde(default)]
+    kdf: Option<PassphraseKdfParams>,
+    /// Hex encoded, encrypted JSON map of entries
+    entries: String,
+}
+
+/// [`LocalSecureStorage`] keeping entries in a file encrypted with a key derived from a passphrase
+/// or read from a key file.
+///
+/// The file is replaced atomically on every modification. The storage is locked for exclusive use
+/// by a single instance until it is dropped.
+pub struct EncryptedFileLss {
+    path: PathBuf,
+    key: SymmetricKey,
+    salt: Option<Vec<u8>>,
+    kdf: Option<PassphraseKdfParams>,
+    entries: Mutex<BTreeMap<String, String>>,
+    _lock: File,
+}
+
+impl EncryptedFileLss {
+    /// Opens storage kept in the given file. The file is created with the first insertion.
+    ///
+    /// Returns an error if the secret does not match the one the storage was created with or if
+    /// the storage is already opened, possibly by another process.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn open(path: &Path, secret: &LssSecret) -> LssResult<Self> {
+        let lock = lock(path)?;
+        let stored: Option<LssFile> = match fs::read(path) {
+            Ok(content) => Some(serde_json::from_slice(&content).map_err(to_lss_error)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(to_lss_error(e)),
+        };
+        if let Some(LssFile { version, .. }) = stored {
+            if version > FILE_FORMAT_VERSION {
+                return Err(LssError::Error(format!(
+                    "Unsupported LSS file format version {version}"
+                )));
+            }
+        }
+
+        let stored_salt = stored
+            .as_ref()
+            .map(|file| (file.salt.as_ref(), file.kdf.as_ref()));
+        let (key, salt, kdf) = match (secret, stored_salt) {
+            (LssSecret::Passphrase(passphrase), stored_salt) => {
+                let (salt, kdf) = match stored_salt {
+                    Some((Some(salt), kdf)) => (
+                        hex::decode(salt).map_err(to_lss_error)?,
+                        kdf.cloned().unwrap_or_else(PassphraseKdfParams::legacy),
+                    ),
+                    Some((None, _)) => {
+                        return Err(LssError::Error(
+                            "LSS is protected with a key file".to_owned(),
+                        ))
+                    }
+                    None => {
+                        let mut salt = vec![0; SALT_LEN];
+                        rand::thread_rng().fill_bytes(&mut salt);
+                        (salt, PassphraseKdfParams::recommended())
+                    }
+                };
+                let key = SymmetricKey::from_passphrase(passphrase.as_bytes(), &salt, &kdf)
+                    .map_err(to_lss_error)?;
+                (key, Some(salt), Some(kdf))
+            }
+            (LssSecret::KeyFile(_), Some((Some(_), _))) => {
+                return Err(LssError::Error(
+                    "LSS is protected with a passphrase".to_owned(),
+                ))
+            }
+            (LssSecret::KeyFile(key_path), _) => (read_key_file(key_path)?, None, None),
+        };
+
+        let entries = match stored {
+            Some(file) => {
+                let plaintext =
+                    Zeroizing::new(hex::decode(file.entries).map_err(to_lss_error).and_then(
+                        |ciphertext| {
+                            key.decrypt(&ciphertext).map_err(|_| {
+                                LssError::Error("Invalid passphrase or key file".to_owned())
+                            })
+                        },
+                    )?);
+                serde_json::from_slice(&plaintext).map_err(to_lss_error)?
+            }
+            None => BTreeMap::new(),
+        };
+
+        Ok(Self {
+            path: path.to_owned(),
+            key,
+            salt,
+            kdf,
+            entries: Mutex::new(entries),
+            _lock: lock,
+        })
+    }
+
+    /// Creates a file with a new random key, which can be used to protect the storage instead of
+    /// a passphrase. An existing file is never overwritten.
+    pub fn create_key_file(path: &Path) -> LssResult<()> {
+        let key =
+            Zeroizing::new(serde_json::to_vec(&SymmetricKey::generate()).map_err(to_lss_error)?);
+        let mut file = owner_only(OpenOptions::new().write(true).create_new(true))
+            .open(path)
+            .map_err(to_lss_error)?;
+        file.write_all(&key)
+            .and_then(|_| file.sync_all())
+            .map_err(to_lss_error)
+    }
+
+    /// Applies `op` to a copy of the entries, which replaces them only once it is saved, so the
+    /// entries never differ from the file.
+    fn modify<T>(&self, op: impl FnOnce(&mut BTreeMap<String, String>) -> T) -> LssResult<T> {
+        let mut entries = self.entries.lock().expect("Poisoned Mutex");
+        let mut modified = entries.clone();
+        let result = op(&mut modified);
+        if let Err(e) = self.save(&modified) {
+            modified.values_mut().for_each(Zeroize::zeroize);
+            return Err(e);
+        }
+        std::mem::swap(&mut *entries, &mut modified);
+        modified.values_mut().for_each(Zeroize::zeroize);
+        Ok(result)
+    }
+
+    fn read<T>(&self, op: impl FnOnce(&BTreeMap<String, String>) -> T) -> LssResult<T> {
+        Ok(op(&self.entries.lock().expect("Poisoned Mutex")))
+    }
+
+    fn save(&self, entries: &BTreeMap<String, String>) -> LssResult<()> {
+        let plaintext = Zeroizing::new(serde_json::to_vec(entries).map_err(to_lss_error)?);
+        let content = serde_json::to_vec(&LssFile {
+            version: FILE_FORMAT_VERSION,
+            salt: self.salt.as_ref().map(hex::encode),
+            kdf: self.kdf.clone(),
+            entries: hex::encode(self.key.encrypt(&plaintext)),
+        })
+        .map_err(to_lss_error)?;
+
+        // the file is replaced at once, so it is never left half-written
+        let tmp_path = self.path.with_extension("tmp");
+        owner_only(OpenOptions::new().write(true).create(true).truncate(true))
+            .open(&tmp_path)
+            .and_then(|mut file| {
+                file.write_all(&content)?;
+                file.sync_all()
+            })
+            .and_then(|_| fs::rename(&tmp_path, &self.path))
+            .and_then(|_| sync_parent_dir(&self.path))
+            .map_err(to_lss_error)
+    }
+}
+
+/// Flushes the directory entry of the file, so the rename survives a crash.
+fn sync_parent_dir(path: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        let parent = if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        };
+        File::open(parent)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Locks the storage with a file next to it, so it is not modified by two instances at once.
+fn lock(path: &Path) -> LssResult<File> {
+    let lock = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path.with_extension("lock"))
+        .map_err(to_lss_error)?;
+    lock.try_lock_exclusive()
+        .map_err(|_| LssError::Error("LSS is already in use".to_owned()))?;
+    Ok(lock)
+}
+
+fn read_key_file(path: &Path) -> LssResult<SymmetricKey> {
+    let content = Zeroizing::new(fs::read(path).map_err(to_lss_error)?);
+    serde_json::from_slice(&content).map_err(to_lss_error)
+}
+
+impl Drop for EncryptedFileLss {
+    fn drop(&mut self) {
+        if let Ok(entries) = self.entries.get_mut() {
+            entries.values_mut().for_each(Zeroize::zeroize);
+        }
+    }
+}
+
+/// Makes created files readable for their owner only, where supported.
+fn owner_only(options: &mut OpenOptions) -> &mut OpenOptions {
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(options, 0o600);
+    options
+}
+
+fn to_lss_error(error: impl std::fmt::Display) -> LssError {
+    LssError::Error(error.to_string())
+}
+
+impl LocalSecureStorage for EncryptedFileLss {
+    fn insert(&self, key: String, value: String) -> LssResult<Option<String>> {
+        self.modify(|entries| entries.insert(key, value))
+    }
+
+    fn get(&self, key: String) -> LssResult<Option<String>> {
+        self.read(|entries| entries.get(&key).cloned())
+    }
+
+    fn contains_key(&self, key: String) -> LssResult<bool> {
+        self.read(|entries| entries.contains_key(&key))
+    }
+
+    fn keys(&self) -> LssResult<Vec<String>> {
+        self.read(|entries| entries.keys().cloned().collect())
+    }
+
+    fn keys_starting_with(&self, prefix: String) -> LssResult<Vec<String>> {
+        self.read(|entries| {
+            entries
+                .keys()
+                .filter(|key| key.starts_with(&prefix))
+                .cloned()
+                .collect()
+        })
+    }
+
+    fn remove(&self, key: String) -> LssResult<Option<String>> {
+        self.modify(|entries| entries.remove(&key))
+    }
+
+    fn len(&self) -> LssResult<usize> {
+        self.read(|entries| entries.len())
+    }
+
+    fn is_empty(&self) -> LssResult<bool> {
+        self.read(|entries| entries.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rstest::*;
+
+    use super::*;
+
+    #[fixture]
+    fn dir() -> PathBuf {
+        tempfile::tempdir().unwrap().into_path()
+    }
+
+    fn passphrase(passphrase: &str) -> LssSecret {
+        LssSecret::Passphrase(passphrase.to_owned())
+    }
+
+    #[rstest]
+    fn entries_are_persisted_encrypted(dir: PathBuf) {
+        let path = dir.join("lss.json");
+
+        let lss = EncryptedFileLss::open(&path, &passphrase("secret")).unwrap();
+        assert!(lss.is_empty().unwrap());
+        lss.insert("a/1".to_owned(), "one".to_owned()).unwrap();
+        lss.insert("a/2".to_owned(), "two".to_owned()).unwrap();
+        lss.insert("b".to_owned(), "three".to_owned()).unwrap();
+        assert_eq!(
+            lss.remove("b".to_owned()).unwrap(),
+            Some("three".to_owned())
+        );
+        drop(lss);
+
+        assert!(!String::from_utf8(fs::read(&path).unwrap())
+            .unwrap()
+            .contains("a/1"));
+        let lss = EncryptedFileLss::open(&path, &passphrase("secret")).unwrap();
+        assert_eq!(lss.len().unwrap(), 2);
+        assert_eq!(lss.get("a/1".to_owned()).unwrap(), Some("one".to_owned()));
+        assert!(!lss.contains_key("b".to_owned()).unwrap());
+        assert_eq!(
+            lss.keys_starting_with("a/".to_owned()).unwrap(),
+            vec!["a/1".to_owned(), "a/2".to_owned()]
+        );
+    }
+
+    #[rstest]
+    fn key_derivation_params_are_stored_with_salt(dir: PathBuf) {
+        let path = dir.join("lss.json");
+        let lss = EncryptedFileLss::open(&path, &passphrase("secret")).unwrap();
+        lss.insert("key".to_owned(), "value".to_owned()).unwrap();
+        drop(lss);
+
+        let file: LssFile = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(file.version, FILE_FORMAT_VERSION);
+        assert_eq!(file.kdf, Some(PassphraseKdfParams::recommended()));
+    }
+
+    #[rstest]
+    fn storage_created_before_storing_key_derivation_params_is_opened(dir: PathBuf) {
+        let path = dir.join("lss.json");
+        let salt = [5; SALT_LEN];
+        let key = SymmetricKey::from_passphrase(b"secret", &salt, &PassphraseKdfParams::legacy())
+            .unwrap();
+        let entries = BTreeMap::from([("key".to_owned(), "value".to_owned())]);
+        let legacy_file = serde_json::json!({
+            "version": 1,
+            "salt": hex::encode(salt),
+            "entries": hex::encode(key.encrypt(&serde_json::to_vec(&entries).unwrap())),
+        });
+        fs::write(&path, legacy_file.to_string()).unwrap();
+
+        let lss = EncryptedFileLss::open(&path, &passphrase("secret")).unwrap();
+        assert_eq!(lss.get("key".to_owned()).unwrap(), Some("value".to_owned()));
+        lss.insert("other".to_owned(), "value".to_owned()).unwrap();
+        drop(lss);
+
+        // the params are kept once the file is saved again
+        let file: LssFile = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(file.kdf, Some(PassphraseKdfParams::legacy()));
+        let lss = EncryptedFileLss::open(&path, &passphrase("secret")).unwrap();
+        assert_eq!(lss.len().unwrap(), 2);
+    }
+
+    #[rstest]
+    fn storage_is_not_opened_with_invalid_secret(dir: PathBuf) {
+        let path = dir.join("lss.json");
+        let lss = EncryptedFileLss::open(&path, &passphrase("secret")).unwrap();
+        lss.insert("key".to_owned(), "value".to_owned()).unwrap();
+        drop(lss);
+
+        let key_path = dir.join("lss.key");
+        EncryptedFileLss::create_key_file(&key_path).unwrap();
+
+        assert_eq!(
+            EncryptedFileLss::open(&path, &passphrase("other")).err(),
+            Some(LssError::Error("Invalid passphrase or key file".to_owned()))
+        );
+        assert_eq!(
+            EncryptedFileLss::open(&path, &LssSecret::KeyFile(key_path)).err(),
+            Some(LssError::Error(
+                "LSS is protected with a passphrase".to_owned()
+            ))
+        );
+    }
+
+    #[rstest]
+    fn storage_is_protected_with_key_file(dir: PathBuf) {
+        let path = dir.join("lss.json");
+        let key_path = dir.join("lss.key");
+        EncryptedFileLss::create_key_file(&key_path).unwrap();
+        assert!(EncryptedFileLss::create_key_file(&key_path).is_err());
+
+        let lss = EncryptedFileLss::open(&path, &LssSecret::KeyFile(key_path.clone())).unwrap();
+        lss.insert("key".to_owned(), "value".to_owned()).unwrap();
+        drop(lss);
+
+        let lss = EncryptedFileLss::open(&path, &LssSecret::KeyFile(key_path)).unwrap();
+        assert_eq!(lss.get("key".to_owned()).unwrap(), Some("value".to_owned()));
+    }
+
+    #[rstest]
+    fn storage_is_opened_once_at_a_time(dir: PathBuf) {
+        let path = dir.join("lss.json");
+        let lss = EncryptedFileLss::open(&path, &passphrase("secret")).unwrap();
+
+        assert_eq!(
+            EncryptedFileLss::open(&path, &passphrase("secret")).err(),
+            Some(LssError::Error("LSS is already in use".to_owned()))
+        );
+
+        drop(lss);
+        assert!(EncryptedFileLss::open(&path, &passphrase("secret")).is_ok());
+    }
+
+    #[rstest]
+    fn entries_are_not_modified_when_saving_fails(dir: PathBuf) {
+        let path = dir.join("lss.json");
+        let lss = EncryptedFileLss::open(&path, &passphrase("secret")).unwrap();
+        lss.insert("key".to_owned(), "value".to_owned()).unwrap();
+
+        // a directory in place of the temporary file makes saving fail
+        fs::create_dir(path.with_extension("tmp")).unwrap();
+        assert!(lss.insert("key".to_owned(), "other".to_owned()).is_err());
+        assert!(lss.remove("key".to_owned()).is_err());
+
+        assert_eq!(lss.get("key".to_owned()).unwrap(), Some("value".to_owned()));
+        drop(lss);
+        let lss = EncryptedFileLss::open(&path, &passphrase("secret")).unwrap();
+        assert_eq!(lss.get("key".to_owned()).unwrap(), Some("value".to_owned()));
+    }
+}
@@ -0,0 +1,151 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Name of the forest created along with the user, derived with index 0.
+pub const DEFAULT_FOREST_NAME: &str = "default";
+
+/// Forests of the user kept on this device, identified by names given by the user.
+///
+/// Forest keys are derived from the mnemonic with the forest index, so indices of removed forests
+/// are never reused. Indices used by other devices of the user are tracked in CatLib, so the
+/// registry only remembers the ones used by this device.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ForestRegistry {
+    names: BTreeMap<u64, String>,
+    active: u64,
+    next_index: u64,
+}
+
+impl Default for ForestRegistry {
+    fn default() -> Self {
+        Self {
+            names: BTreeMap::from([(0, DEFAULT_FOREST_NAME.to_owned())]),
+            active: 0,
+            next_index: 1,
+        }
+    }
+}
+
+impl ForestRegistry {
+    /// Index of the forest the user works with.
+    pub fn active_index(&self) -> u64 {
+        self.active
+    }
+
+    pub fn active_name(&self) -> &str {
+        self.names
+            .get(&self.active)
+            .map(String::as_str)
+            .unwrap_or(DEFAULT_FOREST_NAME)
+    }
+
+    /// Returns names of the forests in the order of creation.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.values().map(String::as_str)
+    }
+
+    pub fn indices(&self) -> impl Iterator<Item = u64> + '_ {
+        self.names.keys().copied()
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<u64> {
+        self.names
+            .iter()
+            .find(|(_, forest_name)| *forest_name == name)
+            .map(|(index, _)| *index)
+    }
+
+    pub fn name_of(&self, index: u64) -> Option<&str> {
+        self.names.get(&index).map(String::as_str)
+    }
+
+    /// Lowest index not used by any forest registered on this device so far.
+    pub fn next_index(&self) -> u64 {
+        self.next_index
+    }
+
+    /// Registers a forest with the given name and the index its keys are derived with.
+    pub fn add(&mut self, index: u64, name: String) {
+        self.names.insert(index, name);
+        self.next_index = self.next_index.max(index + 1);
+    }
+
+    pub fn remove(&mut self, index: u64) -> Option<String> {
+        self.names.remove(&index)
+    }
+
+    /// Makes the forest active, returns `false` if there is no forest with the given index.
+    pub fn activate(&mut self, index: u64) -> bool {
+        let registered = self.names.contains_key(&index);
+        if registered {
+            self.active = index;
+        }
+        registered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removed_forest_index_is_not_reused() {
+        let mut registry = ForestRegistry::default();
+        registry.add(registry.next_index(), "work".to_owned());
+        assert_eq!(registry.remove(1), Some("work".to_owned()));
+
+        registry.add(registry.next_index(), "personal".to_owned());
+
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            ["default", "personal"]
+        );
+        assert_eq!(registry.index_of("personal"), Some(2));
+        assert_eq!(registry.index_of("work"), None);
+    }
+
+    #[test]
+    fn index_used_by_other_device_is_skipped() {
+        let mut registry = ForestRegistry::default();
+        registry.add(3, "work".to_owned());
+
+        assert_eq!(registry.next_index(), 4);
+        registry.add(1, "personal".to_owned());
+        assert_eq!(registry.next_index(), 4);
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            ["default", "personal", "work"]
+        );
+    }
+
+    #[test]
+    fn only_registered_forest_can_be_activated() {
+        let mut registry = ForestRegistry::default();
+        let work = registry.next_index();
+        registry.add(work, "work".to_owned());
+
+        assert!(!registry.activate(5));
+        assert_eq!(registry.active_name(), DEFAULT_FOREST_NAME);
+        assert!(registry.activate(work));
+        assert_eq!(registry.active_index(), work);
+        assert_eq!(registry.active_name(), "work");
+    }
+}
@@ -16,9 +16,13 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod api;
+mod file;
+mod forest_registry;
 mod result;
 mod service;
 
 pub use api::LocalSecureStorage;
+pub use file::{EncryptedFileLss, LssSecret};
+pub use forest_registry::{ForestRegistry, DEFAULT_FOREST_NAME};
 pub use result::*;
-pub use service::LssService;
+pub use service::{LssService, OverwrittenEntries};
@@ -15,18 +15,34 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
 use std::fmt::{Debug, Display};
 
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use uuid::Uuid;
+use wildland_crypto::identity::encrypting_keypair::EncryptingKeypair;
 use wildland_crypto::identity::SigningKeypair;
+use wildland_crypto::symmetric::SymmetricKey;
+use zeroize::{Zeroize, Zeroizing};
 
 use super::api::LocalSecureStorage;
+use super::forest_registry::ForestRegistry;
 use super::result::LssResult;
 use crate::catlib_service::entities::{ForestManifest, Identity};
 use crate::{ForestRetrievalError, LssError, WildlandIdentity, DEFAULT_FOREST_KEY};
 
+/// Entries overwritten by [`LssService::import_entries`], `None` for the ones which did not exist.
+/// Wiped from memory on drop.
+#[derive(Default)]
+pub struct OverwrittenEntries(BTreeMap<String, Option<String>>);
+
+impl Drop for OverwrittenEntries {
+    fn drop(&mut self) {
+        self.0.values_mut().flatten().for_each(Zeroize::zeroize);
+    }
+}
+
 #[derive(Clone)]
 pub struct LssService {
     lss: &'static dyn LocalSecureStorage,
@@ -34,6 +50,20 @@ pub struct LssService {
 
 const THIS_DEVICE_KEYPAIR_KEY: &str = "wildland.device.keypair";
 const THIS_DEVICE_NAME_KEY: &str = "wildland.device.name";
+const THIS_DEVICE_ENCRYPTION_KEYPAIR_KEY: &str = "wildland.device.encryption_keypair";
+const FOREST_REGISTRY_KEY: &str = "wildland.forests";
+
+fn forest_lss_key(forest_index: u64) -> String {
+    format!("wildland.forest.{forest_index}")
+}
+
+fn forest_data_lss_key(forest_index: u64) -> String {
+    format!("wildland.forest.{forest_index}.data_key")
+}
+
+fn forest_signed_lss_key(forest_index: u64) -> String {
+    format!("wildland.forest.{forest_index}.signed")
+}
 
 impl LssService {
     pub fn new(lss: &'static dyn LocalSecureStorage) -> Self {
@@ -64,6 +94,88 @@ impl LssService {
         })
     }
 
+    pub fn get_forest_identity(
+        &self,
+        forest_index: u64,
+    ) -> Result<Option<WildlandIdentity>, ForestRetrievalError> {
+        tracing::trace!("Getting forest identity.");
+        let keypair: Option<SigningKeypair> = self.get_parsed(forest_lss_key(forest_index))?;
+        Ok(keypair.map(|keypair| WildlandIdentity::Forest(forest_index, keypair)))
+    }
+
+    /// Returns index of the forest owned by the given identity, if its keys are kept in LSS.
+    pub fn find_forest_index(&self, owner: &Identity) -> Result<Option<u64>, ForestRetrievalError> {
+        for index in self.get_forest_registry()?.indices() {
+            if let Some(identity) = self.get_forest_identity(index)? {
+                if Identity::from(identity.get_public_key()) == *owner {
+                    return Ok(Some(index));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Removes keys and uuid of the forest from LSS.
+    pub fn remove_forest(&self, forest_identity: &WildlandIdentity) -> LssResult<()> {
+        tracing::trace!("Removing forest {forest_identity}");
+        let forest_index = match forest_identity {
+            WildlandIdentity::Forest(forest_index, _) => forest_index,
+            _ => {
+                return Err(LssError::Error(format!(
+                    "{forest_identity} is not a forest identity"
+                )))
+            }
+        };
+        self.lss
+            .remove(Identity::from(forest_identity.get_public_key()).encode())?;
+        self.lss.remove(forest_data_lss_key(*forest_index))?;
+        self.lss.remove(forest_signed_lss_key(*forest_index))?;
+        self.lss.remove(forest_identity.to_string())?;
+        Ok(())
+    }
+
+    /// Returns forests of the user, or the registry with the default forest only if none was
+    /// saved yet.
+    pub fn get_forest_registry(&self) -> LssResult<ForestRegistry> {
+        Ok(self.get_parsed(FOREST_REGISTRY_KEY)?.unwrap_or_default())
+    }
+
+    pub fn save_forest_registry(&self, registry: &ForestRegistry) -> LssResult<bool> {
+        tracing::trace!("Saving forest registry");
+        self.serialize_and_save(FOREST_REGISTRY_KEY, registry)
+    }
+
+    /// Records that the forest manifest is signed by its owner, so it must not be accepted unsigned
+    /// anymore.
+    pub fn mark_forest_signed(&self, forest_index: u64) -> LssResult<bool> {
+        self.serialize_and_save(forest_signed_lss_key(forest_index), &true)
+    }
+
+    pub fn is_forest_signed(&self, forest_index: u64) -> LssResult<bool> {
+        Ok(self
+            .get_parsed(forest_signed_lss_key(forest_index))?
+            .unwrap_or(false))
+    }
+
+    pub fn save_forest_data_key(
+        &self,
+        forest_index: u64,
+        data_key: &SymmetricKey,
+    ) -> LssResult<bool> {
+        tracing::trace!("Saving forest data key");
+        self.serialize_and_save(forest_data_lss_key(forest_index), data_key)
+    }
+
+    pub fn get_default_forest_data_key(&self) -> LssResult<Option<SymmetricKey>> {
+        tracing::trace!("Getting default forest data key.");
+        self.get_forest_data_key(0)
+    }
+
+    pub fn get_forest_data_key(&self, forest_index: u64) -> LssResult<Option<SymmetricKey>> {
+        tracing::trace!("Getting forest data key.");
+        self.get_parsed(forest_data_lss_key(forest_index))
+    }
+
     pub fn save_forest_uuid(&self, forest: &dyn ForestManifest) -> LssResult<bool> {
         tracing::trace!("Saving forest uuid");
         self.serialize_and_save(forest.owner().encode(), &forest.uuid())
@@ -91,6 +203,69 @@ impl LssService {
         })
     }
 
+    /// Saves keypair other devices use to send secrets to this device, e.g. keys of the forest it
+    /// is enrolled in.
+    pub fn save_this_device_encryption_keypair(
+        &self,
+        keypair: &EncryptingKeypair,
+    ) -> LssResult<bool> {
+        tracing::trace!("Saving this device encryption keypair");
+        self.serialize_and_save(THIS_DEVICE_ENCRYPTION_KEYPAIR_KEY, keypair)
+    }
+
+    pub fn get_this_device_encryption_keypair(&self) -> LssResult<Option<EncryptingKeypair>> {
+        tracing::trace!("Getting this device encryption keypair.");
+        self.get_parsed(THIS_DEVICE_ENCRYPTION_KEYPAIR_KEY)
+    }
+
+    /// Returns all entries of the LSS, e.g. to back them up.
+    pub fn export_entries(&self) -> LssResult<BTreeMap<String, String>> {
+        self.lss
+            .keys()?
+            .into_iter()
+            .filter_map(|key| match self.lss.get(key.clone()) {
+                Ok(Some(value)) => Some(Ok((key, value))),
+                Ok(None) => None,
+                Err(e) => Some(Err(e)),
+            })
+            .collect()
+    }
+
+    /// Saves entries returned by [`Self::export_entries`], overwriting existing ones. Returns the
+    /// overwritten entries, so the import can be undone with [`Self::revert_import`].
+    ///
+    /// Entries saved before a failure are reverted right away.
+    pub fn import_entries(
+        &self,
+        entries: &BTreeMap<String, String>,
+    ) -> LssResult<OverwrittenEntries> {
+        let mut overwritten = OverwrittenEntries::default();
+        for (key, value) in entries {
+            match self.lss.insert(key.clone(), value.clone()) {
+                Ok(previous) => {
+                    overwritten.0.insert(key.clone(), previous);
+                }
+                Err(e) => {
+                    self.revert_import(overwritten)?;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(overwritten)
+    }
+
+    /// Restores entries overwritten by [`Self::import_entries`] and removes the imported ones.
+    pub fn revert_import(&self, mut overwritten: OverwrittenEntries) -> LssResult<()> {
+        tracing::trace!("Reverting import of LSS entries");
+        for (key, previous) in std::mem::take(&mut overwritten.0) {
+            match previous {
+                Some(value) => self.lss.insert(key, value)?.map(Zeroizing::new),
+                None => self.lss.remove(key)?.map(Zeroizing::new),
+            };
+        }
+        Ok(())
+    }
+
     fn get_this_device_name(&self) -> LssResult<Option<String>> {
         self.get_parsed(THIS_DEVICE_NAME_KEY)
     }
@@ -116,7 +291,7 @@ impl LssService {
                 serde_json::to_string(obj)
                     .map_err(|e| LssError::Error(format!("Could not serialize object: {e}")))?,
             )
-            .map(|bytes| bytes.is_some())
+            .map(|previous| previous.map(Zeroizing::new).is_some())
     }
 
     /// retrieves bytes from LSS, deserializes them as json and parses as a type specified with template parameter
@@ -124,7 +299,7 @@ impl LssService {
     fn get_parsed<T: DeserializeOwned>(&self, key: impl Display + Debug) -> LssResult<Option<T>> {
         self.lss.get(key.to_string()).and_then(|optional_bytes| {
             optional_bytes.map_or(Ok(None), |input| {
-                serde_json::from_str(&input)
+                serde_json::from_str(&Zeroizing::new(input))
                     .map_err(|e| LssError::Error(format!("Could not parse LSS entry: {e}")))
             })
         })
@@ -138,7 +313,9 @@ mod tests {
 
     use rstest::{fixture, rstest};
     use uuid::Uuid;
+    use wildland_crypto::identity::encrypting_keypair::EncryptingKeypair;
     use wildland_crypto::identity::SigningKeypair;
+    use wildland_crypto::symmetric::SymmetricKey;
 
     use crate::catlib_service::entities::Identity;
     use crate::lss::service::{THIS_DEVICE_KEYPAIR_KEY, THIS_DEVICE_NAME_KEY};
@@ -273,6 +450,78 @@ mod tests {
         assert_eq!(default_forest.unwrap(), expecte_forest_identity)
     }
 
+    #[rstest]
+    fn test_save_and_get_default_forest_data_key(lss_stub: &'static dyn LocalSecureStorage) {
+        let service = LssService::new(lss_stub);
+        assert!(service.get_default_forest_data_key().unwrap().is_none());
+
+        let data_key = SymmetricKey::from_bytes([3; 32]);
+        service.save_forest_data_key(0, &data_key).unwrap();
+
+        assert_eq!(
+            service.get_default_forest_data_key().unwrap(),
+            Some(data_key)
+        );
+        assert!(lss_stub
+            .contains_key("wildland.forest.0.data_key".to_owned())
+            .unwrap());
+    }
+
+    #[rstest]
+    fn test_save_and_get_this_device_encryption_keypair(lss_stub: &'static dyn LocalSecureStorage) {
+        let service = LssService::new(lss_stub);
+        assert!(service
+            .get_this_device_encryption_keypair()
+            .unwrap()
+            .is_none());
+
+        let keypair = EncryptingKeypair::from_bytes_slices([1; 32], [2; 32]);
+        service
+            .save_this_device_encryption_keypair(&keypair)
+            .unwrap();
+
+        let retrieved = service
+            .get_this_device_encryption_keypair()
+            .unwrap()
+            .unwrap();
+        assert_eq!(retrieved.encode_pub(), keypair.encode_pub());
+        assert_eq!(retrieved.secret.to_bytes(), keypair.secret.to_bytes());
+    }
+
+    #[rstest]
+    fn test_find_and_remove_additional_forest(lss_stub: &'static dyn LocalSecureStorage) {
+        let service = LssService::new(lss_stub);
+        let mut registry = service.get_forest_registry().unwrap();
+        let index = registry.next_index();
+        registry.add(index, "work".to_owned());
+        service.save_forest_registry(&registry).unwrap();
+        let keypair = SigningKeypair::try_from_bytes_slices([1; 32], [2; 32]).unwrap();
+        let forest_identity = WildlandIdentity::Forest(index, SigningKeypair::from(&keypair));
+        service.save_identity(&forest_identity).unwrap();
+        service
+            .save_forest_data_key(index, &SymmetricKey::from_bytes([3; 32]))
+            .unwrap();
+
+        assert_eq!(service.get_forest_registry().unwrap(), registry);
+        assert_eq!(
+            service.find_forest_index(&Identity([1; 32])).unwrap(),
+            Some(index)
+        );
+        assert_eq!(
+            service
+                .get_forest_identity(index)
+                .unwrap()
+                .map(|identity| identity.get_public_key()),
+            Some([1; 32])
+        );
+
+        service.remove_forest(&forest_identity).unwrap();
+
+        assert_eq!(service.find_forest_index(&Identity([1; 32])).unwrap(), None);
+        assert!(service.get_forest_data_key(index).unwrap().is_none());
+        assert!(!lss_stub.contains_key(forest_identity.to_string()).unwrap());
+    }
+
     #[rstest]
     fn test_save_forest_uuid(lss_stub: &'static dyn LocalSecureStorage) {
         let service = LssService::new(lss_stub);
@@ -356,4 +605,63 @@ mod tests {
             WildlandIdentity::Device(device_name, SigningKeypair::from(&keypair));
         assert_eq!(device_identity, expected_device_identity);
     }
+
+    #[rstest]
+    fn test_exported_entries_should_be_imported(
+        lss_stub: &'static dyn LocalSecureStorage,
+        #[from(lss_stub)] other_lss_stub: &'static dyn LocalSecureStorage,
+    ) {
+        let service = LssService::new(lss_stub);
+        let keypair = SigningKeypair::try_from_bytes_slices([1; 32], [2; 32]).unwrap();
+        service
+            .save_identity(&WildlandIdentity::Device("device".to_owned(), keypair))
+            .unwrap();
+
+        let other_service = LssService::new(other_lss_stub);
+        other_service
+            .import_entries(&service.export_entries().unwrap())
+            .unwrap();
+
+        assert_eq!(other_service.export_entries().unwrap().len(), 2);
+        assert_eq!(
+            other_service.get_this_device_identity().unwrap(),
+            service.get_this_device_identity().unwrap()
+        );
+    }
+
+    #[rstest]
+    fn test_import_should_be_reverted(
+        lss_stub: &'static dyn LocalSecureStorage,
+        #[from(lss_stub)] other_lss_stub: &'static dyn LocalSecureStorage,
+    ) {
+        let service = LssService::new(lss_stub);
+        service
+            .save_identity(&WildlandIdentity::Device(
+                "device".to_owned(),
+                SigningKeypair::try_from_bytes_slices([1; 32], [2; 32]).unwrap(),
+            ))
+            .unwrap();
+        let other_service = LssService::new(other_lss_stub);
+        other_service
+            .save_forest_data_key(0, &SymmetricKey::from_bytes([3; 32]))
+            .unwrap();
+        other_service
+            .save_identity(&WildlandIdentity::Device(
+                "other device".to_owned(),
+                SigningKeypair::try_from_bytes_slices([1; 32], [2; 32]).unwrap(),
+            ))
+            .unwrap();
+        let entries_before = other_service.export_entries().unwrap();
+
+        let overwritten = other_service
+            .import_entries(&service.export_entries().unwrap())
+            .unwrap();
+        assert_eq!(
+            other_service.get_this_device_identity().unwrap(),
+            service.get_this_device_identity().unwrap()
+        );
+        other_service.revert_import(overwritten).unwrap();
+
+        assert_eq!(other_service.export_entries().unwrap(), entries_before);
+    }
 }
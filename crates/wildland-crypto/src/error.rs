@@ -34,6 +34,14 @@ pub enum CryptoError {
     EntropyTooLow,
     #[error("Could not decrypt content")]
     DecryptionError,
+    #[error("Could not derive key from passphrase: {0}")]
+    PassphraseDerivationError(String),
+    #[error("Secret sharing error: {0}")]
+    SecretSharingError(String),
+    #[error("Unsupported version of ciphertext envelope: {0}")]
+    UnsupportedEnvelopeVersion(u8),
+    #[error("Encrypted stream error: {0}")]
+    StreamError(String),
 }
 
 #[derive(Error, Debug, PartialEq, Eq, Clone)]
@@ -21,12 +21,14 @@ use bip39::{Mnemonic, Seed};
 use crypto_box::SecretKey as EncryptionSecretKey;
 use ed25519_dalek_bip32::{DerivationPath, ExtendedSecretKey};
 use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 use super::encrypting_keypair::EncryptingKeypair;
 use crate::error::{CryptoError, KeyDeriveError};
 use crate::identity::seed::extend_seed;
 use crate::identity::signing_keypair::SigningKeypair;
 use crate::identity::{MnemonicPhrase, MNEMONIC_LEN};
+use crate::symmetric::SymmetricKey;
 use crate::utils;
 
 fn signing_key_path(forest_index: u64) -> String {
@@ -47,18 +49,39 @@ fn backup_key_path() -> String {
     "m/5721156'/3'".to_string()
 }
 
+fn data_key_path(forest_index: u64) -> String {
+    format!("m/5721156'/4'/{forest_index}'")
+}
+
 /// This structure represents Wildland cryptographic identity.
 ///
 /// It uses BIP39 and BIP32 processes to derive keypairs of three purposes:
 /// - signing (not rotated, used to sign "user manifest")
 /// - encryption (used by other people to encrypt secrets to the user, rotated)
 /// - single-use-encryption - to transfer secrets in public
-#[derive(Debug)]
+///
+/// Mnemonic and keys are wiped from memory when the identity is dropped.
 pub struct Identity {
     extended_seckey: ExtendedSecretKey,
     words: MnemonicPhrase,
 }
 
+impl std::fmt::Debug for Identity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Identity").finish_non_exhaustive()
+    }
+}
+
+impl Drop for Identity {
+    fn drop(&mut self) {
+        // the secret key of `ExtendedSecretKey` zeroizes itself on drop
+        self.extended_seckey.chain_code.zeroize();
+        self.words.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for Identity {}
+
 impl TryFrom<&MnemonicPhrase> for Identity {
     type Error = CryptoError;
 
@@ -68,7 +91,7 @@ impl TryFrom<&MnemonicPhrase> for Identity {
     /// produce different seed (number) in other project.
     /// Only English language is accepted.
     fn try_from(mnemonic_phrase: &MnemonicPhrase) -> Result<Self, Self::Error> {
-        Self::from_mnemonic(utils::new_mnemonic_from_phrase(&mnemonic_phrase.join(" "))?)
+        Self::from_mnemonic_with_passphrase(mnemonic_phrase, None)
     }
 }
 
@@ -86,11 +109,29 @@ impl TryFrom<&[u8]> for Identity {
         let mut hasher = Sha256::new();
         hasher.update(entropy);
         let hashed_entropy = hasher.finalize();
-        Self::from_mnemonic(utils::new_mnemonic_from_entropy(&hashed_entropy[0..16])?)
+        Self::from_mnemonic(
+            utils::new_mnemonic_from_entropy(&hashed_entropy[0..16])?,
+            "",
+        )
     }
 }
 
 impl Identity {
+    /// Derive identity from mnemonic phrase protected with BIP39 passphrase ("25th word").
+    ///
+    /// Every passphrase gives a different, valid identity, so a wrong passphrase cannot be
+    /// detected here. No passphrase derives the same identity as an empty one.
+    pub fn from_mnemonic_with_passphrase(
+        mnemonic_phrase: &MnemonicPhrase,
+        passphrase: Option<&str>,
+    ) -> Result<Self, CryptoError> {
+        let phrase = Zeroizing::new(mnemonic_phrase.join(" "));
+        Self::from_mnemonic(
+            utils::new_mnemonic_from_phrase(&phrase)?,
+            passphrase.unwrap_or_default(),
+        )
+    }
+
     /// Derive the key that represents a forest.
     /// Pubkey represents forest to the world.
     pub fn forest_keypair(&self, forest_index: u64) -> Result<SigningKeypair, KeyDeriveError> {
@@ -135,16 +176,27 @@ impl Identity {
         self.derive_encryption_keypair(&backup_key_path())
     }
 
-    pub fn get_mnemonic(&self) -> MnemonicPhrase {
-        self.words.clone()
+    /// Deterministically derive symmetric key used to encrypt the data stored in the forest's
+    /// containers. Per-container keys should be derived from it with
+    /// [`SymmetricKey::derive_subkey`].
+    pub fn forest_data_key(&self, forest_index: u64) -> Result<SymmetricKey, KeyDeriveError> {
+        tracing::debug!("deriving forest data key");
+        let derived_extended_seckey =
+            self.derive_private_key_from_path(&data_key_path(forest_index))?;
+        Ok(SymmetricKey::from_bytes(
+            *derived_extended_seckey.secret_key.as_bytes(),
+        ))
+    }
+
+    /// Returns a copy of the mnemonic, wiped when dropped.
+    pub fn get_mnemonic(&self) -> Zeroizing<MnemonicPhrase> {
+        Zeroizing::new(self.words.clone())
     }
 
     #[tracing::instrument(level = "debug", skip_all)]
-    fn from_mnemonic(mnemonic: Mnemonic) -> Result<Self, CryptoError> {
+    fn from_mnemonic(mnemonic: Mnemonic, passphrase: &str) -> Result<Self, CryptoError> {
         tracing::debug!("Deriving Identity from mnemonic");
-        // Passphrases are great for plausible deniability in case of a cryptocurrency wallet.
-        // We don't need them here.
-        let passphrase = "";
+        // Passphrase gives plausible deniability and a second factor protecting the forest keys.
         let seed = Seed::new(&mnemonic, passphrase);
         // Seed here is randomness of high quality (it is hard to guess).
         // But we only have 64 bytes of it, and we need extra 32 bytes for
@@ -154,7 +206,7 @@ impl Identity {
         // 3. be public, since it will be used as a part of both XPrv and XPub!
         // To achieve this, we use key derivation function (KDF).
         // A very standard variant of that is HKDF.
-        let mut output_key_material = [0u8; 96];
+        let mut output_key_material = Zeroizing::new([0u8; 96]);
         extend_seed(seed.as_bytes(), &mut output_key_material);
 
         // Now we can use this randomness as bip32-dalek-ed25519 extended private key
@@ -184,7 +236,7 @@ impl Identity {
         let derived_extended_seckey = self.derive_private_key_from_path(path)?;
 
         // drop both the chain-code from xprv and last 32 bytes
-        let sec_key = *derived_extended_seckey.secret_key.as_bytes();
+        let sec_key = Zeroizing::new(*derived_extended_seckey.secret_key.as_bytes());
         SigningKeypair::try_from_secret_bytes(&sec_key).map_err(|e| KeyDeriveError(e.to_string()))
     }
 
@@ -231,7 +283,7 @@ mod tests {
 
     fn user() -> Identity {
         let mnemonic = utils::new_mnemonic_from_phrase(MNEMONIC_PHRASE).unwrap();
-        Identity::from_mnemonic(mnemonic).unwrap()
+        Identity::from_mnemonic(mnemonic, "").unwrap()
     }
 
     // please note that this helper is for TESTS ONLY!
@@ -295,13 +347,26 @@ mod tests {
         let skeypair = user.forest_keypair(0).unwrap();
         let e0key = user.encryption_keypair(0, 0).unwrap();
         let e1key = user.encryption_keypair(0, 1).unwrap();
-        assert_ne!(&skeypair.secret(), e0key.secret.as_bytes());
+        assert_ne!(&*skeypair.secret(), e0key.secret.as_bytes());
         assert_ne!(e0key.secret.as_bytes(), e1key.secret.as_bytes());
 
-        assert_eq!(encode(skeypair.secret()).len(), 64);
+        assert_eq!(encode(*skeypair.secret()).len(), 64);
         assert_eq!(encode(skeypair.public()).len(), 64);
     }
 
+    #[test]
+    fn can_derive_distinct_forest_data_keys() {
+        let user = user();
+        let data_key = user.forest_data_key(0).unwrap();
+
+        assert_eq!(data_key, user.forest_data_key(0).unwrap());
+        assert_ne!(data_key, user.forest_data_key(1).unwrap());
+        assert_ne!(
+            data_key,
+            SymmetricKey::from_bytes(*user.forest_keypair(0).unwrap().secret())
+        );
+    }
+
     const TEST_MNEMONIC_12: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
     const TEST_MNEMONIC_ITALIAN: &str =
         "abaco abaco abaco abaco abaco abaco abaco abaco abaco abaco abaco abbaglio";
@@ -342,7 +407,27 @@ mod tests {
                 "noise".to_owned(),
                 "voice".to_owned()
             ],
-            user.get_mnemonic()
+            *user.get_mnemonic()
+        );
+    }
+
+    #[test]
+    fn debug_output_does_not_reveal_secrets() {
+        let user = user();
+        let debug = format!("{user:?}");
+
+        assert_eq!(debug, "Identity { .. }");
+        for word in MNEMONIC_PHRASE.split(' ') {
+            assert!(!debug.contains(word));
+        }
+        let encryption_keypair = user.encryption_keypair(0, 0).unwrap();
+        assert!(!format!("{encryption_keypair:?}")
+            .contains(&encode(encryption_keypair.secret.as_bytes())));
+        let forest_keypair = user.forest_keypair(0).unwrap();
+        assert!(!format!("{forest_keypair:?}").contains(&encode(*forest_keypair.secret())));
+        assert_eq!(
+            format!("{:?}", user.forest_data_key(0).unwrap()),
+            "SymmetricKey(..)"
         );
     }
 
@@ -375,6 +460,31 @@ mod tests {
         )
     }
 
+    #[test]
+    fn passphrase_derives_other_identity() {
+        let mnemonic_array: MnemonicPhrase = TEST_MNEMONIC_12
+            .split(' ')
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>()
+            .try_into()
+            .unwrap();
+        let user = Identity::try_from(&mnemonic_array).unwrap();
+        let with_empty_passphrase =
+            Identity::from_mnemonic_with_passphrase(&mnemonic_array, Some("")).unwrap();
+        let with_passphrase =
+            Identity::from_mnemonic_with_passphrase(&mnemonic_array, Some("TREZOR")).unwrap();
+
+        assert_eq!(
+            with_empty_passphrase.forest_keypair(0).unwrap().public(),
+            user.forest_keypair(0).unwrap().public()
+        );
+        assert_ne!(
+            with_passphrase.forest_keypair(0).unwrap().public(),
+            user.forest_keypair(0).unwrap().public()
+        );
+        assert_eq!(*with_passphrase.get_mnemonic(), mnemonic_array);
+    }
+
     #[test]
     // this is still valid test, we are supporting only english, but do not
     // expose the language to the user
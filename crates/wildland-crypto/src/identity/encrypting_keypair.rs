@@ -15,20 +15,84 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crypto_box::{PublicKey, SecretKey};
+use crypto_box::aead::{Aead, AeadCore};
+use crypto_box::{PublicKey, SalsaBox, SecretKey};
 use hex::ToHex;
+use salsa20::XNonce;
+use serde::{Deserialize, Serialize};
+use zeroize::{ZeroizeOnDrop, Zeroizing};
 
 use super::bytes_key_from_str;
 use crate::error::CryptoError;
 
+/// Version of the ciphertext envelope written by [`EncryptingKeypair`].
+pub const ENVELOPE_VERSION: u8 = 1;
+
+const NONCE_LEN: usize = 24;
+const KEY_LEN: usize = 32;
+
+/// Kind of the box kept in the ciphertext envelope.
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BoxKind {
+    /// Encrypted with an ephemeral keypair, the sender stays anonymous
+    Sealed = 1,
+    /// Encrypted with the sender's keypair, the recipient can verify who sent it
+    Authenticated = 2,
+}
+
 /// Keypair that can be used for encryption.
 /// See crypto-box crate for details.
-#[derive(Debug)]
 pub struct EncryptingKeypair {
     pub secret: SecretKey,
     pub public: PublicKey,
 }
 
+/// `crypto_box::SecretKey` zeroizes itself on drop.
+impl ZeroizeOnDrop for EncryptingKeypair {}
+
+impl std::fmt::Debug for EncryptingKeypair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptingKeypair")
+            .field("public", &self.encode_pub())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'de> Deserialize<'de> for EncryptingKeypair {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let hex_encoded_str = Zeroizing::new(String::deserialize(deserializer)?);
+        let bytes =
+            Zeroizing::new(hex::decode(&*hex_encoded_str).map_err(serde::de::Error::custom)?);
+        if bytes.len() != 2 * KEY_LEN {
+            return Err(serde::de::Error::invalid_length(
+                bytes.len(),
+                &"public and secret key",
+            ));
+        }
+        let (pubkey, seckey) = bytes.split_at(KEY_LEN);
+        Ok(Self::from_bytes_slices(
+            pubkey.try_into().expect("Split at key length"),
+            seckey.try_into().expect("Split at key length"),
+        ))
+    }
+}
+
+/// Serialized as hex encoded `public key | secret key`.
+impl Serialize for EncryptingKeypair {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let bytes =
+            Zeroizing::new([self.public.as_bytes().as_slice(), &self.secret.to_bytes()].concat());
+        String::serialize(&Zeroizing::new(hex::encode(&*bytes)), serializer)
+    }
+}
+
 impl EncryptingKeypair {
     pub fn from_bytes_slices(pubkey: [u8; 32], seckey: [u8; 32]) -> Self {
         Self {
@@ -55,6 +119,94 @@ impl EncryptingKeypair {
     pub fn encode_pub(&self) -> String {
         self.public.as_bytes().encode_hex::<String>()
     }
+
+    /// Parses public key encoded with [`EncryptingKeypair::encode_pub`].
+    pub fn decode_pub(public_key: &str) -> Result<PublicKey, CryptoError> {
+        Ok(PublicKey::from(bytes_key_from_str(public_key)?))
+    }
+
+    /// Generates a random nonce.
+    pub fn generate_nonce() -> [u8; NONCE_LEN] {
+        SalsaBox::generate_nonce(&mut rand_core::OsRng).into()
+    }
+
+    /// Encrypts the plaintext to the recipient with a single-use keypair (sealed box), so the
+    /// sender is anonymous. Only the recipient can decrypt it with [`EncryptingKeypair::open`].
+    ///
+    /// Envelope: `version | kind | ephemeral pubkey | nonce | ciphertext`.
+    pub fn seal(recipient: &PublicKey, plaintext: &[u8]) -> Vec<u8> {
+        let ephemeral = Self::new();
+        let ciphertext = ephemeral.encrypt_box(recipient, plaintext);
+        [
+            &[ENVELOPE_VERSION, BoxKind::Sealed as u8],
+            ephemeral.public.as_bytes().as_slice(),
+            &ciphertext,
+        ]
+        .concat()
+    }
+
+    /// Decrypts the output of [`EncryptingKeypair::seal`] addressed to this keypair.
+    pub fn open(&self, envelope: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let content = envelope_content(envelope, BoxKind::Sealed)?;
+        if content.len() < KEY_LEN {
+            return Err(CryptoError::DecryptionError);
+        }
+        let (ephemeral_pubkey, ciphertext) = content.split_at(KEY_LEN);
+        let ephemeral_pubkey =
+            <[u8; KEY_LEN]>::try_from(ephemeral_pubkey).expect("Split at key length");
+        self.decrypt_box(&PublicKey::from(ephemeral_pubkey), ciphertext)
+    }
+
+    /// Encrypts the plaintext to the recipient, authenticated with this keypair.
+    ///
+    /// Envelope: `version | kind | nonce | ciphertext`.
+    pub fn encrypt_for(&self, recipient: &PublicKey, plaintext: &[u8]) -> Vec<u8> {
+        [
+            &[ENVELOPE_VERSION, BoxKind::Authenticated as u8],
+            self.encrypt_box(recipient, plaintext).as_slice(),
+        ]
+        .concat()
+    }
+
+    /// Decrypts the output of [`EncryptingKeypair::encrypt_for`], checking that it was sent by
+    /// the owner of `sender` public key.
+    pub fn decrypt_from(
+        &self,
+        sender: &PublicKey,
+        envelope: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        self.decrypt_box(sender, envelope_content(envelope, BoxKind::Authenticated)?)
+    }
+
+    /// Returns `nonce | ciphertext`.
+    fn encrypt_box(&self, recipient: &PublicKey, plaintext: &[u8]) -> Vec<u8> {
+        let nonce = Self::generate_nonce();
+        let ciphertext = SalsaBox::new(recipient, &self.secret)
+            .encrypt(XNonce::from_slice(&nonce), plaintext)
+            .expect("Encryption of in-memory buffer should not fail");
+        [nonce.as_slice(), &ciphertext].concat()
+    }
+
+    fn decrypt_box(&self, sender: &PublicKey, content: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        if content.len() < NONCE_LEN {
+            return Err(CryptoError::DecryptionError);
+        }
+        let (nonce, ciphertext) = content.split_at(NONCE_LEN);
+        SalsaBox::new(sender, &self.secret)
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| CryptoError::DecryptionError)
+    }
+}
+
+/// Checks the header of the envelope and returns its content.
+fn envelope_content(envelope: &[u8], kind: BoxKind) -> Result<&[u8], CryptoError> {
+    match envelope {
+        [ENVELOPE_VERSION, envelope_kind, content @ ..] if *envelope_kind == kind as u8 => {
+            Ok(content)
+        }
+        [ENVELOPE_VERSION, ..] | [] => Err(CryptoError::DecryptionError),
+        [version, ..] => Err(CryptoError::UnsupportedEnvelopeVersion(*version)),
+    }
 }
 
 impl Default for EncryptingKeypair {
@@ -62,3 +214,119 @@ impl Default for EncryptingKeypair {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MSG: &[u8] = b"Hello World";
+
+    #[test]
+    fn should_open_sealed_box_only_with_recipient_key() {
+        // given
+        let recipient = EncryptingKeypair::new();
+        let other = EncryptingKeypair::new();
+
+        // when
+        let envelope = EncryptingKeypair::seal(&recipient.public, MSG);
+
+        // then
+        assert_eq!(envelope[0], ENVELOPE_VERSION);
+        assert_ne!(EncryptingKeypair::seal(&recipient.public, MSG), envelope);
+        assert_eq!(recipient.open(&envelope).unwrap(), MSG);
+        assert_eq!(
+            other.open(&envelope).unwrap_err(),
+            CryptoError::DecryptionError
+        );
+    }
+
+    #[test]
+    fn should_decrypt_authenticated_box_only_from_sender() {
+        // given
+        let alice = EncryptingKeypair::new();
+        let bob = EncryptingKeypair::new();
+        let mallory = EncryptingKeypair::new();
+
+        // when
+        let envelope = alice.encrypt_for(&bob.public, MSG);
+
+        // then
+        assert_eq!(bob.decrypt_from(&alice.public, &envelope).unwrap(), MSG);
+        assert_eq!(
+            bob.decrypt_from(&mallory.public, &envelope).unwrap_err(),
+            CryptoError::DecryptionError
+        );
+        assert_eq!(
+            bob.open(&envelope).unwrap_err(),
+            CryptoError::DecryptionError
+        );
+    }
+
+    #[test]
+    fn should_reject_tampered_and_unknown_envelopes() {
+        // given
+        let recipient = EncryptingKeypair::new();
+        let envelope = EncryptingKeypair::seal(&recipient.public, MSG);
+
+        // when
+        let mut tampered = envelope.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        let mut newer = envelope.clone();
+        newer[0] = ENVELOPE_VERSION + 1;
+
+        // then
+        assert_eq!(
+            recipient.open(&tampered).unwrap_err(),
+            CryptoError::DecryptionError
+        );
+        assert_eq!(
+            recipient.open(&newer).unwrap_err(),
+            CryptoError::UnsupportedEnvelopeVersion(ENVELOPE_VERSION + 1)
+        );
+        assert_eq!(
+            recipient.open(&envelope[..20]).unwrap_err(),
+            CryptoError::DecryptionError
+        );
+    }
+
+    #[test]
+    fn should_serialize_and_deserialize_keypair() {
+        // given
+        let keypair = EncryptingKeypair::new();
+
+        // when
+        let serialized = serde_json::to_string(&keypair).unwrap();
+        let deserialized: EncryptingKeypair = serde_json::from_str(&serialized).unwrap();
+
+        // then
+        assert_eq!(deserialized.encode_pub(), keypair.encode_pub());
+        assert_eq!(deserialized.secret.as_bytes(), keypair.secret.as_bytes());
+        assert!(serde_json::from_str::<EncryptingKeypair>(r#""00ff""#).is_err());
+    }
+
+    #[test]
+    fn should_not_reveal_secret_key_in_debug_output() {
+        // given
+        let keypair = EncryptingKeypair::new();
+
+        // when
+        let debug = format!("{keypair:?}");
+
+        // then
+        assert!(debug.contains(&keypair.encode_pub()));
+        assert!(!debug.contains(&hex::encode(keypair.secret.as_bytes())));
+        assert!(!debug.contains(&format!("{:?}", keypair.secret.as_bytes())));
+    }
+
+    #[test]
+    fn should_decode_encoded_public_key() {
+        // given
+        let keypair = EncryptingKeypair::new();
+
+        // when
+        let public = EncryptingKeypair::decode_pub(&keypair.encode_pub()).unwrap();
+
+        // then
+        assert_eq!(public.as_bytes(), keypair.public.as_bytes());
+    }
+}
@@ -23,6 +23,7 @@ mod derivation;
 mod device;
 pub mod encrypting_keypair;
 mod seed;
+pub mod shamir;
 pub mod signing_keypair;
 
 pub use device::new_device_identity;
@@ -0,0 +1,942 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Backup of the mnemonic split into SLIP-0039 shares.
+//!
+//! The entropy of the mnemonic (the master secret) is encrypted with an optional passphrase and
+//! shared in two levels: any `group_threshold` groups recover it, and each group is recovered from
+//! `member_threshold` of its member shares. Shares are written as words of the SLIP-0039 wordlist
+//! and are interchangeable with other SLIP-0039 implementations.
+//!
+//! See <https://github.com/satoshilabs/slips/blob/master/slip-0039.md>.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use hmac::{Hmac, Mac};
+use rand_core::{OsRng, RngCore};
+use sha2::Sha256;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::error::CryptoError;
+use crate::identity::{Identity, MnemonicPhrase};
+use crate::utils;
+
+/// Maximal number of groups and of member shares in a group, limited by 4 bits of the indices.
+pub const MAX_SHARE_COUNT: u8 = 16;
+
+/// Exponent of PBKDF2 iterations used for the new shares, as in the reference implementation.
+const DEFAULT_ITERATION_EXPONENT: u8 = 1;
+const BASE_ITERATION_COUNT: u32 = 10000;
+const ROUND_COUNT: u8 = 4;
+const MIN_SECRET_LEN: usize = 16;
+
+const WORDLIST: &str = include_str!("shamir_wordlist.txt");
+const CUSTOMIZATION_STRING: &[u8] = b"shamir";
+const CUSTOMIZATION_STRING_EXTENDABLE: &[u8] = b"shamir_extendable";
+const CHECKSUM_WORDS: usize = 3;
+const HEADER_WORDS: usize = 4;
+const RADIX_BITS: usize = 10;
+const DIGEST_LEN: usize = 4;
+const DIGEST_INDEX: u8 = 254;
+const SECRET_INDEX: u8 = 255;
+
+/// A single member share of the master secret.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MnemonicShare {
+    /// Random identifier, common to all shares of the master secret
+    identifier: u16,
+    /// Whether the identifier is left out of the encryption of the master secret
+    extendable: bool,
+    iteration_exponent: u8,
+    group_index: u8,
+    group_threshold: u8,
+    group_count: u8,
+    member_index: u8,
+    member_threshold: u8,
+    value: Vec<u8>,
+}
+
+impl MnemonicShare {
+    pub fn group_threshold(&self) -> u8 {
+        self.group_threshold
+    }
+
+    pub fn member_threshold(&self) -> u8 {
+        self.member_threshold
+    }
+
+    fn words(&self) -> Vec<u16> {
+        let mut bits = BitWriter::default();
+        bits.push(self.identifier.into(), 15);
+        bits.push(self.extendable.into(), 1);
+        bits.push(self.iteration_exponent.into(), 4);
+        bits.push(self.group_index.into(), 4);
+        bits.push((self.group_threshold - 1).into(), 4);
+        bits.push((self.group_count - 1).into(), 4);
+        bits.push(self.member_index.into(), 4);
+        bits.push((self.member_threshold - 1).into(), 4);
+        bits.push(
+            0,
+            (RADIX_BITS - self.value.len() * 8 % RADIX_BITS) % RADIX_BITS,
+        );
+        for byte in &self.value {
+            bits.push((*byte).into(), 8);
+        }
+        let mut words = bits.words();
+        words.extend(rs1024_checksum(
+            customization_string(self.extendable),
+            &words,
+        ));
+        words
+    }
+
+    /// Whether both shares come from the same split of a master secret.
+    fn is_compatible_with(&self, other: &Self) -> bool {
+        self.identifier == other.identifier
+            && self.extendable == other.extendable
+            && self.iteration_exponent == other.iteration_exponent
+            && self.group_threshold == other.group_threshold
+            && self.group_count == other.group_count
+            && self.value.len() == other.value.len()
+    }
+}
+
+impl fmt::Display for MnemonicShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let wordlist = wordlist();
+        let words: Vec<_> = self
+            .words()
+            .into_iter()
+            .map(|word| wordlist[usize::from(word)])
+            .collect();
+        f.write_str(&words.join(" "))
+    }
+}
+
+impl FromStr for MnemonicShare {
+    type Err = CryptoError;
+
+    fn from_str(share: &str) -> Result<Self, Self::Err> {
+        let wordlist = wordlist();
+        let words = share
+            .split_whitespace()
+            .map(|word| {
+                wordlist
+                    .binary_search(&word.to_lowercase().as_str())
+                    .map(|index| index as u16)
+                    .map_err(|_| sharing_error(format!("Invalid word {word}")))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if words.len() < HEADER_WORDS + CHECKSUM_WORDS + MIN_SECRET_LEN * 8 / RADIX_BITS + 1 {
+            return Err(sharing_error("Share is too short"));
+        }
+        let value_bits = (words.len() - HEADER_WORDS - CHECKSUM_WORDS) * RADIX_BITS;
+        let padding = value_bits % 16;
+        if padding > 8 {
+            return Err(sharing_error("Invalid length of share"));
+        }
+
+        let mut bits = BitReader::new(&words);
+        let identifier = bits.read(15) as u16;
+        let extendable = bits.read(1) == 1;
+        if rs1024_polymod(
+            customization_string(extendable)
+                .iter()
+                .map(|&c| c.into())
+                .chain(words.iter().copied()),
+        ) != 1
+        {
+            return Err(sharing_error("Invalid share checksum"));
+        }
+        let iteration_exponent = bits.read(4) as u8;
+        let group_index = bits.read(4) as u8;
+        let group_threshold = bits.read(4) as u8 + 1;
+        let group_count = bits.read(4) as u8 + 1;
+        let member_index = bits.read(4) as u8;
+        let member_threshold = bits.read(4) as u8 + 1;
+        if group_threshold > group_count {
+            return Err(sharing_error(format!(
+                "Invalid group threshold {group_threshold} of {group_count} groups"
+            )));
+        }
+        if bits.read(padding) != 0 {
+            return Err(sharing_error("Invalid padding of share value"));
+        }
+        let value = (0..(value_bits - padding) / 8)
+            .map(|_| bits.read(8) as u8)
+            .collect();
+
+        Ok(Self {
+            identifier,
+            extendable,
+            iteration_exponent,
+            group_index,
+            group_threshold,
+            group_count,
+            member_index,
+            member_threshold,
+            value,
+        })
+    }
+}
+
+/// Splits entropy of the mnemonic into groups of shares. `groups` lists `(member_threshold,
+/// member_count)` of every group; the mnemonic is recovered from member shares of any
+/// `group_threshold` groups and the same `passphrase`.
+pub fn split_mnemonic(
+    mnemonic: &MnemonicPhrase,
+    passphrase: &str,
+    group_threshold: u8,
+    groups: &[(u8, u8)],
+) -> Result<Vec<Vec<MnemonicShare>>, CryptoError> {
+    let secret = Zeroizing::new(
+        utils::new_mnemonic_from_phrase(&mnemonic.join(" "))?
+            .entropy()
+            .to_vec(),
+    );
+    split_master_secret(
+        &secret,
+        passphrase,
+        group_threshold,
+        groups,
+        DEFAULT_ITERATION_EXPONENT,
+    )
+}
+
+/// Recovers the mnemonic from the shares and the passphrase they were split with.
+///
+/// As SLIP-0039 has no way to check the passphrase, a wrong one recovers a different mnemonic.
+pub fn combine_shares(
+    shares: &[MnemonicShare],
+    passphrase: &str,
+) -> Result<MnemonicPhrase, CryptoError> {
+    let secret = combine_master_secret(shares, passphrase)?;
+    utils::new_mnemonic_from_entropy(&secret)?
+        .phrase()
+        .split(' ')
+        .map(|word| word.to_owned())
+        .collect::<Vec<_>>()
+        .try_into()
+        .map_err(|_| sharing_error("Recovered secret is not an entropy of 12 words mnemonic"))
+}
+
+/// Splits the master secret into extendable SLIP-0039 shares, see [`split_mnemonic`].
+pub fn split_master_secret(
+    master_secret: &[u8],
+    passphrase: &str,
+    group_threshold: u8,
+    groups: &[(u8, u8)],
+    iteration_exponent: u8,
+) -> Result<Vec<Vec<MnemonicShare>>, CryptoError> {
+    if master_secret.len() < MIN_SECRET_LEN || master_secret.len() % 2 == 1 {
+        return Err(sharing_error(format!(
+            "Invalid length {} of the master secret",
+            master_secret.len()
+        )));
+    }
+    if group_threshold == 0
+        || usize::from(group_threshold) > groups.len()
+        || groups.len() > MAX_SHARE_COUNT.into()
+    {
+        return Err(sharing_error(format!(
+            "Invalid group threshold {group_threshold} of {} groups",
+            groups.len()
+        )));
+    }
+    for &(threshold, count) in groups {
+        if threshold == 0 || threshold > count || count > MAX_SHARE_COUNT {
+            return Err(sharing_error(format!(
+                "Invalid threshold {threshold} of {count} shares"
+            )));
+        }
+        if threshold == 1 && count > 1 {
+            return Err(sharing_error(
+                "Threshold 1 would make every share of the group a copy of the group secret",
+            ));
+        }
+    }
+    if iteration_exponent >= 16 {
+        return Err(sharing_error(format!(
+            "Invalid iteration exponent {iteration_exponent}"
+        )));
+    }
+
+    let identifier = (OsRng.next_u32() & 0x7fff) as u16;
+    let encrypted_secret = Zeroizing::new(feistel(
+        master_secret,
+        passphrase,
+        iteration_exponent,
+        identifier,
+        true,
+        (0..ROUND_COUNT).collect(),
+    )?);
+
+    let mut group_secrets = split_secret(&encrypted_secret, group_threshold, groups.len() as u8);
+    let shares = group_secrets
+        .iter()
+        .zip(groups)
+        .map(
+            |((group_index, group_secret), &(member_threshold, member_count))| {
+                split_secret(group_secret, member_threshold, member_count)
+                    .into_iter()
+                    .map(|(member_index, value)| MnemonicShare {
+                        identifier,
+                        extendable: true,
+                        iteration_exponent,
+                        group_index: *group_index,
+                        group_threshold,
+                        group_count: groups.len() as u8,
+                        member_index,
+                        member_threshold,
+                        value,
+                    })
+                    .collect()
+            },
+        )
+        .collect();
+    group_secrets
+        .iter_mut()
+        .for_each(|(_, group_secret)| group_secret.zeroize());
+    Ok(shares)
+}
+
+/// Recovers the master secret from member shares of `group_threshold` groups, see
+/// [`combine_shares`].
+pub fn combine_master_secret(
+    shares: &[MnemonicShare],
+    passphrase: &str,
+) -> Result<Zeroizing<Vec<u8>>, CryptoError> {
+    let first = shares
+        .first()
+        .ok_or_else(|| sharing_error("No shares provided"))?;
+    if shares.iter().any(|share| !share.is_compatible_with(first)) {
+        return Err(sharing_error(
+            "Shares do not belong to the same master secret",
+        ));
+    }
+    let mut groups: BTreeMap<u8, Vec<&MnemonicShare>> = BTreeMap::new();
+    for share in shares {
+        groups.entry(share.group_index).or_default().push(share);
+    }
+    if groups.len() != usize::from(first.group_threshold) {
+        return Err(sharing_error(format!(
+            "Shares of exactly {} groups are required, got {}",
+            first.group_threshold,
+            groups.len()
+        )));
+    }
+
+    let mut group_secrets = Vec::with_capacity(groups.len());
+    for (group_index, mut members) in groups {
+        members.sort_by_key(|share| share.member_index);
+        members.dedup();
+        let member_threshold = members[0].member_threshold;
+        if members
+            .iter()
+            .any(|share| share.member_threshold != member_threshold)
+        {
+            return Err(sharing_error(format!(
+                "Shares of group {group_index} have different thresholds"
+            )));
+        }
+        if members
+            .windows(2)
+            .any(|pair| pair[0].member_index == pair[1].member_index)
+        {
+            return Err(sharing_error(format!(
+                "Shares of group {group_index} are repeated"
+            )));
+        }
+        if members.len() != usize::from(member_threshold) {
+            return Err(sharing_error(format!(
+                "{member_threshold} shares of group {group_index} are required, got {}",
+                members.len()
+            )));
+        }
+        let points: Vec<_> = members
+            .iter()
+            .map(|share| (share.member_index, share.value.clone()))
+            .collect();
+        group_secrets.push((group_index, recover_secret(member_threshold, &points)?));
+    }
+
+    let encrypted_secret = Zeroizing::new(recover_secret(first.group_threshold, &group_secrets)?);
+    group_secrets
+        .iter_mut()
+        .for_each(|(_, group_secret)| group_secret.zeroize());
+    feistel(
+        &encrypted_secret,
+        passphrase,
+        first.iteration_exponent,
+        first.identifier,
+        first.extendable,
+        (0..ROUND_COUNT).rev().collect(),
+    )
+    .map(Zeroizing::new)
+}
+
+impl TryFrom<&[MnemonicShare]> for Identity {
+    type Error = CryptoError;
+
+    /// Derive identity from mnemonic recovered from its shares split without a passphrase.
+    fn try_from(shares: &[MnemonicShare]) -> Result<Self, Self::Error> {
+        Identity::try_from(&combine_shares(shares, "")?)
+    }
+}
+
+fn sharing_error(reason: impl Into<String>) -> CryptoError {
+    CryptoError::SecretSharingError(reason.into())
+}
+
+fn wordlist() -> Vec<&'static str> {
+    WORDLIST.lines().collect()
+}
+
+fn customization_string(extendable: bool) -> &'static [u8] {
+    if extendable {
+        CUSTOMIZATION_STRING_EXTENDABLE
+    } else {
+        CUSTOMIZATION_STRING
+    }
+}
+
+/// Encrypts (rounds in ascending order) or decrypts (descending order) the master secret with the
+/// Feistel cipher of SLIP-0039, using PBKDF2 of the passphrase as the round function.
+fn feistel(
+    secret: &[u8],
+    passphrase: &str,
+    iteration_exponent: u8,
+    identifier: u16,
+    extendable: bool,
+    rounds: Vec<u8>,
+) -> Result<Vec<u8>, CryptoError> {
+    if !passphrase.bytes().all(|c| (32..=126).contains(&c)) {
+        return Err(sharing_error(
+            "Passphrase must consist of printable ASCII characters",
+        ));
+    }
+    let salt = if extendable {
+        Vec::new()
+    } else {
+        [CUSTOMIZATION_STRING, &identifier.to_be_bytes()].concat()
+    };
+    let iterations = (BASE_ITERATION_COUNT << iteration_exponent) / u32::from(ROUND_COUNT);
+
+    let (left, right) = secret.split_at(secret.len() / 2);
+    let (mut left, mut right) = (
+        Zeroizing::new(left.to_vec()),
+        Zeroizing::new(right.to_vec()),
+    );
+    for round in rounds {
+        let mut round_key = Zeroizing::new(vec![0; right.len()]);
+        pbkdf2::pbkdf2::<Hmac<Sha256>>(
+            &[&[round], passphrase.as_bytes()].concat(),
+            &[salt.as_slice(), &right].concat(),
+            iterations,
+            &mut round_key,
+        );
+        let mixed = left
+            .iter()
+            .zip(round_key.iter())
+            .map(|(l, k)| l ^ k)
+            .collect();
+        left = std::mem::replace(&mut right, Zeroizing::new(mixed));
+    }
+    Ok([right.as_slice(), &left].concat())
+}
+
+/// Returns the secret of points `(x, f(x))` and checks it against the shared digest.
+fn recover_secret(threshold: u8, points: &[(u8, Vec<u8>)]) -> Result<Vec<u8>, CryptoError> {
+    if threshold == 1 {
+        return Ok(points[0].1.clone());
+    }
+    let secret = interpolate(points, SECRET_INDEX);
+    let digest_share = interpolate(points, DIGEST_INDEX);
+    let (expected_digest, random_part) = digest_share.split_at(DIGEST_LEN);
+    if digest(random_part, &secret) != expected_digest {
+        return Err(sharing_error("Invalid digest of the recovered secret"));
+    }
+    Ok(secret)
+}
+
+/// Returns shares `(x, f(x))` for `x` in `0..count` of polynomials passing through the secret at
+/// [`SECRET_INDEX`] and through the digest of the secret at [`DIGEST_INDEX`].
+fn split_secret(secret: &[u8], threshold: u8, count: u8) -> Vec<(u8, Vec<u8>)> {
+    if threshold == 1 {
+        return (0..count).map(|index| (index, secret.to_vec())).collect();
+    }
+    let random_bytes = |len| {
+        let mut bytes = vec![0; len];
+        OsRng.fill_bytes(&mut bytes);
+        bytes
+    };
+
+    let random_count = threshold - 2;
+    let mut shares: Vec<_> = (0..random_count)
+        .map(|index| (index, random_bytes(secret.len())))
+        .collect();
+    let random_part = random_bytes(secret.len() - DIGEST_LEN);
+    let mut base = shares.clone();
+    base.push((
+        DIGEST_INDEX,
+        [digest(&random_part, secret), random_part].concat(),
+    ));
+    base.push((SECRET_INDEX, secret.to_vec()));
+    shares.extend((random_count..count).map(|index| (index, interpolate(&base, index))));
+    base.iter_mut().for_each(|(_, value)| value.zeroize());
+    shares
+}
+
+fn digest(random_part: &[u8], secret: &[u8]) -> Vec<u8> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(random_part)
+        .expect("HMAC accepts keys of any length");
+    mac.update(secret);
+    mac.finalize().into_bytes()[..DIGEST_LEN].to_vec()
+}
+
+/// Returns value at `x` of the polynomials (one per byte) passing through the given points.
+fn interpolate(points: &[(u8, Vec<u8>)], x: u8) -> Vec<u8> {
+    if let Some((_, value)) = points.iter().find(|(point_x, _)| *point_x == x) {
+        return value.clone();
+    }
+    let gf = Gf256::new();
+    let mut result = vec![0; points[0].1.len()];
+    for (point_x, value) in points {
+        // Lagrange basis polynomial of the point, evaluated at x
+        let basis = points
+            .iter()
+            .filter(|(other_x, _)| other_x != point_x)
+            .fold(1, |basis, (other_x, _)| {
+                gf.mul(basis, gf.div(x ^ other_x, point_x ^ other_x))
+            });
+        for (result, byte) in result.iter_mut().zip(value) {
+            *result ^= gf.mul(*byte, basis);
+        }
+    }
+    result
+}
+
+/// Arithmetic in GF(2^8) with the Rijndael polynomial x^8 + x^4 + x^3 + x + 1.
+struct Gf256 {
+    exp: [u8; 255],
+    log: [u8; 256],
+}
+
+impl Gf256 {
+    fn new() -> Self {
+        let mut exp = [0; 255];
+        let mut log = [0; 256];
+        let mut poly: u16 = 1;
+        for (i, exp) in exp.iter_mut().enumerate() {
+            *exp = poly as u8;
+            log[poly as usize] = i as u8;
+            // multiply by the generator x + 1
+            poly = (poly << 1) ^ poly;
+            if poly & 0x100 != 0 {
+                poly ^= 0x11b;
+            }
+        }
+        Self { exp, log }
+    }
+
+    fn mul(&self, a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            return 0;
+        }
+        self.exp[(self.log[a as usize] as usize + self.log[b as usize] as usize) % 255]
+    }
+
+    fn div(&self, a: u8, b: u8) -> u8 {
+        assert_ne!(b, 0, "Division by zero in GF(256)");
+        if a == 0 {
+            return 0;
+        }
+        self.exp[(self.log[a as usize] as usize + 255 - self.log[b as usize] as usize) % 255]
+    }
+}
+
+fn rs1024_polymod(values: impl IntoIterator<Item = u16>) -> u32 {
+    const GEN: [u32; 10] = [
+        0xe0e040, 0x1c1c080, 0x3838100, 0x7070200, 0xe0e0009, 0x1c0c2412, 0x38086c24, 0x3090fc48,
+        0x21b1f890, 0x3f3f120,
+    ];
+    values.into_iter().fold(1, |chk, value| {
+        let b = chk >> 20;
+        let chk = (chk & 0xfffff) << 10 ^ u32::from(value);
+        (0..10)
+            .filter(|i| (b >> i) & 1 == 1)
+            .fold(chk, |chk, i| chk ^ GEN[i])
+    })
+}
+
+fn rs1024_checksum(customization_string: &[u8], words: &[u16]) -> [u16; CHECKSUM_WORDS] {
+    let values = customization_string
+        .iter()
+        .map(|&c| c.into())
+        .chain(words.iter().copied())
+        .chain([0; CHECKSUM_WORDS]);
+    let polymod = rs1024_polymod(values) ^ 1;
+    [2, 1, 0].map(|i| ((polymod >> (RADIX_BITS * i)) & 0x3ff) as u16)
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bits: Vec<bool>,
+}
+
+impl BitWriter {
+    fn push(&mut self, value: u32, len: usize) {
+        self.bits
+            .extend((0..len).rev().map(|i| (value >> i) & 1 == 1));
+    }
+
+    fn words(&self) -> Vec<u16> {
+        self.bits
+            .chunks(RADIX_BITS)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .fold(0, |word, &bit| word << 1 | u16::from(bit))
+            })
+            .collect()
+    }
+}
+
+struct BitReader {
+    bits: std::vec::IntoIter<bool>,
+}
+
+impl BitReader {
+    fn new(words: &[u16]) -> Self {
+        let mut writer = BitWriter::default();
+        for word in words {
+            writer.push((*word).into(), RADIX_BITS);
+        }
+        Self {
+            bits: writer.bits.into_iter(),
+        }
+    }
+
+    fn read(&mut self, len: usize) -> u32 {
+        (0..len).fold(0, |value, _| {
+            value << 1 | u32::from(self.bits.next().unwrap_or_default())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_utilities::MNEMONIC_PHRASE;
+
+    // Test vectors of the reference implementation (trezor/python-shamir-mnemonic, vectors.json),
+    // all of them split with passphrase "TREZOR"
+    const PASSPHRASE: &str = "TREZOR";
+    const VALID_128_BITS: &str =
+        "duckling enlarge academic academic agency result length solution \
+        fridge kidney coal piece deal husband erode duke ajar critical decision keyboard";
+    const INVALID_CHECKSUM: &str = "duckling enlarge academic academic agency result length \
+        solution fridge kidney coal piece deal husband erode duke ajar critical decision kidney";
+    const INVALID_PADDING: &str = "duckling enlarge academic academic email result length \
+        solution fridge kidney coal piece deal husband erode duke ajar music cargo fitness";
+    const TWO_OF_THREE: [&str; 2] = [
+        "shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist \
+         rescue view short owner flip making coding armed",
+        "shadow pistol academic acid actress prayer class unknown daughter sweater depict flip \
+         twice unkind craft early superior advocate guest smoking",
+    ];
+    // Group threshold 2 of 4 groups: a single share of group 1, three shares of group 2 and
+    // a share of group 3, which requires two shares
+    const GROUP_ONE: &str = "eraser senior beard romp adorn nuclear spill corner cradle style \
+        ancient family general leader ambition exchange unusual garlic promise voice";
+    const GROUP_TWO: [&str; 3] = [
+        "eraser senior ceramic snake clay various huge numb argue hesitate auction category \
+         timber browser greatest hanger petition script leaf pickup",
+        "eraser senior ceramic shaft dynamic become junior wrist silver peasant force math alto \
+         coal amazing segment yelp velvet image paces",
+        "eraser senior ceramic round column hawk trust auction smug shame alive greatest sheriff \
+         living perfect corner chest sled fumes adequate",
+    ];
+    const GROUP_THREE: &str = "eraser senior decision smug corner ruin rescue cubic angel tackle \
+        skin skunk program roster trash rumor slush angel flea amazing";
+    const VALID_256_BITS: &str = "theory painting academic academic armed sweater year military \
+        elder discuss acne wildlife boring employer fused large satoshi bundle carbon diagnose \
+        anatomy hamster leaves tracks paces beyond phantom capital marvel lips brave detect luck";
+    const TWO_OF_THREE_256_BITS: [&str; 2] = [
+        "humidity disease academic always aluminum jewelry energy woman receiver strategy amuse \
+         duckling lying evidence network walnut tactics forget hairy rebound impulse brother \
+         survive clothes stadium mailman rival ocean reward venture always armed unwrap",
+        "humidity disease academic agency actress jacket gross physics cylinder solution fake \
+         mortgage benefit public busy prepare sharp friar change work slow purchase ruler again \
+         tricycle involve viral wireless mixture anatomy desert cargo upgrade",
+    ];
+    const VALID_EXTENDABLE: &str = "testify swimming academic academic column loyalty smear \
+        include exotic bedroom exotic wrist lobe cover grief golden smart junior estimate learn";
+
+    fn mnemonic() -> MnemonicPhrase {
+        MNEMONIC_PHRASE
+            .split(' ')
+            .map(|word| word.to_owned())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap()
+    }
+
+    fn parse(shares: &[&str]) -> Result<Vec<MnemonicShare>, CryptoError> {
+        shares.iter().map(|share| share.parse()).collect()
+    }
+
+    #[test]
+    fn should_load_wordlist() {
+        // when
+        let wordlist = wordlist();
+
+        // then
+        assert_eq!(wordlist.len(), 1 << RADIX_BITS);
+        assert!(wordlist.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(wordlist[0], "academic");
+        assert_eq!(wordlist[1023], "zero");
+    }
+
+    #[test]
+    fn should_multiply_like_aes_field() {
+        // given examples of multiplication published in FIPS-197, section 4.2
+        let gf = Gf256::new();
+
+        // then
+        assert_eq!(gf.mul(0x57, 0x83), 0xc1);
+        assert_eq!(gf.mul(0x57, 0x13), 0xfe);
+        assert_eq!(gf.div(0xc1, 0x83), 0x57);
+    }
+
+    #[test]
+    fn should_recover_master_secret_of_reference_vectors() {
+        let vectors: [(&[&str], &str); 6] = [
+            (&[VALID_128_BITS], "bb54aac4b89dc868ba37d9cc21b2cece"),
+            (&TWO_OF_THREE, "b43ceb7e57a0ea8766221624d01b0864"),
+            (
+                &[GROUP_ONE, GROUP_TWO[0], GROUP_TWO[1], GROUP_TWO[2]],
+                "7c3397a292a5941682d7a4ae2d898d11",
+            ),
+            (
+                &[VALID_256_BITS],
+                "989baf9dcaad5b10ca33dfd8cc75e42477025dce88ae83e75a230086a0e00e92",
+            ),
+            (
+                &TWO_OF_THREE_256_BITS,
+                "c938b319067687e990e05e0da0ecce1278f75ff58d9853f19dcaeed5de104aae",
+            ),
+            (&[VALID_EXTENDABLE], "1679b4516e0ee5954351d288a838f45e"),
+        ];
+
+        for (shares, expected_secret) in vectors {
+            // when
+            let secret = combine_master_secret(&parse(shares).unwrap(), PASSPHRASE).unwrap();
+
+            // then
+            assert_eq!(hex::encode(secret.as_slice()), expected_secret);
+        }
+    }
+
+    #[test]
+    fn should_reject_malformed_share() {
+        let cases = [
+            (INVALID_CHECKSUM, "Invalid share checksum"),
+            (INVALID_PADDING, "Invalid padding of share value"),
+            (
+                "duckling enlarge academic wildland",
+                "Invalid word wildland",
+            ),
+            ("duckling enlarge academic academic", "Share is too short"),
+        ];
+
+        for (share, expected_error) in cases {
+            // when
+            let result = share.parse::<MnemonicShare>();
+
+            // then
+            assert_eq!(
+                result.unwrap_err(),
+                CryptoError::SecretSharingError(expected_error.to_owned())
+            );
+        }
+    }
+
+    #[test]
+    fn should_not_recover_master_secret_of_invalid_share_sets() {
+        let cases: [(&[&str], &str); 5] = [
+            (
+                &[TWO_OF_THREE[0]],
+                "2 shares of group 0 are required, got 1",
+            ),
+            (
+                &[GROUP_ONE],
+                "Shares of exactly 2 groups are required, got 1",
+            ),
+            (
+                &[GROUP_ONE, GROUP_THREE],
+                "2 shares of group 3 are required, got 1",
+            ),
+            (
+                &[
+                    GROUP_ONE,
+                    GROUP_TWO[0],
+                    GROUP_TWO[1],
+                    GROUP_TWO[2],
+                    GROUP_THREE,
+                ],
+                "Shares of exactly 2 groups are required, got 3",
+            ),
+            (
+                &[TWO_OF_THREE[0], VALID_128_BITS],
+                "Shares do not belong to the same master secret",
+            ),
+        ];
+
+        for (shares, expected_error) in cases {
+            // when
+            let result = combine_master_secret(&parse(shares).unwrap(), PASSPHRASE);
+
+            // then
+            assert_eq!(
+                result.unwrap_err(),
+                CryptoError::SecretSharingError(expected_error.to_owned())
+            );
+        }
+    }
+
+    #[test]
+    fn should_write_share_as_it_was_read() {
+        for share in [VALID_128_BITS, GROUP_ONE, VALID_256_BITS, VALID_EXTENDABLE] {
+            // when
+            let parsed: MnemonicShare = share.parse().unwrap();
+
+            // then
+            assert_eq!(
+                parsed.to_string(),
+                share.split_whitespace().collect::<Vec<_>>().join(" ")
+            );
+        }
+    }
+
+    #[test]
+    fn should_recover_mnemonic_from_any_threshold_of_groups() {
+        // given
+        let groups = split_mnemonic(&mnemonic(), "", 2, &[(1, 1), (3, 5), (2, 2)]).unwrap();
+
+        for chosen in [
+            vec![&groups[0][0], &groups[1][4], &groups[1][0], &groups[1][2]],
+            vec![&groups[2][1], &groups[0][0], &groups[2][0]],
+            vec![
+                &groups[1][1],
+                &groups[2][0],
+                &groups[1][3],
+                &groups[2][1],
+                &groups[1][1],
+                &groups[1][4],
+            ],
+        ] {
+            // when
+            let chosen: Vec<_> = chosen
+                .into_iter()
+                .map(|share| share.to_string().parse::<MnemonicShare>().unwrap())
+                .collect();
+
+            // then
+            assert_eq!(combine_shares(&chosen, "").unwrap(), mnemonic());
+            assert_eq!(
+                *Identity::try_from(chosen.as_slice())
+                    .unwrap()
+                    .get_mnemonic(),
+                mnemonic()
+            );
+        }
+    }
+
+    #[test]
+    fn should_encrypt_master_secret_with_passphrase() {
+        // given
+        let secret = [7; 32];
+        let shares = split_master_secret(&secret, "passphrase", 1, &[(2, 3)], 0)
+            .unwrap()
+            .remove(0);
+
+        // when
+        let recovered = combine_master_secret(&shares[1..], "passphrase").unwrap();
+        let without_passphrase = combine_master_secret(&shares[1..], "").unwrap();
+
+        // then
+        assert_eq!(recovered.as_slice(), secret);
+        assert_ne!(without_passphrase.as_slice(), secret);
+        assert!(shares.iter().all(|share| share.extendable));
+    }
+
+    #[test]
+    fn should_detect_shares_of_different_splits() {
+        // given
+        let mut shares = split_mnemonic(&mnemonic(), "", 1, &[(2, 3)])
+            .unwrap()
+            .remove(0);
+        let mut other = split_mnemonic(&mnemonic(), "", 1, &[(2, 3)]).unwrap()[0].remove(1);
+        other.identifier = shares[0].identifier;
+
+        // when
+        shares.truncate(1);
+        shares.push(other);
+
+        // then
+        assert_eq!(
+            combine_shares(&shares, "").unwrap_err(),
+            CryptoError::SecretSharingError("Invalid digest of the recovered secret".to_owned())
+        );
+    }
+
+    #[test]
+    fn should_not_split_with_invalid_thresholds() {
+        let cases = [
+            (
+                1,
+                &[(1, 1); 17][..],
+                "Invalid group threshold 1 of 17 groups",
+            ),
+            (
+                3,
+                &[(1, 1), (2, 2)],
+                "Invalid group threshold 3 of 2 groups",
+            ),
+            (1, &[(3, 2)], "Invalid threshold 3 of 2 shares"),
+            (
+                1,
+                &[(1, 2)],
+                "Threshold 1 would make every share of the group a copy of the group secret",
+            ),
+        ];
+
+        for (group_threshold, groups, expected_error) in cases {
+            // when
+            let result = split_mnemonic(&mnemonic(), "", group_threshold, groups);
+
+            // then
+            assert_eq!(
+                result.unwrap_err(),
+                CryptoError::SecretSharingError(expected_error.to_owned())
+            );
+        }
+    }
+}
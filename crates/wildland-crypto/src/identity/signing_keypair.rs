@@ -18,6 +18,7 @@
 use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signer};
 use rand::{CryptoRng, RngCore};
 use serde::{Deserialize, Serialize};
+use zeroize::{ZeroizeOnDrop, Zeroizing};
 
 use super::bytes_key_from_str;
 use crate::error::CryptoError;
@@ -26,18 +27,31 @@ use crate::signature::Signature;
 pub type PubKey = [u8; 32];
 pub type SecKey = [u8; 32];
 
-#[derive(Debug)]
+/// Ed25519 keypair. The secret key is wiped from memory on drop.
 pub struct SigningKeypair(ed25519_dalek::Keypair);
 
+/// `ed25519_dalek::SecretKey` zeroizes itself on drop.
+impl ZeroizeOnDrop for SigningKeypair {}
+
+impl std::fmt::Debug for SigningKeypair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigningKeypair")
+            .field("public", &hex::encode(self.public()))
+            .finish_non_exhaustive()
+    }
+}
+
 impl<'de> Deserialize<'de> for SigningKeypair {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        let hex_encoded_str = String::deserialize(deserializer)?;
-        let bytes = hex::decode(hex_encoded_str).map_err(serde::de::Error::custom)?;
+        let hex_encoded_str = Zeroizing::new(String::deserialize(deserializer)?);
+        let bytes =
+            Zeroizing::new(hex::decode(&*hex_encoded_str).map_err(serde::de::Error::custom)?);
         Ok(Self(
-            ed25519_dalek::Keypair::from_bytes(&bytes).map_err(serde::de::Error::custom)?,
+            ed25519_dalek::Keypair::from_bytes(bytes.as_slice())
+                .map_err(serde::de::Error::custom)?,
         ))
     }
 }
@@ -47,7 +61,7 @@ impl Serialize for SigningKeypair {
     where
         S: serde::Serializer,
     {
-        let hex = hex::encode(self.0.to_bytes());
+        let hex = Zeroizing::new(hex::encode(self.to_bytes()));
         String::serialize(&hex, serializer)
     }
 }
@@ -73,7 +87,7 @@ impl From<&SigningKeypair> for SigningKeypair {
     fn from(other: &SigningKeypair) -> Self {
         Self(ed25519_dalek::Keypair {
             public: PublicKey::from_bytes(&other.public()).unwrap(),
-            secret: SecretKey::from_bytes(&other.secret()).unwrap(),
+            secret: SecretKey::from_bytes(other.secret().as_slice()).unwrap(),
         })
     }
 }
@@ -85,8 +99,8 @@ impl SigningKeypair {
     {
         //TODO: WILX-366 use ed25519_dalek::Keypair::generate(csprng) when ed25519_dalek will support rand 0.8
 
-        let mut bytes = [0u8; 32];
-        csprng.fill_bytes(&mut bytes);
+        let mut bytes = Zeroizing::new([0u8; 32]);
+        csprng.fill_bytes(bytes.as_mut());
 
         let sk = SecretKey::from_bytes(bytes.as_ref()).unwrap();
 
@@ -98,8 +112,10 @@ impl SigningKeypair {
 
     pub fn try_from_bytes_slices(pubkey: PubKey, seckey: SecKey) -> Result<Self, CryptoError> {
         Ok(Self(
-            ed25519_dalek::Keypair::from_bytes([seckey, pubkey].concat().as_slice())
-                .map_err(|e| CryptoError::InvalidSignatureBytesError(e.to_string()))?,
+            ed25519_dalek::Keypair::from_bytes(
+                Zeroizing::new([seckey, pubkey].concat()).as_slice(),
+            )
+            .map_err(|e| CryptoError::InvalidSignatureBytesError(e.to_string()))?,
         ))
     }
 
@@ -123,12 +139,14 @@ impl SigningKeypair {
         self.0.public.to_bytes()
     }
 
-    pub fn secret(&self) -> SecKey {
-        self.0.secret.to_bytes()
+    /// Returns a copy of the secret key, wiped when dropped.
+    pub fn secret(&self) -> Zeroizing<SecKey> {
+        Zeroizing::new(self.0.secret.to_bytes())
     }
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        Vec::from(self.0.to_bytes())
+    /// Returns secret and public key bytes, wiped when dropped.
+    pub fn to_bytes(&self) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(Vec::from(self.0.to_bytes()))
     }
 
     pub fn sign(&self, msg: &[u8]) -> Signature {
@@ -141,6 +159,20 @@ mod tests {
     use crate::common::test_utilities::{SIGNING_PUBLIC_KEY, SIGNING_SECRET_KEY};
     use crate::identity::signing_keypair::SigningKeypair;
 
+    #[test]
+    fn should_not_reveal_secret_key_in_debug_output() {
+        // given
+        let keypair = SigningKeypair::try_from_str(SIGNING_PUBLIC_KEY, SIGNING_SECRET_KEY).unwrap();
+
+        // when
+        let debug = format!("{keypair:?}");
+
+        // then
+        assert!(debug.contains(SIGNING_PUBLIC_KEY));
+        assert!(!debug.contains(SIGNING_SECRET_KEY));
+        assert!(!debug.contains(&format!("{:?}", &keypair.secret()[..])));
+    }
+
     #[test]
     fn should_create_keypair_when_keys_have_proper_length() {
         // when
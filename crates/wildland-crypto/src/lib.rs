@@ -24,4 +24,6 @@ mod common;
 pub mod error;
 pub mod identity;
 pub mod signature;
+pub mod stream;
+pub mod symmetric;
 pub mod utils;
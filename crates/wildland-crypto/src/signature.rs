@@ -28,6 +28,10 @@ impl Signature {
         self.0.encode_hex::<String>()
     }
 
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes().to_vec()
+    }
+
     #[tracing::instrument(level = "debug", skip_all)]
     pub fn verify(&self, msg: &[u8], public_key: &[u8; 32]) -> Result<(), CryptoError> {
         PublicKey::from_bytes(public_key)
@@ -37,6 +41,16 @@ impl Signature {
     }
 }
 
+impl TryFrom<&[u8]> for Signature {
+    type Error = CryptoError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        ed25519_dalek::Signature::try_from(bytes)
+            .map(Signature)
+            .map_err(|e| CryptoError::InvalidSignatureBytesError(e.to_string()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::common::test_utilities::{
@@ -46,6 +60,7 @@ mod tests {
         SIGNING_SECRET_KEY,
     };
     use crate::identity::SigningKeypair;
+    use crate::signature::Signature;
 
     #[test]
     fn should_sign_custom_struct() {
@@ -62,4 +77,19 @@ mod tests {
             .verify(&expected_message, &keypair.public())
             .expect("OK");
     }
+
+    #[test]
+    fn should_verify_signature_restored_from_bytes() {
+        // given
+        let keypair = SigningKeypair::try_from_str(SIGNING_PUBLIC_KEY, SIGNING_SECRET_KEY).unwrap();
+        let message = generate_message();
+        let bytes = keypair.sign(&message).to_bytes();
+
+        // when
+        let signature = Signature::try_from(bytes.as_slice()).unwrap();
+
+        // then
+        signature.verify(&message, &keypair.public()).expect("OK");
+        assert!(Signature::try_from(&bytes[1..]).is_err());
+    }
 }
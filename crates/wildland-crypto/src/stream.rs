@@ -0,0 +1,627 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Encryption of file contents as a stream of chunks, so files never have to be loaded into
+//! memory as a whole.
+//!
+//! Every file is encrypted with its own random key, which is stored in the header wrapped with the
+//! container key. Chunks are encrypted with XChaCha20-Poly1305 following the STREAM construction:
+//! the nonce of a chunk consists of a random prefix, the chunk counter and a flag marking the last
+//! chunk, so reordered, dropped or truncated chunks fail to decrypt.
+//!
+//! Layout: `header | chunk 0 | chunk 1 | ... | last chunk`, where
+//! - header is `magic | version | chunk size (u32 BE) | nonce prefix | wrapped file key`,
+//! - every chunk but the last one holds exactly `chunk size` bytes of plaintext,
+//! - the last chunk holds less than `chunk size` bytes (possibly none),
+//! - each chunk is followed by its authentication tag.
+//!
+//! Chunks authenticate the header up to the wrapped file key. The wrapped file key authenticates
+//! the same part of the header and associated data given by the caller, e.g. path of the file, so
+//! a stream cannot be passed off as another one. Binding the stream to other associated data
+//! requires rewrapping the file key only (see [`StreamCipher::rewrap`]).
+//!
+//! Besides [`encrypt_stream`] and [`StreamDecryptor`] working on readers and writers,
+//! [`StreamCipher`] allows reading and rewriting single chunks of a stream kept in a storage
+//! supporting random access. Encrypted DFS storages keep file contents this way.
+
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand_core::{OsRng, RngCore};
+use zeroize::Zeroizing;
+
+use crate::error::CryptoError;
+use crate::symmetric::{SymmetricKey, ENCRYPTION_OVERHEAD};
+
+/// Version of the stream layout written by this version of the crate.
+pub const STREAM_VERSION: u8 = 1;
+/// Size of plaintext chunks used unless specified otherwise.
+pub const DEFAULT_CHUNK_SIZE: u32 = 64 * 1024;
+/// Length of the header preceding the encrypted chunks.
+pub const HEADER_LEN: usize = AUTHENTICATED_HEADER_LEN + WRAPPED_KEY_LEN;
+/// Number of bytes added to each chunk by encryption.
+pub const CHUNK_OVERHEAD: usize = TAG_LEN;
+
+const MAGIC: &[u8; 8] = b"WLSTREAM";
+const NONCE_PREFIX_LEN: usize = 19;
+const KEY_LEN: usize = 32;
+const TAG_LEN: usize = 16;
+const WRAPPED_KEY_LEN: usize = KEY_LEN + ENCRYPTION_OVERHEAD;
+/// Part of the header authenticated by every chunk
+const AUTHENTICATED_HEADER_LEN: usize = MAGIC.len() + 1 + 4 + NONCE_PREFIX_LEN;
+
+/// Cipher of a single stream, holding its header and the file key unwrapped from it.
+pub struct StreamCipher {
+    chunk_size: u32,
+    nonce_prefix: [u8; NONCE_PREFIX_LEN],
+    file_key: Zeroizing<[u8; KEY_LEN]>,
+    header: Vec<u8>,
+}
+
+impl StreamCipher {
+    /// Creates a cipher of a new stream with a random file key wrapped with `container_key` and
+    /// bound to `associated_data`.
+    pub fn new(
+        container_key: &SymmetricKey,
+        chunk_size: u32,
+        associated_data: &[u8],
+    ) -> Result<Self, CryptoError> {
+        let mut file_key = Zeroizing::new([0u8; KEY_LEN]);
+        OsRng.fill_bytes(&mut *file_key);
+        let mut nonce_prefix = [0u8; NONCE_PREFIX_LEN];
+        OsRng.fill_bytes(&mut nonce_prefix);
+        let wrapping_nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        Self::with_keys(
+            container_key,
+            chunk_size,
+            file_key,
+            nonce_prefix,
+            &wrapping_nonce,
+            associated_data,
+        )
+    }
+
+    /// Parses the header of a stream and unwraps its file key with `container_key`. Fails if the
+    /// stream was bound to other associated data.
+    pub fn open(
+        container_key: &SymmetricKey,
+        header: &[u8],
+        associated_data: &[u8],
+    ) -> Result<Self, CryptoError> {
+        if header.len() < HEADER_LEN {
+            return Err(stream_error("Stream is too short"));
+        }
+        let header = &header[..HEADER_LEN];
+        let (authenticated, wrapped_key) = header.split_at(AUTHENTICATED_HEADER_LEN);
+        let (magic, rest) = authenticated.split_at(MAGIC.len());
+        if magic != MAGIC {
+            return Err(stream_error("Not an encrypted stream"));
+        }
+        let (version, rest) = rest.split_at(1);
+        if version[0] != STREAM_VERSION {
+            return Err(CryptoError::UnsupportedEnvelopeVersion(version[0]));
+        }
+        let (chunk_size, nonce_prefix) = rest.split_at(4);
+        let chunk_size = u32::from_be_bytes(chunk_size.try_into().expect("Split at 4 bytes"));
+        if chunk_size == 0 {
+            return Err(stream_error("Invalid chunk size"));
+        }
+
+        let unwrapped = Zeroizing::new(container_key.decrypt_with_associated_data(
+            wrapped_key,
+            &[authenticated, associated_data].concat(),
+        )?);
+        let mut file_key = Zeroizing::new([0u8; KEY_LEN]);
+        if unwrapped.len() != KEY_LEN {
+            return Err(CryptoError::DecryptionError);
+        }
+        file_key.copy_from_slice(&unwrapped);
+
+        Ok(Self {
+            chunk_size,
+            nonce_prefix: nonce_prefix.try_into().expect("Split at prefix length"),
+            file_key,
+            header: header.to_vec(),
+        })
+    }
+
+    fn with_keys(
+        container_key: &SymmetricKey,
+        chunk_size: u32,
+        file_key: Zeroizing<[u8; KEY_LEN]>,
+        nonce_prefix: [u8; NONCE_PREFIX_LEN],
+        wrapping_nonce: &XNonce,
+        associated_data: &[u8],
+    ) -> Result<Self, CryptoError> {
+        if chunk_size == 0 {
+            return Err(stream_error("Invalid chunk size"));
+        }
+        let mut cipher = Self {
+            chunk_size,
+            nonce_prefix,
+            file_key,
+            header: [
+                MAGIC.as_slice(),
+                &[STREAM_VERSION],
+                &chunk_size.to_be_bytes(),
+                &nonce_prefix,
+            ]
+            .concat(),
+        };
+        cipher.wrap_key(container_key, wrapping_nonce, associated_data);
+        Ok(cipher)
+    }
+
+    /// Wraps the file key again, binding the stream to other associated data. Only the header
+    /// changes, so the chunks of the stream remain valid.
+    pub fn rewrap(&mut self, container_key: &SymmetricKey, associated_data: &[u8]) {
+        let wrapping_nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        self.wrap_key(container_key, &wrapping_nonce, associated_data)
+    }
+
+    fn wrap_key(
+        &mut self,
+        container_key: &SymmetricKey,
+        wrapping_nonce: &XNonce,
+        associated_data: &[u8],
+    ) {
+        self.header.truncate(AUTHENTICATED_HEADER_LEN);
+        let wrapped_key = container_key.encrypt_with_nonce(
+            wrapping_nonce,
+            &*self.file_key,
+            &[&self.header, associated_data].concat(),
+        );
+        self.header.extend_from_slice(&wrapped_key);
+    }
+
+    /// Header of the stream, [`HEADER_LEN`] bytes long.
+    pub fn header(&self) -> &[u8] {
+        &self.header
+    }
+
+    /// Size of plaintext held by every chunk but the last one.
+    pub fn chunk_size(&self) -> u32 {
+        self.chunk_size
+    }
+
+    /// Offset of the chunk within the stream.
+    pub fn chunk_offset(&self, index: u32) -> u64 {
+        HEADER_LEN as u64 + index as u64 * (self.chunk_size as u64 + TAG_LEN as u64)
+    }
+
+    /// Length of the stream holding `plaintext_len` bytes.
+    pub fn stream_len(&self, plaintext_len: u64) -> u64 {
+        HEADER_LEN as u64
+            + plaintext_len
+            + (plaintext_len / self.chunk_size as u64 + 1) * TAG_LEN as u64
+    }
+
+    /// Length of plaintext held by the stream of `stream_len` bytes, see [`plaintext_len`].
+    pub fn plaintext_len(&self, stream_len: u64) -> Result<u64, CryptoError> {
+        plaintext_len(self.chunk_size, stream_len)
+    }
+
+    /// Index of the last chunk of the stream holding `plaintext_len` bytes. Fails if the plaintext
+    /// does not fit in the chunks that can be counted.
+    pub fn last_chunk_index(&self, plaintext_len: u64) -> Result<u32, CryptoError> {
+        u32::try_from(plaintext_len / self.chunk_size as u64)
+            .map_err(|_| stream_error("Stream is too long"))
+    }
+
+    fn nonce(&self, index: u32, last: bool) -> XNonce {
+        let nonce = [
+            self.nonce_prefix.as_slice(),
+            &index.to_be_bytes(),
+            &[last as u8],
+        ]
+        .concat();
+        *XNonce::from_slice(&nonce)
+    }
+
+    /// Encrypts the chunk of the given index. `last` has to be set for the last chunk only.
+    pub fn encrypt_chunk(&self, index: u32, last: bool, plaintext: &[u8]) -> Vec<u8> {
+        let payload = Payload {
+            msg: plaintext,
+            aad: &self.header[..AUTHENTICATED_HEADER_LEN],
+        };
+        XChaCha20Poly1305::new((&*self.file_key).into())
+            .encrypt(&self.nonce(index, last), payload)
+            .expect("Encryption of in-memory buffer should not fail")
+    }
+
+    /// Decrypts the chunk encrypted with [`StreamCipher::encrypt_chunk`] with the same index and
+    /// `last` flag.
+    pub fn decrypt_chunk(
+        &self,
+        index: u32,
+        last: bool,
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        let payload = Payload {
+            msg: ciphertext,
+            aad: &self.header[..AUTHENTICATED_HEADER_LEN],
+        };
+        XChaCha20Poly1305::new((&*self.file_key).into())
+            .decrypt(&self.nonce(index, last), payload)
+            .map_err(|_| CryptoError::DecryptionError)
+    }
+}
+
+/// Length of plaintext held by the stream of `stream_len` bytes written with `chunk_size`. Fails if
+/// no stream could have such a length.
+pub fn plaintext_len(chunk_size: u32, stream_len: u64) -> Result<u64, CryptoError> {
+    let chunks_len = stream_len
+        .checked_sub(HEADER_LEN as u64)
+        .ok_or_else(|| stream_error("Stream is too short"))?;
+    let chunk_size = chunk_size as u64;
+    let encrypted_chunk_size = chunk_size + TAG_LEN as u64;
+    let chunk_count = chunks_len / encrypted_chunk_size + 1;
+    let last_chunk_len = (chunks_len % encrypted_chunk_size)
+        .checked_sub(TAG_LEN as u64)
+        .ok_or(CryptoError::DecryptionError)?;
+    if chunk_count > u32::MAX as u64 + 1 {
+        return Err(stream_error("Stream is too long"));
+    }
+    Ok((chunk_count - 1) * chunk_size + last_chunk_len)
+}
+
+/// Encrypts everything read from `reader` with a new random file key wrapped with
+/// `container_key` and bound to `associated_data`, writing the stream to `writer`. Returns number
+/// of written bytes.
+pub fn encrypt_stream(
+    container_key: &SymmetricKey,
+    chunk_size: u32,
+    associated_data: &[u8],
+    reader: impl Read,
+    writer: impl Write,
+) -> Result<u64, CryptoError> {
+    let cipher = StreamCipher::new(container_key, chunk_size, associated_data)?;
+    write_stream(&cipher, u32::MAX, reader, writer)
+}
+
+/// Writes the header and chunks read from `reader`, failing if the content does not fit in
+/// chunks indexed up to `max_index`. Returns number of written bytes.
+fn write_stream(
+    cipher: &StreamCipher,
+    max_index: u32,
+    mut reader: impl Read,
+    mut writer: impl Write,
+) -> Result<u64, CryptoError> {
+    writer.write_all(cipher.header()).map_err(io_error)?;
+    let mut written = HEADER_LEN as u64;
+    let mut chunk = vec![0u8; cipher.chunk_size as usize];
+    let mut index = 0;
+    loop {
+        let len = read_full(&mut reader, &mut chunk)?;
+        let last = len < chunk.len();
+        if !last && index == max_index {
+            return Err(stream_error("Stream is too long"));
+        }
+        let ciphertext = cipher.encrypt_chunk(index, last, &chunk[..len]);
+        writer.write_all(&ciphertext).map_err(io_error)?;
+        written += ciphertext.len() as u64;
+        if last {
+            writer.flush().map_err(io_error)?;
+            return Ok(written);
+        }
+        index += 1;
+    }
+}
+
+/// Decryptor of a stream written by [`encrypt_stream`], able to decrypt any range of the
+/// plaintext without reading the other chunks.
+pub struct StreamDecryptor<R> {
+    reader: R,
+    cipher: StreamCipher,
+    plaintext_len: u64,
+}
+
+impl<R: Read + Seek> StreamDecryptor<R> {
+    /// Reads the header and unwraps the file key with `container_key`. Fails if the stream was
+    /// bound to other associated data.
+    pub fn new(
+        container_key: &SymmetricKey,
+        associated_data: &[u8],
+        mut reader: R,
+    ) -> Result<Self, CryptoError> {
+        let mut header = [0u8; HEADER_LEN];
+        reader.seek(SeekFrom::Start(0)).map_err(io_error)?;
+        reader
+            .read_exact(&mut header)
+            .map_err(|_| stream_error("Stream is too short"))?;
+        let cipher = StreamCipher::open(container_key, &header, associated_data)?;
+        let stream_len = reader.seek(SeekFrom::End(0)).map_err(io_error)?;
+        Ok(Self {
+            reader,
+            plaintext_len: cipher.plaintext_len(stream_len)?,
+            cipher,
+        })
+    }
+
+    /// Length of the decrypted content.
+    pub fn plaintext_len(&self) -> u64 {
+        self.plaintext_len
+    }
+
+    /// Decrypts `len` bytes of plaintext starting at `offset`, reading only the chunks covering
+    /// the range. The range is clamped to the plaintext length.
+    pub fn read_range(&mut self, offset: u64, len: u64) -> Result<Vec<u8>, CryptoError> {
+        let end = offset.saturating_add(len).min(self.plaintext_len);
+        if offset >= end {
+            return Ok(Vec::new());
+        }
+        let chunk_size = self.cipher.chunk_size as u64;
+        let mut plaintext = Vec::with_capacity((end - offset) as usize);
+        for index in offset / chunk_size..=(end - 1) / chunk_size {
+            let chunk = self.decrypt_chunk(index as u32)?;
+            let chunk_start = index * chunk_size;
+            let from = offset.saturating_sub(chunk_start) as usize;
+            let to = (end - chunk_start).min(chunk.len() as u64) as usize;
+            plaintext.extend_from_slice(&chunk[from..to]);
+        }
+        Ok(plaintext)
+    }
+
+    /// Decrypts the whole stream chunk by chunk into `writer`. Returns number of written bytes.
+    pub fn decrypt_to(&mut self, mut writer: impl Write) -> Result<u64, CryptoError> {
+        let last_index = self.cipher.last_chunk_index(self.plaintext_len)?;
+        for index in 0..=last_index {
+            writer
+                .write_all(&self.decrypt_chunk(index)?)
+                .map_err(io_error)?;
+        }
+        writer.flush().map_err(io_error)?;
+        Ok(self.plaintext_len)
+    }
+
+    fn decrypt_chunk(&mut self, index: u32) -> Result<Vec<u8>, CryptoError> {
+        let last_index = self.cipher.last_chunk_index(self.plaintext_len)?;
+        let chunk_size = self.cipher.chunk_size as u64;
+        let len = if index == last_index {
+            self.plaintext_len - index as u64 * chunk_size
+        } else {
+            chunk_size
+        };
+        let mut ciphertext = vec![0u8; len as usize + TAG_LEN];
+        self.reader
+            .seek(SeekFrom::Start(self.cipher.chunk_offset(index)))
+            .map_err(io_error)?;
+        self.reader.read_exact(&mut ciphertext).map_err(io_error)?;
+        self.cipher
+            .decrypt_chunk(index, index == last_index, &ciphertext)
+    }
+}
+
+/// Reads until the buffer is full or the reader is exhausted.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> Result<usize, CryptoError> {
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(read) => len += read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(io_error(e)),
+        }
+    }
+    Ok(len)
+}
+
+fn io_error(e: std::io::Error) -> CryptoError {
+    stream_error(e.to_string())
+}
+
+fn stream_error(reason: impl Into<String>) -> CryptoError {
+    CryptoError::StreamError(reason.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use hex_literal::hex;
+
+    use super::*;
+
+    const CHUNK_SIZE: u32 = 16;
+    const PATH: &[u8] = b"/dir/file.txt";
+
+    fn content(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn encrypt(key: &SymmetricKey, plaintext: &[u8]) -> Vec<u8> {
+        let mut stream = Vec::new();
+        encrypt_stream(key, CHUNK_SIZE, PATH, plaintext, &mut stream).unwrap();
+        stream
+    }
+
+    fn decrypt(key: &SymmetricKey, stream: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let mut plaintext = Vec::new();
+        StreamDecryptor::new(key, PATH, Cursor::new(stream))?.decrypt_to(&mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    /// Known answer computed independently of this crate: the file key is wrapped with
+    /// XChaCha20-Poly1305 keyed with the container key, with associated data
+    /// `header up to the wrapped key | path`, and each chunk is encrypted with XChaCha20-Poly1305
+    /// keyed with the file key, with nonce `prefix | counter (u32 BE) | last flag` and associated
+    /// data `header up to the wrapped key`.
+    #[test]
+    fn should_match_known_answer_stream() {
+        // given
+        let container_key = SymmetricKey::from_bytes(hex!(
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+        ));
+        let file_key = hex!("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f");
+        let nonce_prefix = hex!("a0a1a2a3a4a5a6a7a8a9aaabacadaeafb0b1b2");
+        let wrapping_nonce = hex!("404142434445464748494a4b4c4d4e4f5051525354555657");
+        let expected = [
+            // magic | version | chunk size | nonce prefix
+            hex!("574c53545245414d 01 00000010 a0a1a2a3a4a5a6a7a8a9aaabacadaeafb0b1b2").as_slice(),
+            // wrapped file key: nonce | ciphertext | tag
+            &hex!(
+                "404142434445464748494a4b4c4d4e4f5051525354555657"
+                "54b887f35465ff91077d0d352311eb1d022b3f5787ccc50df2a867de9599bd0f"
+                "5609241b44a7c99d52d1847a2a12248b"
+            ),
+            // chunk 0
+            &hex!("1742180b784ace83aeddab13493d2c90 c18e830cc3ccaa16763b24de55e1cd2f"),
+            // chunk 1
+            &hex!("d6925cee1731096640966251932be4fb 8058d43ec26eafe1edb2fde40f7bd0bf"),
+            // last chunk
+            &hex!("bfd34752d89c927c 6d34b29fcce51030052de2eea623f80f"),
+        ]
+        .concat();
+        let cipher = StreamCipher::with_keys(
+            &container_key,
+            CHUNK_SIZE,
+            Zeroizing::new(file_key),
+            nonce_prefix,
+            XNonce::from_slice(&wrapping_nonce),
+            PATH,
+        )
+        .unwrap();
+
+        // when
+        let mut stream = Vec::new();
+        write_stream(&cipher, u32::MAX, &content(40)[..], &mut stream).unwrap();
+
+        // then
+        assert_eq!(stream, expected);
+        assert_eq!(decrypt(&container_key, &expected).unwrap(), content(40));
+    }
+
+    #[test]
+    fn should_decrypt_contents_of_any_length() {
+        // given
+        let key = SymmetricKey::generate();
+
+        for len in [0, 1, 15, 16, 17, 32, 100] {
+            // when
+            let stream = encrypt(&key, &content(len));
+
+            // then
+            let chunks = len / CHUNK_SIZE as usize + 1;
+            assert_eq!(stream.len(), HEADER_LEN + len + chunks * TAG_LEN);
+            assert_eq!(decrypt(&key, &stream).unwrap(), content(len));
+        }
+    }
+
+    #[test]
+    fn should_decrypt_ranges_of_content() {
+        // given
+        let key = SymmetricKey::generate();
+        let stream = encrypt(&key, &content(100));
+        let mut decryptor = StreamDecryptor::new(&key, PATH, Cursor::new(stream)).unwrap();
+
+        // then
+        assert_eq!(decryptor.plaintext_len(), 100);
+        for (offset, len) in [(0, 10), (5, 16), (16, 16), (30, 50), (90, 100), (100, 1)] {
+            let expected = content(100)
+                .into_iter()
+                .skip(offset as usize)
+                .take(len as usize)
+                .collect::<Vec<_>>();
+            assert_eq!(decryptor.read_range(offset, len).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn should_detect_truncated_and_reordered_chunks() {
+        // given
+        let key = SymmetricKey::generate();
+        let stream = encrypt(&key, &content(40));
+        let chunk = CHUNK_SIZE as usize + TAG_LEN;
+
+        // when
+        let truncated = &stream[..HEADER_LEN + 2 * chunk];
+        let mut reordered = stream.clone();
+        reordered[HEADER_LEN..HEADER_LEN + 2 * chunk].copy_from_slice(
+            &[
+                &stream[HEADER_LEN + chunk..][..chunk],
+                &stream[HEADER_LEN..][..chunk],
+            ]
+            .concat(),
+        );
+        let mut tampered_header = stream.clone();
+        tampered_header[MAGIC.len() + 1 + 4] ^= 1;
+
+        // then
+        assert_eq!(decrypt(&key, truncated), Err(CryptoError::DecryptionError));
+        assert_eq!(decrypt(&key, &reordered), Err(CryptoError::DecryptionError));
+        assert_eq!(
+            decrypt(&key, &tampered_header),
+            Err(CryptoError::DecryptionError)
+        );
+    }
+
+    #[test]
+    fn should_not_decrypt_with_other_container_key() {
+        // given
+        let key = SymmetricKey::generate();
+        let stream = encrypt(&key, &content(20));
+
+        // then
+        assert_eq!(
+            decrypt(&SymmetricKey::generate(), &stream),
+            Err(CryptoError::DecryptionError)
+        );
+        assert_eq!(
+            decrypt(&key, b"WLSTREAM"),
+            Err(CryptoError::StreamError("Stream is too short".to_owned()))
+        );
+    }
+
+    #[test]
+    fn should_not_decrypt_stream_bound_to_other_path() {
+        // given
+        let key = SymmetricKey::generate();
+        let stream = encrypt(&key, &content(40));
+        let mut cipher = StreamCipher::open(&key, &stream, PATH).unwrap();
+
+        // when
+        cipher.rewrap(&key, b"/other.txt");
+        let moved = [cipher.header(), &stream[HEADER_LEN..]].concat();
+
+        // then
+        assert_eq!(decrypt(&key, &moved), Err(CryptoError::DecryptionError));
+        let mut plaintext = Vec::new();
+        StreamDecryptor::new(&key, b"/other.txt", Cursor::new(moved))
+            .unwrap()
+            .decrypt_to(&mut plaintext)
+            .unwrap();
+        assert_eq!(plaintext, content(40));
+    }
+
+    #[test]
+    fn should_not_encrypt_more_chunks_than_counter_allows() {
+        // given
+        let cipher = StreamCipher::new(&SymmetricKey::generate(), CHUNK_SIZE, PATH).unwrap();
+        let encrypt = |len| write_stream(&cipher, 2, &content(len)[..], Vec::new());
+
+        // then
+        assert_eq!(
+            encrypt(3 * CHUNK_SIZE as usize - 1),
+            Ok(cipher.stream_len(3 * CHUNK_SIZE as u64 - 1))
+        );
+        assert_eq!(
+            encrypt(3 * CHUNK_SIZE as usize),
+            Err(CryptoError::StreamError("Stream is too long".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,370 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+use crate::error::CryptoError;
+
+const NONCE_LEN: usize = 24;
+const TAG_LEN: usize = 16;
+
+/// Number of bytes added to each plaintext by encryption (nonce and authentication tag).
+pub const ENCRYPTION_OVERHEAD: usize = NONCE_LEN + TAG_LEN;
+
+/// Algorithm deriving keys from passphrases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PassphraseKdfAlgorithm {
+    /// Argon2id, version 0x13
+    Argon2id,
+}
+
+/// Parameters of the key derivation from a passphrase. They have to be stored along with the salt,
+/// so the same key is derived even if the recommended parameters change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PassphraseKdfParams {
+    pub algorithm: PassphraseKdfAlgorithm,
+    /// Memory size in KiB
+    pub m_cost: u32,
+    /// Number of iterations
+    pub t_cost: u32,
+    /// Degree of parallelism
+    pub p_cost: u32,
+}
+
+impl PassphraseKdfParams {
+    /// Parameters for new keys, as recommended by OWASP for Argon2id.
+    pub fn recommended() -> Self {
+        Self {
+            algorithm: PassphraseKdfAlgorithm::Argon2id,
+            m_cost: 19 * 1024,
+            t_cost: 2,
+            p_cost: 1,
+        }
+    }
+
+    /// Parameters keys were derived with before they were stored along with the salt (defaults
+    /// of the `argon2` crate 0.4).
+    pub fn legacy() -> Self {
+        Self {
+            algorithm: PassphraseKdfAlgorithm::Argon2id,
+            m_cost: 4096,
+            t_cost: 3,
+            p_cost: 1,
+        }
+    }
+}
+
+/// 256-bit key used to encrypt user's data with XChaCha20-Poly1305. Wiped from memory on drop.
+#[derive(Clone, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct SymmetricKey([u8; 32]);
+
+impl std::fmt::Debug for SymmetricKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SymmetricKey(..)")
+    }
+}
+
+impl<'de> Deserialize<'de> for SymmetricKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let hex_encoded_str = Zeroizing::new(String::deserialize(deserializer)?);
+        let bytes =
+            Zeroizing::new(hex::decode(&*hex_encoded_str).map_err(serde::de::Error::custom)?);
+        let key = <[u8; 32]>::try_from(bytes.as_slice()).map_err(serde::de::Error::custom)?;
+        Ok(Self(key))
+    }
+}
+
+impl Serialize for SymmetricKey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        String::serialize(&Zeroizing::new(hex::encode(self.0)), serializer)
+    }
+}
+
+impl SymmetricKey {
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// Generates a random key.
+    pub fn generate() -> Self {
+        let mut key = Self([0u8; 32]);
+        rand_core::RngCore::fill_bytes(&mut rand_core::OsRng, &mut key.0);
+        key
+    }
+
+    /// Derives a key from the passphrase with the given parameters. The salt (at least 8 bytes)
+    /// should be random and stored, along with the parameters, next to the encrypted data.
+    pub fn from_passphrase(
+        passphrase: &[u8],
+        salt: &[u8],
+        params: &PassphraseKdfParams,
+    ) -> Result<Self, CryptoError> {
+        let to_error = |e: argon2::Error| CryptoError::PassphraseDerivationError(e.to_string());
+        let argon2 = match params.algorithm {
+            PassphraseKdfAlgorithm::Argon2id => Argon2::new(
+                Algorithm::Argon2id,
+                Version::V0x13,
+                Params::new(params.m_cost, params.t_cost, params.p_cost, Some(32))
+                    .map_err(to_error)?,
+            ),
+        };
+        let mut key = Self([0u8; 32]);
+        argon2
+            .hash_password_into(passphrase, salt, &mut key.0)
+            .map_err(to_error)?;
+        Ok(key)
+    }
+
+    /// Deterministically derives an independent key bound to the given context (e.g. a container
+    /// uuid), so compromising one of the derived keys does not reveal the others.
+    pub fn derive_subkey(&self, context: &[u8]) -> SymmetricKey {
+        let mut subkey = Self([0u8; 32]);
+        Hkdf::<Sha256>::new(None, &self.0)
+            .expand(context, &mut subkey.0)
+            .expect("32 bytes is a valid HKDF output length");
+        subkey
+    }
+
+    /// Encrypts the plaintext using a random nonce. The nonce is prepended to the ciphertext.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        self.encrypt_with_associated_data(plaintext, &[])
+    }
+
+    /// Encrypts the plaintext like [`SymmetricKey::encrypt`], authenticating also the associated
+    /// data, which has to be passed unchanged to decryption.
+    pub fn encrypt_with_associated_data(
+        &self,
+        plaintext: &[u8],
+        associated_data: &[u8],
+    ) -> Vec<u8> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut rand_core::OsRng);
+        self.encrypt_with_nonce(&nonce, plaintext, associated_data)
+    }
+
+    /// Encrypts the plaintext using a nonce derived from the plaintext itself (SIV-like
+    /// construction), so the same plaintext always gives the same ciphertext. It is meant for
+    /// data that has to be looked up by its encrypted form, like file names.
+    pub fn encrypt_deterministic(&self, plaintext: &[u8]) -> Vec<u8> {
+        let mac_key = self.derive_subkey(b"wildland.siv.nonce");
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&mac_key.0)
+            .expect("HMAC accepts keys of any length");
+        mac.update(plaintext);
+        let nonce = XNonce::clone_from_slice(&mac.finalize().into_bytes()[..NONCE_LEN]);
+        self.encrypt_with_nonce(&nonce, plaintext, &[])
+    }
+
+    /// Decrypts the output of [`SymmetricKey::encrypt`] or [`SymmetricKey::encrypt_deterministic`].
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.decrypt_with_associated_data(ciphertext, &[])
+    }
+
+    /// Decrypts the output of [`SymmetricKey::encrypt_with_associated_data`]. Fails if the
+    /// associated data differs from the one passed to encryption.
+    pub fn decrypt_with_associated_data(
+        &self,
+        ciphertext: &[u8],
+        associated_data: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        if ciphertext.len() < ENCRYPTION_OVERHEAD {
+            return Err(CryptoError::DecryptionError);
+        }
+        let (nonce, ciphertext) = ciphertext.split_at(NONCE_LEN);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: associated_data,
+        };
+        XChaCha20Poly1305::new(&self.0.into())
+            .decrypt(XNonce::from_slice(nonce), payload)
+            .map_err(|_| CryptoError::DecryptionError)
+    }
+
+    pub(crate) fn encrypt_with_nonce(
+        &self,
+        nonce: &XNonce,
+        plaintext: &[u8],
+        associated_data: &[u8],
+    ) -> Vec<u8> {
+        let payload = Payload {
+            msg: plaintext,
+            aad: associated_data,
+        };
+        let ciphertext = XChaCha20Poly1305::new(&self.0.into())
+            .encrypt(nonce, payload)
+            .expect("Encryption of in-memory buffer should not fail");
+        [nonce.as_slice(), &ciphertext].concat()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MSG: &[u8] = b"Hello World";
+
+    #[test]
+    fn can_encrypt_and_decrypt_message() {
+        let key = SymmetricKey::from_bytes([7; 32]);
+
+        let ciphertext = key.encrypt(MSG);
+
+        assert_eq!(ciphertext.len(), MSG.len() + ENCRYPTION_OVERHEAD);
+        assert_ne!(key.encrypt(MSG), ciphertext);
+        assert_eq!(key.decrypt(&ciphertext).unwrap(), MSG);
+    }
+
+    #[test]
+    fn passphrase_derives_the_same_key_for_the_same_salt_and_params() {
+        let params = PassphraseKdfParams::recommended();
+        let key = SymmetricKey::from_passphrase(b"passphrase", b"salt of lss", &params).unwrap();
+
+        assert_eq!(
+            SymmetricKey::from_passphrase(b"passphrase", b"salt of lss", &params).unwrap(),
+            key
+        );
+        assert_ne!(
+            SymmetricKey::from_passphrase(b"passphrase", b"other salt", &params).unwrap(),
+            key
+        );
+        assert_ne!(
+            SymmetricKey::from_passphrase(
+                b"passphrase",
+                b"salt of lss",
+                &PassphraseKdfParams::legacy()
+            )
+            .unwrap(),
+            key
+        );
+        assert!(SymmetricKey::from_passphrase(b"passphrase", b"short", &params).is_err());
+        assert!(SymmetricKey::from_passphrase(
+            b"passphrase",
+            b"salt of lss",
+            &PassphraseKdfParams {
+                m_cost: 1,
+                ..params
+            }
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn legacy_params_derive_keys_of_argon2_defaults() {
+        let mut expected = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(b"passphrase", b"salt of lss", &mut expected)
+            .unwrap();
+
+        assert_eq!(
+            SymmetricKey::from_passphrase(
+                b"passphrase",
+                b"salt of lss",
+                &PassphraseKdfParams::legacy()
+            )
+            .unwrap(),
+            SymmetricKey::from_bytes(expected)
+        );
+    }
+
+    #[test]
+    fn deterministic_encryption_gives_the_same_ciphertext() {
+        let key = SymmetricKey::from_bytes([7; 32]);
+
+        let ciphertext = key.encrypt_deterministic(MSG);
+
+        assert_eq!(key.encrypt_deterministic(MSG), ciphertext);
+        assert_ne!(key.encrypt_deterministic(b"Hello World!"), ciphertext);
+        assert_eq!(key.decrypt(&ciphertext).unwrap(), MSG);
+    }
+
+    #[test]
+    fn cannot_decrypt_with_other_key() {
+        let key = SymmetricKey::from_bytes([7; 32]);
+        let other_key = key.derive_subkey(b"other");
+
+        let ciphertext = key.encrypt(MSG);
+
+        assert_ne!(key, other_key);
+        assert_eq!(
+            other_key.decrypt(&ciphertext).unwrap_err(),
+            CryptoError::DecryptionError
+        );
+    }
+
+    #[test]
+    fn associated_data_is_authenticated() {
+        let key = SymmetricKey::from_bytes([7; 32]);
+
+        let ciphertext = key.encrypt_with_associated_data(MSG, b"context");
+
+        assert_eq!(
+            key.decrypt_with_associated_data(&ciphertext, b"context")
+                .unwrap(),
+            MSG
+        );
+        assert_eq!(
+            key.decrypt_with_associated_data(&ciphertext, b"other context")
+                .unwrap_err(),
+            CryptoError::DecryptionError
+        );
+        assert_eq!(
+            key.decrypt(&ciphertext).unwrap_err(),
+            CryptoError::DecryptionError
+        );
+    }
+
+    #[test]
+    fn cannot_decrypt_tampered_ciphertext() {
+        let key = SymmetricKey::from_bytes([7; 32]);
+        let mut ciphertext = key.encrypt(MSG);
+        *ciphertext.last_mut().unwrap() ^= 1;
+
+        assert_eq!(
+            key.decrypt(&ciphertext).unwrap_err(),
+            CryptoError::DecryptionError
+        );
+        assert_eq!(
+            key.decrypt(&ciphertext[..10]).unwrap_err(),
+            CryptoError::DecryptionError
+        );
+    }
+
+    #[test]
+    fn key_survives_serialization() {
+        let key = SymmetricKey::from_bytes([7; 32]);
+
+        let serialized = serde_json::to_string(&key).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<SymmetricKey>(&serialized).unwrap(),
+            key
+        );
+        assert_eq!(format!("{key:?}"), "SymmetricKey(..)");
+    }
+}
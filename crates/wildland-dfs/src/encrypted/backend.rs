@@ -0,0 +1,438 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::ffi::{OsStr, OsString};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use base64::alphabet::URL_SAFE;
+use base64::engine::fast_portable::{FastPortable, NO_PAD};
+use wildland_corex::dfs::interface::{NodeType, Stat};
+use wildland_corex::stream::{self, StreamCipher, CHUNK_OVERHEAD, DEFAULT_CHUNK_SIZE, HEADER_LEN};
+use wildland_corex::{CryptoError, Storage, SymmetricKey};
+
+use super::EncryptionKeyProvider;
+use crate::storage_backend::{StorageBackend, StorageBackendError};
+use crate::unencrypted::StorageBackendFactory;
+
+const NAME_ENCODING: FastPortable = FastPortable::from(&URL_SAFE, NO_PAD);
+/// Longest name accepted by common filesystems and object stores
+const MAX_ENCRYPTED_NAME_LEN: usize = 255;
+/// Size of plaintext chunks of file contents. Sizes of files are computed with it, so it cannot
+/// change for existing files.
+const CHUNK_SIZE: u32 = DEFAULT_CHUNK_SIZE;
+/// Number of chunks encrypted in memory before they are written to the storage
+const CHUNKS_PER_WRITE: u32 = 16;
+
+pub(crate) struct EncryptedStorageBackendFactory {
+    pub(crate) inner: Box<dyn StorageBackendFactory>,
+    pub(crate) key_provider: Rc<dyn EncryptionKeyProvider>,
+}
+
+impl StorageBackendFactory for EncryptedStorageBackendFactory {
+    fn init_backend(&self, storage: Storage) -> Result<Arc<dyn StorageBackend>, anyhow::Error> {
+        let container_key = self.key_provider.container_key(&storage)?;
+        Ok(Arc::new(EncryptedStorageBackend::new(
+            self.inner.init_backend(storage)?,
+            &container_key,
+        )))
+    }
+}
+
+/// Content of a file opened for reading or modification.
+struct EncryptedContent {
+    cipher: StreamCipher,
+    /// Length of plaintext
+    len: u64,
+    /// Whether the file is empty and the header of its content has not been written yet
+    new: bool,
+}
+
+/// Storage backend encrypting names and contents of files stored by another backend.
+///
+/// Names are encrypted deterministically, so files can be looked up by their encrypted names.
+/// Contents are kept in the chunked stream format of [`wildland_corex::stream`], so reading or
+/// writing a part of a file touches only the chunks covering it. The file key of each file is
+/// bound to the path of the file, so files swapped within the container fail to decrypt. Empty
+/// files are stored as they are, so newly created files need no special handling.
+pub(crate) struct EncryptedStorageBackend {
+    inner: Arc<dyn StorageBackend>,
+    names_key: SymmetricKey,
+    content_key: SymmetricKey,
+}
+
+impl EncryptedStorageBackend {
+    pub(crate) fn new(inner: Arc<dyn StorageBackend>, container_key: &SymmetricKey) -> Self {
+        Self {
+            inner,
+            names_key: container_key.derive_subkey(b"wildland.dfs.names"),
+            content_key: container_key.derive_subkey(b"wildland.dfs.content"),
+        }
+    }
+
+    /// Fails with [`StorageBackendError::NameTooLong`] if an encrypted name would not fit in
+    /// [`MAX_ENCRYPTED_NAME_LEN`] bytes.
+    fn encrypt_path(&self, path: &Path) -> Result<PathBuf, StorageBackendError> {
+        path.components()
+            .map(|component| match component {
+                Component::Normal(name) => {
+                    let encrypted_name = base64::encode_engine(
+                        self.names_key
+                            .encrypt_deterministic(name.to_string_lossy().as_bytes()),
+                        &NAME_ENCODING,
+                    );
+                    if encrypted_name.len() > MAX_ENCRYPTED_NAME_LEN {
+                        return Err(StorageBackendError::NameTooLong);
+                    }
+                    Ok(OsString::from(encrypted_name))
+                }
+                other => Ok(other.as_os_str().to_owned()),
+            })
+            .collect()
+    }
+
+    fn decrypt_name(&self, encrypted_name: &OsStr) -> Result<String, StorageBackendError> {
+        let ciphertext =
+            base64::decode_engine(encrypted_name.to_string_lossy().as_bytes(), &NAME_ENCODING)
+                .map_err(|e| anyhow!("Name {encrypted_name:?} is not encrypted: {e}"))?;
+        let name = self
+            .names_key
+            .decrypt(&ciphertext)
+            .map_err(|e| anyhow!("Could not decrypt name {encrypted_name:?}: {e}"))?;
+        Ok(String::from_utf8(name).map_err(|e| anyhow!(e))?)
+    }
+
+    /// Reads the header of the file content. Content of an empty file gets a new header, which is
+    /// written along with the first chunk.
+    fn open_content(
+        &self,
+        path: &Path,
+        encrypted_path: &Path,
+    ) -> Result<EncryptedContent, StorageBackendError> {
+        let stream_len = self.inner.getattr(encrypted_path)?.size;
+        if stream_len == 0 {
+            return Ok(EncryptedContent {
+                cipher: StreamCipher::new(&self.content_key, CHUNK_SIZE, &bound_path(path))
+                    .map_err(content_error)?,
+                len: 0,
+                new: true,
+            });
+        }
+        let header = self.inner.read(encrypted_path, 0, HEADER_LEN)?;
+        let cipher = StreamCipher::open(&self.content_key, &header, &bound_path(path))
+            .map_err(content_error)?;
+        if cipher.chunk_size() != CHUNK_SIZE {
+            return Err(anyhow!("Unsupported chunk size {}", cipher.chunk_size()).into());
+        }
+        Ok(EncryptedContent {
+            len: cipher.plaintext_len(stream_len).map_err(content_error)?,
+            cipher,
+            new: false,
+        })
+    }
+
+    /// Decrypts chunks from `first` to `last` (inclusive) of the existing content.
+    fn read_chunks(
+        &self,
+        encrypted_path: &Path,
+        content: &EncryptedContent,
+        first: u32,
+        last: u32,
+    ) -> Result<Vec<Vec<u8>>, StorageBackendError> {
+        let last_index = content
+            .cipher
+            .last_chunk_index(content.len)
+            .map_err(content_error)?;
+        let start = content.cipher.chunk_offset(first);
+        let end = content
+            .cipher
+            .chunk_offset(last)
+            .saturating_add(CHUNK_SIZE as u64 + CHUNK_OVERHEAD as u64)
+            .min(content.cipher.stream_len(content.len));
+        let ciphertext = self.inner.read(
+            encrypted_path,
+            start,
+            usize::try_from(end - start).map_err(|e| anyhow!(e))?,
+        )?;
+        let encrypted_chunk_size = CHUNK_SIZE as usize + CHUNK_OVERHEAD;
+        (first..=last)
+            .zip(ciphertext.chunks(encrypted_chunk_size))
+            .map(|(index, chunk)| {
+                content
+                    .cipher
+                    .decrypt_chunk(index, index == last_index, chunk)
+                    .map_err(content_error)
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|chunks| {
+                if chunks.len() == (last - first) as usize + 1 {
+                    Ok(chunks)
+                } else {
+                    Err(content_error(CryptoError::DecryptionError))
+                }
+            })
+    }
+
+    /// Writes `buf` at `offset` of the content growing it to at least `len` bytes, filling gaps
+    /// with zeros. Only the chunks covering the modified range (and the last chunk, which changes
+    /// when the content grows) are rewritten.
+    fn write_content(
+        &self,
+        encrypted_path: &Path,
+        content: &EncryptedContent,
+        offset: u64,
+        buf: &[u8],
+        len: u64,
+    ) -> Result<(), StorageBackendError> {
+        let chunk_size = CHUNK_SIZE as u64;
+        let end = offset
+            .checked_add(buf.len() as u64)
+            .ok_or_else(|| anyhow!("Offset {offset} exceeds the supported file size"))?;
+        let new_len = content.len.max(end).max(len);
+        let new_last = content
+            .cipher
+            .last_chunk_index(new_len)
+            .map_err(|_| anyhow!("File size {new_len} exceeds the supported file size"))?;
+        let old_last = content
+            .cipher
+            .last_chunk_index(content.len)
+            .map_err(content_error)?;
+        let first = (offset.min(content.len) / chunk_size) as u32;
+        let last = if new_len > content.len {
+            new_last
+        } else {
+            ((end - 1) / chunk_size) as u32
+        };
+
+        let mut batch_start = first;
+        while batch_start <= last {
+            let batch_end = batch_start.saturating_add(CHUNKS_PER_WRITE - 1).min(last);
+            let mut chunks = if content.new || batch_start > old_last {
+                Vec::new()
+            } else {
+                self.read_chunks(
+                    encrypted_path,
+                    content,
+                    batch_start,
+                    batch_end.min(old_last),
+                )?
+            }
+            .into_iter();
+            let mut ciphertext = Vec::new();
+            for index in batch_start..=batch_end {
+                let chunk_start = index as u64 * chunk_size;
+                let chunk_len = if index == new_last {
+                    new_len - chunk_start
+                } else {
+                    chunk_size
+                };
+                let mut chunk = chunks.next().unwrap_or_default();
+                chunk.resize(chunk_len as usize, 0);
+                let from = offset.max(chunk_start);
+                let to = end.min(chunk_start + chunk_len);
+                if from < to {
+                    chunk[(from - chunk_start) as usize..(to - chunk_start) as usize]
+                        .copy_from_slice(&buf[(from - offset) as usize..(to - offset) as usize]);
+                }
+                ciphertext.extend(
+                    content
+                        .cipher
+                        .encrypt_chunk(index, index == new_last, &chunk),
+                );
+            }
+            if content.new && batch_start == 0 {
+                ciphertext.splice(0..0, content.cipher.header().iter().copied());
+                self.inner.write(encrypted_path, 0, &ciphertext)?;
+            } else {
+                self.inner.write(
+                    encrypted_path,
+                    content.cipher.chunk_offset(batch_start),
+                    &ciphertext,
+                )?;
+            }
+            match batch_end.checked_add(1) {
+                Some(next) => batch_start = next,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Binds contents of the file, or of all files within the directory, moved from `old_path` to
+    /// `new_path` to their new paths.
+    fn rebind(&self, old_path: &Path, new_path: &Path) -> Result<(), StorageBackendError> {
+        let encrypted_path = self.encrypt_path(new_path)?;
+        let stat = self.inner.getattr(&encrypted_path)?;
+        match stat.node_type {
+            NodeType::Dir => {
+                for child in self.readdir(new_path)? {
+                    if let Some(name) = child.file_name() {
+                        self.rebind(&old_path.join(name), &child)?;
+                    }
+                }
+                Ok(())
+            }
+            NodeType::File if stat.size > 0 => {
+                let header = self.inner.read(&encrypted_path, 0, HEADER_LEN)?;
+                let mut cipher =
+                    StreamCipher::open(&self.content_key, &header, &bound_path(old_path))
+                        .map_err(content_error)?;
+                cipher.rewrap(&self.content_key, &bound_path(new_path));
+                self.inner.write(&encrypted_path, 0, cipher.header())?;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Associated data binding file content to the path of the file
+fn bound_path(path: &Path) -> Vec<u8> {
+    let names: Vec<_> = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+            _ => None,
+        })
+        .collect();
+    format!("/{}", names.join("/")).into_bytes()
+}
+
+fn content_error(e: CryptoError) -> StorageBackendError {
+    anyhow!("Could not decrypt file content: {e}").into()
+}
+
+impl StorageBackend for EncryptedStorageBackend {
+    fn readdir(&self, path: &Path) -> Result<Vec<PathBuf>, StorageBackendError> {
+        Ok(self
+            .inner
+            .readdir(&self.encrypt_path(path)?)?
+            .into_iter()
+            .filter_map(|encrypted_path| {
+                let name = encrypted_path
+                    .file_name()
+                    .map(|encrypted_name| self.decrypt_name(encrypted_name));
+                match name {
+                    Some(Ok(name)) => Some(path.join(name)),
+                    Some(Err(e)) => {
+                        tracing::warn!("Skipping {encrypted_path:?}: {e}");
+                        None
+                    }
+                    None => None,
+                }
+            })
+            .collect())
+    }
+
+    fn getattr(&self, path: &Path) -> Result<Stat, StorageBackendError> {
+        let mut stat = self.inner.getattr(&self.encrypt_path(path)?)?;
+        if stat.node_type == NodeType::File && stat.size > 0 {
+            stat.size = stream::plaintext_len(CHUNK_SIZE, stat.size).map_err(content_error)?;
+        }
+        Ok(stat)
+    }
+
+    fn read(&self, path: &Path, offset: u64, count: usize) -> Result<Vec<u8>, StorageBackendError> {
+        let encrypted_path = self.encrypt_path(path)?;
+        let content = self.open_content(path, &encrypted_path)?;
+        let end = offset.saturating_add(count as u64).min(content.len);
+        if offset >= end {
+            return Ok(Vec::new());
+        }
+        let chunk_size = CHUNK_SIZE as u64;
+        let first = (offset / chunk_size) as u32;
+        let last = ((end - 1) / chunk_size) as u32;
+        let mut plaintext = Vec::with_capacity((end - offset) as usize);
+        for (index, chunk) in
+            (first..=last).zip(self.read_chunks(&encrypted_path, &content, first, last)?)
+        {
+            let chunk_start = index as u64 * chunk_size;
+            let from = offset.saturating_sub(chunk_start) as usize;
+            let to = (end - chunk_start).min(chunk.len() as u64) as usize;
+            plaintext.extend_from_slice(&chunk[from..to]);
+        }
+        Ok(plaintext)
+    }
+
+    fn write(&self, path: &Path, offset: u64, buf: &[u8]) -> Result<usize, StorageBackendError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let encrypted_path = self.encrypt_path(path)?;
+        let content = self.open_content(path, &encrypted_path)?;
+        self.write_content(&encrypted_path, &content, offset, buf, 0)?;
+        Ok(buf.len())
+    }
+
+    fn create(&self, path: &Path) -> Result<(), StorageBackendError> {
+        self.inner.create(&self.encrypt_path(path)?)
+    }
+
+    fn unlink(&self, path: &Path) -> Result<(), StorageBackendError> {
+        self.inner.unlink(&self.encrypt_path(path)?)
+    }
+
+    fn mkdir(&self, path: &Path) -> Result<(), StorageBackendError> {
+        self.inner.mkdir(&self.encrypt_path(path)?)
+    }
+
+    fn rmdir(&self, path: &Path) -> Result<(), StorageBackendError> {
+        self.inner.rmdir(&self.encrypt_path(path)?)
+    }
+
+    /// Contents are bound to the new paths after the rename. If that is interrupted, contents
+    /// bound to the old paths fail to decrypt instead of being accepted.
+    fn rename(&self, old_path: &Path, new_path: &Path) -> Result<(), StorageBackendError> {
+        self.inner
+            .rename(&self.encrypt_path(old_path)?, &self.encrypt_path(new_path)?)?;
+        self.rebind(old_path, new_path)
+    }
+
+    /// Shrinking rewrites the new last chunk before the content is cut. If that is interrupted,
+    /// the content fails to decrypt instead of being accepted.
+    fn truncate(&self, path: &Path, size: u64) -> Result<(), StorageBackendError> {
+        let encrypted_path = self.encrypt_path(path)?;
+        let content = self.open_content(path, &encrypted_path)?;
+        if size == content.len {
+            return Ok(());
+        }
+        if size == 0 {
+            return self.inner.truncate(&encrypted_path, 0);
+        }
+        if size > content.len {
+            return self.write_content(&encrypted_path, &content, content.len, &[], size);
+        }
+
+        let new_last = content
+            .cipher
+            .last_chunk_index(size)
+            .map_err(content_error)?;
+        let mut chunk = self
+            .read_chunks(&encrypted_path, &content, new_last, new_last)?
+            .remove(0);
+        chunk.truncate((size - new_last as u64 * CHUNK_SIZE as u64) as usize);
+        self.inner.write(
+            &encrypted_path,
+            content.cipher.chunk_offset(new_last),
+            &content.cipher.encrypt_chunk(new_last, true, &chunk),
+        )?;
+        self.inner
+            .truncate(&encrypted_path, content.cipher.stream_len(size))
+    }
+}
@@ -0,0 +1,160 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod backend;
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use uuid::Uuid;
+use wildland_corex::dfs::interface::{
+    DfsEventReceiver,
+    DfsFrontend,
+    DfsFrontendError,
+    FileHandle,
+    NodeDescriptor,
+    Stat,
+};
+use wildland_corex::{PathResolver, Storage, SymmetricKey};
+
+use self::backend::EncryptedStorageBackendFactory;
+use crate::unencrypted::{
+    ConflictResolutionStrategy,
+    ExecutionPolicy,
+    StorageBackendFactory,
+    UnencryptedDfs,
+};
+
+/// Source of keys used by [`EncryptedDfs`].
+#[mockall::automock]
+pub trait EncryptionKeyProvider {
+    /// Returns key of the container the given storage belongs to. All storages (replicas) of a
+    /// container must get the same key.
+    fn container_key(&self, storage: &Storage) -> Result<SymmetricKey, anyhow::Error>;
+}
+
+/// DFS encrypting names and content of files before they reach any [`crate::storage_backend::StorageBackend`].
+pub struct EncryptedDfs {
+    inner: UnencryptedDfs,
+}
+
+impl EncryptedDfs {
+    pub fn new(
+        path_resolver: Rc<dyn PathResolver>,
+        storage_backend_factories: HashMap<String, Box<dyn StorageBackendFactory>>,
+        key_provider: Rc<dyn EncryptionKeyProvider>,
+    ) -> Self {
+        let storage_backend_factories = storage_backend_factories
+            .into_iter()
+            .map(|(backend_type, factory)| {
+                let encrypted_factory: Box<dyn StorageBackendFactory> =
+                    Box::new(EncryptedStorageBackendFactory {
+                        inner: factory,
+                        key_provider: key_provider.clone(),
+                    });
+                (backend_type, encrypted_factory)
+            })
+            .collect();
+        Self {
+            inner: UnencryptedDfs::new(path_resolver, storage_backend_factories),
+        }
+    }
+
+    /// See [`UnencryptedDfs::set_execution_policy`].
+    pub fn set_execution_policy(&mut self, policy: ExecutionPolicy) {
+        self.inner.set_execution_policy(policy)
+    }
+
+    /// See [`UnencryptedDfs::set_container_execution_policy`].
+    pub fn set_container_execution_policy(
+        &mut self,
+        container_uuid: Uuid,
+        policy: Option<ExecutionPolicy>,
+    ) {
+        self.inner
+            .set_container_execution_policy(container_uuid, policy)
+    }
+
+    /// See [`UnencryptedDfs::set_conflict_resolution_strategy`].
+    pub fn set_conflict_resolution_strategy(&mut self, strategy: ConflictResolutionStrategy) {
+        self.inner.set_conflict_resolution_strategy(strategy)
+    }
+}
+
+impl DfsFrontend for EncryptedDfs {
+    fn readdir(&mut self, path: String) -> Vec<NodeDescriptor> {
+        self.inner.readdir(path)
+    }
+
+    fn getattr(&mut self, path: String) -> Result<Stat, DfsFrontendError> {
+        self.inner.getattr(path)
+    }
+
+    fn open(&mut self, path: String) -> Result<FileHandle, DfsFrontendError> {
+        self.inner.open(path)
+    }
+
+    fn close(&mut self, file: &FileHandle) -> Result<(), DfsFrontendError> {
+        self.inner.close(file)
+    }
+
+    fn read(
+        &mut self,
+        file: &FileHandle,
+        offset: u64,
+        count: usize,
+    ) -> Result<Vec<u8>, DfsFrontendError> {
+        self.inner.read(file, offset, count)
+    }
+
+    fn write(
+        &mut self,
+        file: &FileHandle,
+        offset: u64,
+        buf: Vec<u8>,
+    ) -> Result<usize, DfsFrontendError> {
+        self.inner.write(file, offset, buf)
+    }
+
+    fn create(&mut self, path: String) -> Result<FileHandle, DfsFrontendError> {
+        self.inner.create(path)
+    }
+
+    fn unlink(&mut self, path: String) -> Result<(), DfsFrontendError> {
+        self.inner.unlink(path)
+    }
+
+    fn mkdir(&mut self, path: String) -> Result<(), DfsFrontendError> {
+        self.inner.mkdir(path)
+    }
+
+    fn rmdir(&mut self, path: String) -> Result<(), DfsFrontendError> {
+        self.inner.rmdir(path)
+    }
+
+    fn rename(&mut self, old_path: String, new_path: String) -> Result<(), DfsFrontendError> {
+        self.inner.rename(old_path, new_path)
+    }
+
+    fn truncate(&mut self, path: String, size: u64) -> Result<(), DfsFrontendError> {
+        self.inner.truncate(path, size)
+    }
+
+    fn subscribe_events(&mut self, receiver: Box<dyn DfsEventReceiver>) {
+        self.inner.subscribe_events(receiver)
+    }
+}
@@ -0,0 +1,535 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod template;
+
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use anyhow::anyhow;
+pub use template::InMemoryStorageTemplate;
+use wildland_corex::dfs::interface::{NodeType, Stat, UnixTimestamp};
+use wildland_corex::Storage;
+
+use crate::storage_backend::{content_range, resize_content, StorageBackend, StorageBackendError};
+use crate::unencrypted::StorageBackendFactory;
+
+pub const IN_MEMORY_BACKEND_TYPE: &str = "InMemory";
+
+enum MemoryNode {
+    File {
+        content: Vec<u8>,
+        modification_time: SystemTime,
+    },
+    Dir {
+        modification_time: SystemTime,
+    },
+}
+
+impl MemoryNode {
+    fn new_file() -> Self {
+        Self::File {
+            content: Vec::new(),
+            modification_time: SystemTime::now(),
+        }
+    }
+
+    fn new_dir() -> Self {
+        Self::Dir {
+            modification_time: SystemTime::now(),
+        }
+    }
+
+    fn is_dir(&self) -> bool {
+        matches!(self, Self::Dir { .. })
+    }
+}
+
+/// Filesystem tree kept in RAM. Its content is lost when the last reference is dropped.
+///
+/// It implements [`StorageBackend`] itself, treating paths as absolute ones, so it may be used
+/// directly, e.g. to prepare the content of storages in tests.
+pub struct InMemoryFs {
+    /// Nodes by their normalized absolute paths; the root directory is always present
+    nodes: Mutex<BTreeMap<PathBuf, MemoryNode>>,
+}
+
+impl Default for InMemoryFs {
+    fn default() -> Self {
+        Self {
+            nodes: Mutex::new(BTreeMap::from([(
+                PathBuf::from("/"),
+                MemoryNode::new_dir(),
+            )])),
+        }
+    }
+}
+
+/// Makes path absolute and resolves `.` and `..` components (`..` of the root is the root).
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::from("/");
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+        }
+    }
+    normalized
+}
+
+impl InMemoryFs {
+    /// Creates a directory with all its missing parents.
+    pub fn create_dir_all(&self, path: &Path) -> Result<(), StorageBackendError> {
+        let path = normalize(path);
+        let mut nodes = self.nodes.lock().expect("Poisoned Mutex");
+        for ancestor in path.ancestors().collect::<Vec<_>>().into_iter().rev() {
+            match nodes.get(ancestor) {
+                Some(node) if node.is_dir() => {}
+                Some(_) => return Err(StorageBackendError::NotADirectory),
+                None => {
+                    nodes.insert(ancestor.to_owned(), MemoryNode::new_dir());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Inserts a new node after checking that its parent is an existing directory
+    fn insert_node(&self, path: &Path, node: MemoryNode) -> Result<(), StorageBackendError> {
+        let path = normalize(path);
+        let mut nodes = self.nodes.lock().expect("Poisoned Mutex");
+        if nodes.contains_key(&path) {
+            return Err(StorageBackendError::PathAlreadyExists);
+        }
+        Self::ensure_parent_dir(&nodes, &path)?;
+        nodes.insert(path, node);
+        Ok(())
+    }
+
+    fn ensure_parent_dir(
+        nodes: &BTreeMap<PathBuf, MemoryNode>,
+        path: &Path,
+    ) -> Result<(), StorageBackendError> {
+        match path.parent().map(|parent| nodes.get(parent)) {
+            Some(Some(parent)) if parent.is_dir() => Ok(()),
+            Some(Some(_)) => Err(StorageBackendError::NotADirectory),
+            _ => Err(StorageBackendError::NoSuchPath),
+        }
+    }
+
+    fn children<'a>(
+        nodes: &'a BTreeMap<PathBuf, MemoryNode>,
+        path: &'a Path,
+    ) -> impl Iterator<Item = &'a PathBuf> + 'a {
+        nodes
+            .range(path.to_owned()..)
+            .map(|(node_path, _)| node_path)
+            .take_while(move |node_path| node_path.starts_with(path))
+            .filter(move |node_path| node_path.parent() == Some(path))
+    }
+
+    fn with_file<T>(
+        &self,
+        path: &Path,
+        op: impl FnOnce(&mut Vec<u8>, &mut SystemTime) -> T,
+    ) -> Result<T, StorageBackendError> {
+        match self
+            .nodes
+            .lock()
+            .expect("Poisoned Mutex")
+            .get_mut(&normalize(path))
+        {
+            Some(MemoryNode::File {
+                content,
+                modification_time,
+            }) => Ok(op(content, modification_time)),
+            Some(MemoryNode::Dir { .. }) => Err(StorageBackendError::NotAFile),
+            None => Err(StorageBackendError::NoSuchPath),
+        }
+    }
+}
+
+impl StorageBackend for InMemoryFs {
+    fn readdir(&self, path: &Path) -> Result<Vec<PathBuf>, StorageBackendError> {
+        let path = normalize(path);
+        let nodes = self.nodes.lock().expect("Poisoned Mutex");
+        match nodes.get(&path) {
+            Some(node) if node.is_dir() => Ok(Self::children(&nodes, &path).cloned().collect()),
+            Some(_) => Err(StorageBackendError::NotADirectory),
+            None => Err(StorageBackendError::NoSuchPath),
+        }
+    }
+
+    fn getattr(&self, path: &Path) -> Result<Stat, StorageBackendError> {
+        let nodes = self.nodes.lock().expect("Poisoned Mutex");
+        let (node_type, size, modification_time) = match nodes.get(&normalize(path)) {
+            Some(MemoryNode::File {
+                content,
+                modification_time,
+            }) => (NodeType::File, content.len() as u64, modification_time),
+            Some(MemoryNode::Dir { modification_time }) => (NodeType::Dir, 0, modification_time),
+            None => return Err(StorageBackendError::NoSuchPath),
+        };
+        Ok(Stat {
+            node_type,
+            size,
+            access_time: None,
+            modification_time: Some(UnixTimestamp::from(*modification_time)),
+            change_time: None,
+        })
+    }
+
+    fn read(&self, path: &Path, offset: u64, count: usize) -> Result<Vec<u8>, StorageBackendError> {
+        self.with_file(path, |content, _| {
+            let start =
+                usize::try_from(offset).map_or(content.len(), |start| start.min(content.len()));
+            let end = start.saturating_add(count).min(content.len());
+            content[start..end].to_vec()
+        })
+    }
+
+    fn write(&self, path: &Path, offset: u64, buf: &[u8]) -> Result<usize, StorageBackendError> {
+        self.with_file(path, |content, modification_time| {
+            let range = content_range(offset, buf.len())?;
+            if content.len() < range.end {
+                resize_content(content, range.end as u64)?;
+            }
+            content[range].copy_from_slice(buf);
+            *modification_time = SystemTime::now();
+            Ok(buf.len())
+        })?
+    }
+
+    fn create(&self, path: &Path) -> Result<(), StorageBackendError> {
+        self.insert_node(path, MemoryNode::new_file())
+    }
+
+    fn unlink(&self, path: &Path) -> Result<(), StorageBackendError> {
+        let path = normalize(path);
+        let mut nodes = self.nodes.lock().expect("Poisoned Mutex");
+        match nodes.get(&path) {
+            Some(MemoryNode::File { .. }) => {
+                nodes.remove(&path);
+                Ok(())
+            }
+            Some(MemoryNode::Dir { .. }) => Err(StorageBackendError::NotAFile),
+            None => Err(StorageBackendError::NoSuchPath),
+        }
+    }
+
+    fn mkdir(&self, path: &Path) -> Result<(), StorageBackendError> {
+        self.insert_node(path, MemoryNode::new_dir())
+    }
+
+    fn rmdir(&self, path: &Path) -> Result<(), StorageBackendError> {
+        let path = normalize(path);
+        let mut nodes = self.nodes.lock().expect("Poisoned Mutex");
+        match nodes.get(&path) {
+            Some(MemoryNode::Dir { .. }) if path.parent().is_none() => {
+                Err(anyhow!("Root directory cannot be removed").into())
+            }
+            Some(MemoryNode::Dir { .. }) => {
+                if Self::children(&nodes, &path).next().is_some() {
+                    return Err(StorageBackendError::DirNotEmpty);
+                }
+                nodes.remove(&path);
+                Ok(())
+            }
+            Some(MemoryNode::File { .. }) => Err(StorageBackendError::NotADirectory),
+            None => Err(StorageBackendError::NoSuchPath),
+        }
+    }
+
+    fn rename(&self, old_path: &Path, new_path: &Path) -> Result<(), StorageBackendError> {
+        let old_path = normalize(old_path);
+        let new_path = normalize(new_path);
+        let mut nodes = self.nodes.lock().expect("Poisoned Mutex");
+        if !nodes.contains_key(&old_path) {
+            return Err(StorageBackendError::NoSuchPath);
+        }
+        if old_path == new_path {
+            return Ok(());
+        }
+        if new_path.starts_with(&old_path) {
+            return Err(anyhow!("Cannot move {old_path:?} into itself").into());
+        }
+        if nodes.contains_key(&new_path) {
+            return Err(StorageBackendError::PathAlreadyExists);
+        }
+        Self::ensure_parent_dir(&nodes, &new_path)?;
+
+        // moves the node along with all its descendants
+        let moved_paths: Vec<PathBuf> = nodes
+            .range(old_path.clone()..)
+            .map(|(path, _)| path)
+            .take_while(|path| path.starts_with(&old_path))
+            .cloned()
+            .collect();
+        for path in moved_paths {
+            let node = nodes.remove(&path).expect("Path has just been found");
+            let path_within_node = path
+                .strip_prefix(&old_path)
+                .expect("Path has a known prefix");
+            nodes.insert(new_path.join(path_within_node), node);
+        }
+        Ok(())
+    }
+
+    fn truncate(&self, path: &Path, size: u64) -> Result<(), StorageBackendError> {
+        self.with_file(path, |content, modification_time| {
+            resize_content(content, size)?;
+            *modification_time = SystemTime::now();
+            Ok(())
+        })?
+    }
+}
+
+/// Storage kept in a subdirectory of [`InMemoryFs`]
+pub struct InMemoryStorage {
+    fs: Arc<InMemoryFs>,
+    base_dir: PathBuf,
+}
+
+impl InMemoryStorage {
+    fn to_fs_path(&self, path: &Path) -> PathBuf {
+        // normalizing first keeps `..` components within the base dir
+        self.base_dir
+            .join(normalize(path).strip_prefix("/").expect("Path is absolute"))
+    }
+}
+
+impl StorageBackend for InMemoryStorage {
+    fn readdir(&self, path: &Path) -> Result<Vec<PathBuf>, StorageBackendError> {
+        Ok(self
+            .fs
+            .readdir(&self.to_fs_path(path))?
+            .into_iter()
+            .map(|fs_path| {
+                Path::new("/").join(
+                    fs_path
+                        .strip_prefix(&self.base_dir)
+                        .expect("Children have the same base dir"),
+                )
+            })
+            .collect())
+    }
+
+    fn getattr(&self, path: &Path) -> Result<Stat, StorageBackendError> {
+        self.fs.getattr(&self.to_fs_path(path))
+    }
+
+    fn read(&self, path: &Path, offset: u64, count: usize) -> Result<Vec<u8>, StorageBackendError> {
+        self.fs.read(&self.to_fs_path(path), offset, count)
+    }
+
+    fn write(&self, path: &Path, offset: u64, buf: &[u8]) -> Result<usize, StorageBackendError> {
+        self.fs.write(&self.to_fs_path(path), offset, buf)
+    }
+
+    fn create(&self, path: &Path) -> Result<(), StorageBackendError> {
+        self.fs.create(&self.to_fs_path(path))
+    }
+
+    fn unlink(&self, path: &Path) -> Result<(), StorageBackendError> {
+        self.fs.unlink(&self.to_fs_path(path))
+    }
+
+    fn mkdir(&self, path: &Path) -> Result<(), StorageBackendError> {
+        self.fs.mkdir(&self.to_fs_path(path))
+    }
+
+    fn rmdir(&self, path: &Path) -> Result<(), StorageBackendError> {
+        self.fs.rmdir(&self.to_fs_path(path))
+    }
+
+    fn rename(&self, old_path: &Path, new_path: &Path) -> Result<(), StorageBackendError> {
+        self.fs
+            .rename(&self.to_fs_path(old_path), &self.to_fs_path(new_path))
+    }
+
+    fn truncate(&self, path: &Path, size: u64) -> Result<(), StorageBackendError> {
+        self.fs.truncate(&self.to_fs_path(path), size)
+    }
+}
+
+/// Creates backends of [`IN_MEMORY_BACKEND_TYPE`] storages. All of them share a single
+/// [`InMemoryFs`], each one in its own directory created on demand.
+#[derive(Default)]
+pub struct InMemoryBackendFactory {
+    fs: Arc<InMemoryFs>,
+}
+
+impl InMemoryBackendFactory {
+    pub fn new(fs: Arc<InMemoryFs>) -> Self {
+        Self { fs }
+    }
+}
+
+impl StorageBackendFactory for InMemoryBackendFactory {
+    fn init_backend(&self, storage: Storage) -> Result<Arc<dyn StorageBackend>, anyhow::Error> {
+        let template: InMemoryStorageTemplate = serde_json::from_value(storage.data().clone())?;
+        let base_dir = normalize(Path::new(&template.container_prefix));
+        self.fs.create_dir_all(&base_dir)?;
+        Ok(Arc::new(InMemoryStorage {
+            fs: self.fs.clone(),
+            base_dir,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    fn in_memory_backend(fs: &Arc<InMemoryFs>, container_prefix: &str) -> Arc<dyn StorageBackend> {
+        let storage = Storage::new(
+            None,
+            IN_MEMORY_BACKEND_TYPE.to_owned(),
+            json!({ "container_prefix": container_prefix }),
+        );
+        InMemoryBackendFactory::new(fs.clone())
+            .init_backend(storage)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_file_operations_of_in_memory_backend() {
+        let fs = Arc::new(InMemoryFs::default());
+        let backend = in_memory_backend(&fs, "books");
+
+        backend.mkdir(Path::new("/dir")).unwrap();
+        backend.create(Path::new("/dir/file1")).unwrap();
+        backend
+            .write(Path::new("/dir/file1"), 0, b"hello world")
+            .unwrap();
+        backend.truncate(Path::new("/dir/file1"), 5).unwrap();
+
+        assert_eq!(
+            backend.read(Path::new("/dir/file1"), 1, 100).unwrap(),
+            b"ello".to_vec()
+        );
+        let stat = backend.getattr(Path::new("/dir/file1")).unwrap();
+        assert_eq!(stat.node_type, NodeType::File);
+        assert_eq!(stat.size, 5);
+        assert_eq!(
+            backend.readdir(Path::new("/dir")).unwrap(),
+            vec![PathBuf::from("/dir/file1")]
+        );
+        assert!(matches!(
+            backend.create(Path::new("/dir/file1")),
+            Err(StorageBackendError::PathAlreadyExists)
+        ));
+        assert!(matches!(
+            backend.create(Path::new("/missing/file")),
+            Err(StorageBackendError::NoSuchPath)
+        ));
+        assert!(matches!(
+            backend.rmdir(Path::new("/dir")),
+            Err(StorageBackendError::DirNotEmpty)
+        ));
+        assert!(matches!(
+            backend.unlink(Path::new("/dir")),
+            Err(StorageBackendError::NotAFile)
+        ));
+
+        backend
+            .rename(Path::new("/dir/file1"), Path::new("/file2"))
+            .unwrap();
+        backend.rmdir(Path::new("/dir")).unwrap();
+        backend.unlink(Path::new("/file2")).unwrap();
+
+        assert_eq!(
+            backend.readdir(Path::new("/")).unwrap(),
+            Vec::<PathBuf>::new()
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_writes_fail_without_modifying_file() {
+        let fs = Arc::new(InMemoryFs::default());
+        let backend = in_memory_backend(&fs, "books");
+        backend.create(Path::new("/file")).unwrap();
+        backend.write(Path::new("/file"), 0, b"hello").unwrap();
+
+        assert!(matches!(
+            backend.write(Path::new("/file"), u64::MAX - 1, b"world"),
+            Err(StorageBackendError::Generic(_))
+        ));
+        assert!(matches!(
+            backend.truncate(Path::new("/file"), u64::MAX),
+            Err(StorageBackendError::Generic(_))
+        ));
+        assert_eq!(
+            backend.read(Path::new("/file"), 0, 100).unwrap(),
+            b"hello".to_vec()
+        );
+        assert!(backend
+            .read(Path::new("/file"), u64::MAX, 100)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_renaming_dir_moves_its_content() {
+        let fs = Arc::new(InMemoryFs::default());
+        let backend = in_memory_backend(&fs, "books");
+
+        backend.mkdir(Path::new("/a")).unwrap();
+        backend.mkdir(Path::new("/a/b")).unwrap();
+        backend.create(Path::new("/a/b/file")).unwrap();
+        backend.create(Path::new("/ab")).unwrap();
+
+        backend.rename(Path::new("/a"), Path::new("/c")).unwrap();
+
+        assert_eq!(
+            backend.readdir(Path::new("/")).unwrap(),
+            vec![PathBuf::from("/ab"), PathBuf::from("/c")]
+        );
+        assert_eq!(
+            backend.readdir(Path::new("/c/b")).unwrap(),
+            vec![PathBuf::from("/c/b/file")]
+        );
+        assert!(backend
+            .rename(Path::new("/c"), Path::new("/c/b/d"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_storages_do_not_see_each_other() {
+        let fs = Arc::new(InMemoryFs::default());
+        let backend1 = in_memory_backend(&fs, "container1");
+        let backend2 = in_memory_backend(&fs, "container2");
+
+        backend1.create(Path::new("/file")).unwrap();
+        backend1.create(Path::new("/../../escaped")).unwrap();
+
+        assert_eq!(
+            backend1.readdir(Path::new("/")).unwrap(),
+            vec![PathBuf::from("/escaped"), PathBuf::from("/file")]
+        );
+        assert!(backend2.readdir(Path::new("/")).unwrap().is_empty());
+        assert!(fs.getattr(Path::new("/container1/escaped")).is_ok());
+    }
+}
@@ -0,0 +1,73 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+use wildland_corex::{StorageTemplate, StorageTemplateError, CONTAINER_UUID_PARAM};
+
+use super::IN_MEMORY_BACKEND_TYPE;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InMemoryStorageTemplate {
+    pub container_prefix: String,
+}
+
+impl InMemoryStorageTemplate {
+    pub fn new() -> Self {
+        Self {
+            container_prefix: format!("{{{{ {CONTAINER_UUID_PARAM} }}}}"),
+        }
+    }
+}
+
+impl Default for InMemoryStorageTemplate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TryFrom<InMemoryStorageTemplate> for StorageTemplate {
+    type Error = StorageTemplateError;
+    fn try_from(template: InMemoryStorageTemplate) -> Result<Self, Self::Error> {
+        StorageTemplate::try_new(IN_MEMORY_BACKEND_TYPE, template)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use wildland_corex::StorageTemplate;
+
+    use super::*;
+
+    #[test]
+    fn test_serializing_in_memory_template() {
+        let template: StorageTemplate = InMemoryStorageTemplate::new().try_into().unwrap();
+        let template_uuid = template.uuid();
+
+        let expected_json_form = json!({
+            "uuid": template_uuid,
+            "backend_type": "InMemory",
+            "name": null,
+            "template": {
+                "container_prefix": "{{ CONTAINER_UUID }}"
+            }
+        });
+
+        assert_eq!(expected_json_form, serde_json::to_value(&template).unwrap());
+    }
+}
@@ -16,9 +16,11 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 pub mod encrypted;
+pub mod in_memory;
 pub mod storage_backend;
 pub mod unencrypted;
 
+pub use wildland_corex::dfs::interface::{NodeType, Stat, UnixTimestamp};
 pub use wildland_corex::{
     Storage,
     StorageTemplate,
@@ -15,9 +15,100 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 
-pub trait StorageBackend {
+use anyhow::anyhow;
+use thiserror::Error;
+use wildland_corex::dfs::interface::{DfsFrontendError, Stat};
+
+#[derive(Debug, Error)]
+pub enum StorageBackendError {
+    #[error("No such path")]
+    NoSuchPath,
+    #[error("Path already exists")]
+    PathAlreadyExists,
+    #[error("Not a directory")]
+    NotADirectory,
+    #[error("Not a file")]
+    NotAFile,
+    #[error("Directory is not empty")]
+    DirNotEmpty,
+    #[error("File name is too long")]
+    NameTooLong,
+    #[error(transparent)]
+    Generic(#[from] anyhow::Error),
+}
+
+impl From<std::io::Error> for StorageBackendError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::NotFound => Self::NoSuchPath,
+            std::io::ErrorKind::AlreadyExists => Self::PathAlreadyExists,
+            _ => Self::Generic(e.into()),
+        }
+    }
+}
+
+impl From<StorageBackendError> for DfsFrontendError {
+    fn from(e: StorageBackendError) -> Self {
+        match e {
+            StorageBackendError::NoSuchPath => Self::NoSuchPath,
+            StorageBackendError::PathAlreadyExists => Self::PathAlreadyExists,
+            StorageBackendError::NotADirectory => Self::NotADirectory,
+            StorageBackendError::NotAFile => Self::NotAFile,
+            StorageBackendError::DirNotEmpty => Self::DirNotEmpty,
+            StorageBackendError::NameTooLong => Self::NameTooLong,
+            StorageBackendError::Generic(e) => Self::Generic(e.to_string()),
+        }
+    }
+}
+
+/// Returns range of bytes of an in-memory file content covered by a write of `len` bytes at
+/// `offset`. Fails instead of wrapping around if the range does not fit in the address space.
+pub fn content_range(offset: u64, len: usize) -> Result<Range<usize>, StorageBackendError> {
+    let start = usize::try_from(offset).map_err(|_| anyhow!("Offset {offset} is too large"))?;
+    let end = start
+        .checked_add(len)
+        .ok_or_else(|| anyhow!("Write of {len} bytes at offset {offset} is too large"))?;
+    Ok(start..end)
+}
+
+/// Resizes an in-memory file content to `size` bytes, filling it with zeros if it grows.
+///
+/// Fails if the content cannot be allocated instead of aborting the process.
+pub fn resize_content(content: &mut Vec<u8>, size: u64) -> Result<(), StorageBackendError> {
+    let size = usize::try_from(size).map_err(|_| anyhow!("Size {size} is too large"))?;
+    if size > content.len() {
+        content
+            .try_reserve_exact(size - content.len())
+            .map_err(|e| anyhow!("Cannot resize file to {size} bytes: {e}"))?;
+    }
+    content.resize(size, 0);
+    Ok(())
+}
+
+/// Operations which have to be provided by every type of storage supported by DFS.
+///
+/// All paths are paths within a storage, so they are relative to the storage root even if they
+/// start with `/`.
+///
+/// Backends may be used by multiple threads at once, e.g. when an operation is executed on all
+/// replicas of a container concurrently.
+pub trait StorageBackend: Send + Sync {
     /// Returns list of files descriptors, which for now is (Storage, path within Storage) pair.
-    fn readdir(&self, path: &Path) -> Result<Vec<PathBuf>, anyhow::Error>;
+    fn readdir(&self, path: &Path) -> Result<Vec<PathBuf>, StorageBackendError>;
+    fn getattr(&self, path: &Path) -> Result<Stat, StorageBackendError>;
+    /// Reads up to `count` bytes of a file starting at `offset`.
+    fn read(&self, path: &Path, offset: u64, count: usize) -> Result<Vec<u8>, StorageBackendError>;
+    /// Writes `buf` at `offset` of an existing file and returns number of written bytes.
+    fn write(&self, path: &Path, offset: u64, buf: &[u8]) -> Result<usize, StorageBackendError>;
+    /// Creates a new empty file. Fails if the path already exists.
+    fn create(&self, path: &Path) -> Result<(), StorageBackendError>;
+    fn unlink(&self, path: &Path) -> Result<(), StorageBackendError>;
+    fn mkdir(&self, path: &Path) -> Result<(), StorageBackendError>;
+    /// Removes an empty directory.
+    fn rmdir(&self, path: &Path) -> Result<(), StorageBackendError>;
+    fn rename(&self, old_path: &Path, new_path: &Path) -> Result<(), StorageBackendError>;
+    fn truncate(&self, path: &Path, size: u64) -> Result<(), StorageBackendError>;
 }
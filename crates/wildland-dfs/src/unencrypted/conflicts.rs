@@ -0,0 +1,235 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use wildland_corex::dfs::interface::{ConflictStatus, NodeDescriptor, NodeStorage};
+use wildland_corex::{PathResolver, Storage};
+
+/// Number of characters of a storage uuid used as a tag distinguishing conflicting nodes
+const STORAGE_TAG_LENGTH: usize = 8;
+
+/// Describes how nodes of different containers claiming the same full path are presented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictResolutionStrategy {
+    /// Conflicting files keep their paths and are only flagged with [`ConflictStatus::Unresolved`]
+    KeepPaths,
+    /// Beginning of the storage uuid is added to names of conflicting files, e.g. `file.txt`
+    /// becomes `file (0a1b2c3d).txt`.
+    #[default]
+    StorageTagSuffix,
+    /// Name of the container is added to names of conflicting files, e.g. `file.txt` becomes
+    /// `file (Photos).txt`. Storage tag is used instead if container names are not unique.
+    ContainerNameSuffix,
+}
+
+/// Returns beginning of the storage uuid, which distinguishes nodes of the storage from the
+/// conflicting ones.
+pub(crate) fn storage_tag(storage: &Storage) -> String {
+    let mut tag = storage.uuid().simple().to_string();
+    tag.truncate(STORAGE_TAG_LENGTH);
+    tag
+}
+
+/// Returns the file name with the tag inserted before its extension.
+fn with_suffix(path: &Path, tag: &str) -> PathBuf {
+    let mut file_name = OsString::new();
+    file_name.push(path.file_stem().unwrap_or_default());
+    file_name.push(format!(" ({tag})"));
+    if let Some(extension) = path.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+    path.with_file_name(file_name)
+}
+
+/// Reverses [`with_suffix`]. Returns every pair of an original path and a tag which the given
+/// path could be made of, as both file names and tags may contain parentheses.
+pub(crate) fn without_suffix(path: &Path) -> Vec<(PathBuf, String)> {
+    let file_name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(file_name) => file_name,
+        None => return Vec::new(),
+    };
+    let mut candidates = Vec::new();
+    for (tag_start, _) in file_name.match_indices(" (") {
+        let suffix = &file_name[tag_start + 2..];
+        for (tag_end, _) in suffix.match_indices(')') {
+            let tag = &suffix[..tag_end];
+            let extension = &suffix[tag_end + 1..];
+            if tag_start == 0 || tag.is_empty() {
+                continue;
+            }
+            let original_path =
+                path.with_file_name(format!("{}{extension}", &file_name[..tag_start]));
+            if with_suffix(&original_path, tag) == path {
+                candidates.push((original_path, tag.to_owned()));
+            }
+        }
+    }
+    candidates
+}
+
+/// Detects nodes sharing their absolute paths and makes them distinguishable according to the
+/// strategy.
+///
+/// Directories sharing a path are merged into a single node, as listing the path presents the
+/// contents of all of them. Virtual nodes are directories as well. Only files are renamed, so
+/// every directory stays accessible by its path. The result depends only on the nodes, not on
+/// their order, so a renamed node gets the same path in every listing.
+pub(crate) fn resolve_conflicts(
+    nodes: Vec<NodeDescriptor>,
+    strategy: ConflictResolutionStrategy,
+    path_resolver: &dyn PathResolver,
+    mut is_dir: impl FnMut(&NodeStorage) -> bool,
+) -> Vec<NodeDescriptor> {
+    let mut occurrences: HashMap<PathBuf, usize> = HashMap::new();
+    for node in &nodes {
+        *occurrences.entry(node.absolute_path.clone()).or_default() += 1;
+    }
+
+    // positions of directories representing all directories sharing their paths
+    let mut merged_dirs: HashMap<PathBuf, usize> = HashMap::new();
+    let mut resolved: Vec<NodeDescriptor> = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        let node_is_dir = match &node.storage {
+            _ if occurrences[&node.absolute_path] == 1 => false,
+            Some(node_storage) => is_dir(node_storage),
+            None => true,
+        };
+        if !node_is_dir {
+            resolved.push(node);
+            continue;
+        }
+        match merged_dirs.get(&node.absolute_path) {
+            Some(&position) => {
+                // stored directories take precedence over virtual ones, like in path resolution
+                let storage_uuid = |node: &NodeDescriptor| {
+                    node.storage
+                        .as_ref()
+                        .map(|node_storage| node_storage.storage().uuid())
+                };
+                let merged = storage_uuid(&resolved[position]);
+                let candidate = storage_uuid(&node);
+                if candidate.is_some() && (merged.is_none() || candidate < merged) {
+                    resolved[position] = node;
+                }
+            }
+            None => {
+                merged_dirs.insert(node.absolute_path.clone(), resolved.len());
+                resolved.push(node);
+            }
+        }
+    }
+    let is_conflicting_file = |position: usize, node: &NodeDescriptor| {
+        occurrences[&node.absolute_path] > 1
+            && merged_dirs.get(&node.absolute_path) != Some(&position)
+    };
+
+    let storage_tag = |node: &NodeDescriptor| {
+        node.storage
+            .as_ref()
+            .map(|node_storage| storage_tag(node_storage.storage()))
+    };
+    let container_name = |node: &NodeDescriptor| {
+        node.storage.as_ref().and_then(|node_storage| {
+            path_resolver.container_name_by_storage(&node_storage.storage().uuid())
+        })
+    };
+
+    // names are used only if they are unique among nodes conflicting with each other
+    let mut container_names: HashMap<(PathBuf, String), usize> = HashMap::new();
+    if strategy == ConflictResolutionStrategy::ContainerNameSuffix {
+        for (_, node) in resolved
+            .iter()
+            .enumerate()
+            .filter(|(position, node)| is_conflicting_file(*position, node))
+        {
+            if let Some(name) = container_name(node) {
+                *container_names
+                    .entry((node.absolute_path.clone(), name))
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    let conflicting_files: Vec<usize> = resolved
+        .iter()
+        .enumerate()
+        .filter(|(position, node)| is_conflicting_file(*position, node))
+        .map(|(position, _)| position)
+        .collect();
+    for position in conflicting_files {
+        let node = &mut resolved[position];
+        let tag = match strategy {
+            ConflictResolutionStrategy::KeepPaths => None,
+            ConflictResolutionStrategy::StorageTagSuffix => storage_tag(node),
+            ConflictResolutionStrategy::ContainerNameSuffix => container_name(node)
+                .filter(|name| container_names[&(node.absolute_path.clone(), name.clone())] == 1)
+                .or_else(|| storage_tag(node)),
+        };
+        match tag {
+            Some(tag) => {
+                node.absolute_path = with_suffix(&node.absolute_path, &tag);
+                node.conflict_status = ConflictStatus::Renamed;
+            }
+            None => node.conflict_status = ConflictStatus::Unresolved,
+        }
+    }
+
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("/a/file.txt", "/a/file (tag).txt")]
+    #[case("/a/archive.tar.gz", "/a/archive.tar (tag).gz")]
+    #[case("/a/dir", "/a/dir (tag)")]
+    #[case("/a/.hidden", "/a/.hidden (tag)")]
+    fn test_adding_suffix(#[case] path: &str, #[case] expected: &str) {
+        assert_eq!(with_suffix(Path::new(path), "tag"), PathBuf::from(expected));
+    }
+
+    #[rstest]
+    #[case("/a/file (tag).txt", vec![("/a/file.txt", "tag")])]
+    #[case("/a/archive.tar (tag).gz", vec![("/a/archive.tar.gz", "tag")])]
+    #[case("/a/dir (tag)", vec![("/a/dir", "tag")])]
+    #[case(
+        "/a/notes (Work (old))",
+        vec![("/a/notes", "Work (old)"), ("/a/notes (Work", "old)")]
+    )]
+    #[case("/a/f (x) (y)", vec![("/a/f", "x) (y"), ("/a/f (x)", "y")])]
+    #[case("/a/file.txt", vec![])]
+    #[case("/a/file (tag).tar.gz", vec![])]
+    #[case("/a/ (tag)", vec![])]
+    fn test_removing_suffix(#[case] path: &str, #[case] expected: Vec<(&str, &str)>) {
+        let expected: Vec<(PathBuf, String)> = expected
+            .into_iter()
+            .map(|(path, tag)| (PathBuf::from(path), tag.to_owned()))
+            .collect();
+        assert_eq!(without_suffix(Path::new(path)), expected);
+    }
+}
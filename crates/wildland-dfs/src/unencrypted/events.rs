@@ -0,0 +1,165 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use uuid::Uuid;
+use wildland_corex::dfs::interface::{DfsEvent, DfsEventReceiver};
+use wildland_corex::Storage;
+
+use crate::storage_backend::StorageBackendError;
+
+enum Dispatch {
+    Subscribe(Box<dyn DfsEventReceiver>),
+    Event(DfsEvent),
+    /// Acknowledges that all earlier events have been delivered
+    #[cfg(test)]
+    Flush(mpsc::Sender<()>),
+}
+
+/// Delivers [`DfsEvent`]s to the receivers subscribed by the application and keeps track of failing
+/// storages, so a degraded storage is reported once instead of on every operation.
+///
+/// Events are delivered in order by a dedicated thread, so receivers are not called with any lock
+/// held, neither by the emitter nor by the caller of DFS, and they may use DFS themselves.
+#[derive(Clone)]
+pub(crate) struct EventEmitter {
+    dispatcher: mpsc::Sender<Dispatch>,
+    failed_storages: Arc<Mutex<HashSet<Uuid>>>,
+}
+
+impl Default for EventEmitter {
+    fn default() -> Self {
+        let (dispatcher, dispatches) = mpsc::channel();
+        // the thread exits once all clones of the emitter are dropped
+        thread::spawn(move || {
+            let mut receivers: Vec<Box<dyn DfsEventReceiver>> = Vec::new();
+            for dispatch in dispatches {
+                match dispatch {
+                    Dispatch::Subscribe(receiver) => receivers.push(receiver),
+                    Dispatch::Event(event) => {
+                        for receiver in &receivers {
+                            receiver.receive_event(event.clone());
+                        }
+                    }
+                    #[cfg(test)]
+                    Dispatch::Flush(ack) => {
+                        let _ = ack.send(());
+                    }
+                }
+            }
+        });
+        Self {
+            dispatcher,
+            failed_storages: Arc::default(),
+        }
+    }
+}
+
+impl EventEmitter {
+    pub(crate) fn subscribe(&self, receiver: Box<dyn DfsEventReceiver>) {
+        self.dispatch(Dispatch::Subscribe(receiver));
+    }
+
+    fn emit(&self, event: DfsEvent) {
+        self.dispatch(Dispatch::Event(event));
+    }
+
+    fn dispatch(&self, dispatch: Dispatch) {
+        if self.dispatcher.send(dispatch).is_err() {
+            tracing::error!("Events dispatching thread is gone");
+        }
+    }
+
+    /// Waits until all events emitted so far are delivered.
+    #[cfg(test)]
+    pub(crate) fn flush(&self) {
+        let (ack, acked) = mpsc::channel();
+        self.dispatch(Dispatch::Flush(ack));
+        acked.recv().expect("Events dispatching thread is gone");
+    }
+
+    /// Returns true if the storage has not been marked as failed before.
+    fn mark_failed(&self, storage: &Storage) -> bool {
+        self.failed_storages
+            .lock()
+            .expect("Poisoned Mutex")
+            .insert(storage.uuid())
+    }
+
+    pub(crate) fn backend_unsupported(&self, storage: &Storage) {
+        if self.mark_failed(storage) {
+            self.emit(DfsEvent::BackendUnsupported {
+                storage_uuid: storage.uuid(),
+                backend_type: storage.backend_type().to_owned(),
+            });
+        }
+    }
+
+    /// Reports the result of an operation executed by the storage backend. Only generic errors mean
+    /// that a storage does not work; the other ones are regular answers, like a missing path.
+    pub(crate) fn replica_responded<T>(
+        &self,
+        storage: &Storage,
+        result: &Result<T, StorageBackendError>,
+    ) {
+        match result {
+            Err(StorageBackendError::Generic(e)) => {
+                if self.mark_failed(storage) {
+                    self.emit(DfsEvent::ReplicaFailed {
+                        storage_uuid: storage.uuid(),
+                        backend_type: storage.backend_type().to_owned(),
+                        reason: e.to_string(),
+                    });
+                }
+            }
+            _ => {
+                let recovered = self
+                    .failed_storages
+                    .lock()
+                    .expect("Poisoned Mutex")
+                    .remove(&storage.uuid());
+                if recovered {
+                    self.emit(DfsEvent::StorageRecovered {
+                        storage_uuid: storage.uuid(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Reports an operation which failed, emitting [`DfsEvent::AllReplicasFailed`] if none of the
+    /// storages works.
+    pub(crate) fn operation_failed(&self, storages: &[Storage]) {
+        if storages.is_empty() {
+            return;
+        }
+        let all_failed = {
+            let failed_storages = self.failed_storages.lock().expect("Poisoned Mutex");
+            storages
+                .iter()
+                .all(|storage| failed_storages.contains(&storage.uuid()))
+        };
+        if all_failed {
+            self.emit(DfsEvent::AllReplicasFailed {
+                storage_uuids: storages.iter().map(Storage::uuid).collect(),
+            });
+        }
+    }
+}
@@ -0,0 +1,376 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use uuid::Uuid;
+use wildland_corex::dfs::interface::{DfsFrontendError, Stat};
+use wildland_corex::Storage;
+
+use super::events::EventEmitter;
+use crate::storage_backend::{StorageBackend, StorageBackendError};
+
+/// Latency recorded for a replica which failed to execute an operation due to a storage failure, so
+/// it is tried last by [`ExecutionPolicy::LowestLatencyFirst`] until it proves to work again.
+const FAILED_OPERATION_LATENCY: Duration = Duration::from_secs(30);
+
+/// Number of operations started on a replica by concurrent policies which may wait for its answer
+/// at once. Further operations skip the replica until some of them finish, so threads waiting for
+/// an unresponsive replica do not pile up.
+pub(crate) const MAX_PENDING_OPERATIONS: usize = 4;
+
+/// Describes how an operation is executed on replicas, i.e. on all storages of a container.
+///
+/// Concurrent policies are used for reading only. Modifications have to reach every replica in
+/// the order they are made, so they are never executed concurrently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecutionPolicy {
+    /// Replicas are tried one by one in the order of storages until the first success.
+    #[default]
+    SequentiallyToFirstSuccess,
+    /// Operation is started on all replicas at once and the first success is returned without
+    /// waiting for the remaining replicas. Modifications are executed as with
+    /// [`ExecutionPolicy::SequentiallyToFirstSuccess`].
+    ConcurrentlyToFirstSuccess,
+    /// Replicas are tried one by one, starting from the one with the lowest recent latency. Replicas
+    /// not used so far are tried before the others.
+    LowestLatencyFirst,
+    /// Operation is started on all replicas at once and succeeds when the given number of replicas
+    /// return agreeing results. The number has to be positive. Modifications are executed on
+    /// replicas one by one in the order of storages.
+    Quorum(usize),
+}
+
+/// Whether an operation modifies replicas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OperationKind {
+    Read,
+    Modification,
+}
+
+/// Result of an operation which may be compared with results returned by other replicas.
+pub(crate) trait ReplicaResult: Send + 'static {
+    fn agrees_with(&self, other: &Self) -> bool;
+}
+
+impl ReplicaResult for () {
+    fn agrees_with(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl ReplicaResult for usize {
+    fn agrees_with(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl ReplicaResult for Vec<u8> {
+    fn agrees_with(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl ReplicaResult for Vec<PathBuf> {
+    fn agrees_with(&self, other: &Self) -> bool {
+        let mut paths = self.clone();
+        let mut other_paths = other.clone();
+        paths.sort();
+        other_paths.sort();
+        paths == other_paths
+    }
+}
+
+impl ReplicaResult for Stat {
+    /// Timestamps are set independently by each replica, so they are not compared.
+    fn agrees_with(&self, other: &Self) -> bool {
+        self.node_type == other.node_type && self.size == other.size
+    }
+}
+
+pub(crate) type ReplicaOperation<T> =
+    Arc<dyn Fn(&dyn StorageBackend) -> Result<T, StorageBackendError> + Send + Sync>;
+
+/// Moving average of recent latencies of storages.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ReplicaLatencies(Arc<Mutex<HashMap<Uuid, Duration>>>);
+
+impl ReplicaLatencies {
+    fn record(&self, storage: &Storage, latency: Duration) {
+        let mut latencies = self.0.lock().expect("Poisoned Mutex");
+        latencies
+            .entry(storage.uuid())
+            .and_modify(|average| *average = (*average * 3 + latency) / 4)
+            .or_insert(latency);
+    }
+
+    fn get(&self, storage: &Storage) -> Option<Duration> {
+        self.0
+            .lock()
+            .expect("Poisoned Mutex")
+            .get(&storage.uuid())
+            .copied()
+    }
+}
+
+/// Numbers of operations started on storages by concurrent policies, which have not finished yet.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PendingOperations(Arc<Mutex<HashMap<Uuid, usize>>>);
+
+impl PendingOperations {
+    /// Returns `false` if there are too many pending operations on the storage already.
+    fn start(&self, storage: &Storage) -> bool {
+        let mut pending = self.0.lock().expect("Poisoned Mutex");
+        let count = pending.entry(storage.uuid()).or_default();
+        if *count >= MAX_PENDING_OPERATIONS {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+
+    fn finish(&self, storage: &Storage) {
+        let mut pending = self.0.lock().expect("Poisoned Mutex");
+        if let Entry::Occupied(mut count) = pending.entry(storage.uuid()) {
+            *count.get_mut() -= 1;
+            if *count.get() == 0 {
+                count.remove();
+            }
+        }
+    }
+}
+
+/// Backend of a storage along with the storage itself.
+pub(crate) type Replica = (Arc<dyn StorageBackend>, Storage);
+
+/// Executes the operation on a single replica recording its latency and reporting its failures.
+fn execute_on_replica<T>(
+    (backend, storage): &Replica,
+    op: &ReplicaOperation<T>,
+    latencies: &ReplicaLatencies,
+    events: &EventEmitter,
+) -> Result<T, StorageBackendError> {
+    let start = Instant::now();
+    let result = op(backend.as_ref());
+    // other errors are answers of a working storage, e.g. about a missing file
+    let latency = match result {
+        Err(StorageBackendError::Generic(_)) => start.elapsed().max(FAILED_OPERATION_LATENCY),
+        _ => start.elapsed(),
+    };
+    latencies.record(storage, latency);
+    events.replica_responded(storage, &result);
+    if let Err(e) = &result {
+        tracing::error!(
+            "Backend of storage {} returned error for operation: {e}",
+            storage.uuid()
+        );
+    }
+    result
+}
+
+/// Starts the operation on every replica in a separate thread. Results are sent through the
+/// returned channel as they come. Threads are detached, so unresponsive replicas do not block the
+/// caller once it has got the results it needs. Replicas with too many pending operations are
+/// skipped and answer with an error right away.
+fn spawn_on_replicas<T: ReplicaResult>(
+    replicas: Vec<Replica>,
+    op: &ReplicaOperation<T>,
+    context: &ExecutionContext,
+) -> mpsc::Receiver<(Storage, Result<T, StorageBackendError>)> {
+    let (sender, receiver) = mpsc::channel();
+    for replica in replicas {
+        if !context.pending.start(&replica.1) {
+            let error = anyhow!(
+                "Too many pending operations on storage {}",
+                replica.1.uuid()
+            );
+            let _ = sender.send((replica.1, Err(error.into())));
+            continue;
+        }
+        let sender = sender.clone();
+        let op = op.clone();
+        let context = context.clone();
+        std::thread::spawn(move || {
+            let result = execute_on_replica(&replica, &op, &context.latencies, &context.events);
+            context.pending.finish(&replica.1);
+            // the receiver is gone if the result is not needed anymore
+            let _ = sender.send((replica.1, result));
+        });
+    }
+    receiver
+}
+
+/// State shared by executions of operations on replicas.
+#[derive(Clone, Default)]
+pub(crate) struct ExecutionContext {
+    pub latencies: ReplicaLatencies,
+    pub pending: PendingOperations,
+    pub events: EventEmitter,
+}
+
+/// Collects results of replicas until the given number of them agree.
+struct QuorumCounter<T> {
+    quorum: usize,
+    remaining: usize,
+    /// groups of agreeing results along with the number of replicas which returned them
+    answers: Vec<(Storage, T, usize)>,
+    last_error: Option<StorageBackendError>,
+}
+
+impl<T: ReplicaResult> QuorumCounter<T> {
+    fn new(quorum: usize, replicas_count: usize) -> Self {
+        Self {
+            quorum,
+            remaining: replicas_count,
+            answers: Vec::new(),
+            last_error: None,
+        }
+    }
+
+    /// Returns the agreed result if the quorum is reached, `Err` if it cannot be reached anymore
+    /// and `Ok(None)` if more results are needed.
+    fn add(
+        &mut self,
+        storage: Storage,
+        result: Result<T, StorageBackendError>,
+    ) -> Result<Option<(Storage, T)>, DfsFrontendError> {
+        self.remaining -= 1;
+        match result {
+            Ok(value) => {
+                match self
+                    .answers
+                    .iter_mut()
+                    .find(|(_, answer, _)| answer.agrees_with(&value))
+                {
+                    Some((_, _, count)) => *count += 1,
+                    None => self.answers.push((storage, value, 1)),
+                }
+            }
+            Err(e) => self.last_error = Some(e),
+        }
+        if let Some(index) = self
+            .answers
+            .iter()
+            .position(|(_, _, count)| *count >= self.quorum)
+        {
+            let (storage, value, _) = self.answers.swap_remove(index);
+            return Ok(Some((storage, value)));
+        }
+        let best_count = self.answers.iter().map(|(_, _, count)| *count).max();
+        if best_count.unwrap_or(0) + self.remaining < self.quorum {
+            return Err(self.failure());
+        }
+        Ok(None)
+    }
+
+    fn failure(&mut self) -> DfsFrontendError {
+        // unanimous errors are more meaningful than a general failure
+        match (self.answers.is_empty(), self.last_error.take()) {
+            (true, Some(e)) => e.into(),
+            _ => DfsFrontendError::Generic(format!(
+                "Quorum of {} agreeing replicas not reached",
+                self.quorum
+            )),
+        }
+    }
+}
+
+/// Returns the result along with the storage which provided it.
+pub(crate) fn execute_with_policy<T: ReplicaResult>(
+    mut replicas: Vec<Replica>,
+    op: ReplicaOperation<T>,
+    kind: OperationKind,
+    policy: ExecutionPolicy,
+    context: &ExecutionContext,
+) -> Result<(Storage, T), DfsFrontendError> {
+    if replicas.is_empty() {
+        return Err(DfsFrontendError::StorageNotResponsive);
+    }
+
+    match policy {
+        // there is no point in spawning threads for a single replica
+        ExecutionPolicy::ConcurrentlyToFirstSuccess
+            if kind == OperationKind::Read && replicas.len() > 1 =>
+        {
+            let mut last_error = None;
+            for (storage, result) in spawn_on_replicas(replicas, &op, context) {
+                match result {
+                    Ok(value) => return Ok((storage, value)),
+                    Err(e) => last_error = Some(e),
+                }
+            }
+            Err(last_error
+                .map(DfsFrontendError::from)
+                .unwrap_or(DfsFrontendError::StorageNotResponsive))
+        }
+        ExecutionPolicy::Quorum(0) => Err(DfsFrontendError::Generic(
+            "Quorum has to consist of at least one replica".to_owned(),
+        )),
+        ExecutionPolicy::Quorum(quorum) if quorum > replicas.len() => {
+            Err(DfsFrontendError::Generic(format!(
+                "Quorum of {quorum} replicas cannot be reached with {} storages",
+                replicas.len()
+            )))
+        }
+        ExecutionPolicy::Quorum(quorum) if kind == OperationKind::Read => {
+            let mut counter = QuorumCounter::new(quorum, replicas.len());
+            for (storage, result) in spawn_on_replicas(replicas, &op, context) {
+                if let Some(agreed) = counter.add(storage, result)? {
+                    return Ok(agreed);
+                }
+            }
+            Err(counter.failure())
+        }
+        // every replica is modified, even if the quorum is reached earlier, so that they do not
+        // diverge
+        ExecutionPolicy::Quorum(quorum) => {
+            let mut counter = QuorumCounter::new(quorum, replicas.len());
+            let mut outcome = Ok(None);
+            for replica in &replicas {
+                let result = execute_on_replica(replica, &op, &context.latencies, &context.events);
+                if let Ok(None) = outcome {
+                    outcome = counter.add(replica.1.clone(), result);
+                }
+            }
+            outcome?.ok_or_else(|| counter.failure())
+        }
+        ExecutionPolicy::SequentiallyToFirstSuccess
+        | ExecutionPolicy::ConcurrentlyToFirstSuccess
+        | ExecutionPolicy::LowestLatencyFirst => {
+            if policy == ExecutionPolicy::LowestLatencyFirst {
+                // sort is stable, so replicas with equal latencies keep the order of storages
+                replicas
+                    .sort_by_key(|(_, storage)| context.latencies.get(storage).unwrap_or_default());
+            }
+            let mut last_error = None;
+            for replica in &replicas {
+                match execute_on_replica(replica, &op, &context.latencies, &context.events) {
+                    Ok(value) => return Ok((replica.1.clone(), value)),
+                    Err(e) => last_error = Some(e),
+                }
+            }
+            Err(last_error
+                .map(DfsFrontendError::from)
+                .unwrap_or(DfsFrontendError::StorageNotResponsive))
+        }
+    }
+}
@@ -15,24 +15,48 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod conflicts;
+mod events;
+mod execution_policy;
 #[cfg(test)]
 mod tests;
 
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::Arc;
 
+pub use conflicts::ConflictResolutionStrategy;
+use conflicts::{resolve_conflicts, storage_tag, without_suffix};
+pub use execution_policy::ExecutionPolicy;
+use execution_policy::{
+    execute_with_policy,
+    ExecutionContext,
+    OperationKind,
+    Replica,
+    ReplicaResult,
+};
 use itertools::Either;
 use uuid::Uuid;
-use wildland_corex::dfs::interface::{DfsFrontend, NodeDescriptor, NodeStorage};
+use wildland_corex::dfs::interface::{
+    ConflictStatus,
+    DfsEventReceiver,
+    DfsFrontend,
+    DfsFrontendError,
+    FileHandle,
+    NodeDescriptor,
+    NodeStorage,
+    NodeType,
+    Stat,
+};
 use wildland_corex::{PathResolver, ResolvedPath, Storage};
 
-use crate::storage_backend::StorageBackend;
+use crate::storage_backend::{StorageBackend, StorageBackendError};
 
 pub trait StorageBackendFactory {
-    fn init_backend(&self, storage: Storage) -> Result<Rc<dyn StorageBackend>, anyhow::Error>;
+    fn init_backend(&self, storage: Storage) -> Result<Arc<dyn StorageBackend>, anyhow::Error>;
 }
 
 pub struct UnencryptedDfs {
@@ -44,7 +68,34 @@ pub struct UnencryptedDfs {
     /// It is up to StorageBackend and StorageBackendFactory implementation whether all backends of a
     /// given type reuse some connector/client (factory could initiate each backend with some shared
     /// reference).
-    storage_backends: HashMap<Uuid, Rc<dyn StorageBackend>>,
+    storage_backends: HashMap<Uuid, Arc<dyn StorageBackend>>,
+    opened_files: HashMap<FileHandle, OpenedFile>,
+    last_file_descriptor: u64,
+    execution_policy: ExecutionPolicy,
+    /// Policies overriding the default one for particular containers
+    container_execution_policies: HashMap<Uuid, ExecutionPolicy>,
+    /// Latencies and pending operations of replicas along with the emitter of events about them
+    execution_context: ExecutionContext,
+    conflict_resolution_strategy: ConflictResolutionStrategy,
+}
+
+#[derive(Clone)]
+struct OpenedFile {
+    path_within_storage: PathBuf,
+    storages: Vec<Storage>,
+}
+
+/// Node found in one of the containers claiming a path
+struct StoredNode {
+    path_within_storage: PathBuf,
+    storages: Vec<Storage>,
+    stat: Stat,
+}
+
+enum NodeLocation {
+    Stored(StoredNode),
+    /// Node not backed by any storage, like a parent directory of a path claimed by a container
+    Virtual,
 }
 
 impl UnencryptedDfs {
@@ -56,9 +107,55 @@ impl UnencryptedDfs {
             path_resolver,
             storage_backend_factories,
             storage_backends: HashMap::new(),
+            opened_files: HashMap::new(),
+            last_file_descriptor: 0,
+            execution_policy: ExecutionPolicy::default(),
+            container_execution_policies: HashMap::new(),
+            execution_context: ExecutionContext::default(),
+            conflict_resolution_strategy: ConflictResolutionStrategy::default(),
         }
     }
 
+    /// Sets policy of executing operations on replicas of containers without their own policy.
+    pub fn set_execution_policy(&mut self, policy: ExecutionPolicy) {
+        self.execution_policy = policy;
+    }
+
+    /// Sets policy of executing operations on replicas of the given container. If `None` is given,
+    /// the default policy of DFS is used again.
+    pub fn set_container_execution_policy(
+        &mut self,
+        container_uuid: Uuid,
+        policy: Option<ExecutionPolicy>,
+    ) {
+        match policy {
+            Some(policy) => self
+                .container_execution_policies
+                .insert(container_uuid, policy),
+            None => self.container_execution_policies.remove(&container_uuid),
+        };
+    }
+
+    /// Sets how nodes of different containers claiming the same full path are presented.
+    pub fn set_conflict_resolution_strategy(&mut self, strategy: ConflictResolutionStrategy) {
+        self.conflict_resolution_strategy = strategy;
+    }
+
+    fn execution_policy_for(&self, storages: &[Storage]) -> ExecutionPolicy {
+        if self.container_execution_policies.is_empty() {
+            return self.execution_policy;
+        }
+        storages
+            .first()
+            .and_then(|storage| {
+                self.path_resolver
+                    .container_uuid_by_storage(&storage.uuid())
+            })
+            .and_then(|container_uuid| self.container_execution_policies.get(&container_uuid))
+            .copied()
+            .unwrap_or(self.execution_policy)
+    }
+
     /// Returns StorageBackend for a given Storage.
     ///
     /// If there is no StorageBackend for a given Storage, StorageBackendFactory, related to its
@@ -66,7 +163,7 @@ impl UnencryptedDfs {
     fn get_backend(
         &mut self,
         storage: &Storage,
-    ) -> Result<Rc<dyn StorageBackend>, Box<dyn std::error::Error>> {
+    ) -> Result<Arc<dyn StorageBackend>, Box<dyn std::error::Error>> {
         let backend = match self.storage_backends.entry(storage.uuid()) {
             Entry::Occupied(occupied_entry) => occupied_entry.get().clone(),
             Entry::Vacant(vacant_entry) => {
@@ -88,15 +185,13 @@ impl UnencryptedDfs {
         Ok(backend)
     }
 
-    /// Matches every given Storage with its StorageBackend and return iterator over such pairs.
-    fn assign_backends_to_storages<'a>(
-        &'a mut self,
-        storages: &'a [Storage],
-    ) -> impl Iterator<Item = (Rc<dyn StorageBackend>, &Storage)> + '_ {
-        storages.iter().filter_map(|storage| {
-            match self.get_backend(storage) {
+    /// Matches every given Storage with its StorageBackend skipping storages without a backend.
+    fn assign_backends_to_storages(&mut self, storages: &[Storage]) -> Vec<Replica> {
+        storages
+            .iter()
+            .filter_map(|storage| match self.get_backend(storage) {
                 Err(e) => {
-                    // TODO WILX-363 send alert to the wildland app bypassing DFS Frontend API
+                    self.execution_context.events.backend_unsupported(storage);
                     tracing::error!(
                         "Unsupported storage backend: {}; Reason: {}",
                         storage.backend_type(),
@@ -104,9 +199,229 @@ impl UnencryptedDfs {
                     );
                     None
                 }
-                Ok(backend) => Some((backend, storage)),
+                Ok(backend) => Some((backend, storage.clone())),
+            })
+            .collect()
+    }
+
+    /// Executes reading operation on backends of the given storages (replicas) according to the
+    /// execution policy of their container.
+    fn execute_on_storages<T: ReplicaResult>(
+        &mut self,
+        storages: &[Storage],
+        op: impl Fn(&dyn StorageBackend) -> Result<T, StorageBackendError> + Send + Sync + 'static,
+    ) -> Result<T, DfsFrontendError> {
+        self.execute_on_storages_with_origin(storages, OperationKind::Read, op)
+            .map(|(_storage, value)| value)
+    }
+
+    /// Same as [`UnencryptedDfs::execute_on_storages`] but for operations modifying storages,
+    /// which are never executed on replicas concurrently.
+    fn modify_storages<T: ReplicaResult>(
+        &mut self,
+        storages: &[Storage],
+        op: impl Fn(&dyn StorageBackend) -> Result<T, StorageBackendError> + Send + Sync + 'static,
+    ) -> Result<T, DfsFrontendError> {
+        self.execute_on_storages_with_origin(storages, OperationKind::Modification, op)
+            .map(|(_storage, value)| value)
+    }
+
+    /// Executes operation on backends of the given storages and returns the storage which
+    /// provided the result along with it.
+    fn execute_on_storages_with_origin<T: ReplicaResult>(
+        &mut self,
+        storages: &[Storage],
+        kind: OperationKind,
+        op: impl Fn(&dyn StorageBackend) -> Result<T, StorageBackendError> + Send + Sync + 'static,
+    ) -> Result<(Storage, T), DfsFrontendError> {
+        let policy = self.execution_policy_for(storages);
+        let replicas = self.assign_backends_to_storages(storages);
+
+        // TODO WILX-362 the same policy is used for modifying operations, so with sequential
+        // policies only the first responsive replica is modified. Propagating changes to other
+        // replicas is not a part of DFS.
+        let result = execute_with_policy(
+            replicas,
+            Arc::new(op),
+            kind,
+            policy,
+            &self.execution_context,
+        );
+        if result.is_err() {
+            self.execution_context.events.operation_failed(storages);
+        }
+        result
+    }
+
+    /// Looks for a node in all containers claiming the given path.
+    ///
+    /// Directories take precedence over files, as files sharing their paths with directories are
+    /// renamed by [`DfsFrontend::readdir`]. Nodes found in containers take precedence over virtual
+    /// nodes otherwise.
+    fn find_node(&mut self, path: &Path) -> Result<NodeLocation, DfsFrontendError> {
+        let mut is_virtual = path == Path::new("/");
+        let mut error = DfsFrontendError::NoSuchPath;
+        let mut file = None;
+        for resolved_path in self.path_resolver.resolve(path) {
+            match resolved_path {
+                ResolvedPath::VirtualPath(_) => is_virtual = true,
+                ResolvedPath::PathWithStorages {
+                    path_within_storage,
+                    storages,
+                } => {
+                    let path = path_within_storage.clone();
+                    let result =
+                        self.execute_on_storages(&storages, move |backend| backend.getattr(&path));
+                    match result {
+                        Ok(stat) => {
+                            let node = StoredNode {
+                                path_within_storage,
+                                storages,
+                                stat,
+                            };
+                            if node.stat.node_type == NodeType::Dir {
+                                return Ok(NodeLocation::Stored(node));
+                            }
+                            file = file.or(Some(node));
+                        }
+                        Err(DfsFrontendError::NoSuchPath) => {}
+                        Err(e) => error = e,
+                    }
+                }
             }
-        })
+        }
+
+        if is_virtual {
+            Ok(NodeLocation::Virtual)
+        } else if let Some(file) = file {
+            Ok(NodeLocation::Stored(file))
+        } else if error == DfsFrontendError::NoSuchPath {
+            self.find_renamed_node(path)
+                .map(NodeLocation::Stored)
+                .ok_or(error)
+        } else {
+            Err(error)
+        }
+    }
+
+    /// Looks for a node presented by [`DfsFrontend::readdir`] under a path changed because of
+    /// a conflict with nodes of other containers.
+    fn find_renamed_node(&mut self, path: &Path) -> Option<StoredNode> {
+        if self.conflict_resolution_strategy == ConflictResolutionStrategy::KeepPaths {
+            return None;
+        }
+        without_suffix(path)
+            .into_iter()
+            .find_map(|(original_path, tag)| self.find_tagged_file(&original_path, &tag))
+    }
+
+    /// Looks for a file, conflicting with nodes of other containers, in the container distinguished
+    /// by the tag.
+    fn find_tagged_file(&mut self, path: &Path, tag: &str) -> Option<StoredNode> {
+        let mut tagged_file = None;
+        let mut is_conflicting = false;
+        for resolved_path in self.path_resolver.resolve(path) {
+            match resolved_path {
+                ResolvedPath::VirtualPath(_) => is_conflicting = true,
+                ResolvedPath::PathWithStorages {
+                    path_within_storage,
+                    storages,
+                } => {
+                    let is_tagged = tagged_file.is_none()
+                        && storages.iter().any(|storage| self.is_tagged(storage, tag));
+                    let path = path_within_storage.clone();
+                    match self.execute_on_storages(&storages, move |backend| backend.getattr(&path))
+                    {
+                        Ok(stat) if is_tagged && stat.node_type == NodeType::File => {
+                            tagged_file = Some(StoredNode {
+                                path_within_storage,
+                                storages,
+                                stat,
+                            })
+                        }
+                        Ok(_) => is_conflicting = true,
+                        Err(_) => {}
+                    }
+                }
+            }
+        }
+        tagged_file.filter(|_| is_conflicting)
+    }
+
+    /// Whether the tag added to names of conflicting nodes points to the given storage.
+    fn is_tagged(&self, storage: &Storage, tag: &str) -> bool {
+        storage_tag(storage) == tag
+            || (self.conflict_resolution_strategy
+                == ConflictResolutionStrategy::ContainerNameSuffix
+                && self
+                    .path_resolver
+                    .container_name_by_storage(&storage.uuid())
+                    .as_deref()
+                    == Some(tag))
+    }
+
+    /// Same as [`UnencryptedDfs::find_node`] but fails for virtual nodes, as they can not be modified.
+    fn find_stored_node(&mut self, path: &Path) -> Result<StoredNode, DfsFrontendError> {
+        match self.find_node(path)? {
+            NodeLocation::Stored(node) => Ok(node),
+            NodeLocation::Virtual => Err(DfsFrontendError::ReadOnlyPath),
+        }
+    }
+
+    /// Same as [`UnencryptedDfs::find_stored_node`] but fails for roots of containers, as they are
+    /// bound to paths claimed by containers and can not be removed or moved.
+    fn find_removable_node(&mut self, path: &Path) -> Result<StoredNode, DfsFrontendError> {
+        let node = self.find_stored_node(path)?;
+        if node.path_within_storage == Path::new("/") {
+            return Err(DfsFrontendError::ReadOnlyPath);
+        }
+        Ok(node)
+    }
+
+    /// Chooses storages in which a new node with the given path should be created.
+    ///
+    /// If more than one container claims the path, the one claiming the most specific path (so the
+    /// path within its storages is the shortest one) is chosen.
+    fn find_storages_for_new_node(
+        &self,
+        path: &Path,
+    ) -> Result<(PathBuf, Vec<Storage>), DfsFrontendError> {
+        self.path_resolver
+            .resolve(path)
+            .into_iter()
+            .filter_map(|resolved_path| match resolved_path {
+                ResolvedPath::PathWithStorages {
+                    path_within_storage,
+                    storages,
+                } => Some((path_within_storage, storages)),
+                ResolvedPath::VirtualPath(_) => None,
+            })
+            .min_by_key(|(path_within_storage, _)| path_within_storage.components().count())
+            .ok_or(DfsFrontendError::ReadOnlyPath)
+    }
+
+    fn insert_opened_file(
+        &mut self,
+        path_within_storage: PathBuf,
+        storages: Vec<Storage>,
+    ) -> FileHandle {
+        self.last_file_descriptor += 1;
+        let handle = FileHandle::new(self.last_file_descriptor);
+        self.opened_files.insert(
+            handle,
+            OpenedFile {
+                path_within_storage,
+                storages,
+            },
+        );
+        handle
+    }
+
+    fn get_opened_file(&self, file: &FileHandle) -> Result<OpenedFile, DfsFrontendError> {
+        self.opened_files
+            .get(file)
+            .cloned()
+            .ok_or(DfsFrontendError::InvalidFileHandle)
     }
 }
 
@@ -117,21 +432,23 @@ impl DfsFrontend for UnencryptedDfs {
     /// **NOTE: Conflicting paths**
     /// More than one container may have nodes claiming the same "full path" - meaning
     /// concatenation of a path claimed by a container with a path of the file inside the container.
-    /// It is possible that returned nodes include both such files.
     /// Example:
     /// Container C1 claims path `/a` and includes file `/b/c`.
     /// Container C2 claims path `/a/b/` and includes file `/c`.
-    /// In this case, result includes the following descriptors:
+    /// Full path within the user's forest for both nodes is `/a/b/c`. Such nodes are presented
+    /// according to [`ConflictResolutionStrategy`], by default with storage tags added to their
+    /// names:
     /// [
-    ///     NodeDescriptor { path: "/b/c", storage: <C1 storage>},
-    ///     NodeDescriptor { path: "/c", storage: <C2 storage>},
+    ///     NodeDescriptor { path: "/a/b/c (<C1 storage tag>)", storage: <C1 storage>},
+    ///     NodeDescriptor { path: "/a/b/c (<C2 storage tag>)", storage: <C2 storage>},
     /// ]
-    /// Full path within the user's forest for both nodes is `/a/b/c`. It is up to FS frontend how to
-    /// show it to a user (e.g. by prefixing it with some storage-specific tag).
+    /// Renamed nodes may be accessed by their new paths. Directories are not renamed, instead
+    /// directories sharing a full path are presented as a single one listing contents of all of
+    /// them.
     fn readdir(&mut self, path: String) -> Vec<NodeDescriptor> {
         let path = PathBuf::from_str(&path).unwrap();
         let resolved_paths = self.path_resolver.resolve(path.as_ref());
-        let nodes = resolved_paths
+        let nodes: Vec<NodeDescriptor> = resolved_paths
             .into_iter()
             .filter_map(|resolved_path| {
                 match resolved_path {
@@ -139,43 +456,36 @@ impl DfsFrontend for UnencryptedDfs {
                         Some(Either::Left(std::iter::once(NodeDescriptor {
                             storage: None,
                             absolute_path: path.join(virtual_path.file_name().unwrap()),
+                            conflict_status: ConflictStatus::NoConflict,
                         })))
                     }
                     ResolvedPath::PathWithStorages {
                         path_within_storage,
                         storages,
                     } => {
-                        let backends_with_storages = self.assign_backends_to_storages(&storages);
-
-                        let operations_on_backends =
-                            backends_with_storages.map(|(backend, storage)| {
-                                backend
-                                    .readdir(&path_within_storage)
-                                    .map(|resulting_paths| {
-                                        resulting_paths.into_iter().map({
-                                            let storage = storage.clone();
-                                            let path = path.clone();
-                                            move |entry_path| NodeDescriptor {
-                                                storage: Some(NodeStorage::new(
-                                                    storage.clone(),
-                                                    entry_path.clone(),
-                                                )),
-                                                absolute_path: path
-                                                    .join(entry_path.file_name().unwrap()),
-                                            }
-                                        })
+                        let node_descriptors = self
+                            .execute_on_storages_with_origin(
+                                &storages,
+                                OperationKind::Read,
+                                move |backend| backend.readdir(&path_within_storage),
+                            )
+                            .map(|(storage, resulting_paths)| {
+                                let path = path.clone();
+                                resulting_paths
+                                    .into_iter()
+                                    .map(move |entry_path| NodeDescriptor {
+                                        storage: Some(NodeStorage::new(
+                                            storage.clone(),
+                                            entry_path.clone(),
+                                        )),
+                                        absolute_path: path.join(entry_path.file_name().unwrap()),
+                                        conflict_status: ConflictStatus::NoConflict,
                                     })
-                            });
-
-                        let node_descriptors = execute_backend_op_with_policy(
-                            operations_on_backends,
-                            // TODO WILX-362 getting first should be a temporary policy, maybe we should ping backends to check if any of them
-                            // is responsive and use the one that answered as the first one or ask all of them at once and return the first answer.
-                            ExecutionPolicy::SequentiallyToFirstSuccess,
-                        );
+                            })
+                            .ok();
 
                         if node_descriptors.is_none() {
-                            // TODO WILX-363 send alert to the wildland app bypassing DFS Frontend API
+                            // the application is notified with DfsEvent::AllReplicasFailed
                             tracing::error!(
                                 "None of the backends for storages {:?} works",
                                 storages.iter().map(|s| s.backend_type())
@@ -185,32 +495,148 @@ impl DfsFrontend for UnencryptedDfs {
                     }
                 }
             })
-            .flatten();
+            .flatten()
+            .collect();
 
-        nodes.collect()
+        let path_resolver = self.path_resolver.clone();
+        resolve_conflicts(
+            nodes,
+            self.conflict_resolution_strategy,
+            path_resolver.as_ref(),
+            |node_storage| {
+                let path = node_storage.path_within_storage().to_owned();
+                self.execute_on_storages(&[node_storage.storage().clone()], move |backend| {
+                    backend.getattr(&path)
+                })
+                .map(|stat| stat.node_type == NodeType::Dir)
+                .unwrap_or_default()
+            },
+        )
     }
-}
 
-enum ExecutionPolicy {
-    SequentiallyToFirstSuccess,
-}
-fn execute_backend_op_with_policy<T: std::fmt::Debug>(
-    ops: impl Iterator<Item = Result<T, anyhow::Error>>,
-    policy: ExecutionPolicy,
-) -> Option<T> {
-    match policy {
-        ExecutionPolicy::SequentiallyToFirstSuccess => {
-            ops.inspect(|result| {
-                if result.is_err() {
-                    // TODO WILX-363 send alert to the wildland app bypassing DFS Frontend API
-                    tracing::error!(
-                        "Backend returned error for operation: {}",
-                        result.as_ref().unwrap_err()
-                    );
-                }
+    fn getattr(&mut self, path: String) -> Result<Stat, DfsFrontendError> {
+        match self.find_node(Path::new(&path))? {
+            NodeLocation::Stored(node) => Ok(node.stat),
+            NodeLocation::Virtual => Ok(Stat::virtual_dir()),
+        }
+    }
+
+    fn open(&mut self, path: String) -> Result<FileHandle, DfsFrontendError> {
+        match self.find_node(Path::new(&path))? {
+            NodeLocation::Stored(node) if node.stat.node_type == NodeType::File => {
+                Ok(self.insert_opened_file(node.path_within_storage, node.storages))
+            }
+            _ => Err(DfsFrontendError::NotAFile),
+        }
+    }
+
+    fn close(&mut self, file: &FileHandle) -> Result<(), DfsFrontendError> {
+        self.opened_files
+            .remove(file)
+            .map(|_| ())
+            .ok_or(DfsFrontendError::InvalidFileHandle)
+    }
+
+    fn read(
+        &mut self,
+        file: &FileHandle,
+        offset: u64,
+        count: usize,
+    ) -> Result<Vec<u8>, DfsFrontendError> {
+        let OpenedFile {
+            path_within_storage,
+            storages,
+        } = self.get_opened_file(file)?;
+        self.execute_on_storages(&storages, move |backend| {
+            backend.read(&path_within_storage, offset, count)
+        })
+    }
+
+    fn write(
+        &mut self,
+        file: &FileHandle,
+        offset: u64,
+        buf: Vec<u8>,
+    ) -> Result<usize, DfsFrontendError> {
+        let OpenedFile {
+            path_within_storage,
+            storages,
+        } = self.get_opened_file(file)?;
+        self.modify_storages(&storages, move |backend| {
+            backend.write(&path_within_storage, offset, &buf)
+        })
+    }
+
+    fn create(&mut self, path: String) -> Result<FileHandle, DfsFrontendError> {
+        let (path_within_storage, storages) = self.find_storages_for_new_node(Path::new(&path))?;
+        let path = path_within_storage.clone();
+        self.modify_storages(&storages, move |backend| backend.create(&path))?;
+        Ok(self.insert_opened_file(path_within_storage, storages))
+    }
+
+    fn unlink(&mut self, path: String) -> Result<(), DfsFrontendError> {
+        let node = self.find_removable_node(Path::new(&path))?;
+        if node.stat.node_type == NodeType::Dir {
+            return Err(DfsFrontendError::NotAFile);
+        }
+        self.modify_storages(&node.storages, move |backend| {
+            backend.unlink(&node.path_within_storage)
+        })
+    }
+
+    fn mkdir(&mut self, path: String) -> Result<(), DfsFrontendError> {
+        let (path_within_storage, storages) = self.find_storages_for_new_node(Path::new(&path))?;
+        self.modify_storages(&storages, move |backend| {
+            backend.mkdir(&path_within_storage)
+        })
+    }
+
+    fn rmdir(&mut self, path: String) -> Result<(), DfsFrontendError> {
+        let node = self.find_removable_node(Path::new(&path))?;
+        if node.stat.node_type != NodeType::Dir {
+            return Err(DfsFrontendError::NotADirectory);
+        }
+        self.modify_storages(&node.storages, move |backend| {
+            backend.rmdir(&node.path_within_storage)
+        })
+    }
+
+    /// Renames node within a single container. Moving nodes between containers would require
+    /// copying data between storages, so it is not supported.
+    fn rename(&mut self, old_path: String, new_path: String) -> Result<(), DfsFrontendError> {
+        let node = self.find_removable_node(Path::new(&old_path))?;
+        let new_path_within_storage = self
+            .path_resolver
+            .resolve(Path::new(&new_path))
+            .into_iter()
+            .find_map(|resolved_path| match resolved_path {
+                ResolvedPath::PathWithStorages {
+                    path_within_storage,
+                    storages,
+                } if storages == node.storages => Some(path_within_storage),
+                _ => None,
             })
-            .find(Result::is_ok)
-            .map(Result::unwrap)
+            .ok_or(DfsFrontendError::MoveBetweenContainers)?;
+        if new_path_within_storage == Path::new("/") {
+            return Err(DfsFrontendError::ReadOnlyPath);
+        }
+
+        self.modify_storages(&node.storages, move |backend| {
+            backend.rename(&node.path_within_storage, &new_path_within_storage)
+        })
+    }
+
+    fn truncate(&mut self, path: String, size: u64) -> Result<(), DfsFrontendError> {
+        let node = self.find_stored_node(Path::new(&path))?;
+        if node.stat.node_type == NodeType::Dir {
+            return Err(DfsFrontendError::NotAFile);
         }
+        self.modify_storages(&node.storages, move |backend| {
+            backend.truncate(&node.path_within_storage, size)
+        })
+    }
+
+    fn subscribe_events(&mut self, receiver: Box<dyn DfsEventReceiver>) {
+        self.execution_context.events.subscribe(receiver)
     }
 }
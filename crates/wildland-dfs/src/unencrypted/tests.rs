@@ -2,101 +2,191 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
+use anyhow::anyhow;
 use mockall::predicate;
 use pretty_assertions::assert_eq;
-use rsfs::mem::FS;
-use rsfs::{DirEntry, GenFS};
 use rstest::rstest;
-use wildland_corex::dfs::interface::{DfsFrontend, NodeDescriptor, NodeStorage};
+use uuid::Uuid;
+use wildland_corex::dfs::interface::{
+    ConflictStatus,
+    DfsEvent,
+    DfsEventReceiver,
+    DfsFrontend,
+    DfsFrontendError,
+    NodeDescriptor,
+    NodeStorage,
+    NodeType,
+    Stat,
+};
 use wildland_corex::{MockPathResolver, ResolvedPath, Storage};
 
-use crate::storage_backend::StorageBackend;
-use crate::unencrypted::{StorageBackendFactory, UnencryptedDfs};
+use crate::in_memory::{InMemoryBackendFactory, InMemoryFs, IN_MEMORY_BACKEND_TYPE};
+use crate::storage_backend::{StorageBackend, StorageBackendError};
+use crate::unencrypted::execution_policy::{
+    execute_with_policy,
+    ExecutionContext,
+    OperationKind,
+    ReplicaOperation,
+    MAX_PENDING_OPERATIONS,
+};
+use crate::unencrypted::{
+    ConflictResolutionStrategy,
+    ExecutionPolicy,
+    StorageBackendFactory,
+    UnencryptedDfs,
+};
 
-/// Made up Filesystem
-struct Mufs {
-    fs: Rc<FS>,
-    base_dir: PathBuf,
+const TEST_BACKEND_TYPE: &str = "Test";
+
+/// Backend of an in-memory storage answering after a delay, which may also be switched off
+struct TestBackend {
+    inner: Arc<dyn StorageBackend>,
+    delay: Duration,
+    available: Arc<AtomicBool>,
 }
-impl Mufs {
-    fn new(fs: Rc<FS>, base_dir: impl Into<PathBuf>) -> Self {
-        Self {
-            fs,
-            base_dir: base_dir.into(),
+
+impl TestBackend {
+    fn call<T>(
+        &self,
+        op: impl FnOnce(&dyn StorageBackend) -> Result<T, StorageBackendError>,
+    ) -> Result<T, StorageBackendError> {
+        thread::sleep(self.delay);
+        if !self.available.load(Ordering::SeqCst) {
+            return Err(anyhow!("Storage is switched off").into());
         }
+        op(self.inner.as_ref())
     }
 }
-impl StorageBackend for Mufs {
-    fn readdir(&self, path: &Path) -> Result<Vec<PathBuf>, anyhow::Error> {
-        let relative_path = if path.is_absolute() {
-            path.strip_prefix("/").unwrap()
-        } else {
-            path
-        };
-        Ok(self
-            .fs
-            .read_dir(self.base_dir.join(relative_path))
-            .map(|readdir| {
-                readdir
-                    .into_iter()
-                    .map(|entry| {
-                        Path::new("/")
-                            .join(entry.unwrap().path().strip_prefix(&self.base_dir).unwrap())
-                    })
-                    .collect()
-            })?)
+
+impl StorageBackend for TestBackend {
+    fn readdir(&self, path: &Path) -> Result<Vec<PathBuf>, StorageBackendError> {
+        self.call(|backend| backend.readdir(path))
+    }
+    fn getattr(&self, path: &Path) -> Result<Stat, StorageBackendError> {
+        self.call(|backend| backend.getattr(path))
+    }
+    fn read(&self, path: &Path, offset: u64, count: usize) -> Result<Vec<u8>, StorageBackendError> {
+        self.call(|backend| backend.read(path, offset, count))
+    }
+    fn write(&self, path: &Path, offset: u64, buf: &[u8]) -> Result<usize, StorageBackendError> {
+        self.call(|backend| backend.write(path, offset, buf))
+    }
+    fn create(&self, path: &Path) -> Result<(), StorageBackendError> {
+        self.call(|backend| backend.create(path))
+    }
+    fn unlink(&self, path: &Path) -> Result<(), StorageBackendError> {
+        self.call(|backend| backend.unlink(path))
+    }
+    fn mkdir(&self, path: &Path) -> Result<(), StorageBackendError> {
+        self.call(|backend| backend.mkdir(path))
+    }
+    fn rmdir(&self, path: &Path) -> Result<(), StorageBackendError> {
+        self.call(|backend| backend.rmdir(path))
+    }
+    fn rename(&self, old_path: &Path, new_path: &Path) -> Result<(), StorageBackendError> {
+        self.call(|backend| backend.rename(old_path, new_path))
+    }
+    fn truncate(&self, path: &Path, size: u64) -> Result<(), StorageBackendError> {
+        self.call(|backend| backend.truncate(path, size))
     }
 }
 
-struct MufsFactory {
-    fs: Rc<FS>,
-}
-impl MufsFactory {
-    fn new(fs: Rc<FS>) -> Self {
-        Self { fs }
-    }
+/// Creates [`TestBackend`]s with the delay taken from `delay_ms` field of a storage template. All
+/// of them are switched on and off at once.
+struct TestBackendFactory {
+    inner: InMemoryBackendFactory,
+    available: Arc<AtomicBool>,
 }
-impl StorageBackendFactory for MufsFactory {
-    fn init_backend(&self, storage: Storage) -> Result<Rc<dyn StorageBackend>, anyhow::Error> {
-        Ok(Rc::new(Mufs::new(
-            self.fs.clone(),
-            serde_json::from_value::<String>(storage.data().clone())?,
-        )))
+
+impl StorageBackendFactory for TestBackendFactory {
+    fn init_backend(&self, storage: Storage) -> Result<Arc<dyn StorageBackend>, anyhow::Error> {
+        let delay = Duration::from_millis(storage.data()["delay_ms"].as_u64().unwrap_or_default());
+        Ok(Arc::new(TestBackend {
+            inner: self.inner.init_backend(storage)?,
+            delay,
+            available: self.available.clone(),
+        }))
     }
 }
 
-type DfsFixture = (UnencryptedDfs, Rc<FS>);
+type DfsFixture = (UnencryptedDfs, Arc<InMemoryFs>);
 fn dfs_with_fs(path_resolver: Rc<MockPathResolver>) -> DfsFixture {
-    let fs = Rc::new(FS::new());
-    let factory = MufsFactory::new(fs.clone());
-    let mut backend_factories: HashMap<String, Box<dyn StorageBackendFactory>> = HashMap::new();
-    backend_factories.insert("MUFS".to_string(), Box::new(factory));
+    dfs_with_switchable_fs(path_resolver, Arc::new(AtomicBool::new(true)))
+}
 
+/// Creates DFS in which backends of [`TEST_BACKEND_TYPE`] storages work only if `available` is set
+fn dfs_with_switchable_fs(
+    path_resolver: Rc<MockPathResolver>,
+    available: Arc<AtomicBool>,
+) -> DfsFixture {
+    let fs = Arc::new(InMemoryFs::default());
+    let factory = InMemoryBackendFactory::new(fs.clone());
+    let mut backend_factories: HashMap<String, Box<dyn StorageBackendFactory>> = HashMap::new();
+    backend_factories.insert(IN_MEMORY_BACKEND_TYPE.to_string(), Box::new(factory));
+    backend_factories.insert(
+        TEST_BACKEND_TYPE.to_string(),
+        Box::new(TestBackendFactory {
+            inner: InMemoryBackendFactory::new(fs.clone()),
+            available,
+        }),
+    );
     let dfs = UnencryptedDfs::new(path_resolver, backend_factories);
 
     (dfs, fs)
 }
 
-fn new_mufs_storage(base_dir: impl Into<String>) -> Storage {
+fn new_in_memory_storage(base_dir: impl Into<String>) -> Storage {
+    Storage::new(
+        Some("Test InMemory".to_owned()),
+        IN_MEMORY_BACKEND_TYPE.to_owned(),
+        serde_json::json!({ "container_prefix": base_dir.into() }),
+    )
+}
+
+fn new_test_storage(base_dir: impl Into<String>, delay: Duration) -> Storage {
     Storage::new(
-        Some("Test MUFS".to_owned()),
-        "MUFS".to_owned(),
-        serde_json::Value::String(base_dir.into()),
+        Some("Test".to_owned()),
+        TEST_BACKEND_TYPE.to_owned(),
+        serde_json::json!({
+            "container_prefix": base_dir.into(),
+            "delay_ms": delay.as_millis() as u64,
+        }),
     )
 }
 
+/// Path resolver of a single container claiming `/` path
+fn root_container_path_resolver(storage: Storage) -> MockPathResolver {
+    replicated_root_container_path_resolver(vec![storage])
+}
+
+/// Path resolver of a single container with multiple storages claiming `/` path
+fn replicated_root_container_path_resolver(storages: Vec<Storage>) -> MockPathResolver {
+    let mut path_resolver = MockPathResolver::new();
+    path_resolver.expect_resolve().returning(move |path| {
+        vec![ResolvedPath::PathWithStorages {
+            path_within_storage: path.into(),
+            storages: storages.clone(),
+        }]
+    });
+    path_resolver
+}
+
 #[rstest]
 fn test_listing_files_from_root_of_one_container() {
     let mut path_resolver = MockPathResolver::new();
-    let mufs_storage = new_mufs_storage("/");
+    let in_memory_storage = new_in_memory_storage("/");
 
     path_resolver
         .expect_resolve()
         .with(predicate::eq(Path::new("/a/b/")))
         .times(2)
         .returning({
-            let storage = mufs_storage.clone();
+            let storage = in_memory_storage.clone();
             move |_path| {
                 vec![ResolvedPath::PathWithStorages {
                     path_within_storage: "/".into(),
@@ -111,16 +201,17 @@ fn test_listing_files_from_root_of_one_container() {
     let files_descriptors = dfs.readdir("/a/b/".to_string());
     assert_eq!(files_descriptors, vec![]);
 
-    fs.create_file("/file_in_root").unwrap();
+    fs.create(Path::new("/file_in_root")).unwrap();
     let files_descriptors = dfs.readdir("/a/b/".to_string());
     assert_eq!(
         files_descriptors,
         vec![NodeDescriptor {
             storage: Some(NodeStorage::new(
-                mufs_storage,
+                in_memory_storage,
                 PathBuf::from_str("/file_in_root").unwrap()
             )),
             absolute_path: PathBuf::from_str("/a/b/file_in_root").unwrap(),
+            conflict_status: ConflictStatus::NoConflict,
         }]
     );
 }
@@ -129,14 +220,14 @@ fn test_listing_files_from_root_of_one_container() {
 fn test_listing_files_from_nested_dir_of_one_container() {
     let mut path_resolver = MockPathResolver::new();
 
-    let mufs_storage = new_mufs_storage("/");
+    let in_memory_storage = new_in_memory_storage("/");
 
     path_resolver
         .expect_resolve()
         .with(predicate::eq(Path::new("/a/b/dir")))
         .times(2)
         .returning({
-            let storage = mufs_storage.clone();
+            let storage = in_memory_storage.clone();
             move |_path| {
                 vec![ResolvedPath::PathWithStorages {
                     path_within_storage: "/dir".into(),
@@ -151,9 +242,9 @@ fn test_listing_files_from_nested_dir_of_one_container() {
     let files_descriptors = dfs.readdir("/a/b/dir".to_string());
     assert_eq!(files_descriptors, vec![]);
 
-    fs.create_dir("/dir/").unwrap();
-    fs.create_file("/dir/nested_file_1").unwrap();
-    fs.create_file("/dir/nested_file_2").unwrap();
+    fs.mkdir(Path::new("/dir/")).unwrap();
+    fs.create(Path::new("/dir/nested_file_1")).unwrap();
+    fs.create(Path::new("/dir/nested_file_2")).unwrap();
 
     let files_descriptors = dfs.readdir("/a/b/dir".to_string());
     assert_eq!(
@@ -161,17 +252,19 @@ fn test_listing_files_from_nested_dir_of_one_container() {
         vec![
             NodeDescriptor {
                 storage: Some(NodeStorage::new(
-                    mufs_storage.clone(),
+                    in_memory_storage.clone(),
                     PathBuf::from_str("/dir/nested_file_1").unwrap()
                 )),
                 absolute_path: PathBuf::from_str("/a/b/dir/nested_file_1").unwrap(),
+                conflict_status: ConflictStatus::NoConflict,
             },
             NodeDescriptor {
                 storage: Some(NodeStorage::new(
-                    mufs_storage,
+                    in_memory_storage,
                     PathBuf::from_str("/dir/nested_file_2").unwrap()
                 )),
                 absolute_path: PathBuf::from_str("/a/b/dir/nested_file_2").unwrap(),
+                conflict_status: ConflictStatus::NoConflict,
             }
         ]
     );
@@ -181,14 +274,14 @@ fn test_listing_files_from_nested_dir_of_one_container() {
 fn test_listing_dirs_from_one_container() {
     let mut path_resolver = MockPathResolver::new();
 
-    let mufs_storage = new_mufs_storage("/");
+    let in_memory_storage = new_in_memory_storage("/");
 
     path_resolver
         .expect_resolve()
         .with(predicate::eq(Path::new("/")))
         .times(2)
         .returning({
-            let storage = mufs_storage.clone();
+            let storage = in_memory_storage.clone();
             move |_path| {
                 vec![ResolvedPath::PathWithStorages {
                     path_within_storage: "/".into(),
@@ -203,8 +296,8 @@ fn test_listing_dirs_from_one_container() {
     let files_descriptors = dfs.readdir("/".to_string());
     assert_eq!(files_descriptors, vec![]);
 
-    fs.create_dir("/dir_a").unwrap();
-    fs.create_dir("/dir_b").unwrap();
+    fs.mkdir(Path::new("/dir_a")).unwrap();
+    fs.mkdir(Path::new("/dir_b")).unwrap();
 
     let files_descriptors = dfs.readdir("/".to_string());
     assert_eq!(
@@ -212,17 +305,19 @@ fn test_listing_dirs_from_one_container() {
         vec![
             NodeDescriptor {
                 storage: Some(NodeStorage::new(
-                    mufs_storage.clone(),
+                    in_memory_storage.clone(),
                     PathBuf::from_str("/dir_a").unwrap()
                 )),
                 absolute_path: PathBuf::from_str("/dir_a").unwrap(),
+                conflict_status: ConflictStatus::NoConflict,
             },
             NodeDescriptor {
                 storage: Some(NodeStorage::new(
-                    mufs_storage,
+                    in_memory_storage,
                     PathBuf::from_str("/dir_b").unwrap()
                 )),
                 absolute_path: PathBuf::from_str("/dir_b").unwrap(),
+                conflict_status: ConflictStatus::NoConflict,
             },
         ]
     );
@@ -233,8 +328,8 @@ fn test_listing_files_and_dirs_from_two_containers() {
     let mut path_resolver = MockPathResolver::new();
 
     // each container has its own subfolder
-    let storage1 = new_mufs_storage("/storage1/");
-    let storage2 = new_mufs_storage("/storage2/");
+    let storage1 = new_in_memory_storage("/storage1/");
+    let storage2 = new_in_memory_storage("/storage2/");
 
     path_resolver
         .expect_resolve()
@@ -260,19 +355,19 @@ fn test_listing_files_and_dirs_from_two_containers() {
     let path_resolver = Rc::new(path_resolver);
     let (mut dfs, fs) = dfs_with_fs(path_resolver);
 
-    fs.create_dir("/storage1/").unwrap();
-    fs.create_dir("/storage1/dir/").unwrap();
-    fs.create_dir("/storage2/").unwrap();
-    fs.create_dir("/storage2/c/").unwrap();
-    fs.create_dir("/storage2/c/dir/").unwrap();
+    fs.mkdir(Path::new("/storage1/")).unwrap();
+    fs.mkdir(Path::new("/storage1/dir/")).unwrap();
+    fs.mkdir(Path::new("/storage2/")).unwrap();
+    fs.mkdir(Path::new("/storage2/c/")).unwrap();
+    fs.mkdir(Path::new("/storage2/c/dir/")).unwrap();
 
     let files_descriptors = dfs.readdir("/a/b/c/dir".to_string());
     assert_eq!(files_descriptors, vec![]);
 
-    fs.create_file("/storage1/dir/file_from_container_1")
+    fs.create(Path::new("/storage1/dir/file_from_container_1"))
         .unwrap();
-    fs.create_dir("/storage2/c/dir/next_dir").unwrap();
-    fs.create_file("/storage2/c/dir/file_from_container_2")
+    fs.mkdir(Path::new("/storage2/c/dir/next_dir")).unwrap();
+    fs.create(Path::new("/storage2/c/dir/file_from_container_2"))
         .unwrap();
 
     let files_descriptors = dfs.readdir("/a/b/c/dir".to_string());
@@ -285,6 +380,7 @@ fn test_listing_files_and_dirs_from_two_containers() {
                     PathBuf::from_str("/dir/file_from_container_1").unwrap()
                 )),
                 absolute_path: PathBuf::from_str("/a/b/c/dir/file_from_container_1").unwrap(),
+                conflict_status: ConflictStatus::NoConflict,
             },
             NodeDescriptor {
                 storage: Some(NodeStorage::new(
@@ -292,6 +388,7 @@ fn test_listing_files_and_dirs_from_two_containers() {
                     PathBuf::from_str("/c/dir/file_from_container_2").unwrap()
                 )),
                 absolute_path: PathBuf::from_str("/a/b/c/dir/file_from_container_2").unwrap(),
+                conflict_status: ConflictStatus::NoConflict,
             },
             NodeDescriptor {
                 storage: Some(NodeStorage::new(
@@ -299,6 +396,7 @@ fn test_listing_files_and_dirs_from_two_containers() {
                     PathBuf::from_str("/c/dir/next_dir").unwrap()
                 )),
                 absolute_path: PathBuf::from_str("/a/b/c/dir/next_dir").unwrap(),
+                conflict_status: ConflictStatus::NoConflict,
             }
         ]
     );
@@ -309,8 +407,8 @@ fn test_getting_one_file_descriptor_from_container_with_multiple_storages() {
     let mut path_resolver = MockPathResolver::new();
 
     // each container has its own subfolder
-    let storage1 = new_mufs_storage("/storage1/");
-    let storage2 = new_mufs_storage("/storage2/");
+    let storage1 = new_in_memory_storage("/storage1/");
+    let storage2 = new_in_memory_storage("/storage2/");
 
     path_resolver
         .expect_resolve()
@@ -330,16 +428,16 @@ fn test_getting_one_file_descriptor_from_container_with_multiple_storages() {
     let path_resolver = Rc::new(path_resolver);
     let (mut dfs, fs) = dfs_with_fs(path_resolver);
 
-    fs.create_dir("/storage1/").unwrap();
-    fs.create_dir("/storage1/a").unwrap();
-    fs.create_dir("/storage2/").unwrap();
-    fs.create_dir("/storage2/a").unwrap();
+    fs.mkdir(Path::new("/storage1/")).unwrap();
+    fs.mkdir(Path::new("/storage1/a")).unwrap();
+    fs.mkdir(Path::new("/storage2/")).unwrap();
+    fs.mkdir(Path::new("/storage2/a")).unwrap();
 
     let files_descriptors = dfs.readdir("/a".to_string());
     assert_eq!(files_descriptors, vec![]);
 
-    fs.create_file("/storage1/a/b").unwrap();
-    fs.create_file("/storage2/a/b").unwrap();
+    fs.create(Path::new("/storage1/a/b")).unwrap();
+    fs.create(Path::new("/storage2/a/b")).unwrap();
 
     let files_descriptors = dfs.readdir("/a".to_string());
     assert_eq!(
@@ -350,6 +448,7 @@ fn test_getting_one_file_descriptor_from_container_with_multiple_storages() {
                 PathBuf::from_str("/a/b").unwrap()
             )),
             absolute_path: PathBuf::from_str("/a/b").unwrap(),
+            conflict_status: ConflictStatus::NoConflict,
         },]
     );
 }
@@ -360,8 +459,8 @@ fn test_more_than_one_file_descriptor_claim_the_same_full_path() {
     let mut path_resolver = MockPathResolver::new();
 
     // each container has its own subfolder
-    let storage1 = new_mufs_storage("/storage1/");
-    let storage2 = new_mufs_storage("/storage2/");
+    let storage1 = new_in_memory_storage("/storage1/");
+    let storage2 = new_in_memory_storage("/storage2/");
 
     path_resolver
         .expect_resolve()
@@ -386,16 +485,17 @@ fn test_more_than_one_file_descriptor_claim_the_same_full_path() {
 
     let path_resolver = Rc::new(path_resolver);
     let (mut dfs, fs) = dfs_with_fs(path_resolver);
+    dfs.set_conflict_resolution_strategy(ConflictResolutionStrategy::KeepPaths);
 
-    fs.create_dir("/storage1/").unwrap();
-    fs.create_dir("/storage1/b").unwrap();
-    fs.create_dir("/storage2/").unwrap();
+    fs.mkdir(Path::new("/storage1/")).unwrap();
+    fs.mkdir(Path::new("/storage1/b")).unwrap();
+    fs.mkdir(Path::new("/storage2/")).unwrap();
 
     let files_descriptors = dfs.readdir("/a/b/".to_string());
     assert_eq!(files_descriptors, vec![]);
 
-    fs.create_file("/storage1/b/c").unwrap();
-    fs.create_file("/storage2/c").unwrap();
+    fs.create(Path::new("/storage1/b/c")).unwrap();
+    fs.create(Path::new("/storage2/c")).unwrap();
 
     let files_descriptors = dfs.readdir("/a/b".to_string());
     assert_eq!(
@@ -408,11 +508,13 @@ fn test_more_than_one_file_descriptor_claim_the_same_full_path() {
                     PathBuf::from_str("/b/c").unwrap()
                 )),
                 absolute_path: PathBuf::from_str("/a/b/c").unwrap(),
+                conflict_status: ConflictStatus::Unresolved,
             },
             // Storage of the container claiming path `/a/b` + `c` within the container also gives full path `/a/b/c`
             NodeDescriptor {
                 storage: Some(NodeStorage::new(storage2, PathBuf::from_str("/c").unwrap())),
                 absolute_path: PathBuf::from_str("/a/b/c").unwrap(),
+                conflict_status: ConflictStatus::Unresolved,
             }
         ]
     );
@@ -423,8 +525,8 @@ fn test_first_storage_unavailable() {
     let mut path_resolver = MockPathResolver::new();
 
     // each container has its own subfolder
-    let storage1 = new_mufs_storage("/storage1/");
-    let storage2 = new_mufs_storage("/storage2/");
+    let storage1 = new_in_memory_storage("/storage1/");
+    let storage2 = new_in_memory_storage("/storage2/");
 
     path_resolver
         .expect_resolve()
@@ -444,10 +546,11 @@ fn test_first_storage_unavailable() {
     let path_resolver = Rc::new(path_resolver);
     let (mut dfs, fs) = dfs_with_fs(path_resolver);
 
-    // don't create storage1 directory so readdir returned "No such file or directory" error
-    // then dfs should choose storage2
-    fs.create_dir("/storage2/").unwrap();
-    fs.create_file("/storage2/a").unwrap();
+    // storage1 directory cannot be created because of a file occupying its path, so storage1 is
+    // unavailable and dfs should choose storage2
+    fs.create(Path::new("/storage1")).unwrap();
+    fs.mkdir(Path::new("/storage2/")).unwrap();
+    fs.create(Path::new("/storage2/a")).unwrap();
 
     let files_descriptors = dfs.readdir("/".to_string());
     assert_eq!(
@@ -455,6 +558,7 @@ fn test_first_storage_unavailable() {
         vec![NodeDescriptor {
             storage: Some(NodeStorage::new(storage2, PathBuf::from_str("/a").unwrap())),
             absolute_path: PathBuf::from_str("/a").unwrap(),
+            conflict_status: ConflictStatus::NoConflict,
         },]
     );
 }
@@ -464,7 +568,7 @@ fn test_listing_virtual_node() {
     let mut path_resolver = MockPathResolver::new();
 
     // C1 storage
-    let storage1 = new_mufs_storage("/storage_c1/");
+    let storage1 = new_in_memory_storage("/storage_c1/");
 
     path_resolver
         .expect_resolve()
@@ -486,10 +590,11 @@ fn test_listing_virtual_node() {
     let path_resolver = Rc::new(path_resolver);
     let (mut dfs, fs) = dfs_with_fs(path_resolver);
 
-    fs.create_dir("/storage_c1/").unwrap();
-    fs.create_file("/storage_c1/file_1").unwrap();
-    fs.create_dir("/storage_c1/dir/").unwrap();
-    fs.create_file("storage_c1/dir/file_in_nested_dir").unwrap(); // it should not be present in result
+    fs.mkdir(Path::new("/storage_c1/")).unwrap();
+    fs.create(Path::new("/storage_c1/file_1")).unwrap();
+    fs.mkdir(Path::new("/storage_c1/dir/")).unwrap();
+    fs.create(Path::new("storage_c1/dir/file_in_nested_dir"))
+        .unwrap(); // it should not be present in result
 
     let files_descriptors = dfs.readdir("/a".to_string());
     assert_eq!(
@@ -501,6 +606,7 @@ fn test_listing_virtual_node() {
                     PathBuf::from_str("/dir").unwrap()
                 )),
                 absolute_path: PathBuf::from_str("/a/dir").unwrap(),
+                conflict_status: ConflictStatus::NoConflict,
             },
             NodeDescriptor {
                 storage: Some(NodeStorage::new(
@@ -508,11 +614,823 @@ fn test_listing_virtual_node() {
                     PathBuf::from_str("/file_1").unwrap()
                 )),
                 absolute_path: PathBuf::from_str("/a/file_1").unwrap(),
+                conflict_status: ConflictStatus::NoConflict,
             },
             NodeDescriptor {
                 storage: None,
                 absolute_path: PathBuf::from_str("/a/b").unwrap(),
+                conflict_status: ConflictStatus::NoConflict,
             },
         ]
     );
 }
+
+#[rstest]
+fn test_getattr_of_file_dir_and_virtual_node() {
+    let mut path_resolver = MockPathResolver::new();
+    let storage = new_in_memory_storage("/");
+
+    path_resolver.expect_resolve().returning({
+        let storage = storage.clone();
+        move |path| {
+            if path == Path::new("/virtual") {
+                vec![ResolvedPath::VirtualPath("/virtual/container".into())]
+            } else {
+                vec![ResolvedPath::PathWithStorages {
+                    path_within_storage: path.into(),
+                    storages: vec![storage.clone()],
+                }]
+            }
+        }
+    });
+
+    let (mut dfs, fs) = dfs_with_fs(Rc::new(path_resolver));
+
+    fs.mkdir(Path::new("/dir")).unwrap();
+    fs.create(Path::new("/dir/file")).unwrap();
+    fs.write(Path::new("/dir/file"), 0, b"content").unwrap();
+
+    let stat = dfs.getattr("/dir".to_string()).unwrap();
+    assert_eq!(stat.node_type, NodeType::Dir);
+
+    let stat = dfs.getattr("/dir/file".to_string()).unwrap();
+    assert_eq!(stat.node_type, NodeType::File);
+    assert_eq!(stat.size, 7);
+
+    let stat = dfs.getattr("/virtual".to_string()).unwrap();
+    assert_eq!(stat, Stat::virtual_dir());
+
+    let err = dfs.getattr("/dir/missing".to_string()).unwrap_err();
+    assert_eq!(err, DfsFrontendError::NoSuchPath);
+}
+
+#[rstest]
+fn test_create_write_and_read_file() {
+    let storage = new_in_memory_storage("/");
+    let (mut dfs, _fs) = dfs_with_fs(Rc::new(root_container_path_resolver(storage)));
+
+    let handle = dfs.create("/file".to_string()).unwrap();
+    assert_eq!(dfs.write(&handle, 0, b"hello world".to_vec()).unwrap(), 11);
+    assert_eq!(dfs.write(&handle, 6, b"there".to_vec()).unwrap(), 5);
+    dfs.close(&handle).unwrap();
+
+    let err = dfs.create("/file".to_string()).unwrap_err();
+    assert_eq!(err, DfsFrontendError::PathAlreadyExists);
+
+    let handle = dfs.open("/file".to_string()).unwrap();
+    assert_eq!(dfs.read(&handle, 0, 100).unwrap(), b"hello there".to_vec());
+    assert_eq!(dfs.read(&handle, 6, 3).unwrap(), b"the".to_vec());
+    dfs.close(&handle).unwrap();
+
+    let err = dfs.read(&handle, 0, 100).unwrap_err();
+    assert_eq!(err, DfsFrontendError::InvalidFileHandle);
+}
+
+#[rstest]
+fn test_opening_dir_fails() {
+    let storage = new_in_memory_storage("/");
+    let (mut dfs, fs) = dfs_with_fs(Rc::new(root_container_path_resolver(storage)));
+
+    fs.mkdir(Path::new("/dir")).unwrap();
+
+    let err = dfs.open("/dir".to_string()).unwrap_err();
+    assert_eq!(err, DfsFrontendError::NotAFile);
+}
+
+#[rstest]
+fn test_mkdir_and_rmdir() {
+    let storage = new_in_memory_storage("/");
+    let (mut dfs, fs) = dfs_with_fs(Rc::new(root_container_path_resolver(storage)));
+
+    dfs.mkdir("/dir".to_string()).unwrap();
+    assert!(fs.getattr(Path::new("/dir")).unwrap().node_type == NodeType::Dir);
+
+    fs.create(Path::new("/dir/file")).unwrap();
+    let err = dfs.rmdir("/dir".to_string()).unwrap_err();
+    assert_eq!(err, DfsFrontendError::DirNotEmpty);
+
+    let err = dfs.rmdir("/dir/file".to_string()).unwrap_err();
+    assert_eq!(err, DfsFrontendError::NotADirectory);
+
+    dfs.unlink("/dir/file".to_string()).unwrap();
+    dfs.rmdir("/dir".to_string()).unwrap();
+    assert!(fs.getattr(Path::new("/dir")).is_err());
+}
+
+#[rstest]
+fn test_unlink_dir_fails() {
+    let storage = new_in_memory_storage("/");
+    let (mut dfs, fs) = dfs_with_fs(Rc::new(root_container_path_resolver(storage)));
+
+    fs.mkdir(Path::new("/dir")).unwrap();
+
+    let err = dfs.unlink("/dir".to_string()).unwrap_err();
+    assert_eq!(err, DfsFrontendError::NotAFile);
+}
+
+#[rstest]
+fn test_rename_within_container() {
+    let storage = new_in_memory_storage("/");
+    let (mut dfs, fs) = dfs_with_fs(Rc::new(root_container_path_resolver(storage)));
+
+    fs.create(Path::new("/old")).unwrap();
+
+    dfs.rename("/old".to_string(), "/new".to_string()).unwrap();
+    assert!(fs.getattr(Path::new("/old")).is_err());
+    assert!(fs.getattr(Path::new("/new")).unwrap().node_type == NodeType::File);
+}
+
+#[rstest]
+fn test_rename_between_containers_fails() {
+    let mut path_resolver = MockPathResolver::new();
+    let storage1 = new_in_memory_storage("/storage1/");
+    let storage2 = new_in_memory_storage("/storage2/");
+
+    path_resolver.expect_resolve().returning({
+        let storage1 = storage1;
+        let storage2 = storage2;
+        move |path| {
+            let (container_path, storage) = if path.starts_with("/c1") {
+                ("/c1", storage1.clone())
+            } else {
+                ("/c2", storage2.clone())
+            };
+            vec![ResolvedPath::PathWithStorages {
+                path_within_storage: Path::new("/")
+                    .join(path.strip_prefix(container_path).unwrap()),
+                storages: vec![storage],
+            }]
+        }
+    });
+
+    let (mut dfs, fs) = dfs_with_fs(Rc::new(path_resolver));
+
+    fs.mkdir(Path::new("/storage1/")).unwrap();
+    fs.mkdir(Path::new("/storage2/")).unwrap();
+    fs.create(Path::new("/storage1/file")).unwrap();
+
+    let err = dfs
+        .rename("/c1/file".to_string(), "/c2/file".to_string())
+        .unwrap_err();
+    assert_eq!(err, DfsFrontendError::MoveBetweenContainers);
+    assert!(fs.getattr(Path::new("/storage1/file")).unwrap().node_type == NodeType::File);
+}
+
+#[rstest]
+fn test_truncate() {
+    let storage = new_in_memory_storage("/");
+    let (mut dfs, fs) = dfs_with_fs(Rc::new(root_container_path_resolver(storage)));
+
+    fs.create(Path::new("/file")).unwrap();
+    fs.write(Path::new("/file"), 0, b"hello world").unwrap();
+
+    dfs.truncate("/file".to_string(), 5).unwrap();
+    assert_eq!(dfs.getattr("/file".to_string()).unwrap().size, 5);
+}
+
+#[rstest]
+fn test_creating_node_in_path_not_claimed_by_any_container_fails() {
+    let mut path_resolver = MockPathResolver::new();
+    path_resolver
+        .expect_resolve()
+        .returning(|_path| vec![ResolvedPath::VirtualPath("/a/b".into())]);
+
+    let (mut dfs, _fs) = dfs_with_fs(Rc::new(path_resolver));
+
+    let err = dfs.mkdir("/a".to_string()).unwrap_err();
+    assert_eq!(err, DfsFrontendError::ReadOnlyPath);
+    let err = dfs.create("/a".to_string()).unwrap_err();
+    assert_eq!(err, DfsFrontendError::ReadOnlyPath);
+}
+
+#[rstest]
+fn test_container_root_cannot_be_removed_or_moved() {
+    let storage = new_in_memory_storage("/storage1/");
+    let mut path_resolver = MockPathResolver::new();
+    path_resolver.expect_resolve().returning({
+        let storage = storage.clone();
+        move |path| match path.strip_prefix("/a") {
+            Ok(path_within_container) => vec![ResolvedPath::PathWithStorages {
+                path_within_storage: Path::new("/").join(path_within_container),
+                storages: vec![storage.clone()],
+            }],
+            Err(_) => vec![ResolvedPath::VirtualPath("/a".into())],
+        }
+    });
+    let (mut dfs, fs) = dfs_with_fs(Rc::new(path_resolver));
+
+    fs.create_dir_all(Path::new("/storage1/dir")).unwrap();
+
+    for err in [
+        dfs.rmdir("/a".to_string()).unwrap_err(),
+        dfs.unlink("/a".to_string()).unwrap_err(),
+        dfs.rename("/a".to_string(), "/a/moved".to_string())
+            .unwrap_err(),
+        dfs.rename("/a/dir".to_string(), "/a".to_string())
+            .unwrap_err(),
+    ] {
+        assert_eq!(err, DfsFrontendError::ReadOnlyPath);
+    }
+    assert_eq!(
+        dfs.getattr("/a/dir".to_string()).unwrap().node_type,
+        NodeType::Dir
+    );
+}
+
+#[rstest]
+fn test_concurrent_policy_does_not_wait_for_slow_replica() {
+    let slow_storage = new_test_storage("/storage1/", Duration::from_secs(2));
+    let fast_storage = new_in_memory_storage("/storage2/");
+    let path_resolver = replicated_root_container_path_resolver(vec![slow_storage, fast_storage]);
+    let (mut dfs, fs) = dfs_with_fs(Rc::new(path_resolver));
+    dfs.set_execution_policy(ExecutionPolicy::ConcurrentlyToFirstSuccess);
+
+    fs.create_dir_all(Path::new("/storage2/")).unwrap();
+    fs.create(Path::new("/storage2/file")).unwrap();
+
+    let start = Instant::now();
+    let stat = dfs.getattr("/file".to_string()).unwrap();
+    assert_eq!(stat.node_type, NodeType::File);
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[rstest]
+fn test_lowest_latency_first_policy_prefers_faster_replica() {
+    let slow_storage = new_test_storage("/storage1/", Duration::from_millis(50));
+    let fast_storage = new_in_memory_storage("/storage2/");
+    let path_resolver =
+        replicated_root_container_path_resolver(vec![slow_storage.clone(), fast_storage.clone()]);
+    let (mut dfs, fs) = dfs_with_fs(Rc::new(path_resolver));
+    dfs.set_execution_policy(ExecutionPolicy::LowestLatencyFirst);
+
+    fs.create_dir_all(Path::new("/storage1/")).unwrap();
+    fs.create(Path::new("/storage1/file")).unwrap();
+    fs.create_dir_all(Path::new("/storage2/")).unwrap();
+    fs.create(Path::new("/storage2/file")).unwrap();
+
+    let answered_by = |storage: &Storage| {
+        Some(NodeStorage::new(
+            storage.clone(),
+            PathBuf::from_str("/file").unwrap(),
+        ))
+    };
+
+    // replicas without known latency go first in the order of storages
+    assert_eq!(
+        dfs.readdir("/".to_string())[0].storage,
+        answered_by(&slow_storage)
+    );
+    assert_eq!(
+        dfs.readdir("/".to_string())[0].storage,
+        answered_by(&fast_storage)
+    );
+    // from now on latencies of both replicas are known
+    assert_eq!(
+        dfs.readdir("/".to_string())[0].storage,
+        answered_by(&fast_storage)
+    );
+    assert_eq!(
+        dfs.readdir("/".to_string())[0].storage,
+        answered_by(&fast_storage)
+    );
+}
+
+#[rstest]
+fn test_lowest_latency_first_policy_does_not_penalize_missing_files() {
+    let fast_storage = new_in_memory_storage("/storage1/");
+    let slow_storage = new_test_storage("/storage2/", Duration::from_millis(50));
+    let path_resolver = replicated_root_container_path_resolver(vec![fast_storage, slow_storage]);
+    let (mut dfs, fs) = dfs_with_fs(Rc::new(path_resolver));
+    dfs.set_execution_policy(ExecutionPolicy::LowestLatencyFirst);
+
+    for (dir, content) in [("/storage1/", "hello"), ("/storage2/", "hello world")] {
+        let path = Path::new(dir).join("file");
+        fs.create_dir_all(Path::new(dir)).unwrap();
+        fs.create(&path).unwrap();
+        fs.write(&path, 0, content.as_bytes()).unwrap();
+    }
+    fs.create(Path::new("/storage2/other")).unwrap();
+
+    // the first replica does not have the file, but it is still the fastest one
+    dfs.getattr("/other".to_string()).unwrap();
+    assert_eq!(dfs.getattr("/file".to_string()).unwrap().size, 5);
+}
+
+#[rstest]
+fn test_quorum_policy() {
+    let storages = vec![
+        new_in_memory_storage("/storage1/"),
+        new_in_memory_storage("/storage2/"),
+        new_in_memory_storage("/storage3/"),
+    ];
+    let path_resolver = replicated_root_container_path_resolver(storages);
+    let (mut dfs, fs) = dfs_with_fs(Rc::new(path_resolver));
+
+    for (dir, content) in [
+        ("/storage1/", "hello"),
+        ("/storage2/", "hello"),
+        ("/storage3/", "hello world"),
+    ] {
+        let path = Path::new(dir).join("file");
+        fs.create_dir_all(Path::new(dir)).unwrap();
+        fs.create(&path).unwrap();
+        fs.write(&path, 0, content.as_bytes()).unwrap();
+    }
+
+    dfs.set_execution_policy(ExecutionPolicy::Quorum(2));
+    assert_eq!(dfs.getattr("/file".to_string()).unwrap().size, 5);
+
+    dfs.set_execution_policy(ExecutionPolicy::Quorum(3));
+    let err = dfs.getattr("/file".to_string()).unwrap_err();
+    assert!(matches!(err, DfsFrontendError::Generic(_)));
+
+    dfs.set_execution_policy(ExecutionPolicy::Quorum(4));
+    let err = dfs.getattr("/file".to_string()).unwrap_err();
+    assert!(matches!(err, DfsFrontendError::Generic(_)));
+
+    dfs.set_execution_policy(ExecutionPolicy::Quorum(0));
+    let err = dfs.getattr("/file".to_string()).unwrap_err();
+    assert!(matches!(err, DfsFrontendError::Generic(_)));
+}
+
+#[rstest]
+fn test_quorum_policy_modifies_every_replica_in_order() {
+    let storages = vec![
+        new_test_storage("/storage1/", Duration::from_millis(20)),
+        new_in_memory_storage("/storage2/"),
+        new_in_memory_storage("/storage3/"),
+    ];
+    let path_resolver = replicated_root_container_path_resolver(storages);
+    let (mut dfs, fs) = dfs_with_fs(Rc::new(path_resolver));
+    dfs.set_execution_policy(ExecutionPolicy::Quorum(2));
+    for dir in ["/storage1/", "/storage2/", "/storage3/"] {
+        fs.create_dir_all(Path::new(dir)).unwrap();
+    }
+
+    let handle = dfs.create("/file".to_string()).unwrap();
+    dfs.write(&handle, 0, b"hello world".to_vec()).unwrap();
+    dfs.truncate("/file".to_string(), 5).unwrap();
+
+    // modifications are done once the call returns, even on the slowest replica
+    for dir in ["/storage1/", "/storage2/", "/storage3/"] {
+        assert_eq!(
+            fs.read(&Path::new(dir).join("file"), 0, 100).unwrap(),
+            b"hello".to_vec()
+        );
+    }
+}
+
+#[rstest]
+fn test_concurrent_policy_limits_pending_operations_on_slow_replica() {
+    let fs = Arc::new(InMemoryFs::default());
+    fs.create_dir_all(Path::new("/storage1/")).unwrap();
+    fs.create_dir_all(Path::new("/storage2/")).unwrap();
+    let factory = InMemoryBackendFactory::new(fs);
+    let slow_storage = new_in_memory_storage("/storage1/");
+    let fast_storage = new_in_memory_storage("/storage2/");
+    let slow_backend: Arc<dyn StorageBackend> = Arc::new(TestBackend {
+        inner: factory.init_backend(slow_storage.clone()).unwrap(),
+        delay: Duration::from_secs(1),
+        available: Arc::new(AtomicBool::new(true)),
+    });
+    let fast_backend = factory.init_backend(fast_storage.clone()).unwrap();
+    let replicas = vec![(slow_backend, slow_storage), (fast_backend, fast_storage)];
+    let calls = Arc::new(AtomicUsize::new(0));
+    let op: ReplicaOperation<Stat> = Arc::new({
+        let calls = calls.clone();
+        move |backend| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            backend.getattr(Path::new("/"))
+        }
+    });
+    let context = ExecutionContext::default();
+
+    for _ in 0..10 {
+        execute_with_policy(
+            replicas.clone(),
+            op.clone(),
+            OperationKind::Read,
+            ExecutionPolicy::ConcurrentlyToFirstSuccess,
+            &context,
+        )
+        .unwrap();
+    }
+    thread::sleep(Duration::from_millis(100));
+
+    // every operation reached the fast replica, but only some of them wait for the slow one
+    assert_eq!(calls.load(Ordering::SeqCst), 10 + MAX_PENDING_OPERATIONS);
+}
+
+#[rstest]
+fn test_container_policy_overrides_dfs_policy() {
+    let container_uuid = Uuid::new_v4();
+    let storages = vec![
+        new_in_memory_storage("/storage1/"),
+        new_in_memory_storage("/storage2/"),
+    ];
+    let mut path_resolver = replicated_root_container_path_resolver(storages);
+    path_resolver
+        .expect_container_uuid_by_storage()
+        .returning(move |_storage_uuid| Some(container_uuid));
+    let (mut dfs, fs) = dfs_with_fs(Rc::new(path_resolver));
+
+    fs.create_dir_all(Path::new("/storage1/")).unwrap();
+    fs.create(Path::new("/storage1/file")).unwrap();
+
+    // the file exists in one replica only, so quorum of both cannot be reached
+    dfs.set_execution_policy(ExecutionPolicy::Quorum(2));
+    assert!(dfs.getattr("/file".to_string()).is_err());
+
+    dfs.set_container_execution_policy(
+        container_uuid,
+        Some(ExecutionPolicy::SequentiallyToFirstSuccess),
+    );
+    assert_eq!(
+        dfs.getattr("/file".to_string()).unwrap().node_type,
+        NodeType::File
+    );
+
+    dfs.set_container_execution_policy(container_uuid, None);
+    assert!(dfs.getattr("/file".to_string()).is_err());
+}
+
+#[derive(Clone, Default)]
+struct EventsCollector(Arc<Mutex<Vec<DfsEvent>>>);
+
+impl EventsCollector {
+    /// Returns events delivered so far by the given DFS.
+    fn take(&self, dfs: &UnencryptedDfs) -> Vec<DfsEvent> {
+        dfs.execution_context.events.flush();
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
+impl DfsEventReceiver for EventsCollector {
+    fn receive_event(&self, event: DfsEvent) {
+        self.0.lock().unwrap().push(event);
+    }
+}
+
+#[rstest]
+fn test_unsupported_backend_is_reported_once() {
+    let unsupported_storage = Storage::new(None, "Unknown".to_owned(), serde_json::Value::Null);
+    let storage = new_in_memory_storage("/");
+    let path_resolver =
+        replicated_root_container_path_resolver(vec![unsupported_storage.clone(), storage]);
+    let (mut dfs, _fs) = dfs_with_fs(Rc::new(path_resolver));
+    let events = EventsCollector::default();
+    dfs.subscribe_events(Box::new(events.clone()));
+
+    dfs.readdir("/".to_string());
+    dfs.readdir("/".to_string());
+
+    assert_eq!(
+        events.take(&dfs),
+        vec![DfsEvent::BackendUnsupported {
+            storage_uuid: unsupported_storage.uuid(),
+            backend_type: "Unknown".to_owned(),
+        }]
+    );
+}
+
+#[rstest]
+fn test_events_of_failing_and_recovering_storages() {
+    let storage1 = new_test_storage("/storage1/", Duration::ZERO);
+    let storage2 = new_test_storage("/storage2/", Duration::ZERO);
+    let path_resolver =
+        replicated_root_container_path_resolver(vec![storage1.clone(), storage2.clone()]);
+    let available = Arc::new(AtomicBool::new(true));
+    let (mut dfs, fs) = dfs_with_switchable_fs(Rc::new(path_resolver), available.clone());
+    let events = EventsCollector::default();
+    dfs.subscribe_events(Box::new(events.clone()));
+
+    fs.create_dir_all(Path::new("/storage1/")).unwrap();
+    fs.create(Path::new("/storage1/file")).unwrap();
+
+    // missing path is a regular answer, not a failure of a storage
+    assert_eq!(
+        dfs.getattr("/missing".to_string()).unwrap_err(),
+        DfsFrontendError::NoSuchPath
+    );
+    assert_eq!(events.take(&dfs), vec![]);
+
+    available.store(false, Ordering::SeqCst);
+    assert!(dfs.getattr("/file".to_string()).is_err());
+    let all_replicas_failed = DfsEvent::AllReplicasFailed {
+        storage_uuids: vec![storage1.uuid(), storage2.uuid()],
+    };
+    assert_eq!(
+        events.take(&dfs),
+        vec![
+            DfsEvent::ReplicaFailed {
+                storage_uuid: storage1.uuid(),
+                backend_type: TEST_BACKEND_TYPE.to_owned(),
+                reason: "Storage is switched off".to_owned(),
+            },
+            DfsEvent::ReplicaFailed {
+                storage_uuid: storage2.uuid(),
+                backend_type: TEST_BACKEND_TYPE.to_owned(),
+                reason: "Storage is switched off".to_owned(),
+            },
+            all_replicas_failed.clone(),
+        ]
+    );
+
+    // already failed storages are not reported again
+    assert!(dfs.getattr("/file".to_string()).is_err());
+    assert_eq!(events.take(&dfs), vec![all_replicas_failed]);
+
+    available.store(true, Ordering::SeqCst);
+    assert!(dfs.getattr("/file".to_string()).is_ok());
+    assert_eq!(
+        events.take(&dfs),
+        vec![DfsEvent::StorageRecovered {
+            storage_uuid: storage1.uuid(),
+        }]
+    );
+}
+
+#[rstest]
+fn test_events_are_delivered_without_blocking_dfs_caller() {
+    struct LockingReceiver(Arc<Mutex<Vec<DfsEvent>>>);
+    impl DfsEventReceiver for LockingReceiver {
+        fn receive_event(&self, event: DfsEvent) {
+            self.0.lock().unwrap().push(event);
+        }
+    }
+
+    let unsupported_storage = Storage::new(None, "Unknown".to_owned(), serde_json::Value::Null);
+    let path_resolver = root_container_path_resolver(unsupported_storage.clone());
+    let (mut dfs, _fs) = dfs_with_fs(Rc::new(path_resolver));
+    let events = EventsCollector::default();
+    dfs.subscribe_events(Box::new(LockingReceiver(events.0.clone())));
+
+    // the caller of DFS holds a lock needed by the receiver, like a mutex guarding DFS itself
+    let guard = events.0.lock().unwrap();
+    dfs.readdir("/".to_string());
+    drop(guard);
+
+    assert_eq!(
+        events.take(&dfs)[0],
+        DfsEvent::BackendUnsupported {
+            storage_uuid: unsupported_storage.uuid(),
+            backend_type: "Unknown".to_owned(),
+        }
+    );
+}
+
+/// Path resolver of two containers claiming `/a` path, both with a single storage
+fn overlapping_containers_path_resolver(storage1: Storage, storage2: Storage) -> MockPathResolver {
+    let mut path_resolver = MockPathResolver::new();
+    path_resolver.expect_resolve().returning(move |path| {
+        let path_within_storage = Path::new("/").join(path.strip_prefix("/a").unwrap());
+        vec![
+            ResolvedPath::PathWithStorages {
+                path_within_storage: path_within_storage.clone(),
+                storages: vec![storage1.clone()],
+            },
+            ResolvedPath::PathWithStorages {
+                path_within_storage,
+                storages: vec![storage2.clone()],
+            },
+        ]
+    });
+    path_resolver
+}
+
+fn storage_tag(storage: &Storage) -> String {
+    storage.uuid().simple().to_string()[..8].to_owned()
+}
+
+#[rstest]
+fn test_conflicting_nodes_get_storage_tags_and_stay_accessible() {
+    let storage1 = new_in_memory_storage("/storage1/");
+    let storage2 = new_in_memory_storage("/storage2/");
+    let path_resolver = overlapping_containers_path_resolver(storage1.clone(), storage2.clone());
+    let (mut dfs, fs) = dfs_with_fs(Rc::new(path_resolver));
+
+    fs.create_dir_all(Path::new("/storage1/")).unwrap();
+    fs.create(Path::new("/storage1/file.txt")).unwrap();
+    fs.write(Path::new("/storage1/file.txt"), 0, b"first")
+        .unwrap();
+    fs.create(Path::new("/storage1/other")).unwrap();
+    fs.create_dir_all(Path::new("/storage2/")).unwrap();
+    fs.create(Path::new("/storage2/file.txt")).unwrap();
+    fs.write(Path::new("/storage2/file.txt"), 0, b"second")
+        .unwrap();
+
+    let renamed_path1 = format!("/a/file ({}).txt", storage_tag(&storage1));
+    let renamed_path2 = format!("/a/file ({}).txt", storage_tag(&storage2));
+    let mut files_descriptors = dfs.readdir("/a".to_string());
+    let mut expected = vec![
+        NodeDescriptor {
+            storage: Some(NodeStorage::new(
+                storage1.clone(),
+                PathBuf::from_str("/file.txt").unwrap(),
+            )),
+            absolute_path: PathBuf::from(&renamed_path1),
+            conflict_status: ConflictStatus::Renamed,
+        },
+        NodeDescriptor {
+            storage: Some(NodeStorage::new(
+                storage1,
+                PathBuf::from_str("/other").unwrap(),
+            )),
+            absolute_path: PathBuf::from_str("/a/other").unwrap(),
+            conflict_status: ConflictStatus::NoConflict,
+        },
+        NodeDescriptor {
+            storage: Some(NodeStorage::new(
+                storage2,
+                PathBuf::from_str("/file.txt").unwrap(),
+            )),
+            absolute_path: PathBuf::from(&renamed_path2),
+            conflict_status: ConflictStatus::Renamed,
+        },
+    ];
+    files_descriptors.sort_by(|a, b| a.absolute_path.cmp(&b.absolute_path));
+    expected.sort_by(|a, b| a.absolute_path.cmp(&b.absolute_path));
+    assert_eq!(files_descriptors, expected);
+
+    // renamed nodes are accessible by their new paths
+    assert_eq!(dfs.getattr(renamed_path2.clone()).unwrap().size, 6);
+    let file = dfs.open(renamed_path1).unwrap();
+    assert_eq!(dfs.read(&file, 0, 10).unwrap(), b"first".to_vec());
+    dfs.unlink(renamed_path2.clone()).unwrap();
+    assert_eq!(
+        dfs.getattr(renamed_path2).unwrap_err(),
+        DfsFrontendError::NoSuchPath
+    );
+
+    // the conflict is gone
+    assert_eq!(
+        dfs.readdir("/a".to_string())
+            .into_iter()
+            .map(|node| (node.absolute_path, node.conflict_status))
+            .collect::<HashMap<_, _>>(),
+        HashMap::from([
+            ("/a/file.txt".into(), ConflictStatus::NoConflict),
+            ("/a/other".into(), ConflictStatus::NoConflict),
+        ])
+    );
+}
+
+#[rstest]
+#[case("Photos", "Documents", "Photos", "Documents")]
+#[case("Backup", "Backup", "", "")]
+fn test_conflicting_nodes_get_container_names(
+    #[case] container_name1: &str,
+    #[case] container_name2: &str,
+    #[case] expected_suffix1: &str,
+    #[case] expected_suffix2: &str,
+) {
+    let storage1 = new_in_memory_storage("/storage1/");
+    let storage2 = new_in_memory_storage("/storage2/");
+    let mut path_resolver =
+        overlapping_containers_path_resolver(storage1.clone(), storage2.clone());
+    let names = HashMap::from([
+        (storage1.uuid(), container_name1.to_owned()),
+        (storage2.uuid(), container_name2.to_owned()),
+    ]);
+    path_resolver
+        .expect_container_name_by_storage()
+        .returning(move |storage_uuid| names.get(storage_uuid).cloned());
+    let (mut dfs, fs) = dfs_with_fs(Rc::new(path_resolver));
+    dfs.set_conflict_resolution_strategy(ConflictResolutionStrategy::ContainerNameSuffix);
+
+    fs.create_dir_all(Path::new("/storage1/")).unwrap();
+    fs.create(Path::new("/storage1/notes")).unwrap();
+    fs.create_dir_all(Path::new("/storage2/")).unwrap();
+    fs.create(Path::new("/storage2/notes")).unwrap();
+
+    // storage tags are used if container names do not distinguish nodes
+    let suffix = |expected: &str, storage: &Storage| match expected {
+        "" => storage_tag(storage),
+        name => name.to_owned(),
+    };
+    let mut paths: Vec<PathBuf> = dfs
+        .readdir("/a".to_string())
+        .into_iter()
+        .map(|node| node.absolute_path)
+        .collect();
+    paths.sort();
+    let mut expected = vec![
+        PathBuf::from(format!(
+            "/a/notes ({})",
+            suffix(expected_suffix1, &storage1)
+        )),
+        PathBuf::from(format!(
+            "/a/notes ({})",
+            suffix(expected_suffix2, &storage2)
+        )),
+    ];
+    expected.sort();
+    assert_eq!(paths, expected);
+
+    // renamed nodes are found by their suffixes, without listing the directory
+    for path in paths {
+        let stat = dfs.getattr(path.to_string_lossy().into_owned()).unwrap();
+        assert_eq!(stat.node_type, NodeType::File);
+    }
+    assert_eq!(
+        dfs.getattr("/a/notes (Other)".to_string()).unwrap_err(),
+        DfsFrontendError::NoSuchPath
+    );
+}
+
+#[rstest]
+fn test_conflicting_directories_are_merged() {
+    let storage1 = new_in_memory_storage("/storage1/");
+    let storage2 = new_in_memory_storage("/storage2/");
+    let path_resolver = overlapping_containers_path_resolver(storage1.clone(), storage2.clone());
+    let (mut dfs, fs) = dfs_with_fs(Rc::new(path_resolver));
+
+    fs.create_dir_all(Path::new("/storage1/dir")).unwrap();
+    fs.create(Path::new("/storage1/dir/first")).unwrap();
+    fs.create_dir_all(Path::new("/storage2/dir")).unwrap();
+    fs.create(Path::new("/storage2/dir/second")).unwrap();
+    // file conflicting with a directory is renamed, so the directory stays accessible
+    fs.create(Path::new("/storage1/mixed")).unwrap();
+    fs.create_dir_all(Path::new("/storage2/mixed")).unwrap();
+    fs.create(Path::new("/storage2/mixed/third")).unwrap();
+
+    let mut files_descriptors = dfs
+        .readdir("/a".to_string())
+        .into_iter()
+        .map(|node| (node.absolute_path, node.conflict_status))
+        .collect::<Vec<_>>();
+    files_descriptors.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        files_descriptors,
+        vec![
+            ("/a/dir".into(), ConflictStatus::NoConflict),
+            ("/a/mixed".into(), ConflictStatus::NoConflict),
+            (
+                format!("/a/mixed ({})", storage_tag(&storage1)).into(),
+                ConflictStatus::Renamed
+            ),
+        ]
+    );
+
+    let mut dir_contents = dfs
+        .readdir("/a/dir".to_string())
+        .into_iter()
+        .map(|node| node.absolute_path)
+        .collect::<Vec<_>>();
+    dir_contents.sort();
+    assert_eq!(
+        dir_contents,
+        vec![
+            PathBuf::from("/a/dir/first"),
+            PathBuf::from("/a/dir/second")
+        ]
+    );
+    assert_eq!(
+        dfs.readdir("/a/mixed".to_string())
+            .into_iter()
+            .map(|node| node.absolute_path)
+            .collect::<Vec<_>>(),
+        vec![PathBuf::from("/a/mixed/third")]
+    );
+    assert_eq!(
+        dfs.getattr("/a/mixed".to_string()).unwrap().node_type,
+        NodeType::Dir
+    );
+    assert_eq!(
+        dfs.getattr(format!("/a/mixed ({})", storage_tag(&storage1)))
+            .unwrap()
+            .node_type,
+        NodeType::File
+    );
+}
+
+#[rstest]
+fn test_directory_conflicting_with_virtual_directory_is_merged() {
+    let storage = new_in_memory_storage("/storage1/");
+    let mut path_resolver = MockPathResolver::new();
+    path_resolver.expect_resolve().returning({
+        let storage = storage.clone();
+        move |path| match path.to_str().unwrap() {
+            "/a" => vec![
+                ResolvedPath::PathWithStorages {
+                    path_within_storage: "/".into(),
+                    storages: vec![storage.clone()],
+                },
+                ResolvedPath::VirtualPath("/b".into()),
+            ],
+            _ => vec![],
+        }
+    });
+    let (mut dfs, fs) = dfs_with_fs(Rc::new(path_resolver));
+
+    fs.create_dir_all(Path::new("/storage1/b")).unwrap();
+
+    assert_eq!(
+        dfs.readdir("/a".to_string()),
+        vec![NodeDescriptor {
+            storage: Some(NodeStorage::new(storage, PathBuf::from_str("/b").unwrap())),
+            absolute_path: PathBuf::from_str("/a/b").unwrap(),
+            conflict_status: ConflictStatus::NoConflict,
+        }]
+    );
+}
@@ -0,0 +1,465 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::ffi::OsStr;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use fuser::{
+    FileAttr,
+    FileType,
+    Filesystem,
+    MountOption,
+    ReplyAttr,
+    ReplyCreate,
+    ReplyData,
+    ReplyDirectory,
+    ReplyEmpty,
+    ReplyEntry,
+    ReplyOpen,
+    ReplyWrite,
+    Request,
+    TimeOrNow,
+};
+use libc::c_int;
+use wildland_corex::dfs::interface::{
+    DfsFrontend,
+    DfsFrontendError,
+    FileHandle,
+    NodeType,
+    Stat,
+    UnixTimestamp,
+};
+
+use crate::inodes::{Inodes, ROOT_INODE};
+
+/// How long the kernel may cache attributes and entries
+const TTL: Duration = Duration::from_secs(1);
+const BLOCK_SIZE: u32 = 512;
+
+fn errno(error: &DfsFrontendError) -> c_int {
+    match error {
+        DfsFrontendError::NoSuchPath => libc::ENOENT,
+        DfsFrontendError::PathAlreadyExists => libc::EEXIST,
+        DfsFrontendError::NotADirectory => libc::ENOTDIR,
+        DfsFrontendError::NotAFile => libc::EISDIR,
+        DfsFrontendError::DirNotEmpty => libc::ENOTEMPTY,
+        DfsFrontendError::NameTooLong => libc::ENAMETOOLONG,
+        DfsFrontendError::ReadOnlyPath => libc::EROFS,
+        DfsFrontendError::MoveBetweenContainers => libc::EXDEV,
+        DfsFrontendError::InvalidFileHandle => libc::EBADF,
+        DfsFrontendError::StorageNotResponsive | DfsFrontendError::Generic(_) => libc::EIO,
+    }
+}
+
+fn file_type(node_type: NodeType) -> FileType {
+    match node_type {
+        NodeType::Dir => FileType::Directory,
+        NodeType::Symlink => FileType::Symlink,
+        NodeType::File | NodeType::Other => FileType::RegularFile,
+    }
+}
+
+fn system_time(timestamp: Option<UnixTimestamp>) -> SystemTime {
+    timestamp
+        .map(|timestamp| UNIX_EPOCH + Duration::new(timestamp.sec, timestamp.nano_sec))
+        .unwrap_or(UNIX_EPOCH)
+}
+
+fn dfs_path(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn file_attr(inode: u64, stat: &Stat, uid: u32, gid: u32) -> FileAttr {
+    let kind = file_type(stat.node_type);
+    let is_dir = kind == FileType::Directory;
+    FileAttr {
+        ino: inode,
+        size: stat.size,
+        blocks: stat.size.div_ceil(BLOCK_SIZE as u64),
+        atime: system_time(stat.access_time),
+        mtime: system_time(stat.modification_time),
+        ctime: system_time(stat.change_time),
+        crtime: system_time(stat.change_time),
+        kind,
+        perm: if is_dir { 0o755 } else { 0o644 },
+        nlink: if is_dir { 2 } else { 1 },
+        uid,
+        gid,
+        rdev: 0,
+        blksize: BLOCK_SIZE,
+        flags: 0,
+    }
+}
+
+/// Exposes [`DfsFrontend`] as a FUSE filesystem. Virtual nodes are presented as directories.
+pub(crate) struct WildlandFs {
+    dfs: Arc<Mutex<dyn DfsFrontend>>,
+    inodes: Inodes,
+    uid: u32,
+    gid: u32,
+}
+
+impl WildlandFs {
+    pub(crate) fn new(dfs: Arc<Mutex<dyn DfsFrontend>>) -> Self {
+        // SAFETY: getting ids of the current process never fails
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        Self {
+            dfs,
+            inodes: Inodes::default(),
+            uid,
+            gid,
+        }
+    }
+
+    fn dfs(&self) -> MutexGuard<'_, dyn DfsFrontend + 'static> {
+        self.dfs.lock().expect("Poisoned Mutex")
+    }
+
+    /// Returns attributes of the node assigning an inode to it if needed.
+    fn attr(&mut self, path: &Path) -> Result<FileAttr, c_int> {
+        let stat = self.dfs().getattr(dfs_path(path)).map_err(|e| errno(&e))?;
+        let inode = self.inodes.inode(path);
+        Ok(file_attr(inode, &stat, self.uid, self.gid))
+    }
+
+    fn path(&self, inode: u64) -> Result<&Path, c_int> {
+        self.inodes.path(inode).ok_or(libc::ENOENT)
+    }
+
+    fn child_path(&self, parent: u64, name: &OsStr) -> Result<std::path::PathBuf, c_int> {
+        self.inodes.child_path(parent, name).ok_or(libc::ENOENT)
+    }
+
+    fn directory_entries(&mut self, inode: u64) -> Result<Vec<(u64, FileType, String)>, c_int> {
+        let path = self.path(inode)?.to_owned();
+        let parent_inode = path
+            .parent()
+            .map(|parent| self.inodes.inode(parent))
+            .unwrap_or(ROOT_INODE);
+        let mut entries = vec![
+            (inode, FileType::Directory, ".".to_owned()),
+            (parent_inode, FileType::Directory, "..".to_owned()),
+        ];
+
+        let nodes = self.dfs().readdir(dfs_path(&path));
+        for node in nodes {
+            let name = match node.absolute_path.file_name() {
+                Some(name) => name.to_string_lossy().into_owned(),
+                None => continue,
+            };
+            let kind = match node.storage {
+                None => FileType::Directory,
+                Some(_) => self
+                    .dfs()
+                    .getattr(dfs_path(&node.absolute_path))
+                    .map(|stat| file_type(stat.node_type))
+                    .unwrap_or(FileType::RegularFile),
+            };
+            entries.push((self.inodes.inode(&node.absolute_path), kind, name));
+        }
+        Ok(entries)
+    }
+}
+
+impl Filesystem for WildlandFs {
+    fn lookup(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        match self
+            .child_path(parent, name)
+            .and_then(|path| self.attr(&path))
+        {
+            Ok(attr) => reply.entry(&TTL, &attr, 0),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn getattr(&mut self, _req: &Request<'_>, ino: u64, reply: ReplyAttr) {
+        match self
+            .path(ino)
+            .map(Path::to_owned)
+            .and_then(|path| self.attr(&path))
+        {
+            Ok(attr) => reply.attr(&TTL, &attr),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    /// Only changing the size is supported, other attributes are left untouched.
+    fn setattr(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _mode: Option<u32>,
+        _uid: Option<u32>,
+        _gid: Option<u32>,
+        size: Option<u64>,
+        _atime: Option<TimeOrNow>,
+        _mtime: Option<TimeOrNow>,
+        _ctime: Option<SystemTime>,
+        _fh: Option<u64>,
+        _crtime: Option<SystemTime>,
+        _chgtime: Option<SystemTime>,
+        _bkuptime: Option<SystemTime>,
+        _flags: Option<u32>,
+        reply: ReplyAttr,
+    ) {
+        let result = self.path(ino).map(Path::to_owned).and_then(|path| {
+            if let Some(size) = size {
+                self.dfs()
+                    .truncate(dfs_path(&path), size)
+                    .map_err(|e| errno(&e))?;
+            }
+            self.attr(&path)
+        });
+        match result {
+            Ok(attr) => reply.attr(&TTL, &attr),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn mkdir(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        reply: ReplyEntry,
+    ) {
+        let result = self.child_path(parent, name).and_then(|path| {
+            self.dfs().mkdir(dfs_path(&path)).map_err(|e| errno(&e))?;
+            self.attr(&path)
+        });
+        match result {
+            Ok(attr) => reply.entry(&TTL, &attr, 0),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn unlink(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        let result = self.child_path(parent, name).and_then(|path| {
+            self.dfs().unlink(dfs_path(&path)).map_err(|e| errno(&e))?;
+            self.inodes.remove(&path);
+            Ok(())
+        });
+        match result {
+            Ok(()) => reply.ok(),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn rmdir(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        let result = self.child_path(parent, name).and_then(|path| {
+            self.dfs().rmdir(dfs_path(&path)).map_err(|e| errno(&e))?;
+            self.inodes.remove(&path);
+            Ok(())
+        });
+        match result {
+            Ok(()) => reply.ok(),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn rename(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        newparent: u64,
+        newname: &OsStr,
+        flags: u32,
+        reply: ReplyEmpty,
+    ) {
+        // flags like RENAME_EXCHANGE cannot be implemented with DFS operations atomically
+        if flags != 0 {
+            return reply.error(libc::EINVAL);
+        }
+        let result = self.child_path(parent, name).and_then(|old_path| {
+            let new_path = self.child_path(newparent, newname)?;
+            self.dfs()
+                .rename(dfs_path(&old_path), dfs_path(&new_path))
+                .map_err(|e| errno(&e))?;
+            self.inodes.rename(&old_path, &new_path);
+            Ok(())
+        });
+        match result {
+            Ok(()) => reply.ok(),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn open(&mut self, _req: &Request<'_>, ino: u64, _flags: i32, reply: ReplyOpen) {
+        let result = self
+            .path(ino)
+            .map(Path::to_owned)
+            .and_then(|path| self.dfs().open(dfs_path(&path)).map_err(|e| errno(&e)));
+        match result {
+            Ok(file) => reply.opened(file.descriptor(), 0),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn read(
+        &mut self,
+        _req: &Request<'_>,
+        _ino: u64,
+        fh: u64,
+        offset: i64,
+        size: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyData,
+    ) {
+        match self
+            .dfs()
+            .read(&FileHandle::new(fh), offset as u64, size as usize)
+        {
+            Ok(data) => reply.data(&data),
+            Err(e) => reply.error(errno(&e)),
+        }
+    }
+
+    fn write(
+        &mut self,
+        _req: &Request<'_>,
+        _ino: u64,
+        fh: u64,
+        offset: i64,
+        data: &[u8],
+        _write_flags: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyWrite,
+    ) {
+        match self
+            .dfs()
+            .write(&FileHandle::new(fh), offset as u64, data.to_vec())
+        {
+            Ok(written) => reply.written(written as u32),
+            Err(e) => reply.error(errno(&e)),
+        }
+    }
+
+    fn release(
+        &mut self,
+        _req: &Request<'_>,
+        _ino: u64,
+        fh: u64,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        _flush: bool,
+        reply: ReplyEmpty,
+    ) {
+        match self.dfs().close(&FileHandle::new(fh)) {
+            Ok(()) => reply.ok(),
+            Err(e) => reply.error(errno(&e)),
+        }
+    }
+
+    fn readdir(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        mut reply: ReplyDirectory,
+    ) {
+        let entries = match self.directory_entries(ino) {
+            Ok(entries) => entries,
+            Err(errno) => return reply.error(errno),
+        };
+        for (index, (inode, kind, name)) in entries.into_iter().enumerate().skip(offset as usize) {
+            // offset of an entry is the offset of the next one
+            if reply.add(inode, (index + 1) as i64, kind, name) {
+                break;
+            }
+        }
+        reply.ok()
+    }
+
+    fn create(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        _flags: i32,
+        reply: ReplyCreate,
+    ) {
+        let result = self.child_path(parent, name).and_then(|path| {
+            let file = self.dfs().create(dfs_path(&path)).map_err(|e| errno(&e))?;
+            Ok((self.attr(&path)?, file))
+        });
+        match result {
+            Ok((attr, file)) => reply.created(&TTL, &attr, 0, file.descriptor(), 0),
+            Err(errno) => reply.error(errno),
+        }
+    }
+}
+
+/// Mounts the filesystem and blocks until it is unmounted (e.g. with `fusermount -u`).
+pub(crate) fn mount(dfs: Arc<Mutex<dyn DfsFrontend>>, mountpoint: &Path) -> std::io::Result<()> {
+    let options = [
+        MountOption::FSName("wildland".to_owned()),
+        MountOption::DefaultPermissions,
+    ];
+    fuser::mount2(WildlandFs::new(dfs), mountpoint, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_converting_stat_to_file_attr() {
+        let stat = Stat {
+            node_type: NodeType::File,
+            size: 1025,
+            access_time: None,
+            modification_time: Some(UnixTimestamp {
+                sec: 10,
+                nano_sec: 5,
+            }),
+            change_time: None,
+        };
+
+        let attr = file_attr(7, &stat, 1000, 100);
+        assert_eq!(attr.ino, 7);
+        assert_eq!(attr.kind, FileType::RegularFile);
+        assert_eq!(attr.blocks, 3);
+        assert_eq!(attr.perm, 0o644);
+        assert_eq!(attr.mtime, UNIX_EPOCH + Duration::new(10, 5));
+        assert_eq!(attr.atime, UNIX_EPOCH);
+        assert_eq!((attr.uid, attr.gid), (1000, 100));
+        assert_eq!(
+            file_attr(8, &Stat::virtual_dir(), 1000, 100).kind,
+            FileType::Directory
+        );
+    }
+
+    #[test]
+    fn test_mapping_errors_to_errno() {
+        assert_eq!(errno(&DfsFrontendError::NoSuchPath), libc::ENOENT);
+        assert_eq!(errno(&DfsFrontendError::ReadOnlyPath), libc::EROFS);
+        assert_eq!(errno(&DfsFrontendError::MoveBetweenContainers), libc::EXDEV);
+        assert_eq!(errno(&DfsFrontendError::Generic("".to_owned())), libc::EIO);
+    }
+}
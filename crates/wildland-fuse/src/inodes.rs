@@ -0,0 +1,135 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// Inode of the forest root, the same as `FUSE_ROOT_ID`
+pub(crate) const ROOT_INODE: u64 = 1;
+
+/// Assigns FUSE inode numbers to paths of the user's forest.
+///
+/// DFS identifies nodes by their paths only, so an inode is assigned to a path when the path is
+/// looked up for the first time and it is kept until the node is removed.
+///
+/// TODO WILX-370 release inodes forgotten by the kernel
+pub(crate) struct Inodes {
+    paths: HashMap<u64, PathBuf>,
+    inodes: HashMap<PathBuf, u64>,
+    last_inode: u64,
+}
+
+impl Default for Inodes {
+    fn default() -> Self {
+        Self {
+            paths: HashMap::from([(ROOT_INODE, PathBuf::from("/"))]),
+            inodes: HashMap::from([(PathBuf::from("/"), ROOT_INODE)]),
+            last_inode: ROOT_INODE,
+        }
+    }
+}
+
+impl Inodes {
+    pub(crate) fn path(&self, inode: u64) -> Option<&Path> {
+        self.paths.get(&inode).map(PathBuf::as_path)
+    }
+
+    pub(crate) fn child_path(&self, parent: u64, name: &OsStr) -> Option<PathBuf> {
+        self.path(parent).map(|parent| parent.join(name))
+    }
+
+    /// Returns inode of the path assigning a new one if the path has not been seen yet.
+    pub(crate) fn inode(&mut self, path: &Path) -> u64 {
+        if let Some(inode) = self.inodes.get(path) {
+            return *inode;
+        }
+        self.last_inode += 1;
+        self.paths.insert(self.last_inode, path.to_owned());
+        self.inodes.insert(path.to_owned(), self.last_inode);
+        self.last_inode
+    }
+
+    /// Moves inodes of the path and all of its descendants, so they stay valid after renaming.
+    pub(crate) fn rename(&mut self, old_path: &Path, new_path: &Path) {
+        self.remove(new_path);
+        let moved: Vec<(PathBuf, u64)> = self
+            .inodes
+            .iter()
+            .filter(|(path, _)| path.starts_with(old_path))
+            .map(|(path, inode)| (path.clone(), *inode))
+            .collect();
+        for (path, inode) in moved {
+            let path_after_rename = new_path.join(path.strip_prefix(old_path).unwrap());
+            self.inodes.remove(&path);
+            self.inodes.insert(path_after_rename.clone(), inode);
+            self.paths.insert(inode, path_after_rename);
+        }
+    }
+
+    /// Forgets inodes of the path and all of its descendants.
+    pub(crate) fn remove(&mut self, path: &Path) {
+        let paths = &mut self.paths;
+        self.inodes.retain(|inode_path, inode| {
+            let removed = inode_path.starts_with(path) && *inode != ROOT_INODE;
+            if removed {
+                paths.remove(inode);
+            }
+            !removed
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_assigning_inodes() {
+        let mut inodes = Inodes::default();
+        assert_eq!(inodes.path(ROOT_INODE), Some(Path::new("/")));
+
+        let file_path = inodes.child_path(ROOT_INODE, OsStr::new("file")).unwrap();
+        let file_inode = inodes.inode(&file_path);
+        assert_ne!(file_inode, ROOT_INODE);
+        assert_eq!(inodes.inode(Path::new("/file")), file_inode);
+        assert_eq!(inodes.path(file_inode), Some(Path::new("/file")));
+        assert_eq!(inodes.child_path(file_inode + 1, OsStr::new("file")), None);
+    }
+
+    #[test]
+    fn test_renaming_and_removing_dir_with_content() {
+        let mut inodes = Inodes::default();
+        let dir_inode = inodes.inode(Path::new("/dir"));
+        let file_inode = inodes.inode(Path::new("/dir/file"));
+        let other_inode = inodes.inode(Path::new("/directory"));
+
+        inodes.rename(Path::new("/dir"), Path::new("/a/b"));
+        assert_eq!(inodes.path(dir_inode), Some(Path::new("/a/b")));
+        assert_eq!(inodes.path(file_inode), Some(Path::new("/a/b/file")));
+        assert_eq!(inodes.path(other_inode), Some(Path::new("/directory")));
+        assert_eq!(inodes.inode(Path::new("/a/b/file")), file_inode);
+
+        inodes.remove(Path::new("/a/b"));
+        assert_eq!(inodes.path(dir_inode), None);
+        assert_eq!(inodes.path(file_inode), None);
+        assert_eq!(inodes.path(other_inode), Some(Path::new("/directory")));
+        assert_ne!(inodes.inode(Path::new("/a/b")), dir_inode);
+    }
+}
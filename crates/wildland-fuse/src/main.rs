@@ -0,0 +1,104 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Mounts the user's Wildland forest at a local directory through FUSE, so it can be browsed with
+//! regular tools.
+//!
+//! Usage: `wildland-fuse <config file> <lss file> <mountpoint>`
+//!
+//! - config file - JSON formatted CargoLib configuration, see [`wildland_cargo_lib::api::config`],
+//! - lss file - encrypted local secure storage with a user created before,
+//! - mountpoint - existing, empty directory.
+//!
+//! The LSS is unlocked with a key file pointed by `WILDLAND_LSS_KEY_FILE` environment variable or,
+//! if it is not set, with a passphrase from `WILDLAND_LSS_PASSPHRASE`.
+//!
+//! All containers of the user are mounted. The filesystem stays mounted until it is unmounted with
+//! `fusermount -u <mountpoint>`.
+
+#[cfg(target_os = "linux")]
+mod fs;
+#[cfg(target_os = "linux")]
+mod inodes;
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use wildland_cargo_lib::api::cargo_lib::create_cargo_lib;
+use wildland_cargo_lib::api::config::parse_config;
+use wildland_cargo_lib::api::CargoLib;
+use wildland_corex::{EncryptedFileLss, LssSecret};
+
+fn lss_secret() -> Result<LssSecret, anyhow::Error> {
+    if let Some(key_path) = std::env::var_os("WILDLAND_LSS_KEY_FILE") {
+        return Ok(LssSecret::KeyFile(PathBuf::from(key_path)));
+    }
+    std::env::var("WILDLAND_LSS_PASSPHRASE")
+        .map(LssSecret::Passphrase)
+        .context("Neither WILDLAND_LSS_KEY_FILE nor WILDLAND_LSS_PASSPHRASE is set")
+}
+
+fn init_cargo_lib(config_path: &Path, lss_path: &Path) -> Result<CargoLib, anyhow::Error> {
+    let config = parse_config(std::fs::read(config_path).context("Could not read config")?)?;
+    // CargoLib requires LSS to live for the whole program execution
+    let lss: &'static EncryptedFileLss = Box::leak(Box::new(
+        EncryptedFileLss::open(lss_path, &lss_secret()?).context("Could not open LSS")?,
+    ));
+    let cargo_lib = create_cargo_lib(lss, config)?;
+    let cargo_lib = cargo_lib.lock().expect("Poisoned Mutex").clone();
+    Ok(cargo_lib)
+}
+
+fn mount_containers(cargo_lib: &CargoLib) -> Result<(), anyhow::Error> {
+    let user = cargo_lib.user_api().get_user()?;
+    for container in user.get_containers()? {
+        if let Err(e) = user.mount(&container) {
+            tracing::warn!("Could not mount container: {e}");
+        }
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), anyhow::Error> {
+    let args: Vec<String> = std::env::args().collect();
+    let (config_path, lss_path, mountpoint) = match args.as_slice() {
+        [_, config_path, lss_path, mountpoint] => (
+            Path::new(config_path),
+            Path::new(lss_path),
+            Path::new(mountpoint),
+        ),
+        _ => {
+            return Err(anyhow!(
+                "Usage: wildland-fuse <config file> <lss file> <mountpoint>"
+            ))
+        }
+    };
+
+    let cargo_lib = init_cargo_lib(config_path, lss_path)?;
+    mount_containers(&cargo_lib)?;
+
+    #[cfg(target_os = "linux")]
+    {
+        fs::mount(cargo_lib.dfs_api(), mountpoint)
+            .with_context(|| format!("Could not mount forest at {}", mountpoint.display()))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (cargo_lib, mountpoint);
+        Err(anyhow!("FUSE frontend is supported on Linux only"))
+    }
+}
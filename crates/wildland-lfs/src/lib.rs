@@ -17,47 +17,181 @@
 
 mod template;
 
-use std::fs::read_dir;
-use std::path::{Path, PathBuf};
-use std::rc::Rc;
+use std::fs::{self, read_dir, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
 
 use anyhow::anyhow;
 use template::LocalFilesystemStorageTemplate;
-use wildland_dfs::storage_backend::StorageBackend;
+use wildland_dfs::storage_backend::{StorageBackend, StorageBackendError};
 use wildland_dfs::unencrypted::StorageBackendFactory;
-use wildland_dfs::Storage;
+use wildland_dfs::{NodeType, Stat, Storage, UnixTimestamp};
 
 #[derive(Debug)]
 pub struct LocalFilesystemStorage {
     base_dir: PathBuf,
 }
 
+impl LocalFilesystemStorage {
+    /// Maps a path within the storage to a path in the local filesystem.
+    ///
+    /// `..` components cannot go above `base_dir` (just like `/..` is `/`) and none of the parent
+    /// directories of the resulting path may be a symlink pointing outside of `base_dir`. The last
+    /// component is not checked, so the path may be used by operations that do not follow symlinks.
+    fn to_local_path(&self, path: &Path) -> Result<PathBuf, StorageBackendError> {
+        let mut local_path = self.base_dir.clone();
+        for component in path.components() {
+            match component {
+                Component::Normal(name) => local_path.push(name),
+                Component::ParentDir => {
+                    if local_path != self.base_dir {
+                        local_path.pop();
+                    }
+                }
+                Component::RootDir | Component::CurDir => {}
+                Component::Prefix(_) => {
+                    return Err(anyhow!("Unsupported path within storage: {path:?}").into())
+                }
+            }
+        }
+        if let Some(parent) = local_path.parent() {
+            if local_path != self.base_dir {
+                self.ensure_within_base_dir(parent)?;
+            }
+        }
+        Ok(local_path)
+    }
+
+    /// Same as [`LocalFilesystemStorage::to_local_path`] but also checks the last component, so the
+    /// path may be used by operations following symlinks.
+    fn to_followed_local_path(&self, path: &Path) -> Result<PathBuf, StorageBackendError> {
+        let local_path = self.to_local_path(path)?;
+        self.ensure_within_base_dir(&local_path)?;
+        Ok(local_path)
+    }
+
+    fn ensure_within_base_dir(&self, local_path: &Path) -> Result<(), StorageBackendError> {
+        let canonical_path = match local_path.canonicalize() {
+            Ok(canonical_path) => canonical_path,
+            // operation on a non-existent path fails anyway
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        if canonical_path.starts_with(self.base_dir.canonicalize()?) {
+            Ok(())
+        } else {
+            Err(anyhow!("Path {local_path:?} points outside of the storage").into())
+        }
+    }
+}
+
 impl StorageBackend for LocalFilesystemStorage {
-    fn readdir(&self, path: &Path) -> Result<Vec<PathBuf>, anyhow::Error> {
-        let relative_path = if path.is_absolute() {
-            path.strip_prefix("/").unwrap()
+    fn readdir(&self, path: &Path) -> Result<Vec<PathBuf>, StorageBackendError> {
+        read_dir(self.to_followed_local_path(path)?)?
+            .map(|entry_result| {
+                Ok(Path::new("/").join(
+                    entry_result?
+                        .path()
+                        .strip_prefix(&self.base_dir)
+                        .map_err(|e| anyhow!(e))?,
+                ))
+            })
+            .collect()
+    }
+
+    fn getattr(&self, path: &Path) -> Result<Stat, StorageBackendError> {
+        let metadata = fs::symlink_metadata(self.to_local_path(path)?)?;
+        let node_type = if metadata.is_dir() {
+            NodeType::Dir
+        } else if metadata.is_file() {
+            NodeType::File
+        } else if metadata.is_symlink() {
+            NodeType::Symlink
         } else {
-            path
+            NodeType::Other
         };
-        read_dir(self.base_dir.join(relative_path))
-            .map_err(|e| anyhow!(e))
-            .and_then(|readdir| {
-                readdir
-                    .into_iter()
-                    .map(|entry_result| {
-                        Ok(Path::new("/").join(entry_result?.path().strip_prefix(&self.base_dir)?))
-                    })
-                    .collect::<Result<Vec<_>, anyhow::Error>>()
-            })
+        Ok(Stat {
+            node_type,
+            size: if metadata.is_dir() { 0 } else { metadata.len() },
+            access_time: metadata.accessed().ok().map(UnixTimestamp::from),
+            modification_time: metadata.modified().ok().map(UnixTimestamp::from),
+            change_time: None,
+        })
+    }
+
+    fn read(&self, path: &Path, offset: u64, count: usize) -> Result<Vec<u8>, StorageBackendError> {
+        let mut file = File::open(self.to_followed_local_path(path)?)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut buf = Vec::new();
+        file.take(count as u64).read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn write(&self, path: &Path, offset: u64, buf: &[u8]) -> Result<usize, StorageBackendError> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(self.to_followed_local_path(path)?)?;
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn create(&self, path: &Path) -> Result<(), StorageBackendError> {
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(self.to_local_path(path)?)?;
+        Ok(())
+    }
+
+    fn unlink(&self, path: &Path) -> Result<(), StorageBackendError> {
+        Ok(fs::remove_file(self.to_local_path(path)?)?)
+    }
+
+    fn mkdir(&self, path: &Path) -> Result<(), StorageBackendError> {
+        Ok(fs::create_dir(self.to_local_path(path)?)?)
+    }
+
+    fn rmdir(&self, path: &Path) -> Result<(), StorageBackendError> {
+        let local_path = self.to_followed_local_path(path)?;
+        if read_dir(&local_path)?.next().is_some() {
+            return Err(StorageBackendError::DirNotEmpty);
+        }
+        Ok(fs::remove_dir(local_path)?)
+    }
+
+    fn rename(&self, old_path: &Path, new_path: &Path) -> Result<(), StorageBackendError> {
+        Ok(fs::rename(
+            self.to_local_path(old_path)?,
+            self.to_local_path(new_path)?,
+        )?)
+    }
+
+    fn truncate(&self, path: &Path, size: u64) -> Result<(), StorageBackendError> {
+        let file = OpenOptions::new()
+            .write(true)
+            .open(self.to_followed_local_path(path)?)?;
+        Ok(file.set_len(size)?)
     }
 }
 
 pub struct LfsBackendFactory {}
 impl StorageBackendFactory for LfsBackendFactory {
-    fn init_backend(&self, storage: Storage) -> Result<Rc<dyn StorageBackend>, anyhow::Error> {
+    fn init_backend(&self, storage: Storage) -> Result<Arc<dyn StorageBackend>, anyhow::Error> {
         let template: LocalFilesystemStorageTemplate =
             serde_json::from_value(storage.data().clone())?;
-        Ok(Rc::new(LocalFilesystemStorage {
+        // rendered prefix may contain e.g. container name, so it must not lead outside of local_dir
+        if !Path::new(&template.container_prefix)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(anyhow!(
+                "Invalid container prefix: {}",
+                template.container_prefix
+            ));
+        }
+        Ok(Arc::new(LocalFilesystemStorage {
             base_dir: template.local_dir.join(template.container_prefix),
         }))
     }
@@ -65,15 +199,66 @@ impl StorageBackendFactory for LfsBackendFactory {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
     use std::fs::{create_dir, File};
+    use std::rc::Rc;
     use std::str::FromStr;
 
     use pretty_assertions::assert_eq;
     use serde_json::json;
     use tempdir::TempDir;
+    use wildland_corex::dfs::interface::{DfsFrontend, DfsFrontendError};
+    use wildland_corex::{MockPathResolver, ResolvedPath, SymmetricKey};
+    use wildland_dfs::encrypted::{EncryptedDfs, MockEncryptionKeyProvider};
 
     use super::*;
 
+    /// Creates DFS with a single container stored in `books` subdirectory of `local_dir`
+    fn encrypted_dfs(local_dir: &Path, container_key: SymmetricKey) -> EncryptedDfs {
+        let storage = Storage::new(
+            Some("Test LFS".to_owned()),
+            "LFS".to_owned(),
+            json!({
+                "local_dir": local_dir,
+                "container_prefix": "books"
+            }),
+        );
+        let mut path_resolver = MockPathResolver::new();
+        path_resolver.expect_resolve().returning(move |path| {
+            vec![ResolvedPath::PathWithStorages {
+                path_within_storage: path.into(),
+                storages: vec![storage.clone()],
+            }]
+        });
+        let mut key_provider = MockEncryptionKeyProvider::new();
+        key_provider
+            .expect_container_key()
+            .returning(move |_| Ok(container_key.clone()));
+        let factory: Box<dyn StorageBackendFactory> = Box::new(LfsBackendFactory {});
+
+        EncryptedDfs::new(
+            Rc::new(path_resolver),
+            HashMap::from([("LFS".to_owned(), factory)]),
+            Rc::new(key_provider),
+        )
+    }
+
+    fn files_in_dir(dir: &Path) -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .unwrap()
+            .flat_map(|entry| {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    let mut files = files_in_dir(&path);
+                    files.push(path);
+                    files
+                } else {
+                    vec![path]
+                }
+            })
+            .collect()
+    }
+
     #[test]
     fn test_reading_file_in_root_of_lfs_backend() {
         let tmpdir = TempDir::new("lfs").unwrap(); // storage provider dir
@@ -117,4 +302,316 @@ mod tests {
 
         assert_eq!(files, vec![PathBuf::from_str("/dir/file1").unwrap()]);
     }
+
+    #[test]
+    fn test_writing_and_reading_file_of_lfs_backend() {
+        let tmpdir = TempDir::new("lfs").unwrap();
+        let storage = Storage::new(
+            Some("Test LFS".to_owned()),
+            "LFS".to_owned(),
+            json!({
+                "local_dir": tmpdir.path(),
+                "container_prefix": "books"
+            }),
+        );
+        let factory = LfsBackendFactory {};
+        let backend = factory.init_backend(storage).unwrap();
+
+        create_dir(tmpdir.path().join("books")).unwrap();
+        backend.mkdir(Path::new("/dir")).unwrap();
+        backend.create(Path::new("/dir/file1")).unwrap();
+        backend
+            .write(Path::new("/dir/file1"), 0, b"hello world")
+            .unwrap();
+        backend.truncate(Path::new("/dir/file1"), 5).unwrap();
+
+        assert_eq!(
+            backend.read(Path::new("/dir/file1"), 1, 100).unwrap(),
+            b"ello".to_vec()
+        );
+        let stat = backend.getattr(Path::new("/dir/file1")).unwrap();
+        assert_eq!(stat.node_type, NodeType::File);
+        assert_eq!(stat.size, 5);
+        assert!(matches!(
+            backend.create(Path::new("/dir/file1")),
+            Err(StorageBackendError::PathAlreadyExists)
+        ));
+        assert!(matches!(
+            backend.rmdir(Path::new("/dir")),
+            Err(StorageBackendError::DirNotEmpty)
+        ));
+
+        backend
+            .rename(Path::new("/dir/file1"), Path::new("/file2"))
+            .unwrap();
+        backend.rmdir(Path::new("/dir")).unwrap();
+        backend.unlink(Path::new("/file2")).unwrap();
+
+        assert_eq!(
+            backend.readdir(Path::new("/")).unwrap(),
+            Vec::<PathBuf>::new()
+        );
+    }
+
+    #[test]
+    fn test_encrypted_dfs_round_trip_on_lfs_backend() {
+        let tmpdir = TempDir::new("lfs").unwrap();
+        create_dir(tmpdir.path().join("books")).unwrap();
+        let mut dfs = encrypted_dfs(tmpdir.path(), SymmetricKey::from_bytes([1; 32]));
+
+        dfs.mkdir("/secret_dir".to_owned()).unwrap();
+        let file = dfs.create("/secret_dir/secret_file".to_owned()).unwrap();
+        dfs.write(&file, 0, b"secret content".to_vec()).unwrap();
+        dfs.write(&file, 7, b"CONTENT!".to_vec()).unwrap();
+        dfs.close(&file).unwrap();
+
+        let nodes = dfs.readdir("/secret_dir".to_owned());
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(
+            nodes[0].absolute_path,
+            PathBuf::from("/secret_dir/secret_file")
+        );
+        assert_eq!(
+            dfs.getattr("/secret_dir/secret_file".to_owned())
+                .unwrap()
+                .size,
+            15
+        );
+        let file = dfs.open("/secret_dir/secret_file".to_owned()).unwrap();
+        assert_eq!(
+            dfs.read(&file, 0, 100).unwrap(),
+            b"secret CONTENT!".to_vec()
+        );
+        assert_eq!(dfs.read(&file, 7, 4).unwrap(), b"CONT".to_vec());
+        dfs.close(&file).unwrap();
+
+        dfs.truncate("/secret_dir/secret_file".to_owned(), 6)
+            .unwrap();
+        dfs.rename(
+            "/secret_dir/secret_file".to_owned(),
+            "/secret_dir/renamed_file".to_owned(),
+        )
+        .unwrap();
+        let file = dfs.open("/secret_dir/renamed_file".to_owned()).unwrap();
+        assert_eq!(dfs.read(&file, 0, 100).unwrap(), b"secret".to_vec());
+        dfs.close(&file).unwrap();
+
+        // nothing stored on disk reveals names or content
+        let stored_paths = files_in_dir(&tmpdir.path().join("books"));
+        assert_eq!(stored_paths.len(), 2);
+        for stored_path in stored_paths {
+            let name = stored_path.file_name().unwrap().to_string_lossy();
+            assert!(!name.contains("secret") && !name.contains("renamed"));
+            if stored_path.is_file() {
+                let content = fs::read(&stored_path).unwrap();
+                assert!(!content.windows(6).any(|window| window == b"secret"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_encrypted_data_is_not_readable_with_other_key() {
+        let tmpdir = TempDir::new("lfs").unwrap();
+        create_dir(tmpdir.path().join("books")).unwrap();
+        let mut dfs = encrypted_dfs(tmpdir.path(), SymmetricKey::from_bytes([1; 32]));
+        let file = dfs.create("/file".to_owned()).unwrap();
+        dfs.write(&file, 0, b"content".to_vec()).unwrap();
+        dfs.close(&file).unwrap();
+
+        let mut other_dfs = encrypted_dfs(tmpdir.path(), SymmetricKey::from_bytes([2; 32]));
+
+        assert!(other_dfs.readdir("/".to_owned()).is_empty());
+        assert!(other_dfs.open("/file".to_owned()).is_err());
+        assert_eq!(dfs.readdir("/".to_owned()).len(), 1);
+    }
+
+    #[test]
+    fn test_encrypted_dfs_reads_and_writes_parts_of_large_files() {
+        let tmpdir = TempDir::new("lfs").unwrap();
+        create_dir(tmpdir.path().join("books")).unwrap();
+        let mut dfs = encrypted_dfs(tmpdir.path(), SymmetricKey::from_bytes([1; 32]));
+        let content: Vec<u8> = (0..200_000).map(|i| (i % 251) as u8).collect();
+
+        let file = dfs.create("/large".to_owned()).unwrap();
+        for (index, piece) in content.chunks(4096).enumerate() {
+            dfs.write(&file, index as u64 * 4096, piece.to_vec())
+                .unwrap();
+        }
+        dfs.write(&file, 65_530, b"across chunks".to_vec()).unwrap();
+        dfs.write(&file, 250_000, b"past the end".to_vec()).unwrap();
+
+        let mut expected = content;
+        expected[65_530..65_543].copy_from_slice(b"across chunks");
+        expected.resize(250_000, 0);
+        expected.extend_from_slice(b"past the end");
+        assert_eq!(
+            dfs.getattr("/large".to_owned()).unwrap().size,
+            expected.len() as u64
+        );
+        assert_eq!(dfs.read(&file, 0, 300_000).unwrap(), expected);
+        assert_eq!(
+            dfs.read(&file, 65_000, 70_000).unwrap(),
+            expected[65_000..135_000].to_vec()
+        );
+
+        dfs.truncate("/large".to_owned(), 131_072).unwrap();
+        assert_eq!(dfs.read(&file, 0, 300_000).unwrap(), expected[..131_072]);
+        dfs.truncate("/large".to_owned(), 140_000).unwrap();
+        expected.truncate(131_072);
+        expected.resize(140_000, 0);
+        assert_eq!(dfs.read(&file, 0, 300_000).unwrap(), expected);
+        dfs.close(&file).unwrap();
+
+        // chunks of 64 KiB are stored along with a header and a tag of every chunk
+        let stored = files_in_dir(&tmpdir.path().join("books"));
+        assert_eq!(
+            fs::metadata(&stored[0]).unwrap().len(),
+            140_000 + 104 + 3 * 16
+        );
+    }
+
+    #[test]
+    fn test_encrypted_dfs_detects_swapped_files() {
+        let tmpdir = TempDir::new("lfs").unwrap();
+        create_dir(tmpdir.path().join("books")).unwrap();
+        let mut dfs = encrypted_dfs(tmpdir.path(), SymmetricKey::from_bytes([1; 32]));
+        for (path, content) in [("/first", b"first"), ("/other", b"other")] {
+            let file = dfs.create(path.to_owned()).unwrap();
+            dfs.write(&file, 0, content.to_vec()).unwrap();
+            dfs.close(&file).unwrap();
+        }
+
+        let stored = files_in_dir(&tmpdir.path().join("books"));
+        let swapped = tmpdir.path().join("swapped");
+        fs::rename(&stored[0], &swapped).unwrap();
+        fs::rename(&stored[1], &stored[0]).unwrap();
+        fs::rename(&swapped, &stored[1]).unwrap();
+
+        let file = dfs.open("/first".to_owned()).unwrap();
+        assert!(dfs.read(&file, 0, 100).is_err());
+    }
+
+    #[test]
+    fn test_encrypted_dfs_keeps_contents_of_renamed_directories() {
+        let tmpdir = TempDir::new("lfs").unwrap();
+        create_dir(tmpdir.path().join("books")).unwrap();
+        let mut dfs = encrypted_dfs(tmpdir.path(), SymmetricKey::from_bytes([1; 32]));
+        dfs.mkdir("/dir".to_owned()).unwrap();
+        dfs.mkdir("/dir/subdir".to_owned()).unwrap();
+        let file = dfs.create("/dir/subdir/file".to_owned()).unwrap();
+        dfs.write(&file, 0, b"content".to_vec()).unwrap();
+        dfs.close(&file).unwrap();
+        dfs.create("/dir/empty".to_owned()).unwrap();
+
+        dfs.rename("/dir".to_owned(), "/renamed".to_owned())
+            .unwrap();
+
+        let file = dfs.open("/renamed/subdir/file".to_owned()).unwrap();
+        assert_eq!(dfs.read(&file, 0, 100).unwrap(), b"content".to_vec());
+        let file = dfs.open("/renamed/empty".to_owned()).unwrap();
+        assert_eq!(dfs.read(&file, 0, 100).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_encrypted_dfs_rejects_too_long_names() {
+        let tmpdir = TempDir::new("lfs").unwrap();
+        create_dir(tmpdir.path().join("books")).unwrap();
+        let mut dfs = encrypted_dfs(tmpdir.path(), SymmetricKey::from_bytes([1; 32]));
+
+        assert!(dfs.create(format!("/{}", "a".repeat(151))).is_ok());
+        assert_eq!(
+            dfs.create(format!("/{}", "a".repeat(152))).unwrap_err(),
+            DfsFrontendError::NameTooLong
+        );
+        assert_eq!(
+            dfs.mkdir(format!("/{}/dir", "b".repeat(200))).unwrap_err(),
+            DfsFrontendError::NameTooLong
+        );
+    }
+
+    fn lfs_backend(local_dir: &Path, container_prefix: &str) -> Arc<dyn StorageBackend> {
+        let storage = Storage::new(
+            Some("Test LFS".to_owned()),
+            "LFS".to_owned(),
+            json!({
+                "local_dir": local_dir,
+                "container_prefix": container_prefix
+            }),
+        );
+        LfsBackendFactory {}.init_backend(storage).unwrap()
+    }
+
+    #[test]
+    fn test_parent_dir_components_do_not_escape_lfs_backend() {
+        let tmpdir = TempDir::new("lfs").unwrap();
+        create_dir(tmpdir.path().join("books")).unwrap();
+        fs::write(tmpdir.path().join("outside"), b"outside").unwrap();
+        let backend = lfs_backend(tmpdir.path(), "books");
+
+        assert!(matches!(
+            backend.read(Path::new("/../outside"), 0, 100),
+            Err(StorageBackendError::NoSuchPath)
+        ));
+        backend.create(Path::new("/../../file")).unwrap();
+        backend.mkdir(Path::new("/dir/../../dir")).unwrap();
+
+        assert!(tmpdir.path().join("books/file").exists());
+        assert!(tmpdir.path().join("books/dir").is_dir());
+        assert!(!tmpdir.path().join("file").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_do_not_escape_lfs_backend() {
+        let tmpdir = TempDir::new("lfs").unwrap();
+        create_dir(tmpdir.path().join("books")).unwrap();
+        create_dir(tmpdir.path().join("outside")).unwrap();
+        fs::write(tmpdir.path().join("outside/file"), b"outside").unwrap();
+        std::os::unix::fs::symlink(
+            tmpdir.path().join("outside"),
+            tmpdir.path().join("books/dir_link"),
+        )
+        .unwrap();
+        std::os::unix::fs::symlink(
+            tmpdir.path().join("outside/file"),
+            tmpdir.path().join("books/file_link"),
+        )
+        .unwrap();
+        let backend = lfs_backend(tmpdir.path(), "books");
+
+        assert!(backend.readdir(Path::new("/dir_link")).is_err());
+        assert!(backend.read(Path::new("/dir_link/file"), 0, 100).is_err());
+        assert!(backend.read(Path::new("/file_link"), 0, 100).is_err());
+        assert!(backend.write(Path::new("/file_link"), 0, b"x").is_err());
+        assert!(backend.truncate(Path::new("/file_link"), 0).is_err());
+        assert!(backend.create(Path::new("/dir_link/new_file")).is_err());
+        assert!(backend.unlink(Path::new("/dir_link/file")).is_err());
+
+        // symlinks themselves can still be inspected and removed
+        assert_eq!(
+            backend.getattr(Path::new("/file_link")).unwrap().node_type,
+            NodeType::Symlink
+        );
+        backend.unlink(Path::new("/file_link")).unwrap();
+        assert_eq!(
+            fs::read(tmpdir.path().join("outside/file")).unwrap(),
+            b"outside".to_vec()
+        );
+        assert!(!tmpdir.path().join("outside/new_file").exists());
+    }
+
+    #[test]
+    fn test_container_prefix_cannot_point_outside_of_local_dir() {
+        for container_prefix in ["../books", "/books", "books/../.."] {
+            let storage = Storage::new(
+                None,
+                "LFS".to_owned(),
+                json!({
+                    "local_dir": "/tmp/wildland",
+                    "container_prefix": container_prefix
+                }),
+            );
+            assert!(LfsBackendFactory {}.init_backend(storage).is_err());
+        }
+    }
 }
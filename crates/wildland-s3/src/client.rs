@@ -0,0 +1,484 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Minimal client of the S3 REST API covering operations needed by [`crate::S3Storage`].
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use minreq::{Method, Request, Response};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::Deserialize;
+use url::Url;
+use wildland_dfs::storage_backend::StorageBackendError;
+use wildland_dfs::UnixTimestamp;
+
+use crate::signing::{amz_date, sha256_hex, CanonicalRequest, RequestSigner};
+
+/// Characters which must be encoded in query parameters and signatures.
+const URI_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+/// Same as [`URI_ENCODE_SET`] but leaves slashes separating parts of object keys.
+const PATH_ENCODE_SET: &AsciiSet = &URI_ENCODE_SET.remove(b'/');
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ObjectInfo {
+    pub key: String,
+    pub size: u64,
+    pub last_modified: Option<UnixTimestamp>,
+}
+
+/// Single page of `ListObjectsV2` results.
+#[derive(Debug, Default)]
+pub(crate) struct ObjectList {
+    pub objects: Vec<ObjectInfo>,
+    pub common_prefixes: Vec<String>,
+    pub next_continuation_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ListBucketResult {
+    #[serde(default)]
+    contents: Vec<ListedObject>,
+    #[serde(default)]
+    common_prefixes: Vec<CommonPrefix>,
+    #[serde(default)]
+    is_truncated: bool,
+    next_continuation_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ListedObject {
+    key: String,
+    size: u64,
+    last_modified: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CommonPrefix {
+    prefix: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InitiateMultipartUploadResult {
+    upload_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ErrorResponse {
+    code: String,
+    message: Option<String>,
+}
+
+/// Client of a single bucket.
+pub struct S3Client {
+    endpoint: Url,
+    bucket: String,
+    signer: Arc<dyn RequestSigner>,
+    virtual_hosted_style: bool,
+}
+
+impl S3Client {
+    /// If `virtual_hosted_style` is set, the bucket is addressed as a subdomain of the endpoint
+    /// instead of the first component of the path.
+    pub fn new(
+        endpoint: &str,
+        bucket: String,
+        signer: Arc<dyn RequestSigner>,
+        virtual_hosted_style: bool,
+    ) -> Result<Self, anyhow::Error> {
+        let endpoint = Url::parse(endpoint)?;
+        if endpoint.host_str().is_none() {
+            return Err(anyhow!("S3 endpoint {endpoint} has no host"));
+        }
+        if bucket.is_empty() {
+            return Err(anyhow!("S3 bucket name cannot be empty"));
+        }
+        Ok(Self {
+            endpoint,
+            bucket,
+            signer,
+            virtual_hosted_style,
+        })
+    }
+
+    /// Returns `None` if there is no object with the given key.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) fn head_object(&self, key: &str) -> Result<Option<ObjectInfo>, StorageBackendError> {
+        let response = self.send(Method::Head, key, &[], &[], Vec::new())?;
+        if response.status_code == 404 {
+            return Ok(None);
+        }
+        let response = check_status(response)?;
+        let size = response
+            .headers
+            .get("content-length")
+            .and_then(|length| length.parse().ok())
+            .ok_or_else(|| anyhow!("Missing size of object {key}"))?;
+        let last_modified = response
+            .headers
+            .get("last-modified")
+            .and_then(|time| DateTime::parse_from_rfc2822(time).ok())
+            .map(|time| to_unix_timestamp(time.with_timezone(&Utc)));
+        Ok(Some(ObjectInfo {
+            key: key.to_owned(),
+            size,
+            last_modified,
+        }))
+    }
+
+    /// Downloads the object or only the given inclusive range of its bytes.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) fn get_object(
+        &self,
+        key: &str,
+        range: Option<(u64, u64)>,
+    ) -> Result<Vec<u8>, StorageBackendError> {
+        let headers = range
+            .map(|(first, last)| vec![("range", format!("bytes={first}-{last}"))])
+            .unwrap_or_default();
+        let response = check_status(self.send(Method::Get, key, &[], &headers, Vec::new())?)?;
+        match (range, response.status_code) {
+            // server ignored the range and returned the whole object
+            (Some((first, last)), 200) => {
+                let content = response.into_bytes();
+                let first = (first as usize).min(content.len());
+                let last = (last as usize + 1).min(content.len());
+                Ok(content[first..last].to_vec())
+            }
+            _ => Ok(response.into_bytes()),
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, content))]
+    pub(crate) fn put_object(
+        &self,
+        key: &str,
+        content: Vec<u8>,
+    ) -> Result<(), StorageBackendError> {
+        check_status(self.send(Method::Put, key, &[], &[], content)?)?;
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) fn delete_object(&self, key: &str) -> Result<(), StorageBackendError> {
+        check_status(self.send(Method::Delete, key, &[], &[], Vec::new())?)?;
+        Ok(())
+    }
+
+    // TODO WILX-367 objects bigger than 5GB have to be copied with UploadPartCopy
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) fn copy_object(
+        &self,
+        source_key: &str,
+        target_key: &str,
+    ) -> Result<(), StorageBackendError> {
+        let source = format!(
+            "/{}/{}",
+            self.bucket,
+            utf8_percent_encode(source_key, PATH_ENCODE_SET)
+        );
+        let response = check_status(self.send(
+            Method::Put,
+            target_key,
+            &[],
+            &[("x-amz-copy-source", source)],
+            Vec::new(),
+        )?)?;
+        // copying may fail after the response status has been sent
+        if let Ok(error) = quick_xml::de::from_reader::<_, ErrorResponse>(response.as_bytes()) {
+            return Err(anyhow!("Failed to copy object {source_key}: {error:?}").into());
+        }
+        Ok(())
+    }
+
+    /// Lists objects with keys starting with `prefix`. If `delimiter` is given, keys containing it
+    /// after the prefix are grouped into common prefixes.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) fn list_objects(
+        &self,
+        prefix: &str,
+        delimiter: Option<&str>,
+        max_keys: Option<usize>,
+        continuation_token: Option<&str>,
+    ) -> Result<ObjectList, StorageBackendError> {
+        let mut query = vec![("list-type", "2".to_owned()), ("prefix", prefix.to_owned())];
+        if let Some(delimiter) = delimiter {
+            query.push(("delimiter", delimiter.to_owned()));
+        }
+        if let Some(max_keys) = max_keys {
+            query.push(("max-keys", max_keys.to_string()));
+        }
+        if let Some(token) = continuation_token {
+            query.push(("continuation-token", token.to_owned()));
+        }
+        let response = check_status(self.send(Method::Get, "", &query, &[], Vec::new())?)?;
+        let result: ListBucketResult =
+            quick_xml::de::from_reader(response.as_bytes()).map_err(|e| anyhow!(e))?;
+
+        Ok(ObjectList {
+            objects: result
+                .contents
+                .into_iter()
+                .map(|object| ObjectInfo {
+                    last_modified: object
+                        .last_modified
+                        .and_then(|time| DateTime::parse_from_rfc3339(&time).ok())
+                        .map(|time| to_unix_timestamp(time.with_timezone(&Utc))),
+                    key: object.key,
+                    size: object.size,
+                })
+                .collect(),
+            common_prefixes: result
+                .common_prefixes
+                .into_iter()
+                .map(|common_prefix| common_prefix.prefix)
+                .collect(),
+            next_continuation_token: result
+                .next_continuation_token
+                .filter(|_| result.is_truncated),
+        })
+    }
+
+    /// Lists all objects with keys starting with `prefix` following continuation tokens.
+    pub(crate) fn list_all_objects(
+        &self,
+        prefix: &str,
+        delimiter: Option<&str>,
+    ) -> Result<ObjectList, StorageBackendError> {
+        let mut all = ObjectList::default();
+        let mut continuation_token = None;
+        loop {
+            let page = self.list_objects(prefix, delimiter, None, continuation_token.as_deref())?;
+            all.objects.extend(page.objects);
+            all.common_prefixes.extend(page.common_prefixes);
+            continuation_token = page.next_continuation_token;
+            if continuation_token.is_none() {
+                return Ok(all);
+            }
+        }
+    }
+
+    /// Uploads the object in parts of `part_size` bytes.
+    #[tracing::instrument(level = "debug", skip(self, content))]
+    pub(crate) fn multipart_upload(
+        &self,
+        key: &str,
+        content: &[u8],
+        part_size: usize,
+    ) -> Result<(), StorageBackendError> {
+        let response = check_status(self.send(
+            Method::Post,
+            key,
+            &[("uploads", String::new())],
+            &[],
+            Vec::new(),
+        )?)?;
+        let InitiateMultipartUploadResult { upload_id } =
+            quick_xml::de::from_reader(response.as_bytes()).map_err(|e| anyhow!(e))?;
+
+        let result = self.upload_parts(key, &upload_id, content, part_size);
+        if result.is_err() {
+            // parts of unfinished uploads are stored (and paid for) until the upload is aborted
+            if let Err(e) = self.send(
+                Method::Delete,
+                key,
+                &[("uploadId", upload_id.clone())],
+                &[],
+                Vec::new(),
+            ) {
+                tracing::warn!("Failed to abort multipart upload {upload_id}: {e}");
+            }
+        }
+        result
+    }
+
+    fn upload_parts(
+        &self,
+        key: &str,
+        upload_id: &str,
+        content: &[u8],
+        part_size: usize,
+    ) -> Result<(), StorageBackendError> {
+        let mut completed_parts = String::new();
+        for (index, part) in content.chunks(part_size.max(1)).enumerate() {
+            let part_number = index + 1;
+            let response = check_status(self.send(
+                Method::Put,
+                key,
+                &[
+                    ("partNumber", part_number.to_string()),
+                    ("uploadId", upload_id.to_owned()),
+                ],
+                &[],
+                part.to_vec(),
+            )?)?;
+            let etag = response
+                .headers
+                .get("etag")
+                .ok_or_else(|| anyhow!("Missing ETag of part {part_number} of {key}"))?;
+            completed_parts.push_str(&format!(
+                "<Part><PartNumber>{part_number}</PartNumber><ETag>{}</ETag></Part>",
+                quick_xml::escape::escape(etag)
+            ));
+        }
+
+        let response = check_status(
+            self.send(
+                Method::Post,
+                key,
+                &[("uploadId", upload_id.to_owned())],
+                &[],
+                format!("<CompleteMultipartUpload>{completed_parts}</CompleteMultipartUpload>")
+                    .into_bytes(),
+            )?,
+        )?;
+        // completing upload may fail after the response status has been sent
+        if let Ok(error) = quick_xml::de::from_reader::<_, ErrorResponse>(response.as_bytes()) {
+            return Err(anyhow!("Failed to complete upload of {key}: {error:?}").into());
+        }
+        Ok(())
+    }
+
+    /// Sends a signed request concerning the object with the given key (or the whole bucket if the
+    /// key is empty).
+    fn send(
+        &self,
+        method: Method,
+        key: &str,
+        query: &[(&str, String)],
+        headers: &[(&str, String)],
+        body: Vec<u8>,
+    ) -> Result<Response, StorageBackendError> {
+        let host = self.endpoint.host_str().expect("Checked in constructor");
+        let host = match self.endpoint.port() {
+            Some(port) => format!("{host}:{port}"),
+            None => host.to_owned(),
+        };
+        let encoded_key = utf8_percent_encode(key, PATH_ENCODE_SET);
+        let base_path = self.endpoint.path().trim_end_matches('/');
+        let (host, path) = if self.virtual_hosted_style {
+            (
+                format!("{}.{host}", self.bucket),
+                format!("{base_path}/{encoded_key}"),
+            )
+        } else {
+            (
+                host,
+                format!(
+                    "{base_path}/{}/{encoded_key}",
+                    utf8_percent_encode(&self.bucket, URI_ENCODE_SET)
+                ),
+            )
+        };
+        let query: Vec<(String, String)> = query
+            .iter()
+            .map(|(name, value)| {
+                (
+                    utf8_percent_encode(name, URI_ENCODE_SET).to_string(),
+                    utf8_percent_encode(value, URI_ENCODE_SET).to_string(),
+                )
+            })
+            .collect();
+
+        let time = SystemTime::now();
+        let payload_hash = sha256_hex(&body);
+        let mut signed_headers: BTreeMap<String, String> = headers
+            .iter()
+            .map(|(name, value)| (name.to_lowercase(), value.clone()))
+            .collect();
+        signed_headers.insert("host".to_owned(), host.clone());
+        signed_headers.insert("x-amz-content-sha256".to_owned(), payload_hash.clone());
+        signed_headers.insert("x-amz-date".to_owned(), amz_date(&time.into()));
+        let authorization = self.signer.authorization(
+            &CanonicalRequest {
+                method: method_name(&method),
+                path: &path,
+                query: &query,
+                headers: &signed_headers,
+                payload_hash: &payload_hash,
+            },
+            time,
+        )?;
+
+        let mut url = format!("{}://{host}{path}", self.endpoint.scheme());
+        if !query.is_empty() {
+            let query = query
+                .iter()
+                .map(|(name, value)| {
+                    if value.is_empty() {
+                        name.clone()
+                    } else {
+                        format!("{name}={value}")
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("&");
+            url = format!("{url}?{query}");
+        }
+        // minreq adds the host header on its own
+        signed_headers.remove("host");
+        Request::new(method, url)
+            .with_headers(signed_headers)
+            .with_header("authorization", authorization)
+            .with_body(body)
+            .send()
+            .map_err(|e| anyhow!(e).into())
+    }
+}
+
+fn method_name(method: &Method) -> &'static str {
+    match method {
+        Method::Get => "GET",
+        Method::Head => "HEAD",
+        Method::Post => "POST",
+        Method::Put => "PUT",
+        Method::Delete => "DELETE",
+        _ => unreachable!("Method not used by S3 client"),
+    }
+}
+
+fn check_status(response: Response) -> Result<Response, StorageBackendError> {
+    match response.status_code {
+        200..=299 => Ok(response),
+        404 => Err(StorageBackendError::NoSuchPath),
+        status => {
+            let details = quick_xml::de::from_reader::<_, ErrorResponse>(response.as_bytes())
+                .map(|error| format!("{}: {}", error.code, error.message.unwrap_or_default()))
+                .unwrap_or_else(|_| response.reason_phrase.clone());
+            Err(anyhow!("S3 request failed with status {status}: {details}").into())
+        }
+    }
+}
+
+fn to_unix_timestamp(time: DateTime<Utc>) -> UnixTimestamp {
+    UnixTimestamp {
+        sec: time.timestamp().max(0) as u64,
+        nano_sec: time.timestamp_subsec_nanos(),
+    }
+}
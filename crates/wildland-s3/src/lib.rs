@@ -0,0 +1,398 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod client;
+mod signing;
+mod template;
+#[cfg(test)]
+mod tests;
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::anyhow;
+pub use client::S3Client;
+pub use signing::{CanonicalRequest, RequestSigner};
+use signing::{Credentials, SigV4Signer};
+pub use template::{
+    S3StorageTemplate,
+    DEFAULT_MULTIPART_PART_SIZE,
+    MAX_MULTIPART_PARTS,
+    MIN_MULTIPART_PART_SIZE,
+};
+use wildland_dfs::storage_backend::{
+    content_range,
+    resize_content,
+    StorageBackend,
+    StorageBackendError,
+};
+use wildland_dfs::unencrypted::StorageBackendFactory;
+use wildland_dfs::{NodeType, Stat, Storage};
+
+pub const S3_BACKEND_TYPE: &str = "S3";
+
+/// Storage kept in a bucket of an S3-compatible service.
+///
+/// A file is stored as an object with a key made of the storage prefix and the file path. A
+/// directory is a key prefix ending with `/` and an empty marker object with that very key, so
+/// empty directories are preserved. Prefixes without a marker (e.g. created by other S3 clients)
+/// are treated as directories as well.
+pub struct S3Storage {
+    client: S3Client,
+    /// Empty or ending with `/`
+    prefix: String,
+    multipart_part_size: usize,
+}
+
+impl S3Storage {
+    /// Creates storage kept under `container_prefix` within the bucket of `client`.
+    ///
+    /// Files bigger than `multipart_part_size` are uploaded in parts of that size, so it must
+    /// be at least [`MIN_MULTIPART_PART_SIZE`].
+    pub fn new(
+        client: S3Client,
+        container_prefix: &str,
+        multipart_part_size: usize,
+    ) -> Result<Self, anyhow::Error> {
+        if multipart_part_size < MIN_MULTIPART_PART_SIZE {
+            return Err(anyhow!(
+                "Multipart part size {multipart_part_size} is smaller than {MIN_MULTIPART_PART_SIZE}"
+            ));
+        }
+        // rendered prefix may contain e.g. container name, so it must not lead outside of it
+        let prefix_path = Path::new(container_prefix);
+        if !prefix_path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(anyhow!("Invalid container prefix: {container_prefix}"));
+        }
+        let prefix = Self::components(prefix_path)?
+            .iter()
+            .map(|component| format!("{component}/"))
+            .collect();
+        Ok(Self {
+            client,
+            prefix,
+            multipart_part_size,
+        })
+    }
+
+    /// Returns names of path components with `..` resolved like in the local filesystem.
+    fn components(path: &Path) -> Result<Vec<String>, StorageBackendError> {
+        let mut components = Vec::new();
+        for component in path.components() {
+            match component {
+                Component::Normal(name) => components.push(
+                    name.to_str()
+                        .ok_or_else(|| anyhow!("Path {path:?} is not valid UTF-8"))?
+                        .to_owned(),
+                ),
+                Component::ParentDir => {
+                    components.pop();
+                }
+                Component::RootDir | Component::CurDir => {}
+                Component::Prefix(_) => {
+                    return Err(anyhow!("Unsupported path within storage: {path:?}").into())
+                }
+            }
+        }
+        Ok(components)
+    }
+
+    fn object_key(&self, components: &[String]) -> String {
+        format!("{}{}", self.prefix, components.join("/"))
+    }
+
+    /// Key prefix of directory entries which is also the key of the directory marker.
+    fn dir_prefix(&self, components: &[String]) -> String {
+        if components.is_empty() {
+            self.prefix.clone()
+        } else {
+            format!("{}/", self.object_key(components))
+        }
+    }
+
+    fn stat(&self, components: &[String]) -> Result<Stat, StorageBackendError> {
+        let dir_stat = |modification_time| Stat {
+            node_type: NodeType::Dir,
+            size: 0,
+            access_time: None,
+            modification_time,
+            change_time: None,
+        };
+        if components.is_empty() {
+            return Ok(dir_stat(None));
+        }
+        if let Some(object) = self.client.head_object(&self.object_key(components))? {
+            return Ok(Stat {
+                node_type: NodeType::File,
+                size: object.size,
+                access_time: None,
+                modification_time: object.last_modified,
+                change_time: None,
+            });
+        }
+        let dir_prefix = self.dir_prefix(components);
+        if let Some(marker) = self.client.head_object(&dir_prefix)? {
+            return Ok(dir_stat(marker.last_modified));
+        }
+        if !self
+            .client
+            .list_objects(&dir_prefix, None, Some(1), None)?
+            .objects
+            .is_empty()
+        {
+            return Ok(dir_stat(None));
+        }
+        Err(StorageBackendError::NoSuchPath)
+    }
+
+    fn ensure_file(&self, components: &[String]) -> Result<Stat, StorageBackendError> {
+        let stat = self.stat(components)?;
+        if stat.node_type != NodeType::File {
+            return Err(StorageBackendError::NotAFile);
+        }
+        Ok(stat)
+    }
+
+    fn ensure_dir(&self, components: &[String]) -> Result<(), StorageBackendError> {
+        if self.stat(components)?.node_type != NodeType::Dir {
+            return Err(StorageBackendError::NotADirectory);
+        }
+        Ok(())
+    }
+
+    /// Checks that the path can be created, i.e. it does not exist and its parent is a directory.
+    fn ensure_creatable(&self, components: &[String]) -> Result<(), StorageBackendError> {
+        let parent = match components.split_last() {
+            Some((_, parent)) => parent,
+            None => return Err(StorageBackendError::PathAlreadyExists),
+        };
+        match self.stat(components) {
+            Ok(_) => return Err(StorageBackendError::PathAlreadyExists),
+            Err(StorageBackendError::NoSuchPath) => {}
+            Err(e) => return Err(e),
+        }
+        self.ensure_dir(parent)
+    }
+
+    fn read_content(&self, key: &str, size: u64) -> Result<Vec<u8>, StorageBackendError> {
+        if size == 0 {
+            Ok(Vec::new())
+        } else {
+            self.client.get_object(key, None)
+        }
+    }
+
+    /// Checks whether a file of `size` bytes can be uploaded before its content is built.
+    fn ensure_uploadable(&self, size: u64) -> Result<(), StorageBackendError> {
+        let max_size = self.multipart_part_size.saturating_mul(MAX_MULTIPART_PARTS);
+        if size > max_size as u64 {
+            return Err(
+                anyhow!("File of {size} bytes exceeds the maximum of {max_size} bytes").into(),
+            );
+        }
+        Ok(())
+    }
+
+    fn upload(&self, key: &str, content: Vec<u8>) -> Result<(), StorageBackendError> {
+        if content.len() > self.multipart_part_size {
+            self.client
+                .multipart_upload(key, &content, self.multipart_part_size)
+        } else {
+            self.client.put_object(key, content)
+        }
+    }
+}
+
+impl StorageBackend for S3Storage {
+    fn readdir(&self, path: &Path) -> Result<Vec<PathBuf>, StorageBackendError> {
+        let components = Self::components(path)?;
+        self.ensure_dir(&components)?;
+        let dir_prefix = self.dir_prefix(&components);
+        let listing = self.client.list_all_objects(&dir_prefix, Some("/"))?;
+
+        let dir_path = Path::new("/").join(components.join("/"));
+        let names = listing
+            .objects
+            .iter()
+            .map(|object| &object.key)
+            .chain(listing.common_prefixes.iter())
+            .filter_map(|key| key.strip_prefix(&dir_prefix))
+            .map(|name| name.trim_end_matches('/'))
+            // skips the directory marker
+            .filter(|name| !name.is_empty());
+        Ok(names.map(|name| dir_path.join(name)).collect())
+    }
+
+    fn getattr(&self, path: &Path) -> Result<Stat, StorageBackendError> {
+        self.stat(&Self::components(path)?)
+    }
+
+    fn read(&self, path: &Path, offset: u64, count: usize) -> Result<Vec<u8>, StorageBackendError> {
+        let components = Self::components(path)?;
+        let stat = self.ensure_file(&components)?;
+        if count == 0 || offset >= stat.size {
+            return Ok(Vec::new());
+        }
+        let last = offset.saturating_add(count as u64).min(stat.size) - 1;
+        self.client
+            .get_object(&self.object_key(&components), Some((offset, last)))
+    }
+
+    // TODO WILX-368 S3 objects cannot be modified in place, so every write uploads the whole file.
+    // Writes should be buffered until the file is closed.
+    fn write(&self, path: &Path, offset: u64, buf: &[u8]) -> Result<usize, StorageBackendError> {
+        let components = Self::components(path)?;
+        let stat = self.ensure_file(&components)?;
+        let range = content_range(offset, buf.len())?;
+        self.ensure_uploadable(stat.size.max(range.end as u64))?;
+        let key = self.object_key(&components);
+        let mut content = self.read_content(&key, stat.size)?;
+        if content.len() < range.end {
+            resize_content(&mut content, range.end as u64)?;
+        }
+        content[range].copy_from_slice(buf);
+        self.upload(&key, content)?;
+        Ok(buf.len())
+    }
+
+    fn create(&self, path: &Path) -> Result<(), StorageBackendError> {
+        let components = Self::components(path)?;
+        self.ensure_creatable(&components)?;
+        self.client
+            .put_object(&self.object_key(&components), Vec::new())
+    }
+
+    fn unlink(&self, path: &Path) -> Result<(), StorageBackendError> {
+        let components = Self::components(path)?;
+        self.ensure_file(&components)?;
+        self.client.delete_object(&self.object_key(&components))
+    }
+
+    fn mkdir(&self, path: &Path) -> Result<(), StorageBackendError> {
+        let components = Self::components(path)?;
+        self.ensure_creatable(&components)?;
+        self.client
+            .put_object(&self.dir_prefix(&components), Vec::new())
+    }
+
+    fn rmdir(&self, path: &Path) -> Result<(), StorageBackendError> {
+        let components = Self::components(path)?;
+        if components.is_empty() {
+            return Err(anyhow!("Cannot remove the storage root").into());
+        }
+        self.ensure_dir(&components)?;
+        let dir_prefix = self.dir_prefix(&components);
+        let listing = self.client.list_objects(&dir_prefix, None, Some(2), None)?;
+        if listing
+            .objects
+            .iter()
+            .any(|object| object.key != dir_prefix)
+        {
+            return Err(StorageBackendError::DirNotEmpty);
+        }
+        self.client.delete_object(&dir_prefix)
+    }
+
+    fn rename(&self, old_path: &Path, new_path: &Path) -> Result<(), StorageBackendError> {
+        let old_components = Self::components(old_path)?;
+        let new_components = Self::components(new_path)?;
+        let old_stat = self.stat(&old_components)?;
+        if old_components == new_components {
+            return Ok(());
+        }
+        if new_components.starts_with(&old_components) {
+            return Err(anyhow!("Cannot move {old_path:?} into itself").into());
+        }
+        self.ensure_creatable(&new_components)?;
+
+        if old_stat.node_type == NodeType::File {
+            let old_key = self.object_key(&old_components);
+            self.client
+                .copy_object(&old_key, &self.object_key(&new_components))?;
+            return self.client.delete_object(&old_key);
+        }
+
+        // moves the directory marker along with all objects under the directory prefix
+        let old_prefix = self.dir_prefix(&old_components);
+        let new_prefix = self.dir_prefix(&new_components);
+        self.client.put_object(&new_prefix, Vec::new())?;
+        let moved_keys: Vec<String> = self
+            .client
+            .list_all_objects(&old_prefix, None)?
+            .objects
+            .into_iter()
+            .map(|object| object.key)
+            .filter(|key| *key != old_prefix)
+            .collect();
+        for key in &moved_keys {
+            let key_within_dir = key
+                .strip_prefix(&old_prefix)
+                .expect("Listed keys have a known prefix");
+            self.client
+                .copy_object(key, &format!("{new_prefix}{key_within_dir}"))?;
+        }
+        for key in &moved_keys {
+            self.client.delete_object(key)?;
+        }
+        self.client.delete_object(&old_prefix)
+    }
+
+    fn truncate(&self, path: &Path, size: u64) -> Result<(), StorageBackendError> {
+        let components = Self::components(path)?;
+        let stat = self.ensure_file(&components)?;
+        if stat.size == size {
+            return Ok(());
+        }
+        self.ensure_uploadable(size)?;
+        let key = self.object_key(&components);
+        let mut content = if size < stat.size && size > 0 {
+            self.client.get_object(&key, Some((0, size - 1)))?
+        } else {
+            self.read_content(&key, stat.size)?
+        };
+        resize_content(&mut content, size)?;
+        self.upload(&key, content)
+    }
+}
+
+pub struct S3BackendFactory {}
+impl StorageBackendFactory for S3BackendFactory {
+    fn init_backend(&self, storage: Storage) -> Result<Arc<dyn StorageBackend>, anyhow::Error> {
+        let template: S3StorageTemplate = serde_json::from_value(storage.data().clone())?;
+        let signer = SigV4Signer {
+            credentials: Credentials {
+                access_key_id: template.access_key_id,
+                secret_access_key: template.secret_access_key,
+            },
+            region: template.region,
+        };
+        let client = S3Client::new(
+            &template.endpoint,
+            template.bucket,
+            Arc::new(signer),
+            template.virtual_hosted_style,
+        )?;
+        Ok(Arc::new(S3Storage::new(
+            client,
+            &template.container_prefix,
+            template.multipart_part_size,
+        )?))
+    }
+}
@@ -0,0 +1,166 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+use wildland_dfs::{StorageTemplate, StorageTemplateError, CONTAINER_UUID_PARAM};
+
+use crate::S3_BACKEND_TYPE;
+
+/// S3 requires all parts of a multipart upload but the last one to have at least 5 MiB.
+pub const MIN_MULTIPART_PART_SIZE: usize = 5 * 1024 * 1024;
+/// S3 limits number of parts of a multipart upload.
+pub const MAX_MULTIPART_PARTS: usize = 10_000;
+pub const DEFAULT_MULTIPART_PART_SIZE: usize = 8 * 1024 * 1024;
+
+fn default_region() -> String {
+    "us-east-1".to_owned()
+}
+
+fn default_multipart_part_size() -> usize {
+    DEFAULT_MULTIPART_PART_SIZE
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct S3StorageTemplate {
+    pub endpoint: String,
+    #[serde(default = "default_region")]
+    pub region: String,
+    pub bucket: String,
+    /// Objects of a container are stored under this prefix within the bucket.
+    pub container_prefix: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Whether the bucket is addressed as a subdomain of the endpoint instead of the first
+    /// component of the path.
+    #[serde(default)]
+    pub virtual_hosted_style: bool,
+    /// Files bigger than that are uploaded in parts.
+    #[serde(default = "default_multipart_part_size")]
+    pub multipart_part_size: usize,
+}
+
+impl std::fmt::Debug for S3StorageTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("S3StorageTemplate")
+            .field("endpoint", &self.endpoint)
+            .field("region", &self.region)
+            .field("bucket", &self.bucket)
+            .field("container_prefix", &self.container_prefix)
+            .field("access_key_id", &self.access_key_id)
+            .field("virtual_hosted_style", &self.virtual_hosted_style)
+            .field("multipart_part_size", &self.multipart_part_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl S3StorageTemplate {
+    pub fn new(
+        endpoint: impl Into<String>,
+        bucket: impl Into<String>,
+        access_key_id: impl Into<String>,
+        secret_access_key: impl Into<String>,
+    ) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            region: default_region(),
+            bucket: bucket.into(),
+            container_prefix: format!("{{{{ {CONTAINER_UUID_PARAM} }}}}"),
+            access_key_id: access_key_id.into(),
+            secret_access_key: secret_access_key.into(),
+            virtual_hosted_style: false,
+            multipart_part_size: DEFAULT_MULTIPART_PART_SIZE,
+        }
+    }
+}
+
+impl TryFrom<S3StorageTemplate> for StorageTemplate {
+    type Error = StorageTemplateError;
+    fn try_from(s3t: S3StorageTemplate) -> Result<Self, Self::Error> {
+        StorageTemplate::try_new(S3_BACKEND_TYPE, s3t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use wildland_dfs::StorageTemplate;
+
+    use super::*;
+
+    #[test]
+    fn test_serializing_s3_template() {
+        let template: StorageTemplate = S3StorageTemplate::new(
+            "https://s3.example.com",
+            "wildland",
+            "access-key",
+            "secret-key",
+        )
+        .try_into()
+        .unwrap();
+        let template_uuid = template.uuid();
+
+        let expected_json_form = json!({
+            "uuid": template_uuid,
+            "backend_type": "S3",
+            "name": null,
+            "template": {
+                "endpoint": "https://s3.example.com",
+                "region": "us-east-1",
+                "bucket": "wildland",
+                "container_prefix": "{{ CONTAINER_UUID }}",
+                "access_key_id": "access-key",
+                "secret_access_key": "secret-key",
+                "virtual_hosted_style": false,
+                "multipart_part_size": 8388608
+            }
+        });
+
+        assert_eq!(expected_json_form, serde_json::to_value(&template).unwrap());
+    }
+
+    #[test]
+    fn test_deserializing_s3_template_with_defaults() {
+        let template: S3StorageTemplate = serde_json::from_value(json!({
+            "endpoint": "http://localhost:9000",
+            "bucket": "wildland",
+            "container_prefix": "books",
+            "access_key_id": "access-key",
+            "secret_access_key": "secret-key",
+        }))
+        .unwrap();
+
+        assert_eq!(template.region, "us-east-1");
+        assert!(!template.virtual_hosted_style);
+        assert_eq!(template.multipart_part_size, DEFAULT_MULTIPART_PART_SIZE);
+    }
+
+    #[test]
+    fn test_debug_output_of_s3_template_does_not_contain_secret() {
+        let template = S3StorageTemplate::new(
+            "https://s3.example.com",
+            "wildland",
+            "access-key",
+            "secret-key",
+        );
+
+        let debug_output = format!("{template:?}");
+
+        assert!(debug_output.contains("access-key"));
+        assert!(!debug_output.contains("secret-key"));
+    }
+}
@@ -0,0 +1,647 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Cursor;
+use std::ops::Bound;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use percent_encoding::percent_decode_str;
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use super::*;
+
+const BUCKET: &str = "wildland";
+const ACCESS_KEY_ID: &str = "test-access-key";
+/// Small page size makes listings span several requests.
+const LIST_PAGE_SIZE: usize = 2;
+
+#[derive(Debug, Clone)]
+struct RecordedRequest {
+    method: Method,
+    url: String,
+    range: Option<String>,
+    authorized: bool,
+}
+
+#[derive(Default)]
+struct FakeS3State {
+    objects: BTreeMap<String, Vec<u8>>,
+    multipart_uploads: HashMap<String, BTreeMap<usize, Vec<u8>>>,
+    completed_multipart_uploads: usize,
+    requests: Vec<RecordedRequest>,
+}
+
+/// Local stand-in of an S3 service keeping a single bucket in memory.
+struct FakeS3 {
+    url: String,
+    server: Arc<Server>,
+    state: Arc<Mutex<FakeS3State>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FakeS3 {
+    fn start() -> Self {
+        let server = Arc::new(Server::http("127.0.0.1:0").unwrap());
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let state = Arc::new(Mutex::new(FakeS3State::default()));
+        let thread = std::thread::spawn({
+            let server = server.clone();
+            let state = state.clone();
+            move || {
+                for mut request in server.incoming_requests() {
+                    let response = handle(&mut state.lock().expect("Poisoned Mutex"), &mut request);
+                    request.respond(response).unwrap();
+                }
+            }
+        });
+        Self {
+            url,
+            server,
+            state,
+            thread: Some(thread),
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        let state = self.state.lock().expect("Poisoned Mutex");
+        state.objects.keys().cloned().collect()
+    }
+
+    fn object(&self, key: &str) -> Option<Vec<u8>> {
+        let state = self.state.lock().expect("Poisoned Mutex");
+        state.objects.get(key).cloned()
+    }
+
+    fn put_object(&self, key: &str, content: &[u8]) {
+        let mut state = self.state.lock().expect("Poisoned Mutex");
+        state.objects.insert(key.to_owned(), content.to_vec());
+    }
+
+    fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().expect("Poisoned Mutex").requests.clone()
+    }
+
+    fn backend(
+        &self,
+        container_prefix: &str,
+        multipart_part_size: usize,
+    ) -> Arc<dyn StorageBackend> {
+        let storage = Storage::new(
+            Some("Test S3".to_owned()),
+            S3_BACKEND_TYPE.to_owned(),
+            json!({
+                "endpoint": self.url,
+                "bucket": BUCKET,
+                "container_prefix": container_prefix,
+                "access_key_id": ACCESS_KEY_ID,
+                "secret_access_key": "test-secret-key",
+                "multipart_part_size": multipart_part_size,
+            }),
+        );
+        S3BackendFactory {}.init_backend(storage).unwrap()
+    }
+}
+
+impl Drop for FakeS3 {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn decode(value: &str) -> String {
+    percent_decode_str(value)
+        .decode_utf8()
+        .unwrap()
+        .into_owned()
+}
+
+fn header(request: &Request, name: &'static str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.to_string())
+}
+
+fn xml_response(status: u16, xml: String) -> Response<Cursor<Vec<u8>>> {
+    Response::from_data(xml.into_bytes())
+        .with_status_code(status)
+        .with_header(Header::from_bytes("Content-Type", "application/xml").unwrap())
+}
+
+fn no_such_key() -> Response<Cursor<Vec<u8>>> {
+    xml_response(
+        404,
+        "<Error><Code>NoSuchKey</Code><Message>The specified key does not exist.</Message></Error>"
+            .to_owned(),
+    )
+}
+
+fn handle(state: &mut FakeS3State, request: &mut Request) -> Response<Cursor<Vec<u8>>> {
+    let range = header(request, "Range");
+    state.requests.push(RecordedRequest {
+        method: request.method().clone(),
+        url: request.url().to_owned(),
+        range: range.clone(),
+        authorized: header(request, "Authorization")
+            .map(|value| {
+                value.starts_with(&format!("AWS4-HMAC-SHA256 Credential={ACCESS_KEY_ID}/"))
+            })
+            .unwrap_or_default(),
+    });
+
+    let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+    let key = decode(path.strip_prefix(&format!("/{BUCKET}/")).unwrap());
+    let query: HashMap<String, String> = query
+        .split('&')
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            (decode(name), decode(value))
+        })
+        .collect();
+    let copy_source = header(request, "x-amz-copy-source");
+    let mut body = Vec::new();
+    request.as_reader().read_to_end(&mut body).unwrap();
+
+    match (request.method(), key.as_str()) {
+        (Method::Get, "") => list_objects(state, &query),
+        (Method::Get, key) => {
+            let content = match state.objects.get(key) {
+                Some(content) => content,
+                None => return no_such_key(),
+            };
+            match range {
+                Some(range) => {
+                    let (first, last) = range
+                        .strip_prefix("bytes=")
+                        .and_then(|range| range.split_once('-'))
+                        .unwrap();
+                    let first: usize = first.parse().unwrap();
+                    let last = last.parse::<usize>().unwrap().min(content.len() - 1);
+                    Response::from_data(content[first..=last].to_vec()).with_status_code(206)
+                }
+                None => Response::from_data(content.clone()),
+            }
+        }
+        // tiny_http skips the body but keeps its length in the response to HEAD request, unless
+        // the body is big enough to be sent in chunks
+        (Method::Head, key) => match state.objects.get(key) {
+            Some(content) => Response::from_data(content.clone())
+                .with_chunked_threshold(usize::MAX)
+                .with_header(
+                    Header::from_bytes("Last-Modified", "Wed, 21 Oct 2015 07:28:00 GMT").unwrap(),
+                ),
+            None => Response::from_data(Vec::new()).with_status_code(404),
+        },
+        (Method::Put, key) => {
+            if let Some(upload_id) = query.get("uploadId") {
+                let part_number: usize = query["partNumber"].parse().unwrap();
+                state
+                    .multipart_uploads
+                    .get_mut(upload_id)
+                    .unwrap()
+                    .insert(part_number, body);
+                return Response::from_data(Vec::new()).with_header(
+                    Header::from_bytes("ETag", format!("\"part-{part_number}\"")).unwrap(),
+                );
+            }
+            if let Some(copy_source) = copy_source {
+                let source_key = decode(copy_source.strip_prefix(&format!("/{BUCKET}/")).unwrap());
+                let content = match state.objects.get(&source_key) {
+                    Some(content) => content.clone(),
+                    None => return no_such_key(),
+                };
+                state.objects.insert(key.to_owned(), content);
+                return xml_response(
+                    200,
+                    "<CopyObjectResult><ETag>\"copy\"</ETag></CopyObjectResult>".to_owned(),
+                );
+            }
+            state.objects.insert(key.to_owned(), body);
+            Response::from_data(Vec::new())
+        }
+        (Method::Post, key) if query.contains_key("uploads") => {
+            let upload_id = format!("upload-{}", state.multipart_uploads.len());
+            state
+                .multipart_uploads
+                .insert(upload_id.clone(), BTreeMap::new());
+            xml_response(
+                200,
+                format!(
+                    "<InitiateMultipartUploadResult><Bucket>{BUCKET}</Bucket><Key>{key}</Key>\
+                     <UploadId>{upload_id}</UploadId></InitiateMultipartUploadResult>"
+                ),
+            )
+        }
+        (Method::Post, key) => {
+            let parts = state.multipart_uploads.remove(&query["uploadId"]).unwrap();
+            let body = String::from_utf8(body).unwrap();
+            for part_number in parts.keys() {
+                assert!(body.contains(&format!(
+                    "<PartNumber>{part_number}</PartNumber><ETag>&quot;part-{part_number}&quot;</ETag>"
+                )));
+            }
+            state
+                .objects
+                .insert(key.to_owned(), parts.into_values().flatten().collect());
+            state.completed_multipart_uploads += 1;
+            xml_response(
+                200,
+                format!("<CompleteMultipartUploadResult><Key>{key}</Key></CompleteMultipartUploadResult>"),
+            )
+        }
+        (Method::Delete, key) => {
+            match query.get("uploadId") {
+                Some(upload_id) => {
+                    state.multipart_uploads.remove(upload_id);
+                }
+                None => {
+                    state.objects.remove(key);
+                }
+            }
+            Response::from_data(Vec::new()).with_status_code(204)
+        }
+        _ => Response::from_data(Vec::new()).with_status_code(501),
+    }
+}
+
+fn list_objects(state: &FakeS3State, query: &HashMap<String, String>) -> Response<Cursor<Vec<u8>>> {
+    let prefix = query.get("prefix").cloned().unwrap_or_default();
+    let delimiter = query.get("delimiter");
+    let max_keys = query
+        .get("max-keys")
+        .map(|max_keys| max_keys.parse().unwrap())
+        .unwrap_or(LIST_PAGE_SIZE)
+        .min(LIST_PAGE_SIZE);
+    let start = match query.get("continuation-token") {
+        Some(token) => Bound::Excluded(token.clone()),
+        None => Bound::Unbounded,
+    };
+
+    let mut contents = String::new();
+    let mut common_prefixes: Vec<String> = Vec::new();
+    let mut entries = 0;
+    let mut last_key = None;
+    let mut is_truncated = false;
+    for (key, content) in state
+        .objects
+        .range((start, Bound::Unbounded))
+        .filter(|(key, _)| key.starts_with(&prefix))
+    {
+        let common_prefix = delimiter.and_then(|delimiter| {
+            key[prefix.len()..]
+                .find(delimiter.as_str())
+                .map(|index| key[..prefix.len() + index + delimiter.len()].to_owned())
+        });
+        // keys grouped into already listed common prefix are skipped
+        if common_prefix.is_some() && common_prefix.as_ref() == common_prefixes.last() {
+            last_key = Some(key.clone());
+            continue;
+        }
+        if entries == max_keys {
+            is_truncated = true;
+            break;
+        }
+        entries += 1;
+        last_key = Some(key.clone());
+        match common_prefix {
+            Some(common_prefix) => common_prefixes.push(common_prefix),
+            None => contents.push_str(&format!(
+                "<Contents><Key>{}</Key><LastModified>2015-10-21T07:28:00.000Z</LastModified>\
+                 <Size>{}</Size></Contents>",
+                quick_xml::escape::escape(key),
+                content.len()
+            )),
+        }
+    }
+
+    let common_prefixes: String = common_prefixes
+        .iter()
+        .map(|prefix| {
+            format!(
+                "<CommonPrefixes><Prefix>{}</Prefix></CommonPrefixes>",
+                quick_xml::escape::escape(prefix)
+            )
+        })
+        .collect();
+    let continuation_token = if is_truncated {
+        format!(
+            "<NextContinuationToken>{}</NextContinuationToken>",
+            quick_xml::escape::escape(&last_key.unwrap())
+        )
+    } else {
+        String::new()
+    };
+    xml_response(
+        200,
+        format!(
+            "<ListBucketResult><Name>{BUCKET}</Name><Prefix>{}</Prefix><KeyCount>{entries}</KeyCount>\
+             <IsTruncated>{is_truncated}</IsTruncated>{contents}{common_prefixes}\
+             {continuation_token}</ListBucketResult>",
+            quick_xml::escape::escape(&prefix)
+        ),
+    )
+}
+
+#[test]
+fn test_writing_and_reading_file_of_s3_backend() {
+    let s3 = FakeS3::start();
+    let backend = s3.backend("books", DEFAULT_MULTIPART_PART_SIZE);
+
+    backend.mkdir(Path::new("/dir")).unwrap();
+    backend.create(Path::new("/dir/file1")).unwrap();
+    backend
+        .write(Path::new("/dir/file1"), 0, b"hello world")
+        .unwrap();
+    backend.truncate(Path::new("/dir/file1"), 5).unwrap();
+
+    assert_eq!(
+        backend.read(Path::new("/dir/file1"), 1, 100).unwrap(),
+        b"ello".to_vec()
+    );
+    let stat = backend.getattr(Path::new("/dir/file1")).unwrap();
+    assert_eq!(stat.node_type, NodeType::File);
+    assert_eq!(stat.size, 5);
+    assert!(stat.modification_time.is_some());
+    assert!(matches!(
+        backend.create(Path::new("/dir/file1")),
+        Err(StorageBackendError::PathAlreadyExists)
+    ));
+    assert!(matches!(
+        backend.create(Path::new("/missing_dir/file1")),
+        Err(StorageBackendError::NoSuchPath)
+    ));
+    assert!(matches!(
+        backend.rmdir(Path::new("/dir")),
+        Err(StorageBackendError::DirNotEmpty)
+    ));
+    assert!(matches!(
+        backend.read(Path::new("/dir"), 0, 100),
+        Err(StorageBackendError::NotAFile)
+    ));
+
+    backend
+        .rename(Path::new("/dir/file1"), Path::new("/file2"))
+        .unwrap();
+    backend.rmdir(Path::new("/dir")).unwrap();
+    backend.unlink(Path::new("/file2")).unwrap();
+
+    assert_eq!(
+        backend.readdir(Path::new("/")).unwrap(),
+        Vec::<PathBuf>::new()
+    );
+    assert_eq!(s3.keys(), Vec::<String>::new());
+    assert!(s3.requests().iter().all(|request| request.authorized));
+}
+
+#[test]
+fn test_directories_are_mapped_to_key_prefixes() {
+    let s3 = FakeS3::start();
+    let backend = s3.backend("books", DEFAULT_MULTIPART_PART_SIZE);
+
+    backend.mkdir(Path::new("/a")).unwrap();
+    backend.mkdir(Path::new("/a/b")).unwrap();
+    backend.create(Path::new("/a/b/file")).unwrap();
+    // directory created by some other S3 client without the marker object
+    s3.put_object("books/other/file", b"content");
+
+    assert_eq!(
+        s3.keys(),
+        vec![
+            "books/a/",
+            "books/a/b/",
+            "books/a/b/file",
+            "books/other/file"
+        ]
+    );
+    assert_eq!(
+        backend.getattr(Path::new("/other")).unwrap().node_type,
+        NodeType::Dir
+    );
+    assert_eq!(
+        backend.readdir(Path::new("/")).unwrap(),
+        vec![PathBuf::from("/a"), PathBuf::from("/other")]
+    );
+    assert_eq!(
+        backend.readdir(Path::new("/a/../a/b")).unwrap(),
+        vec![PathBuf::from("/a/b/file")]
+    );
+    assert!(matches!(
+        backend.readdir(Path::new("/a/b/file")),
+        Err(StorageBackendError::NotADirectory)
+    ));
+    assert!(matches!(
+        backend.getattr(Path::new("/a/file")),
+        Err(StorageBackendError::NoSuchPath)
+    ));
+}
+
+#[test]
+fn test_listing_follows_continuation_tokens() {
+    let s3 = FakeS3::start();
+    let backend = s3.backend("books", DEFAULT_MULTIPART_PART_SIZE);
+
+    for name in ["file1", "file2", "file3", "file4", "file5"] {
+        backend.create(&Path::new("/").join(name)).unwrap();
+    }
+    backend.mkdir(Path::new("/dir")).unwrap();
+    backend.create(Path::new("/dir/file6")).unwrap();
+
+    assert_eq!(
+        backend.readdir(Path::new("/")).unwrap(),
+        vec![
+            PathBuf::from("/file1"),
+            PathBuf::from("/file2"),
+            PathBuf::from("/file3"),
+            PathBuf::from("/file4"),
+            PathBuf::from("/file5"),
+            PathBuf::from("/dir"),
+        ]
+    );
+    let continued_listings = s3
+        .requests()
+        .iter()
+        .filter(|request| request.url.contains("continuation-token="))
+        .count();
+    assert_eq!(continued_listings, 2);
+}
+
+#[test]
+fn test_reading_ranges_of_s3_file() {
+    let s3 = FakeS3::start();
+    let backend = s3.backend("books", DEFAULT_MULTIPART_PART_SIZE);
+    backend.create(Path::new("/file")).unwrap();
+    backend
+        .write(Path::new("/file"), 0, b"hello world")
+        .unwrap();
+
+    assert_eq!(
+        backend.read(Path::new("/file"), 6, 100).unwrap(),
+        b"world".to_vec()
+    );
+    assert_eq!(
+        backend.read(Path::new("/file"), 2, 3).unwrap(),
+        b"llo".to_vec()
+    );
+    assert_eq!(
+        backend.read(Path::new("/file"), 20, 3).unwrap(),
+        Vec::<u8>::new()
+    );
+
+    let ranges: Vec<String> = s3
+        .requests()
+        .into_iter()
+        .filter_map(|request| request.range)
+        .collect();
+    assert_eq!(ranges, vec!["bytes=6-10", "bytes=2-4"]);
+}
+
+#[test]
+fn test_large_files_are_uploaded_in_parts() {
+    let s3 = FakeS3::start();
+    let backend = s3.backend("books", MIN_MULTIPART_PART_SIZE);
+    backend.create(Path::new("/file")).unwrap();
+    let content: Vec<u8> = (0..2 * MIN_MULTIPART_PART_SIZE + 10)
+        .map(|i| i as u8)
+        .collect();
+
+    backend
+        .write(Path::new("/file"), 0, &content[..MIN_MULTIPART_PART_SIZE])
+        .unwrap();
+    backend
+        .write(
+            Path::new("/file"),
+            MIN_MULTIPART_PART_SIZE as u64,
+            &content[MIN_MULTIPART_PART_SIZE..],
+        )
+        .unwrap();
+
+    assert_eq!(s3.object("books/file").unwrap(), content);
+    let state = s3.state.lock().expect("Poisoned Mutex");
+    assert_eq!(state.completed_multipart_uploads, 1);
+    assert!(state.multipart_uploads.is_empty());
+    let uploaded_parts = state
+        .requests
+        .iter()
+        .filter(|request| request.method == Method::Put && request.url.contains("partNumber="))
+        .count();
+    assert_eq!(uploaded_parts, 3);
+}
+
+#[test]
+fn test_files_exceeding_maximum_number_of_parts_are_rejected() {
+    let s3 = FakeS3::start();
+    let backend = s3.backend("books", MIN_MULTIPART_PART_SIZE);
+    backend.create(Path::new("/file")).unwrap();
+    let max_size = (MIN_MULTIPART_PART_SIZE * MAX_MULTIPART_PARTS) as u64;
+
+    assert!(matches!(
+        backend.truncate(Path::new("/file"), max_size + 1),
+        Err(StorageBackendError::Generic(_))
+    ));
+    assert!(matches!(
+        backend.write(Path::new("/file"), max_size, b"a"),
+        Err(StorageBackendError::Generic(_))
+    ));
+    assert!(matches!(
+        backend.write(Path::new("/file"), u64::MAX, b"a"),
+        Err(StorageBackendError::Generic(_))
+    ));
+    assert_eq!(s3.object("books/file").unwrap(), Vec::<u8>::new());
+}
+
+#[test]
+fn test_multipart_part_size_must_be_accepted_by_s3() {
+    let storage = Storage::new(
+        None,
+        S3_BACKEND_TYPE.to_owned(),
+        json!({
+            "endpoint": "http://localhost:9000",
+            "bucket": BUCKET,
+            "container_prefix": "books",
+            "access_key_id": ACCESS_KEY_ID,
+            "secret_access_key": "test-secret-key",
+            "multipart_part_size": MIN_MULTIPART_PART_SIZE - 1,
+        }),
+    );
+    assert!(S3BackendFactory {}.init_backend(storage).is_err());
+}
+
+#[test]
+fn test_renaming_directory_moves_all_its_objects() {
+    let s3 = FakeS3::start();
+    let backend = s3.backend("books", DEFAULT_MULTIPART_PART_SIZE);
+    backend.mkdir(Path::new("/a")).unwrap();
+    backend.mkdir(Path::new("/a/b")).unwrap();
+    backend.create(Path::new("/a/b/file")).unwrap();
+    backend
+        .write(Path::new("/a/b/file"), 0, b"content")
+        .unwrap();
+
+    assert!(backend
+        .rename(Path::new("/a"), Path::new("/a/b/c"))
+        .is_err());
+    backend.rename(Path::new("/a"), Path::new("/c")).unwrap();
+
+    assert_eq!(s3.keys(), vec!["books/c/", "books/c/b/", "books/c/b/file"]);
+    assert_eq!(
+        backend.read(Path::new("/c/b/file"), 0, 100).unwrap(),
+        b"content".to_vec()
+    );
+}
+
+#[test]
+fn test_storages_are_kept_under_container_prefixes() {
+    let s3 = FakeS3::start();
+    let books = s3.backend("books", DEFAULT_MULTIPART_PART_SIZE);
+    let music = s3.backend("media/music", DEFAULT_MULTIPART_PART_SIZE);
+
+    books.create(Path::new("/../../file")).unwrap();
+    music.create(Path::new("/file")).unwrap();
+
+    assert_eq!(s3.keys(), vec!["books/file", "media/music/file"]);
+    assert_eq!(
+        books.readdir(Path::new("/")).unwrap(),
+        vec![PathBuf::from("/file")]
+    );
+}
+
+#[test]
+fn test_container_prefix_cannot_point_outside_of_bucket() {
+    for container_prefix in ["../books", "/books", "books/../.."] {
+        let storage = Storage::new(
+            None,
+            S3_BACKEND_TYPE.to_owned(),
+            json!({
+                "endpoint": "http://localhost:9000",
+                "bucket": BUCKET,
+                "container_prefix": container_prefix,
+                "access_key_id": ACCESS_KEY_ID,
+                "secret_access_key": "test-secret-key",
+            }),
+        );
+        assert!(S3BackendFactory {}.init_backend(storage).is_err());
+    }
+}
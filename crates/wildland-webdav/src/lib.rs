@@ -0,0 +1,38 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! WebDAV server exposing the user's forest through
+//! [`DfsFrontend`](wildland_corex::dfs::interface::DfsFrontend), so it can be accessed by file
+//! managers of any OS without native bindings.
+//!
+//! Supported methods are OPTIONS, PROPFIND, GET, HEAD, PUT, MKCOL, MOVE and DELETE (class 1 of
+//! RFC 4918). Requests are served one by one on the thread calling [`WebDavServer::serve`].
+//!
+//! By default the server listens on the loopback interface and accepts only requests addressed to
+//! a loopback host, so web pages cannot reach it through DNS rebinding. Servers listening on other
+//! interfaces require a token sent as a Bearer token or as the password of Basic authentication.
+//!
+//! TODO WILX-372 LOCK and UNLOCK are not supported, so some clients (e.g. macOS Finder) mount the
+//! forest read-only
+
+mod paths;
+mod server;
+#[cfg(test)]
+mod tests;
+mod xml;
+
+pub use server::{WebDavServer, WebDavServerError, WebDavStopHandle};
@@ -0,0 +1,124 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+
+/// Characters escaped within a single segment of an href
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b']')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Translates a request URL or an absolute URL (e.g. from `Destination` header) to an absolute
+/// DFS path.
+///
+/// Returns `None` if the path cannot be decoded or leads outside of the forest. Decoded segments
+/// must not contain `/` or NUL, so that a single segment of the URL is never treated as multiple
+/// path components.
+pub(crate) fn dfs_path(url: &str) -> Option<String> {
+    let path = match url.split_once("://") {
+        Some((_scheme, rest)) => rest.find('/').map(|i| &rest[i..]).unwrap_or("/"),
+        None => url,
+    };
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+
+    let mut segments = Vec::new();
+    for segment in path.split('/') {
+        let segment = percent_decode_str(segment).decode_utf8().ok()?;
+        match segment.as_ref() {
+            "" | "." => {}
+            ".." => return None,
+            _ if segment.contains(['/', '\0']) => return None,
+            _ => segments.push(segment.into_owned()),
+        }
+    }
+    Some(format!("/{}", segments.join("/")))
+}
+
+/// Encodes DFS path as an href. Collections get a trailing slash, as clients use it to tell them
+/// from files.
+pub(crate) fn href(path: &str, is_collection: bool) -> String {
+    let mut href: String = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| format!("/{}", utf8_percent_encode(segment, SEGMENT)))
+        .collect();
+    if is_collection || href.is_empty() {
+        href.push('/');
+    }
+    href
+}
+
+/// Returns path of the parent directory of the given DFS path
+pub(crate) fn parent(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(i) => &path[..i],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_urls_are_translated_to_dfs_paths() {
+        assert_eq!(dfs_path("/"), Some("/".to_owned()));
+        assert_eq!(dfs_path("/books/"), Some("/books".to_owned()));
+        assert_eq!(
+            dfs_path("/books/Dune%20%231.txt?x=1"),
+            Some("/books/Dune #1.txt".to_owned())
+        );
+        assert_eq!(
+            dfs_path("http://localhost:8080/a/./b//c"),
+            Some("/a/b/c".to_owned())
+        );
+        assert_eq!(dfs_path("http://localhost:8080"), Some("/".to_owned()));
+        assert_eq!(dfs_path("/a/../../etc"), None);
+        assert_eq!(dfs_path("/%FF"), None);
+        assert_eq!(dfs_path("/a%2F..%2F..%2Fetc"), None);
+        assert_eq!(dfs_path("/a%2Fb"), None);
+        assert_eq!(dfs_path("/a%00b"), None);
+    }
+
+    #[test]
+    fn test_paths_are_encoded_as_hrefs() {
+        assert_eq!(href("/", true), "/");
+        assert_eq!(href("/books", true), "/books/");
+        assert_eq!(href("/books/Dune #1.txt", false), "/books/Dune%20%231.txt");
+        assert_eq!(href("/zażółć", false), "/za%C5%BC%C3%B3%C5%82%C4%87");
+    }
+
+    #[test]
+    fn test_parent_paths() {
+        assert_eq!(parent("/"), "/");
+        assert_eq!(parent("/a"), "/");
+        assert_eq!(parent("/a/b"), "/a");
+    }
+}
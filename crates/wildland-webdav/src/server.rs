@@ -0,0 +1,657 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::io::{self, Read};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, UNIX_EPOCH};
+
+use thiserror::Error;
+use tiny_http::{Header, Request, Response, Server};
+use wildland_corex::dfs::interface::{
+    DfsFrontend,
+    DfsFrontendError,
+    FileHandle,
+    NodeDescriptor,
+    NodeType,
+    Stat,
+};
+
+use crate::paths::{dfs_path, parent};
+use crate::xml::multistatus;
+
+const ALLOWED_METHODS: &str = "OPTIONS, PROPFIND, GET, HEAD, PUT, MKCOL, MOVE, DELETE";
+/// Size of chunks in which uploaded files are written to DFS
+const WRITE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Hosts by which clients on this machine address the server
+const LOOPBACK_HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "[::1]"];
+
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum WebDavServerError {
+    #[error("Could not start WebDAV server: {0}")]
+    StartFailed(String),
+    #[error("Address {0} is not a loopback address, so the server requires a token")]
+    TokenRequired(String),
+}
+
+/// Describes who may access the server.
+enum Access {
+    /// Clients on this machine only. Requests have to address the server by a loopback host, so
+    /// web pages cannot access it by rebinding their domains to the loopback address.
+    Loopback,
+    /// Clients presenting the token as the password of HTTP Basic authentication (with any user
+    /// name) or as a Bearer token.
+    Token(String),
+}
+
+impl Access {
+    /// Returns the reply rejecting the request if it is not allowed.
+    fn check(&self, request: &Request) -> Option<Reply> {
+        match self {
+            Access::Loopback => {
+                // repeated headers are rejected, as proxies may pick a different one than we do
+                let host_allowed = unique_header_value(request, "Host")
+                    .is_some_and(|host| host.is_some_and(is_loopback_host));
+                let origin_allowed = unique_header_value(request, "Origin").is_some_and(|origin| {
+                    origin.map_or(true, |origin| {
+                        origin.strip_prefix("http://").is_some_and(is_loopback_host)
+                    })
+                });
+                (!host_allowed || !origin_allowed).then_some(Reply::Empty(403))
+            }
+            Access::Token(token) => {
+                let presented = header_value(request, "Authorization").and_then(presented_token);
+                let authorized = presented.is_some_and(|presented| {
+                    constant_time_eq(presented.as_bytes(), token.as_bytes())
+                });
+                (!authorized).then_some(Reply::Unauthorized)
+            }
+        }
+    }
+}
+
+/// Whether the value of `Host` header, or the authority of an origin, points to this machine.
+fn is_loopback_host(host: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if !name.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => name,
+        _ => host,
+    };
+    LOOPBACK_HOSTS
+        .iter()
+        .any(|loopback| loopback.eq_ignore_ascii_case(name))
+}
+
+/// Returns the token sent in `Authorization` header.
+fn presented_token(authorization: &str) -> Option<String> {
+    let (scheme, credentials) = authorization.split_once(' ')?;
+    if scheme.eq_ignore_ascii_case("Bearer") {
+        Some(credentials.trim().to_owned())
+    } else if scheme.eq_ignore_ascii_case("Basic") {
+        let credentials = String::from_utf8(base64::decode(credentials.trim()).ok()?).ok()?;
+        credentials
+            .split_once(':')
+            .map(|(_user, password)| password.to_owned())
+    } else {
+        None
+    }
+}
+
+/// Compares secrets in time not depending on their common prefix.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Response to be sent for a request, before it is serialized
+enum Reply {
+    Empty(u16),
+    /// Request without a valid token
+    Unauthorized,
+    Options,
+    Multistatus(String),
+    /// Attributes of a file, without its content (HEAD)
+    Attributes(Stat),
+    /// Content of a file opened for the request
+    File {
+        handle: FileHandle,
+        stat: Stat,
+    },
+}
+
+fn status_code(error: &DfsFrontendError) -> u16 {
+    match error {
+        DfsFrontendError::NoSuchPath => 404,
+        DfsFrontendError::PathAlreadyExists | DfsFrontendError::NotAFile => 405,
+        DfsFrontendError::NotADirectory | DfsFrontendError::DirNotEmpty => 409,
+        DfsFrontendError::ReadOnlyPath => 403,
+        DfsFrontendError::NameTooLong => 414,
+        // RFC 4918 uses it for a destination the server cannot write to
+        DfsFrontendError::MoveBetweenContainers => 502,
+        DfsFrontendError::StorageNotResponsive => 503,
+        DfsFrontendError::InvalidFileHandle | DfsFrontendError::Generic(_) => 500,
+    }
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name, value).expect("Header must be ASCII")
+}
+
+fn header_value<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+/// Returns `None` if the header is repeated and `Some(None)` if it is missing.
+fn unique_header_value<'a>(request: &'a Request, name: &'static str) -> Option<Option<&'a str>> {
+    let mut values = request
+        .headers()
+        .iter()
+        .filter(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str());
+    let value = values.next();
+    values.next().is_none().then_some(value)
+}
+
+fn file_headers(stat: &Stat) -> Vec<Header> {
+    let mut headers = vec![header("Content-Type", "application/octet-stream")];
+    if let Some(time) = stat.modification_time {
+        let time = UNIX_EPOCH + Duration::new(time.sec, time.nano_sec);
+        headers.push(header("Last-Modified", &httpdate::fmt_http_date(time)));
+    }
+    headers
+}
+
+/// Describes the node as listed in its parent directory.
+fn node_descriptor(
+    dfs: &mut dyn DfsFrontend,
+    path: &str,
+) -> Result<NodeDescriptor, DfsFrontendError> {
+    dfs.readdir(parent(path).to_owned())
+        .into_iter()
+        .find(|node| node.absolute_path == Path::new(path))
+        .ok_or(DfsFrontendError::NoSuchPath)
+}
+
+/// Lists nodes to be removed in order to remove the given one, children first, along with whether
+/// they are directories.
+///
+/// Fails with [`DfsFrontendError::ReadOnlyPath`] before anything is removed if the node contains
+/// virtual directories or roots of containers, as removing them would remove content of other
+/// containers.
+fn removal_plan(
+    dfs: &mut dyn DfsFrontend,
+    node: &NodeDescriptor,
+) -> Result<Vec<(String, bool)>, DfsFrontendError> {
+    let path = node.absolute_path.to_string_lossy().into_owned();
+    match &node.storage {
+        Some(storage) if storage.path_within_storage() != Path::new("/") => {}
+        _ => return Err(DfsFrontendError::ReadOnlyPath),
+    }
+    if dfs.getattr(path.clone())?.node_type != NodeType::Dir {
+        return Ok(vec![(path, false)]);
+    }
+    let mut plan = Vec::new();
+    for child in dfs.readdir(path.clone()) {
+        plan.extend(removal_plan(dfs, &child)?);
+    }
+    plan.push((path, true));
+    Ok(plan)
+}
+
+fn remove_all(dfs: &mut dyn DfsFrontend, path: &str) -> Result<(), DfsFrontendError> {
+    let node = node_descriptor(dfs, path)?;
+    for (path, is_dir) in removal_plan(dfs, &node)? {
+        if is_dir {
+            dfs.rmdir(path)?;
+        } else {
+            dfs.unlink(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns a free path next to the given one, where a node can be kept temporarily, e.g. an
+/// overwritten node until it is removed.
+fn temporary_path(
+    dfs: &mut dyn DfsFrontend,
+    path: &str,
+    purpose: &str,
+) -> Result<String, DfsFrontendError> {
+    let directory = parent(path).trim_end_matches('/');
+    let name = Path::new(path)
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let mut i = 0;
+    loop {
+        let temporary = format!("{directory}/.{name}.{purpose}-{i}");
+        match dfs.getattr(temporary.clone()) {
+            Err(DfsFrontendError::NoSuchPath) => return Ok(temporary),
+            Err(e) => return Err(e),
+            Ok(_) => i += 1,
+        }
+    }
+}
+
+/// Moves the node over an existing destination, which is removed afterwards.
+///
+/// The destination is moved aside and removed only once the node took its place, so it is kept if
+/// the node can not be moved.
+fn replace(
+    dfs: &mut dyn DfsFrontend,
+    path: String,
+    destination: String,
+) -> Result<(), DfsFrontendError> {
+    let node = node_descriptor(dfs, &destination)?;
+    removal_plan(dfs, &node)?;
+    let overwritten = temporary_path(dfs, &destination, "overwritten")?;
+    dfs.rename(destination.clone(), overwritten.clone())?;
+    if let Err(e) = dfs.rename(path, destination.clone()) {
+        if let Err(restore_error) = dfs.rename(overwritten, destination) {
+            tracing::error!("Could not restore overwritten node: {restore_error}");
+        }
+        return Err(e);
+    }
+    remove_all(dfs, &overwritten)
+}
+
+/// Streams content of a file opened in DFS, closing it when dropped
+struct FileReader<'a> {
+    dfs: &'a Mutex<dyn DfsFrontend>,
+    handle: FileHandle,
+    offset: u64,
+}
+
+impl Read for FileReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let data = self
+            .dfs
+            .lock()
+            .expect("Poisoned Mutex")
+            .read(&self.handle, self.offset, buf.len())
+            .map_err(io::Error::other)?;
+        buf[..data.len()].copy_from_slice(&data);
+        self.offset += data.len() as u64;
+        Ok(data.len())
+    }
+}
+
+impl Drop for FileReader<'_> {
+    fn drop(&mut self) {
+        if let Err(e) = self.dfs.lock().expect("Poisoned Mutex").close(&self.handle) {
+            tracing::warn!("Could not close file: {e}");
+        }
+    }
+}
+
+/// Stops [`WebDavServer::serve`] loop, possibly from another thread
+#[derive(Clone)]
+pub struct WebDavStopHandle {
+    server: Arc<Server>,
+}
+
+impl WebDavStopHandle {
+    pub fn stop(&self) {
+        self.server.unblock()
+    }
+}
+
+/// Serves the forest visible through the given DFS over WebDAV.
+///
+/// Paths of the forest are mapped to URL paths directly, e.g. file `/books/a.txt` is available at
+/// `http://<address>/books/a.txt`.
+pub struct WebDavServer {
+    server: Arc<Server>,
+    dfs: Arc<Mutex<dyn DfsFrontend>>,
+    access: Access,
+}
+
+impl WebDavServer {
+    /// Binds the server to the given port of the loopback address, so that it is accessible only
+    /// from this machine. Requests are not handled until [`WebDavServer::serve`] is called.
+    pub fn new(dfs: Arc<Mutex<dyn DfsFrontend>>, port: u16) -> Result<Self, WebDavServerError> {
+        Self::bind(
+            dfs,
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port),
+            None,
+        )
+    }
+
+    /// Binds the server to the given address. Unless the address is a loopback one, clients have
+    /// to present the token, as described in [`WebDavServer::with_token`].
+    pub fn bind(
+        dfs: Arc<Mutex<dyn DfsFrontend>>,
+        addr: impl ToSocketAddrs,
+        token: Option<String>,
+    ) -> Result<Self, WebDavServerError> {
+        let addrs: Vec<SocketAddr> = addr
+            .to_socket_addrs()
+            .map_err(|e| WebDavServerError::StartFailed(e.to_string()))?
+            .collect();
+        let access = match token {
+            Some(token) => Access::Token(token),
+            None => match addrs.iter().find(|addr| !addr.ip().is_loopback()) {
+                Some(addr) => return Err(WebDavServerError::TokenRequired(addr.to_string())),
+                None => Access::Loopback,
+            },
+        };
+        let server = Server::http(addrs.as_slice())
+            .map_err(|e| WebDavServerError::StartFailed(e.to_string()))?;
+        Ok(Self {
+            server: Arc::new(server),
+            dfs,
+            access,
+        })
+    }
+
+    /// Binds the server to the given address, accepting requests only from clients presenting the
+    /// token either as the password of HTTP Basic authentication (with any user name), which is
+    /// supported by file managers, or as a Bearer token.
+    pub fn with_token(
+        dfs: Arc<Mutex<dyn DfsFrontend>>,
+        addr: impl ToSocketAddrs,
+        token: String,
+    ) -> Result<Self, WebDavServerError> {
+        Self::bind(dfs, addr, Some(token))
+    }
+
+    /// Address the server listens on, useful when it has been bound to port 0
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+
+    pub fn stop_handle(&self) -> WebDavStopHandle {
+        WebDavStopHandle {
+            server: self.server.clone(),
+        }
+    }
+
+    /// Handles requests one by one until [`WebDavStopHandle::stop`] is called.
+    pub fn serve(&self) {
+        for request in self.server.incoming_requests() {
+            self.handle(request);
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn handle(&self, mut request: Request) {
+        tracing::debug!("{} {}", request.method(), request.url());
+        let reply = match self.access.check(&request) {
+            Some(rejection) => rejection,
+            None => self
+                .reply(&mut request)
+                .unwrap_or_else(|e| Reply::Empty(status_code(&e))),
+        };
+        if let Err(e) = self.respond(request, reply) {
+            tracing::warn!("Could not send response: {e}");
+        }
+    }
+
+    fn dfs(&self) -> MutexGuard<'_, dyn DfsFrontend + 'static> {
+        self.dfs.lock().expect("Poisoned Mutex")
+    }
+
+    fn reply(&self, request: &mut Request) -> Result<Reply, DfsFrontendError> {
+        let path = match dfs_path(request.url()) {
+            Some(path) => path,
+            None => return Ok(Reply::Empty(400)),
+        };
+        match request.method().as_str() {
+            "OPTIONS" => Ok(Reply::Options),
+            "PROPFIND" => self.propfind(path, header_value(request, "Depth")),
+            "GET" => self.get(path),
+            "HEAD" => self.head(path),
+            "PUT" => {
+                let body_length = request.body_length();
+                self.put(path, request.as_reader(), body_length)
+            }
+            "MKCOL" => self.mkcol(path, request.body_length().unwrap_or_default()),
+            "MOVE" => {
+                let destination = header_value(request, "Destination").and_then(dfs_path);
+                let overwrite = header_value(request, "Overwrite") != Some("F");
+                match destination {
+                    Some(destination) => self.move_node(path, destination, overwrite),
+                    None => Ok(Reply::Empty(400)),
+                }
+            }
+            "DELETE" => self.delete(path),
+            _ => Ok(Reply::Empty(501)),
+        }
+    }
+
+    fn respond(&self, request: Request, reply: Reply) -> io::Result<()> {
+        match reply {
+            Reply::Empty(status_code) => request.respond(Response::empty(status_code)),
+            Reply::Unauthorized => request.respond(
+                Response::empty(401)
+                    .with_header(header("WWW-Authenticate", "Basic realm=\"Wildland\"")),
+            ),
+            Reply::Options => request.respond(
+                Response::empty(200)
+                    .with_header(header("DAV", "1"))
+                    .with_header(header("Allow", ALLOWED_METHODS)),
+            ),
+            Reply::Multistatus(body) => request.respond(
+                Response::from_string(body)
+                    .with_status_code(207)
+                    .with_header(header("Content-Type", "application/xml; charset=utf-8")),
+            ),
+            // length of files is known, so it is sent instead of using chunked encoding
+            Reply::Attributes(stat) => request.respond(
+                Response::new(
+                    200.into(),
+                    file_headers(&stat),
+                    io::empty(),
+                    usize::try_from(stat.size).ok(),
+                    None,
+                )
+                .with_chunked_threshold(usize::MAX),
+            ),
+            Reply::File { handle, stat } => request.respond(
+                Response::new(
+                    200.into(),
+                    file_headers(&stat),
+                    FileReader {
+                        dfs: &*self.dfs,
+                        handle,
+                        offset: 0,
+                    },
+                    usize::try_from(stat.size).ok(),
+                    None,
+                )
+                .with_chunked_threshold(usize::MAX),
+            ),
+        }
+    }
+
+    /// Describes the node and, with `Depth: 1`, its children. Infinite depth is not supported.
+    fn propfind(&self, path: String, depth: Option<&str>) -> Result<Reply, DfsFrontendError> {
+        let with_children = match depth {
+            Some("0") => false,
+            Some("1") => true,
+            _ => return Ok(Reply::Empty(403)),
+        };
+        let mut dfs = self.dfs();
+        let stat = dfs.getattr(path.clone())?;
+        let mut nodes = Vec::new();
+        if with_children && stat.node_type == NodeType::Dir {
+            for node in dfs.readdir(path.clone()) {
+                let child_path = node.absolute_path.to_string_lossy().into_owned();
+                let child_stat = match node.storage {
+                    Some(_) => dfs.getattr(child_path.clone()),
+                    None => Ok(Stat::virtual_dir()),
+                };
+                match child_stat {
+                    Ok(child_stat) => nodes.push((child_path, child_stat)),
+                    Err(e) => tracing::warn!("Could not get attributes of {child_path}: {e}"),
+                }
+            }
+        }
+        nodes.insert(0, (path, stat));
+        Ok(Reply::Multistatus(multistatus(
+            nodes.iter().map(|(path, stat)| (path.as_str(), stat)),
+        )))
+    }
+
+    fn get(&self, path: String) -> Result<Reply, DfsFrontendError> {
+        let mut dfs = self.dfs();
+        let stat = dfs.getattr(path.clone())?;
+        if stat.node_type == NodeType::Dir {
+            return Ok(Reply::Empty(405));
+        }
+        let handle = dfs.open(path)?;
+        Ok(Reply::File { handle, stat })
+    }
+
+    fn head(&self, path: String) -> Result<Reply, DfsFrontendError> {
+        let stat = self.dfs().getattr(path)?;
+        if stat.node_type == NodeType::Dir {
+            return Ok(Reply::Empty(405));
+        }
+        Ok(Reply::Attributes(stat))
+    }
+
+    /// Replaces content of the file with the request body, creating the file if needed.
+    ///
+    /// The body is uploaded to a temporary file first, which replaces the file only once the whole
+    /// body is written, so the file is kept intact if the upload fails.
+    fn put(
+        &self,
+        path: String,
+        body: &mut dyn Read,
+        body_length: Option<usize>,
+    ) -> Result<Reply, DfsFrontendError> {
+        let (upload, handle, created) = {
+            let mut dfs = self.dfs();
+            let created = match dfs.getattr(path.clone()) {
+                Ok(stat) if stat.node_type == NodeType::Dir => return Ok(Reply::Empty(405)),
+                Ok(_) => false,
+                Err(DfsFrontendError::NoSuchPath) => true,
+                Err(e) => return Err(e),
+            };
+            let upload = temporary_path(&mut *dfs, &path, "upload")?;
+            match dfs.create(upload.clone()) {
+                Ok(handle) => (upload, handle, created),
+                Err(DfsFrontendError::NoSuchPath) => return Ok(Reply::Empty(409)),
+                Err(e) => return Err(e),
+            }
+        };
+
+        let written = self
+            .write_body(&handle, body)
+            .and_then(|written| match body_length {
+                // the connection may be closed before the whole body is sent
+                Some(length) if written != length as u64 => Err(DfsFrontendError::Generic(
+                    format!("Received {written} of {length} bytes"),
+                )),
+                _ => Ok(()),
+            });
+        let mut dfs = self.dfs();
+        let closed = dfs.close(&handle);
+        let uploaded = written.and(closed).and_then(|()| {
+            if created {
+                dfs.rename(upload.clone(), path)
+            } else {
+                replace(&mut *dfs, upload.clone(), path)
+            }
+        });
+        if let Err(e) = uploaded {
+            if let Err(remove_error) = dfs.unlink(upload) {
+                tracing::warn!("Could not remove incomplete upload: {remove_error}");
+            }
+            return Err(e);
+        }
+
+        Ok(Reply::Empty(if created { 201 } else { 204 }))
+    }
+
+    /// Writes the request body to the opened file and returns its length. DFS is locked only while
+    /// chunks are written, as clients may send the body slowly.
+    fn write_body(
+        &self,
+        handle: &FileHandle,
+        body: &mut dyn Read,
+    ) -> Result<u64, DfsFrontendError> {
+        let mut buf = vec![0; WRITE_CHUNK_SIZE];
+        let mut offset = 0;
+        loop {
+            let count = match body.read(&mut buf) {
+                Ok(0) => return Ok(offset),
+                Ok(count) => count,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(DfsFrontendError::Generic(e.to_string())),
+            };
+            self.dfs().write(handle, offset, buf[..count].to_vec())?;
+            offset += count as u64;
+        }
+    }
+
+    fn mkcol(&self, path: String, body_length: usize) -> Result<Reply, DfsFrontendError> {
+        // RFC 4918 leaves the meaning of a body undefined
+        if body_length > 0 {
+            return Ok(Reply::Empty(415));
+        }
+        match self.dfs().mkdir(path) {
+            Ok(()) => Ok(Reply::Empty(201)),
+            Err(DfsFrontendError::NoSuchPath) => Ok(Reply::Empty(409)),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn move_node(
+        &self,
+        path: String,
+        destination: String,
+        overwrite: bool,
+    ) -> Result<Reply, DfsFrontendError> {
+        if path == destination || destination.starts_with(&format!("{path}/")) {
+            return Ok(Reply::Empty(403));
+        }
+        let mut dfs = self.dfs();
+        dfs.getattr(path.clone())?;
+        let destination_exists = match dfs.getattr(destination.clone()) {
+            Ok(_) => true,
+            Err(DfsFrontendError::NoSuchPath) => false,
+            Err(e) => return Err(e),
+        };
+        if !destination_exists {
+            if let Err(DfsFrontendError::NoSuchPath) = dfs.getattr(parent(&destination).into()) {
+                return Ok(Reply::Empty(409));
+            }
+            dfs.rename(path, destination)?;
+            return Ok(Reply::Empty(201));
+        }
+        if !overwrite {
+            return Ok(Reply::Empty(412));
+        }
+        replace(&mut *dfs, path, destination)?;
+        Ok(Reply::Empty(204))
+    }
+
+    /// Removes the node, including the whole content of a directory.
+    fn delete(&self, path: String) -> Result<Reply, DfsFrontendError> {
+        if path == "/" {
+            return Ok(Reply::Empty(403));
+        }
+        remove_all(&mut *self.dfs(), &path)?;
+        Ok(Reply::Empty(204))
+    }
+}
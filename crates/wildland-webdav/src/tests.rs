@@ -0,0 +1,478 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+
+use minreq::Method;
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use wildland_corex::{MockPathResolver, ResolvedPath, Storage};
+use wildland_dfs::in_memory::{InMemoryBackendFactory, InMemoryFs, IN_MEMORY_BACKEND_TYPE};
+use wildland_dfs::storage_backend::StorageBackend;
+use wildland_dfs::unencrypted::{StorageBackendFactory, UnencryptedDfs};
+
+use super::*;
+
+/// WebDAV server of a forest kept in [`InMemoryFs`]
+struct TestServer {
+    url: String,
+    fs: Arc<InMemoryFs>,
+    stop_handle: WebDavStopHandle,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl TestServer {
+    /// Starts server of a forest with a single container claiming `/`
+    fn start() -> Self {
+        Self::with_containers(&[("/", "/")])
+    }
+
+    /// Starts server of a forest with containers claiming the given paths, each one kept in
+    /// [`InMemoryFs`] under its own prefix.
+    fn with_containers(containers: &[(&str, &str)]) -> Self {
+        Self::with_containers_and_token(containers, None)
+    }
+
+    /// Starts server of a forest with a single container claiming `/`, which requires the token
+    fn with_token(token: &str) -> Self {
+        Self::with_containers_and_token(&[("/", "/")], Some(token.to_owned()))
+    }
+
+    fn with_containers_and_token(containers: &[(&str, &str)], token: Option<String>) -> Self {
+        let containers: Vec<_> = containers
+            .iter()
+            .map(|(claimed_path, prefix)| {
+                let storage = Storage::new(
+                    None,
+                    IN_MEMORY_BACKEND_TYPE.to_owned(),
+                    json!({ "container_prefix": prefix }),
+                );
+                (PathBuf::from(claimed_path), storage)
+            })
+            .collect();
+        let fs = Arc::new(InMemoryFs::default());
+        let (sender, receiver) = mpsc::channel();
+        // DFS is not Send, so it is created by the thread serving requests
+        let thread = std::thread::spawn({
+            let fs = fs.clone();
+            move || {
+                let mut path_resolver = MockPathResolver::new();
+                path_resolver.expect_resolve().returning(move |path| {
+                    let mut resolved = Vec::new();
+                    for (claimed_path, storage) in &containers {
+                        if let Ok(path_within_container) = path.strip_prefix(claimed_path) {
+                            resolved.push(ResolvedPath::PathWithStorages {
+                                path_within_storage: Path::new("/").join(path_within_container),
+                                storages: vec![storage.clone()],
+                            });
+                        } else if let Some(next_component) = claimed_path
+                            .strip_prefix(path)
+                            .ok()
+                            .and_then(|remaining| remaining.components().next())
+                        {
+                            resolved.push(ResolvedPath::VirtualPath(path.join(next_component)));
+                        }
+                    }
+                    resolved
+                });
+                let mut backend_factories: HashMap<String, Box<dyn StorageBackendFactory>> =
+                    HashMap::new();
+                backend_factories.insert(
+                    IN_MEMORY_BACKEND_TYPE.to_owned(),
+                    Box::new(InMemoryBackendFactory::new(fs)),
+                );
+                let dfs = UnencryptedDfs::new(Rc::new(path_resolver), backend_factories);
+
+                // the same type as the one shared by CargoLib
+                #[allow(clippy::arc_with_non_send_sync)]
+                let dfs = Arc::new(Mutex::new(dfs));
+                let server = match token {
+                    Some(token) => WebDavServer::with_token(dfs, "127.0.0.1:0", token).unwrap(),
+                    None => WebDavServer::new(dfs, 0).unwrap(),
+                };
+                sender
+                    .send((server.local_addr().unwrap(), server.stop_handle()))
+                    .unwrap();
+                server.serve();
+            }
+        });
+        let (addr, stop_handle) = receiver.recv().unwrap();
+        Self {
+            url: format!("http://{addr}"),
+            fs,
+            stop_handle,
+            thread: Some(thread),
+        }
+    }
+
+    fn request(&self, method: &str, path: &str) -> minreq::Request {
+        minreq::Request::new(
+            Method::Custom(method.to_owned()),
+            format!("{}{path}", self.url),
+        )
+    }
+
+    fn send(&self, method: &str, path: &str) -> minreq::Response {
+        self.request(method, path).send().unwrap()
+    }
+
+    /// Sends the request as is, without closing the connection for reading, and returns the
+    /// response
+    fn send_raw(&self, request: &[u8]) -> String {
+        let mut stream = TcpStream::connect(self.url.trim_start_matches("http://")).unwrap();
+        stream.write_all(request).unwrap();
+        stream.shutdown(Shutdown::Write).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn put_file(&self, path: &str, content: &[u8]) {
+        let path = Path::new(path);
+        self.fs.create_dir_all(path.parent().unwrap()).unwrap();
+        self.fs.create(path).unwrap();
+        self.fs.write(path, 0, content).unwrap();
+    }
+
+    fn file(&self, path: &str) -> Option<Vec<u8>> {
+        self.fs.read(Path::new(path), 0, usize::MAX).ok()
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.fs.getattr(Path::new(path)).is_ok()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.stop_handle.stop();
+        if let Some(thread) = self.thread.take() {
+            thread.join().unwrap();
+        }
+    }
+}
+
+fn hrefs(multistatus: &str) -> Vec<&str> {
+    let mut hrefs: Vec<_> = multistatus
+        .split("<D:href>")
+        .skip(1)
+        .filter_map(|part| part.split_once("</D:href>"))
+        .map(|(href, _)| href)
+        .collect();
+    hrefs.sort();
+    hrefs
+}
+
+#[test]
+fn test_options_advertise_webdav() {
+    let server = TestServer::start();
+
+    let response = server.send("OPTIONS", "/");
+
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.headers.get("dav").map(String::as_str), Some("1"));
+    assert!(response.headers["allow"].contains("PROPFIND"));
+}
+
+#[test]
+fn test_propfind_describes_node_and_its_children() {
+    let server = TestServer::start();
+    server.put_file("/books/Dune #1.txt", b"Arrakis");
+    server.fs.create_dir_all(Path::new("/books/poems")).unwrap();
+
+    let response = server
+        .request("PROPFIND", "/books")
+        .with_header("Depth", "1")
+        .send()
+        .unwrap();
+    assert_eq!(response.status_code, 207);
+    let body = response.as_str().unwrap();
+    assert_eq!(
+        hrefs(body),
+        vec!["/books/", "/books/Dune%20%231.txt", "/books/poems/"]
+    );
+    assert!(body.contains("<D:getcontentlength>7</D:getcontentlength>"));
+
+    let response = server
+        .request("PROPFIND", "/books/Dune%20%231.txt")
+        .with_header("Depth", "0")
+        .send()
+        .unwrap();
+    assert_eq!(response.status_code, 207);
+    assert_eq!(
+        hrefs(response.as_str().unwrap()),
+        vec!["/books/Dune%20%231.txt"]
+    );
+
+    let response = server
+        .request("PROPFIND", "/books")
+        .with_header("Depth", "infinity")
+        .send()
+        .unwrap();
+    assert_eq!(response.status_code, 403);
+
+    let response = server
+        .request("PROPFIND", "/films")
+        .with_header("Depth", "0")
+        .send()
+        .unwrap();
+    assert_eq!(response.status_code, 404);
+}
+
+#[test]
+fn test_get_and_head_of_file() {
+    let server = TestServer::start();
+    let content: Vec<u8> = (0..200_000).map(|i| i as u8).collect();
+    server.put_file("/data.bin", &content);
+
+    let response = server.send("GET", "/data.bin");
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.as_bytes(), content);
+
+    let response = minreq::head(format!("{}/data.bin", server.url))
+        .send()
+        .unwrap();
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.headers["content-length"], "200000");
+
+    assert_eq!(server.send("GET", "/").status_code, 405);
+    assert_eq!(server.send("GET", "/missing.bin").status_code, 404);
+}
+
+#[test]
+fn test_put_creates_and_replaces_file() {
+    let server = TestServer::start();
+    server.fs.create_dir_all(Path::new("/books")).unwrap();
+    let content: Vec<u8> = (0..200_000).map(|i| (i % 7) as u8).collect();
+
+    let response = server
+        .request("PUT", "/books/a.txt")
+        .with_body(content.clone())
+        .send()
+        .unwrap();
+    assert_eq!(response.status_code, 201);
+    assert_eq!(server.file("/books/a.txt"), Some(content));
+
+    let response = server
+        .request("PUT", "/books/a.txt")
+        .with_body("short")
+        .send()
+        .unwrap();
+    assert_eq!(response.status_code, 204);
+    assert_eq!(server.file("/books/a.txt"), Some(b"short".to_vec()));
+
+    let response = server
+        .request("PUT", "/films/a.mkv")
+        .with_body("frames")
+        .send()
+        .unwrap();
+    assert_eq!(response.status_code, 409);
+    assert_eq!(server.send("PUT", "/books").status_code, 405);
+}
+
+#[test]
+fn test_put_keeps_file_if_upload_is_incomplete() {
+    let server = TestServer::start();
+    server.put_file("/books/a.txt", b"original");
+
+    // the connection is closed before the whole body is sent, so no response may be written
+    server.send_raw(
+        b"PUT /books/a.txt HTTP/1.1\r\nHost: localhost\r\nContent-Length: 100\r\n\r\nshort",
+    );
+
+    assert_eq!(server.file("/books/a.txt"), Some(b"original".to_vec()));
+    assert_eq!(
+        server.fs.readdir(Path::new("/books")).unwrap(),
+        vec![PathBuf::from("/books/a.txt")]
+    );
+}
+
+#[test]
+fn test_mkcol_creates_directory() {
+    let server = TestServer::start();
+
+    assert_eq!(server.send("MKCOL", "/books").status_code, 201);
+    assert!(server.exists("/books"));
+    assert_eq!(server.send("MKCOL", "/books").status_code, 405);
+    assert_eq!(server.send("MKCOL", "/films/new").status_code, 409);
+}
+
+#[test]
+fn test_move_respects_overwrite_header() {
+    let server = TestServer::start();
+    server.put_file("/a.txt", b"a");
+    server.put_file("/books/b.txt", b"b");
+
+    let response = server
+        .request("MOVE", "/a.txt")
+        .with_header("Destination", format!("{}/books/c.txt", server.url))
+        .send()
+        .unwrap();
+    assert_eq!(response.status_code, 201);
+    assert!(!server.exists("/a.txt"));
+    assert_eq!(server.file("/books/c.txt"), Some(b"a".to_vec()));
+
+    let response = server
+        .request("MOVE", "/books/c.txt")
+        .with_header("Destination", "/books/b.txt")
+        .with_header("Overwrite", "F")
+        .send()
+        .unwrap();
+    assert_eq!(response.status_code, 412);
+    assert_eq!(server.file("/books/b.txt"), Some(b"b".to_vec()));
+
+    let response = server
+        .request("MOVE", "/books/c.txt")
+        .with_header("Destination", "/books/b.txt")
+        .send()
+        .unwrap();
+    assert_eq!(response.status_code, 204);
+    assert_eq!(server.file("/books/b.txt"), Some(b"a".to_vec()));
+
+    let response = server
+        .request("MOVE", "/books/b.txt")
+        .with_header("Destination", "/films/b.txt")
+        .send()
+        .unwrap();
+    assert_eq!(response.status_code, 409);
+    assert_eq!(server.send("MOVE", "/books/b.txt").status_code, 400);
+}
+
+#[test]
+fn test_delete_removes_whole_directory() {
+    let server = TestServer::start();
+    server.put_file("/books/a.txt", b"a");
+    server.put_file("/books/poems/b.txt", b"b");
+
+    assert_eq!(server.send("DELETE", "/books").status_code, 204);
+    assert!(!server.exists("/books"));
+    assert_eq!(server.send("DELETE", "/books").status_code, 404);
+    assert_eq!(server.send("DELETE", "/").status_code, 403);
+}
+
+#[test]
+fn test_failed_move_keeps_overwritten_destination() {
+    let server = TestServer::with_containers(&[("/books", "/books"), ("/films", "/films")]);
+    server.put_file("/books/a.txt", b"a");
+    server.put_file("/films/a.txt", b"film");
+
+    let response = server
+        .request("MOVE", "/books/a.txt")
+        .with_header("Destination", "/films/a.txt")
+        .send()
+        .unwrap();
+    assert_eq!(response.status_code, 502);
+    assert_eq!(server.file("/films/a.txt"), Some(b"film".to_vec()));
+    assert_eq!(server.file("/books/a.txt"), Some(b"a".to_vec()));
+    assert_eq!(server.fs.readdir(Path::new("/films")).unwrap().len(), 1);
+}
+
+#[test]
+fn test_virtual_directories_and_containers_are_not_removed() {
+    let server = TestServer::with_containers(&[("/media/books", "/books"), ("/films", "/films")]);
+    server.put_file("/books/a.txt", b"a");
+    server.put_file("/films/b.txt", b"b");
+
+    assert_eq!(server.send("DELETE", "/media").status_code, 403);
+    assert_eq!(server.send("DELETE", "/films").status_code, 403);
+    let response = server
+        .request("MOVE", "/films/b.txt")
+        .with_header("Destination", "/media")
+        .send()
+        .unwrap();
+    assert_eq!(response.status_code, 403);
+    assert!(server.exists("/books/a.txt"));
+    assert!(server.exists("/films/b.txt"));
+
+    assert_eq!(server.send("DELETE", "/films/b.txt").status_code, 204);
+    assert!(!server.exists("/films/b.txt"));
+}
+
+#[test]
+fn test_requests_have_to_address_loopback_host() {
+    let server = TestServer::start();
+    server.put_file("/a.txt", b"a");
+
+    for (headers, expected_status) in [
+        ("Host: localhost", 200),
+        ("Host: 127.0.0.1:1234\r\nOrigin: http://localhost:1234", 200),
+        ("Host: [::1]", 200),
+        ("Host: attacker.example", 403),
+        ("Host: attacker.example:80", 403),
+        ("Host: localhost\r\nHost: attacker.example", 403),
+        ("Host: localhost\r\nOrigin: http://attacker.example", 403),
+        ("Host: localhost\r\nOrigin: null", 403),
+        ("", 403),
+    ] {
+        let response = server.send_raw(
+            format!("GET /a.txt HTTP/1.1\r\n{headers}\r\nConnection: close\r\n\r\n").as_bytes(),
+        );
+        assert!(
+            response.starts_with(&format!("HTTP/1.1 {expected_status}")),
+            "{headers}: {response}"
+        );
+    }
+}
+
+#[test]
+fn test_server_with_token_requires_it() {
+    let server = TestServer::with_token("secret");
+    server.put_file("/a.txt", b"a");
+
+    let response = server.send("GET", "/a.txt");
+    assert_eq!(response.status_code, 401);
+    assert!(response.headers["www-authenticate"].starts_with("Basic"));
+    let response = server
+        .request("GET", "/a.txt")
+        .with_header("Authorization", "Bearer wrong")
+        .send()
+        .unwrap();
+    assert_eq!(response.status_code, 401);
+
+    let response = server
+        .request("GET", "/a.txt")
+        .with_header("Authorization", "Bearer secret")
+        .send()
+        .unwrap();
+    assert_eq!(response.status_code, 200);
+    // user:secret
+    let response = server
+        .request("GET", "/a.txt")
+        .with_header("Authorization", "Basic dXNlcjpzZWNyZXQ=")
+        .with_header("Host", "nas.example")
+        .send()
+        .unwrap();
+    assert_eq!(response.status_code, 200);
+}
+
+#[test]
+fn test_server_bound_to_public_address_requires_token() {
+    let dfs = UnencryptedDfs::new(Rc::new(MockPathResolver::new()), HashMap::new());
+    #[allow(clippy::arc_with_non_send_sync)]
+    let dfs = Arc::new(Mutex::new(dfs));
+
+    assert!(matches!(
+        WebDavServer::bind(dfs, "0.0.0.0:0", None),
+        Err(WebDavServerError::TokenRequired(_))
+    ));
+}
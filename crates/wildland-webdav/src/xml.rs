@@ -0,0 +1,108 @@
+//
+// Wildland Project
+//
+// Copyright © 2022 Golem Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License version 3 as published by
+// the Free Software Foundation.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt::Write;
+use std::time::{Duration, UNIX_EPOCH};
+
+use wildland_corex::dfs::interface::{NodeType, Stat};
+
+use crate::paths::href;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Builds body of a PROPFIND response describing nodes at the given DFS paths.
+///
+/// All supported properties are reported regardless of the ones requested by a client.
+pub(crate) fn multistatus<'a>(nodes: impl IntoIterator<Item = (&'a str, &'a Stat)>) -> String {
+    let mut body =
+        String::from(r#"<?xml version="1.0" encoding="utf-8"?><D:multistatus xmlns:D="DAV:">"#);
+    for (path, stat) in nodes {
+        let is_collection = stat.node_type == NodeType::Dir;
+        let name = path.rsplit('/').next().unwrap_or_default();
+        let _ = write!(
+            body,
+            "<D:response><D:href>{}</D:href><D:propstat><D:prop><D:displayname>{}</D:displayname>",
+            escape(&href(path, is_collection)),
+            escape(name)
+        );
+        if is_collection {
+            body.push_str("<D:resourcetype><D:collection/></D:resourcetype>");
+        } else {
+            let _ = write!(
+                body,
+                "<D:resourcetype/><D:getcontentlength>{}</D:getcontentlength>",
+                stat.size
+            );
+        }
+        if let Some(time) = stat.modification_time {
+            let time = UNIX_EPOCH + Duration::new(time.sec, time.nano_sec);
+            let _ = write!(
+                body,
+                "<D:getlastmodified>{}</D:getlastmodified>",
+                httpdate::fmt_http_date(time)
+            );
+        }
+        body.push_str("</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>");
+    }
+    body.push_str("</D:multistatus>");
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use wildland_corex::dfs::interface::UnixTimestamp;
+
+    use super::*;
+
+    #[test]
+    fn test_multistatus_of_file_and_dir() {
+        let file = Stat {
+            node_type: NodeType::File,
+            size: 7,
+            access_time: None,
+            modification_time: Some(UnixTimestamp {
+                sec: 784111777,
+                nano_sec: 0,
+            }),
+            change_time: None,
+        };
+        let dir = Stat::virtual_dir();
+
+        assert_eq!(
+            multistatus([("/a & b", &dir), ("/a & b/<c>.txt", &file)]),
+            concat!(
+                r#"<?xml version="1.0" encoding="utf-8"?><D:multistatus xmlns:D="DAV:">"#,
+                "<D:response><D:href>/a%20&amp;%20b/</D:href><D:propstat><D:prop>",
+                "<D:displayname>a &amp; b</D:displayname>",
+                "<D:resourcetype><D:collection/></D:resourcetype>",
+                "</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
+                "<D:response><D:href>/a%20&amp;%20b/%3Cc%3E.txt</D:href><D:propstat><D:prop>",
+                "<D:displayname>&lt;c&gt;.txt</D:displayname>",
+                "<D:resourcetype/><D:getcontentlength>7</D:getcontentlength>",
+                "<D:getlastmodified>Sun, 06 Nov 1994 08:49:37 GMT</D:getlastmodified>",
+                "</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
+                "</D:multistatus>"
+            )
+        );
+    }
+}
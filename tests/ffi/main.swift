@@ -23,6 +23,9 @@ class CargoCfgProviderImpl: CargoCfgProvider {
     public override func getFoundationCloudEnvMode() -> FoundationCloudMode {
         return FoundationCloudMode_Dev
     }
+    public override func getCatlibBackend() -> CatLibBackend {
+        return CatLibBackend_Rustbreak
+    }
 }
 
 class LocalSecureStorageImpl : LocalSecureStorage {
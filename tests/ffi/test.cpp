@@ -33,6 +33,14 @@ class CargoCfgProviderImpl : public CargoCfgProvider
     {
         return FoundationCloudMode::Dev;
     }
+    CatLibBackend get_catlib_backend() override
+    {
+        return CatLibBackend::Rustbreak;
+    }
+    Optional<String> get_catlib_sync_dir() override
+    {
+        return Optional<String>();
+    }
 };
 
 class LocalSecureStorageImpl : public LocalSecureStorage
@@ -26,6 +26,12 @@ namespace Main
         public override FoundationCloudMode get_foundation_cloud_env_mode() {
             return FoundationCloudMode.Dev;
         }
+        public override CatLibBackend get_catlib_backend() {
+            return CatLibBackend.Rustbreak;
+        }
+        public override OptionalRustString get_catlib_sync_dir() {
+            return new OptionalRustString();
+        }
     }
 
     class LocalSecureStorageImpl : LocalSecureStorage {